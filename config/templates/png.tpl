@@ -0,0 +1,14 @@
+# PNG: сигнатура и цепочка блоков до конца файла
+endian big
+
+struct Chunk {
+    u32 length
+    char type[4]
+    u8 data[length]
+    u32 crc @red
+}
+
+struct Png {
+    u8 signature[8]
+    Chunk chunks[..]
+}
@@ -0,0 +1,56 @@
+//! Пропускная способность движка поиска: `cargo bench --bench search`
+
+use hexr_core::search::{SearchMode, SearchPattern};
+use std::hint::black_box;
+use std::time::Instant;
+
+const DATA_SIZE: usize = 256 * 1024 * 1024;
+const ITERATIONS: u32 = 5;
+
+/// Псевдослучайные данные (xorshift), чтобы шаблоны не встречались случайно
+fn generate_data(size: usize) -> Vec<u8> {
+    let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+    (0..size)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect()
+}
+
+fn bench(name: &str, data: &[u8], pattern: &SearchPattern, expected: Option<usize>) {
+    assert_eq!(pattern.find_forward(data, 0), expected, "{}: wrong match", name);
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(pattern.find_forward(black_box(data), 0));
+    }
+    let elapsed = start.elapsed().as_secs_f64() / ITERATIONS as f64;
+    let throughput = data.len() as f64 / (1024.0 * 1024.0) / elapsed;
+
+    println!("{:<28} {:>8.2} ms  {:>9.1} MB/s", name, elapsed * 1000.0, throughput);
+}
+
+fn main() {
+    let mut data = generate_data(DATA_SIZE);
+    // Единственное совпадение в самом конце - поиск проходит весь буфер
+    let needle = b"HEXR-NEEDLE";
+    let tail = data.len() - needle.len();
+    data[tail..].copy_from_slice(needle);
+
+    println!("searching {} MB, {} iterations each", DATA_SIZE / (1024 * 1024), ITERATIONS);
+
+    let exact = SearchPattern::exact(needle.to_vec());
+    bench("exact", &data, &exact, Some(tail));
+
+    let text = SearchPattern::parse("hexr-needle", SearchMode::Text, true).unwrap();
+    bench("text, ignore case", &data, &text, Some(tail));
+
+    let masked = SearchPattern::parse_masked_hex("48 45 58 52 ?? 4E 45").unwrap();
+    bench("masked hex", &data, &masked, Some(tail));
+
+    let utf16 = SearchPattern::parse("HEXR", SearchMode::Utf16Le, false).unwrap();
+    bench("UTF-16LE", &data, &utf16, utf16.find_all(&data).first().copied());
+}
@@ -0,0 +1,523 @@
+//! Небольшой ассемблер для правки инструкций на месте: частые команды x86 и x86-64
+//! (переходы, mov и арифметика между регистрами и с константой, push/pop) и переходы,
+//! nop и ret для ARM, AArch64 и RISC-V. Операнды в памяти не поддерживаются
+//!
+//! Инструкции разделяются `;`, адреса переходов абсолютные (`jmp 0x4010`),
+//! `db 90 90` записывает байты как есть
+
+use crate::disasm::Arch;
+use anyhow::{Result, bail};
+
+/// Байты инструкций из `source`, первая из которых ляжет по адресу `address`
+pub fn assemble(arch: Arch, source: &str, address: u64) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    for line in source.split(';') {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (mnemonic, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let mnemonic = mnemonic.to_ascii_lowercase();
+        let operands: Vec<String> = rest
+            .split(',')
+            .map(|operand| operand.trim().to_ascii_lowercase())
+            .filter(|operand| !operand.is_empty())
+            .collect();
+        let operands: Vec<&str> = operands.iter().map(String::as_str).collect();
+
+        let address = address + bytes.len() as u64;
+        let encoded = if mnemonic == "db" {
+            raw_bytes(rest)?
+        } else {
+            match arch {
+                Arch::X86 => x86(&mnemonic, &operands, address, false)?,
+                Arch::X86_64 => x86(&mnemonic, &operands, address, true)?,
+                Arch::Arm => arm(&mnemonic, &operands, address)?,
+                Arch::Thumb => thumb(&mnemonic, &operands, address)?,
+                Arch::Aarch64 => aarch64(&mnemonic, &operands, address)?,
+                Arch::RiscV32 | Arch::RiscV64 => riscv(&mnemonic, &operands, address)?,
+            }
+        };
+        match encoded {
+            Some(encoded) => bytes.extend(encoded),
+            None => bail!("Cannot assemble '{}' for {}", line, arch.name()),
+        }
+    }
+    if bytes.is_empty() {
+        bail!("Nothing to assemble");
+    }
+    Ok(bytes)
+}
+
+fn raw_bytes(text: &str) -> Result<Option<Vec<u8>>> {
+    let mut bytes = Vec::new();
+    for item in text.split([' ', ',']).filter(|item| !item.is_empty()) {
+        let hex = item.trim_start_matches("0x").trim_start_matches("0X");
+        match u8::from_str_radix(hex, 16) {
+            Ok(byte) => bytes.push(byte),
+            Err(_) => bail!("Invalid byte '{}'", item),
+        }
+    }
+    Ok((!bytes.is_empty()).then_some(bytes))
+}
+
+/// Число в десятичной или шестнадцатеричной (0x) записи, со знаком; `#` перед числом - как в ARM
+fn number(text: &str) -> Option<i64> {
+    let text = text.trim_start_matches('#');
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let value = match text.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok()?,
+        None => text.parse::<u64>().ok()?,
+    } as i64;
+    Some(if negative {
+        value.wrapping_neg()
+    } else {
+        value
+    })
+}
+
+fn operand_count(operands: &[&str], count: usize) -> Result<()> {
+    if operands.len() != count {
+        bail!("Expected {} operand(s), got {}", count, operands.len());
+    }
+    Ok(())
+}
+
+/// Смещение перехода от `base` до абсолютного адреса в операнде
+fn relative(target: &str, base: u64) -> Result<i64> {
+    match number(target) {
+        Some(target) => Ok(target.wrapping_sub(base as i64)),
+        None if target.contains('[') => bail!("Memory operands are not supported: {}", target),
+        None => bail!("Expected a target address, got '{}'", target),
+    }
+}
+
+fn in_range(offset: i64, bits: u32, what: &str) -> Result<()> {
+    let limit = 1i64 << (bits - 1);
+    if !(-limit..limit).contains(&offset) {
+        bail!("{} target is out of range ({:+} bytes)", what, offset);
+    }
+    Ok(())
+}
+
+// x86 и x86-64
+
+#[derive(Clone, Copy)]
+struct Register {
+    number: u8,
+    wide: bool,
+}
+
+fn x86_register(name: &str, long_mode: bool) -> Result<Register> {
+    const WIDE: [&str; 8] = ["rax", "rcx", "rdx", "rbx", "rsp", "rbp", "rsi", "rdi"];
+    const DWORD: [&str; 8] = ["eax", "ecx", "edx", "ebx", "esp", "ebp", "esi", "edi"];
+
+    let register = if let Some(number) = WIDE.iter().position(|&reg| reg == name) {
+        Register {
+            number: number as u8,
+            wide: true,
+        }
+    } else if let Some(number) = DWORD.iter().position(|&reg| reg == name) {
+        Register {
+            number: number as u8,
+            wide: false,
+        }
+    } else {
+        // r8..r15 и r8d..r15d
+        let (digits, wide) = match name.strip_suffix('d') {
+            Some(digits) => (digits, false),
+            None => (name, true),
+        };
+        match digits.strip_prefix('r').and_then(|n| n.parse::<u8>().ok()) {
+            Some(number @ 8..=15) => Register { number, wide },
+            _ if name.contains('[') => bail!("Memory operands are not supported: {}", name),
+            _ if name.starts_with(|c: char| c.is_ascii_digit() || c == '-') => {
+                bail!("Invalid immediate '{}'", name)
+            }
+            _ => bail!("Unknown or unsupported register '{}'", name),
+        }
+    };
+    if !long_mode && (register.wide || register.number >= 8) {
+        bail!("Register '{}' needs x86-64", name);
+    }
+    Ok(register)
+}
+
+/// Префикс REX, если он нужен: W - 64-битный операнд, R и B - старшие биты номеров
+fn rex(wide: bool, reg: u8, rm: u8) -> Option<u8> {
+    let rex = 0x40 | (wide as u8) << 3 | (reg >> 3) << 2 | rm >> 3;
+    (rex != 0x40).then_some(rex)
+}
+
+/// Инструкция с ModRM в режиме регистр-регистр: `opcode`, поле reg (или расширение кода) и r/m
+fn modrm(opcode: &[u8], wide: bool, reg: u8, rm: u8) -> Vec<u8> {
+    let mut bytes: Vec<u8> = rex(wide, reg, rm).into_iter().collect();
+    bytes.extend_from_slice(opcode);
+    bytes.push(0xC0 | (reg & 7) << 3 | rm & 7);
+    bytes
+}
+
+/// Код условия для jcc по суффиксу мнемоники
+fn condition(suffix: &str) -> Option<u8> {
+    Some(match suffix {
+        "o" => 0x0,
+        "no" => 0x1,
+        "b" | "c" | "nae" => 0x2,
+        "ae" | "nb" | "nc" => 0x3,
+        "e" | "z" => 0x4,
+        "ne" | "nz" => 0x5,
+        "be" | "na" => 0x6,
+        "a" | "nbe" => 0x7,
+        "s" => 0x8,
+        "ns" => 0x9,
+        "p" | "pe" => 0xA,
+        "np" | "po" => 0xB,
+        "l" | "nge" => 0xC,
+        "ge" | "nl" => 0xD,
+        "le" | "ng" => 0xE,
+        "g" | "nle" => 0xF,
+        _ => return None,
+    })
+}
+
+fn x86(
+    mnemonic: &str,
+    operands: &[&str],
+    address: u64,
+    long_mode: bool,
+) -> Result<Option<Vec<u8>>> {
+    let fixed: Option<&[u8]> = match mnemonic {
+        "nop" => Some(&[0x90]),
+        "ret" if operands.is_empty() => Some(&[0xC3]),
+        "int3" => Some(&[0xCC]),
+        "hlt" => Some(&[0xF4]),
+        "leave" => Some(&[0xC9]),
+        "cdq" => Some(&[0x99]),
+        "cqo" if long_mode => Some(&[0x48, 0x99]),
+        "syscall" if long_mode => Some(&[0x0F, 0x05]),
+        "ud2" => Some(&[0x0F, 0x0B]),
+        _ => None,
+    };
+    if let Some(bytes) = fixed {
+        operand_count(operands, 0)?;
+        return Ok(Some(bytes.to_vec()));
+    }
+
+    // Двухоперандные операции: код для регистр-регистр и расширение кода для константы
+    let binary = match mnemonic {
+        "add" => Some((0x01, 0)),
+        "or" => Some((0x09, 1)),
+        "and" => Some((0x21, 4)),
+        "sub" => Some((0x29, 5)),
+        "xor" => Some((0x31, 6)),
+        "cmp" => Some((0x39, 7)),
+        _ => None,
+    };
+
+    let bytes = match mnemonic {
+        "ret" => {
+            operand_count(operands, 1)?;
+            let Some(size) = number(operands[0]).and_then(|n| u16::try_from(n).ok()) else {
+                bail!("Expected a 16-bit immediate, got '{}'", operands[0]);
+            };
+            let mut bytes = vec![0xC2];
+            bytes.extend(size.to_le_bytes());
+            bytes
+        }
+        "int" => {
+            operand_count(operands, 1)?;
+            let Some(vector) = number(operands[0]).and_then(|n| u8::try_from(n).ok()) else {
+                bail!("Expected an 8-bit interrupt number, got '{}'", operands[0]);
+            };
+            vec![0xCD, vector]
+        }
+        "push" | "pop" => {
+            operand_count(operands, 1)?;
+            let reg = x86_register(operands[0], long_mode)?;
+            if reg.wide != long_mode {
+                bail!(
+                    "{} needs a {}-bit register",
+                    mnemonic,
+                    if long_mode { 64 } else { 32 }
+                );
+            }
+            let base = if mnemonic == "push" { 0x50 } else { 0x58 };
+            let mut bytes: Vec<u8> = rex(false, 0, reg.number).into_iter().collect();
+            bytes.push(base + (reg.number & 7));
+            bytes
+        }
+        "inc" | "dec" | "not" | "neg" => {
+            operand_count(operands, 1)?;
+            let reg = x86_register(operands[0], long_mode)?;
+            let (opcode, extension) = match mnemonic {
+                "inc" => (0xFF, 0),
+                "dec" => (0xFF, 1),
+                "not" => (0xF7, 2),
+                _ => (0xF7, 3),
+            };
+            modrm(&[opcode], reg.wide, extension, reg.number)
+        }
+        "jmp" | "call" if operands.first().is_some_and(|op| number(op).is_none()) => {
+            // Переход по адресу в регистре
+            operand_count(operands, 1)?;
+            let reg = x86_register(operands[0], long_mode)?;
+            if reg.wide != long_mode {
+                bail!(
+                    "{} needs a {}-bit register",
+                    mnemonic,
+                    if long_mode { 64 } else { 32 }
+                );
+            }
+            let extension = if mnemonic == "jmp" { 4 } else { 2 };
+            modrm(&[0xFF], false, extension, reg.number)
+        }
+        "jmp" => {
+            operand_count(operands, 1)?;
+            let short = relative(operands[0], address + 2)?;
+            if i8::try_from(short).is_ok() {
+                vec![0xEB, short as u8]
+            } else {
+                let near = relative(operands[0], address + 5)?;
+                in_range(near, 32, "Jump")?;
+                let mut bytes = vec![0xE9];
+                bytes.extend((near as i32).to_le_bytes());
+                bytes
+            }
+        }
+        "call" => {
+            operand_count(operands, 1)?;
+            let near = relative(operands[0], address + 5)?;
+            in_range(near, 32, "Call")?;
+            let mut bytes = vec![0xE8];
+            bytes.extend((near as i32).to_le_bytes());
+            bytes
+        }
+        _ if mnemonic.starts_with('j') && condition(&mnemonic[1..]).is_some() => {
+            operand_count(operands, 1)?;
+            let code = condition(&mnemonic[1..]).unwrap_or(0);
+            let short = relative(operands[0], address + 2)?;
+            if i8::try_from(short).is_ok() {
+                vec![0x70 + code, short as u8]
+            } else {
+                let near = relative(operands[0], address + 6)?;
+                in_range(near, 32, "Jump")?;
+                let mut bytes = vec![0x0F, 0x80 + code];
+                bytes.extend((near as i32).to_le_bytes());
+                bytes
+            }
+        }
+        "mov" | "test" => {
+            operand_count(operands, 2)?;
+            let dst = x86_register(operands[0], long_mode)?;
+            match number(operands[1]) {
+                None => {
+                    let src = x86_register(operands[1], long_mode)?;
+                    if src.wide != dst.wide {
+                        bail!("Operand sizes do not match");
+                    }
+                    let opcode = if mnemonic == "mov" { 0x89 } else { 0x85 };
+                    modrm(&[opcode], dst.wide, src.number, dst.number)
+                }
+                Some(value) => {
+                    let imm = immediate32(value, dst.wide)?;
+                    let mut bytes = if mnemonic == "test" {
+                        modrm(&[0xF7], dst.wide, 0, dst.number)
+                    } else if dst.wide && imm.is_none() {
+                        // movabs с 64-битной константой
+                        let mut bytes: Vec<u8> = rex(true, 0, dst.number).into_iter().collect();
+                        bytes.push(0xB8 + (dst.number & 7));
+                        bytes.extend(value.to_le_bytes());
+                        return Ok(Some(bytes));
+                    } else if dst.wide {
+                        modrm(&[0xC7], true, 0, dst.number)
+                    } else {
+                        let mut bytes: Vec<u8> = rex(false, 0, dst.number).into_iter().collect();
+                        bytes.push(0xB8 + (dst.number & 7));
+                        bytes
+                    };
+                    match imm {
+                        Some(imm) => bytes.extend(imm.to_le_bytes()),
+                        None => bail!("Immediate {} does not fit 32 bits", operands[1]),
+                    }
+                    bytes
+                }
+            }
+        }
+        _ => match binary {
+            Some((opcode, extension)) => {
+                operand_count(operands, 2)?;
+                let dst = x86_register(operands[0], long_mode)?;
+                match number(operands[1]) {
+                    None => {
+                        let src = x86_register(operands[1], long_mode)?;
+                        if src.wide != dst.wide {
+                            bail!("Operand sizes do not match");
+                        }
+                        modrm(&[opcode], dst.wide, src.number, dst.number)
+                    }
+                    Some(value) => {
+                        let Some(imm) = immediate32(value, dst.wide)? else {
+                            bail!("Immediate {} does not fit 32 bits", operands[1]);
+                        };
+                        if let Ok(short) = i8::try_from(imm) {
+                            let mut bytes = modrm(&[0x83], dst.wide, extension, dst.number);
+                            bytes.push(short as u8);
+                            bytes
+                        } else {
+                            let mut bytes = modrm(&[0x81], dst.wide, extension, dst.number);
+                            bytes.extend(imm.to_le_bytes());
+                            bytes
+                        }
+                    }
+                }
+            }
+            None => return Ok(None),
+        },
+    };
+    Ok(Some(bytes))
+}
+
+/// Константа для 32-битного поля: у 64-битного операнда она расширяется знаком,
+/// у 32-битного допустимы и беззнаковые значения до 0xFFFFFFFF
+fn immediate32(value: i64, wide: bool) -> Result<Option<i32>> {
+    if let Ok(imm) = i32::try_from(value) {
+        return Ok(Some(imm));
+    }
+    if !wide {
+        return match u32::try_from(value) {
+            Ok(imm) => Ok(Some(imm as i32)),
+            Err(_) => bail!("Immediate {} does not fit 32 bits", value),
+        };
+    }
+    Ok(None)
+}
+
+// ARM, Thumb, AArch64, RISC-V: только то, что нужно, чтобы убрать или перенаправить код
+
+fn is_link_register(operands: &[&str]) -> bool {
+    operands == ["lr"]
+}
+
+fn arm(mnemonic: &str, operands: &[&str], address: u64) -> Result<Option<Vec<u8>>> {
+    let word: u32 = match mnemonic {
+        "nop" => 0xE320_F000,
+        "bx" if is_link_register(operands) => 0xE12F_FF1E,
+        "b" | "bl" => {
+            operand_count(operands, 1)?;
+            // PC в ARM указывает на две инструкции вперед
+            let offset = relative(operands[0], address + 8)?;
+            if offset % 4 != 0 {
+                bail!("Branch target must be 4-byte aligned");
+            }
+            in_range(offset, 26, "Branch")?;
+            let base = if mnemonic == "b" {
+                0xEA00_0000
+            } else {
+                0xEB00_0000
+            };
+            base | ((offset >> 2) as u32 & 0x00FF_FFFF)
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(word.to_le_bytes().to_vec()))
+}
+
+fn thumb(mnemonic: &str, operands: &[&str], address: u64) -> Result<Option<Vec<u8>>> {
+    let halfwords: Vec<u16> = match mnemonic {
+        "nop" => vec![0xBF00],
+        "bx" if is_link_register(operands) => vec![0x4770],
+        "b" => {
+            operand_count(operands, 1)?;
+            let offset = relative(operands[0], address + 4)?;
+            if offset % 2 != 0 {
+                bail!("Branch target must be 2-byte aligned");
+            }
+            in_range(offset, 12, "Branch")?;
+            vec![0xE000 | ((offset >> 1) as u16 & 0x07FF)]
+        }
+        "bl" => {
+            operand_count(operands, 1)?;
+            let offset = relative(operands[0], address + 4)?;
+            if offset % 2 != 0 {
+                bail!("Branch target must be 2-byte aligned");
+            }
+            in_range(offset, 25, "Branch")?;
+            let imm = (offset >> 1) as u32;
+            let sign = (imm >> 23) & 1;
+            let j1 = ((imm >> 22) & 1 ^ 1) ^ sign;
+            let j2 = ((imm >> 21) & 1 ^ 1) ^ sign;
+            vec![
+                (0xF000 | sign << 10 | (imm >> 11) & 0x3FF) as u16,
+                (0xD000 | j1 << 13 | j2 << 11 | imm & 0x7FF) as u16,
+            ]
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(
+        halfwords
+            .iter()
+            .flat_map(|half| half.to_le_bytes())
+            .collect(),
+    ))
+}
+
+fn aarch64(mnemonic: &str, operands: &[&str], address: u64) -> Result<Option<Vec<u8>>> {
+    let word: u32 = match mnemonic {
+        "nop" => 0xD503_201F,
+        "ret" if operands.is_empty() || operands == ["x30"] => 0xD65F_03C0,
+        "brk" => {
+            operand_count(operands, 1)?;
+            let Some(imm) = number(operands[0]).and_then(|n| u16::try_from(n).ok()) else {
+                bail!("Expected a 16-bit immediate, got '{}'", operands[0]);
+            };
+            0xD420_0000 | (imm as u32) << 5
+        }
+        "b" | "bl" => {
+            operand_count(operands, 1)?;
+            let offset = relative(operands[0], address)?;
+            if offset % 4 != 0 {
+                bail!("Branch target must be 4-byte aligned");
+            }
+            in_range(offset, 28, "Branch")?;
+            let base = if mnemonic == "b" {
+                0x1400_0000
+            } else {
+                0x9400_0000
+            };
+            base | ((offset >> 2) as u32 & 0x03FF_FFFF)
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(word.to_le_bytes().to_vec()))
+}
+
+fn riscv(mnemonic: &str, operands: &[&str], address: u64) -> Result<Option<Vec<u8>>> {
+    let word: u32 = match mnemonic {
+        "nop" => 0x0000_0013,
+        "ret" => 0x0000_8067,
+        "ecall" => 0x0000_0073,
+        "ebreak" => 0x0010_0073,
+        "j" | "jal" => {
+            operand_count(operands, 1)?;
+            let offset = relative(operands[0], address)?;
+            if offset % 2 != 0 {
+                bail!("Jump target must be 2-byte aligned");
+            }
+            in_range(offset, 21, "Jump")?;
+            let imm = offset as u32;
+            // j - это jal x0, jal без регистра - jal ra
+            let rd = if mnemonic == "j" { 0 } else { 1 };
+            (imm >> 20 & 1) << 31
+                | (imm >> 1 & 0x3FF) << 21
+                | (imm >> 11 & 1) << 20
+                | (imm >> 12 & 0xFF) << 12
+                | rd << 7
+                | 0x6F
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(word.to_le_bytes().to_vec()))
+}
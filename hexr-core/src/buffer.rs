@@ -0,0 +1,382 @@
+use crate::device;
+use crate::search::SearchSource;
+use anyhow::Result;
+use memmap2::{Mmap, MmapOptions};
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::Read;
+use std::ops::{Deref, Index, Range};
+use std::sync::Arc;
+
+/// Файлы начиная с этого размера отображаются в память, а не читаются целиком
+pub const MMAP_THRESHOLD: usize = 64 * 1024 * 1024;
+
+/// Неизменяемое содержимое файла, каким оно было при открытии
+pub enum Buffer {
+    Owned(Vec<u8>),
+    /// Отображение файла: в памяти живут только прочитанные страницы
+    Mapped(Mmap),
+}
+
+impl Buffer {
+    /// Открывает файл: большие файлы отображаются в память, небольшие читаются.
+    /// Блочные устройства тоже: страницы читаются с диска по мере обращения к ним
+    pub fn open(path: &str) -> Result<Self> {
+        let mut file = File::open(path)?;
+        let len = if device::is_block_device(path) {
+            device::device_len(&mut file)?
+        } else {
+            file.metadata()?.len()
+        };
+
+        if len >= MMAP_THRESHOLD as u64 {
+            // SAFETY: отображение только читается. Если другой процесс изменит или обрежет
+            // файл во время работы, это будет видно в данных - ограничение работы с отображением,
+            // как и у других редакторов больших файлов
+            let map = unsafe { MmapOptions::new().len(len as usize).map(&file)? };
+            return Ok(Buffer::Mapped(map));
+        }
+
+        let mut data = Vec::with_capacity(len as usize);
+        file.read_to_end(&mut data)?;
+        Ok(Buffer::Owned(data))
+    }
+}
+
+impl From<Vec<u8>> for Buffer {
+    fn from(data: Vec<u8>) -> Self {
+        Buffer::Owned(data)
+    }
+}
+
+impl Deref for Buffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Buffer::Owned(data) => data,
+            Buffer::Mapped(map) => map,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Source {
+    Original,
+    Added,
+}
+
+/// Кусок данных: диапазон в исходном содержимом или в буфере добавленных байт
+#[derive(Debug, Clone, Copy)]
+struct Piece {
+    source: Source,
+    start: usize,
+    len: usize,
+}
+
+/// Таблица кусков: исходное содержимое не меняется, новые байты дописываются в `added`,
+/// а порядок данных задается списком кусков. Вставка и удаление стоят O(число кусков),
+/// а не O(размер файла)
+#[derive(Clone)]
+pub struct PieceTable {
+    original: Arc<Buffer>,
+    added: Vec<u8>,
+    pieces: Vec<Piece>,
+    // Логическое смещение начала каждого куска, для двоичного поиска
+    offsets: Vec<usize>,
+    len: usize,
+}
+
+impl PieceTable {
+    pub fn new(original: Buffer) -> Self {
+        Self::from_original(Arc::new(original))
+    }
+
+    /// Таблица с исходным содержимым без правок; само содержимое не копируется
+    pub fn reverted(&self) -> Self {
+        Self::from_original(Arc::clone(&self.original))
+    }
+
+    fn from_original(original: Arc<Buffer>) -> Self {
+        let len = original.len();
+        let pieces = if len > 0 {
+            vec![Piece { source: Source::Original, start: 0, len }]
+        } else {
+            Vec::new()
+        };
+
+        let mut table = Self {
+            original,
+            added: Vec::new(),
+            pieces,
+            offsets: Vec::new(),
+            len,
+        };
+        table.reindex();
+        table
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Содержимое, каким оно было при открытии или последнем сохранении
+    pub fn original(&self) -> &[u8] {
+        &self.original
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Байты диапазона: без копирования, если диапазон лежит внутри одного куска
+    pub fn range(&self, range: Range<usize>) -> Cow<'_, [u8]> {
+        let end = range.end.min(self.len);
+        let start = range.start.min(end);
+        if start == end {
+            return Cow::Borrowed(&[]);
+        }
+
+        let first = self.piece_at(start);
+        if end <= self.offsets[first] + self.pieces[first].len {
+            return Cow::Borrowed(&self.piece_slice(first)[start - self.offsets[first]..end - self.offsets[first]]);
+        }
+
+        let mut bytes = Vec::with_capacity(end - start);
+        for idx in first..self.pieces.len() {
+            let offset = self.offsets[idx];
+            if offset >= end {
+                break;
+            }
+            let slice = self.piece_slice(idx);
+            let from = start.saturating_sub(offset);
+            let to = (end - offset).min(slice.len());
+            bytes.extend_from_slice(&slice[from..to]);
+        }
+        Cow::Owned(bytes)
+    }
+
+    /// Куски данных по порядку, для записи в файл без сборки в один буфер
+    pub fn chunks(&self) -> impl Iterator<Item = &[u8]> {
+        (0..self.pieces.len()).map(|idx| self.piece_slice(idx))
+    }
+
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.len);
+        for chunk in self.chunks() {
+            bytes.extend_from_slice(chunk);
+        }
+        bytes
+    }
+
+    pub fn insert(&mut self, position: usize, bytes: &[u8]) {
+        self.replace(position..position, bytes);
+    }
+
+    /// Удаляет диапазон и возвращает удаленные байты
+    pub fn delete(&mut self, range: Range<usize>) -> Vec<u8> {
+        self.replace(range, &[])
+    }
+
+    /// Заменяет диапазон на `bytes` (длины могут различаться), возвращает старые байты
+    pub fn replace(&mut self, range: Range<usize>, bytes: &[u8]) -> Vec<u8> {
+        let end = range.end.min(self.len);
+        let start = range.start.min(end);
+        let old = self.range(start..end).into_owned();
+
+        if start == end && bytes.is_empty() {
+            return old;
+        }
+
+        // Куски до start и после end сохраняются, пересекающиеся обрезаются
+        let old_pieces = std::mem::take(&mut self.pieces);
+        let mut pieces = Vec::with_capacity(old_pieces.len() + 2);
+        let mut inserted = false;
+        for (idx, piece) in old_pieces.iter().enumerate() {
+            let offset = self.offsets[idx];
+            let piece_end = offset + piece.len;
+
+            if piece_end <= start {
+                pieces.push(*piece);
+                continue;
+            }
+
+            if offset < start {
+                pieces.push(Piece { len: start - offset, ..*piece });
+            }
+            if !inserted {
+                self.push_added(&mut pieces, bytes);
+                inserted = true;
+            }
+            if piece_end > end {
+                let skip = end.saturating_sub(offset);
+                pieces.push(Piece { start: piece.start + skip, len: piece.len - skip, ..*piece });
+            }
+        }
+        if !inserted {
+            self.push_added(&mut pieces, bytes);
+        }
+
+        self.pieces = pieces;
+        self.len = self.len - (end - start) + bytes.len();
+        self.reindex();
+        old
+    }
+
+    /// Диапазоны, которые отличаются от исходного содержимого, если все правки были
+    /// заменой на месте (исходные куски остались на своих смещениях). `None`, если данные сдвигались
+    pub fn overwritten_ranges(&self) -> Option<Vec<Range<usize>>> {
+        if self.len != self.original.len() {
+            return None;
+        }
+
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        for (idx, piece) in self.pieces.iter().enumerate() {
+            let offset = self.offsets[idx];
+            match piece.source {
+                Source::Original if piece.start == offset => {}
+                Source::Original => return None,
+                Source::Added => match ranges.last_mut() {
+                    Some(last) if last.end == offset => last.end = offset + piece.len,
+                    _ => ranges.push(offset..offset + piece.len),
+                },
+            }
+        }
+
+        Some(ranges)
+    }
+
+    /// Участки `range`, измененные с открытия или сохранения: добавленные байты, которые
+    /// не совпадают с исходным байтом на том же смещении. Исходные байты, сдвинутые
+    /// вставкой или удалением, изменением не считаются
+    pub fn modified_ranges(&self, range: Range<usize>) -> Vec<Range<usize>> {
+        let end = range.end.min(self.len);
+        let start = range.start.min(end);
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        if start == end {
+            return ranges;
+        }
+
+        for idx in self.piece_at(start)..self.pieces.len() {
+            let offset = self.offsets[idx];
+            if offset >= end {
+                break;
+            }
+            if self.pieces[idx].source != Source::Added {
+                continue;
+            }
+
+            let slice = self.piece_slice(idx);
+            for pos in start.max(offset)..end.min(offset + slice.len()) {
+                // Возврат исходного значения (в том числе отменой) снимает отметку
+                if self.original().get(pos) == Some(&slice[pos - offset]) {
+                    continue;
+                }
+                match ranges.last_mut() {
+                    Some(last) if last.end == pos => last.end = pos + 1,
+                    _ => ranges.push(pos..pos + 1),
+                }
+            }
+        }
+
+        ranges
+    }
+
+    /// Начала участков, которыми данные отличаются от `other` (не больше `limit`).
+    /// Разница в длине считается одним участком с конца более короткого буфера
+    pub fn diff_runs(&self, other: &[u8], limit: usize) -> Vec<usize> {
+        const WINDOW: usize = 1024 * 1024;
+
+        let common = self.len.min(other.len());
+        let mut runs = Vec::new();
+        let mut in_run = false;
+        let mut offset = 0;
+
+        while offset < common && runs.len() < limit {
+            let end = (offset + WINDOW).min(common);
+            let ours = self.range(offset..end);
+            let theirs = &other[offset..end];
+
+            if ours[..] == theirs[..] {
+                in_run = false;
+            } else {
+                for (idx, (a, b)) in ours.iter().zip(theirs).enumerate() {
+                    let differs = a != b;
+                    if differs && !in_run && runs.len() < limit {
+                        runs.push(offset + idx);
+                    }
+                    in_run = differs;
+                }
+            }
+            offset = end;
+        }
+
+        if self.len != other.len() && !in_run && runs.len() < limit {
+            runs.push(common);
+        }
+        runs
+    }
+
+    /// Новые байты дописываются в `added`; продолжение последнего добавленного
+    /// куска (ввод подряд) расширяет его, а не создает новый
+    fn push_added(&mut self, pieces: &mut Vec<Piece>, bytes: &[u8]) {
+        if bytes.is_empty() {
+            return;
+        }
+
+        let start = self.added.len();
+        self.added.extend_from_slice(bytes);
+
+        if let Some(last) = pieces.last_mut()
+            && last.source == Source::Added
+            && last.start + last.len == start
+        {
+            last.len += bytes.len();
+            return;
+        }
+        pieces.push(Piece { source: Source::Added, start, len: bytes.len() });
+    }
+
+    fn reindex(&mut self) {
+        self.offsets.clear();
+        let mut offset = 0;
+        for piece in &self.pieces {
+            self.offsets.push(offset);
+            offset += piece.len;
+        }
+    }
+
+    /// Индекс куска, содержащего позицию `pos < len`
+    fn piece_at(&self, pos: usize) -> usize {
+        self.offsets.partition_point(|&offset| offset <= pos) - 1
+    }
+
+    fn piece_slice(&self, idx: usize) -> &[u8] {
+        let piece = self.pieces[idx];
+        let source: &[u8] = match piece.source {
+            Source::Original => &self.original,
+            Source::Added => &self.added,
+        };
+        &source[piece.start..piece.start + piece.len]
+    }
+}
+
+impl Index<usize> for PieceTable {
+    type Output = u8;
+
+    fn index(&self, pos: usize) -> &u8 {
+        assert!(pos < self.len, "index {} out of range for data of length {}", pos, self.len);
+        let idx = self.piece_at(pos);
+        &self.piece_slice(idx)[pos - self.offsets[idx]]
+    }
+}
+
+impl SearchSource for PieceTable {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn range(&self, range: Range<usize>) -> Cow<'_, [u8]> {
+        PieceTable::range(self, range)
+    }
+}
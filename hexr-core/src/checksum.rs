@@ -0,0 +1,224 @@
+//! Контрольные суммы участков данных и рецепты, которые пересчитывают поле суммы
+//! после правок
+
+use crate::numeric::Endian;
+use crate::progress::Progress;
+use crate::search::SearchSource;
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// Сколько байт данных читается за раз: таблица кусков не собирается в один буфер
+const CHUNK: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Algorithm {
+    // CRC-16/CCITT-FALSE: полином 0x1021, начальное значение 0xFFFF, без отражения
+    Crc16Ccitt,
+    // CRC-32 (IEEE), как в zlib, PNG и ZIP
+    Crc32,
+    // Сумма байт, обрезанная до размера поля
+    Sum8,
+    Sum16,
+    Sum32,
+    // Дополнение суммы байт до нуля: сумма участка вместе с полем дает 0 (Intel HEX)
+    TwosComplement,
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 6] = [
+        Algorithm::Crc16Ccitt,
+        Algorithm::Crc32,
+        Algorithm::Sum8,
+        Algorithm::Sum16,
+        Algorithm::Sum32,
+        Algorithm::TwosComplement,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::Crc16Ccitt => "crc16",
+            Algorithm::Crc32 => "crc32",
+            Algorithm::Sum8 => "sum8",
+            Algorithm::Sum16 => "sum16",
+            Algorithm::Sum32 => "sum32",
+            Algorithm::TwosComplement => "twos",
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Self> {
+        let name = name.trim().to_ascii_lowercase();
+        match Self::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.name() == name)
+        {
+            Some(algorithm) => Ok(algorithm),
+            None => bail!(
+                "Unknown checksum '{}', expected {}",
+                name,
+                Self::ALL.map(Algorithm::name).join(", ")
+            ),
+        }
+    }
+
+    /// Размер поля суммы в байтах
+    pub fn size(self) -> usize {
+        match self {
+            Algorithm::Sum8 | Algorithm::TwosComplement => 1,
+            Algorithm::Crc16Ccitt | Algorithm::Sum16 => 2,
+            Algorithm::Crc32 | Algorithm::Sum32 => 4,
+        }
+    }
+
+    /// Сумма участка `range`; часть за концом данных не считается
+    pub fn compute<D: SearchSource + ?Sized>(
+        self,
+        data: &D,
+        range: Range<usize>,
+        progress: &mut dyn Progress,
+    ) -> Result<u32> {
+        let end = range.end.min(data.len());
+        let mut state = match self {
+            Algorithm::Crc16Ccitt => 0xFFFF,
+            _ => 0,
+        };
+
+        let mut offset = range.start;
+        while offset < end {
+            progress.update(offset - range.start, end - range.start)?;
+            let chunk_end = (offset + CHUNK).min(end);
+            let bytes = data.range(offset..chunk_end);
+            state = match self {
+                Algorithm::Crc16Ccitt => crc16_ccitt(state as u16, &bytes) as u32,
+                Algorithm::Crc32 => crc32(state, &bytes),
+                _ => bytes
+                    .iter()
+                    .fold(state, |sum, &byte| sum.wrapping_add(byte as u32)),
+            };
+            offset = chunk_end;
+        }
+
+        Ok(match self {
+            Algorithm::Sum8 => state & 0xFF,
+            Algorithm::Sum16 => state & 0xFFFF,
+            Algorithm::TwosComplement => state.wrapping_neg() & 0xFF,
+            _ => state,
+        })
+    }
+
+    /// Байты поля для значения суммы
+    pub fn to_bytes(self, value: u32, endian: Endian) -> Vec<u8> {
+        let bytes = match endian {
+            Endian::Little => value.to_le_bytes(),
+            Endian::Big => value.to_be_bytes(),
+        };
+        match endian {
+            Endian::Little => bytes[..self.size()].to_vec(),
+            Endian::Big => bytes[4 - self.size()..].to_vec(),
+        }
+    }
+}
+
+/// Как пересчитать одно поле суммы: алгоритм, проверяемый участок, позиция поля
+/// и порядок байт в нем
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Recipe {
+    pub algorithm: Algorithm,
+    pub range: Range<usize>,
+    pub target: usize,
+    pub endian: Endian,
+}
+
+impl Recipe {
+    pub fn new(
+        algorithm: Algorithm,
+        range: Range<usize>,
+        target: usize,
+        endian: Endian,
+    ) -> Result<Self> {
+        if range.is_empty() {
+            bail!("The checked range is empty");
+        }
+        let field = target..target + algorithm.size();
+        if field.start < range.end && range.start < field.end {
+            bail!(
+                "The checksum field at 0x{:X} overlaps the checked range 0x{:X}..0x{:X}",
+                target,
+                range.start,
+                range.end
+            );
+        }
+        Ok(Self {
+            algorithm,
+            range,
+            target,
+            endian,
+        })
+    }
+
+    /// Значение суммы и байты поля для текущих данных; участок и поле должны
+    /// лежать внутри данных
+    pub fn compute<D: SearchSource + ?Sized>(
+        &self,
+        data: &D,
+        progress: &mut dyn Progress,
+    ) -> Result<(u32, Vec<u8>)> {
+        let len = data.len();
+        if self.range.end > len || self.target + self.algorithm.size() > len {
+            bail!(
+                "{} at 0x{:X}: the range or the field is past the end of the data ({} bytes)",
+                self.algorithm.name(),
+                self.target,
+                len
+            );
+        }
+        let value = self.algorithm.compute(data, self.range.clone(), progress)?;
+        Ok((value, self.algorithm.to_bytes(value, self.endian)))
+    }
+}
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// CRC32 (IEEE), продолжает подсчет с `crc` предыдущего куска
+pub fn crc32(crc: u32, bytes: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for &byte in bytes {
+        crc = CRC32_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+/// CRC-16 с полиномом 0x1021 без отражения, продолжает подсчет с `crc`
+pub fn crc16_ccitt(crc: u16, bytes: &[u8]) -> u16 {
+    let mut crc = crc;
+    for &byte in bytes {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
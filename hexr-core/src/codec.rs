@@ -0,0 +1,135 @@
+//! Текстовые кодировки двоичных данных: base64, hex-строка и URL-кодирование.
+//! Встроенные в конфигурации и протоколы данные раскодируются на месте и обратно
+
+use crate::hex;
+use anyhow::{Result, anyhow, bail};
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Codec {
+    Base64,
+    // Hex-цифры без разделителей; при раскодировании пробелы и переводы строк пропускаются
+    Hex,
+    // Процентное кодирование всего, кроме незарезервированных символов RFC 3986
+    Url,
+}
+
+impl Codec {
+    pub fn name(self) -> &'static str {
+        match self {
+            Codec::Base64 => "base64",
+            Codec::Hex => "hex",
+            Codec::Url => "url",
+        }
+    }
+
+    pub fn encode(self, bytes: &[u8]) -> Vec<u8> {
+        match self {
+            Codec::Base64 => encode_base64(bytes),
+            Codec::Hex => bytes
+                .iter()
+                .flat_map(|byte| format!("{:02X}", byte).into_bytes())
+                .collect(),
+            Codec::Url => {
+                let mut encoded = Vec::with_capacity(bytes.len());
+                for &byte in bytes {
+                    if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+                        encoded.push(byte);
+                    } else {
+                        encoded.extend(format!("%{:02X}", byte).into_bytes());
+                    }
+                }
+                encoded
+            }
+        }
+    }
+
+    pub fn decode(self, text: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Codec::Base64 => decode_base64(text),
+            Codec::Hex => std::str::from_utf8(text)
+                .ok()
+                .and_then(hex::parse_hex_text)
+                .ok_or_else(|| anyhow!("Not a hex string: expected an even number of hex digits")),
+            Codec::Url => {
+                let mut decoded = Vec::with_capacity(text.len());
+                let mut idx = 0;
+                while idx < text.len() {
+                    if text[idx] == b'%' {
+                        let digits = text
+                            .get(idx + 1..idx + 3)
+                            .and_then(|digits| std::str::from_utf8(digits).ok())
+                            .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                            .ok_or_else(|| anyhow!("Bad percent escape at byte {}", idx))?;
+                        decoded.push(digits);
+                        idx += 3;
+                    } else {
+                        decoded.push(text[idx]);
+                        idx += 1;
+                    }
+                }
+                Ok(decoded)
+            }
+        }
+    }
+}
+
+fn encode_base64(bytes: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (idx, &byte)| {
+            group | (byte as u32) << (16 - 8 * idx)
+        });
+        for idx in 0..4 {
+            if idx <= chunk.len() {
+                encoded.push(BASE64_ALPHABET[(group >> (18 - 6 * idx)) as usize & 0x3F]);
+            } else {
+                encoded.push(b'=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Понимает и URL-safe алфавит (`-_`), пропускает пробелы и переводы строк;
+/// дополнение `=` необязательно
+fn decode_base64(text: &[u8]) -> Result<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(text.len() / 4 * 3);
+    let mut group = 0u32;
+    let mut bits = 0;
+    let mut padding = false;
+
+    for (idx, &ch) in text.iter().enumerate() {
+        let value = match ch {
+            b'A'..=b'Z' => ch - b'A',
+            b'a'..=b'z' => ch - b'a' + 26,
+            b'0'..=b'9' => ch - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => {
+                padding = true;
+                continue;
+            }
+            ch if ch.is_ascii_whitespace() => continue,
+            _ => bail!("Not base64: unexpected byte 0x{:02X} at {}", ch, idx),
+        };
+        if padding {
+            bail!("Not base64: data after '=' padding at {}", idx);
+        }
+
+        group = group << 6 | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((group >> bits) as u8);
+        }
+    }
+
+    // Остаток из 6 бит не может быть целым байтом: данные обрезаны
+    if bits >= 6 {
+        bail!("Not base64: the data is truncated");
+    }
+    Ok(decoded)
+}
@@ -0,0 +1,111 @@
+//! Сжатые потоки внутри файлов: распознавание формата по заголовку, распаковка
+//! с подсчетом занятых потоком байт и обратное сжатие
+
+use anyhow::{Context, Result, bail};
+use std::io::{Read, Write};
+
+const GZIP_MAGIC: &[u8] = &[0x1F, 0x8B];
+const LZ4_MAGIC: &[u8] = &[0x04, 0x22, 0x4D, 0x18];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xB5, 0x2F, 0xFD];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Zlib,
+    Gzip,
+    // Кадровый формат LZ4 (lz4 -c), не сырые блоки
+    Lz4,
+    Zstd,
+}
+
+impl Format {
+    pub const ALL: [Format; 4] = [Format::Zlib, Format::Gzip, Format::Lz4, Format::Zstd];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Zlib => "zlib",
+            Format::Gzip => "gzip",
+            Format::Lz4 => "lz4",
+            Format::Zstd => "zstd",
+        }
+    }
+
+    /// Формат по первым байтам потока
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(GZIP_MAGIC) {
+            Some(Format::Gzip)
+        } else if bytes.starts_with(LZ4_MAGIC) {
+            Some(Format::Lz4)
+        } else if bytes.starts_with(ZSTD_MAGIC) {
+            Some(Format::Zstd)
+        } else if is_zlib_header(bytes) {
+            Some(Format::Zlib)
+        } else {
+            None
+        }
+    }
+
+    /// Распаковывает поток с начала `bytes`; данные после его конца не читаются.
+    /// Возвращает распакованные данные и длину потока
+    pub fn decompress(self, bytes: &[u8]) -> Result<(Vec<u8>, usize)> {
+        let mut input = bytes;
+        let mut output = Vec::new();
+        let result = match self {
+            Format::Zlib => flate2::bufread::ZlibDecoder::new(&mut input).read_to_end(&mut output),
+            Format::Gzip => flate2::bufread::GzDecoder::new(&mut input).read_to_end(&mut output),
+            Format::Lz4 => lz4_flex::frame::FrameDecoder::new(&mut input).read_to_end(&mut output),
+            Format::Zstd => ruzstd::decoding::StreamingDecoder::new(&mut input)
+                .map_err(std::io::Error::other)?
+                .read_to_end(&mut output),
+        };
+        result.with_context(|| format!("Not a valid {} stream", self.name()))?;
+        Ok((output, bytes.len() - input.len()))
+    }
+
+    pub fn compress(self, bytes: &[u8]) -> Result<Vec<u8>> {
+        Ok(match self {
+            Format::Zlib => {
+                let mut encoder =
+                    flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(bytes)?;
+                encoder.finish()?
+            }
+            Format::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(bytes)?;
+                encoder.finish()?
+            }
+            Format::Lz4 => {
+                let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
+                encoder.write_all(bytes)?;
+                encoder.finish()?
+            }
+            Format::Zstd => ruzstd::encoding::compress_to_vec(
+                bytes,
+                ruzstd::encoding::CompressionLevel::Fastest,
+            ),
+        })
+    }
+
+    pub fn parse(name: &str) -> Result<Self> {
+        let name = name.trim().to_ascii_lowercase();
+        match Self::ALL.into_iter().find(|format| format.name() == name) {
+            Some(format) => Ok(format),
+            None => bail!(
+                "Unknown format '{}', expected zlib, gzip, lz4 or zstd",
+                name
+            ),
+        }
+    }
+}
+
+/// Заголовок zlib: метод deflate, окно до 32 KiB, контрольные биты и без словаря
+fn is_zlib_header(bytes: &[u8]) -> bool {
+    let [cmf, flg, ..] = *bytes else {
+        return false;
+    };
+    cmf & 0x0F == 8
+        && cmf >> 4 <= 7
+        && flg & 0x20 == 0
+        && (cmf as u16 * 256 + flg as u16).is_multiple_of(31)
+}
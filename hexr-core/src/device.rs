@@ -0,0 +1,110 @@
+//! Блочные устройства и образы дисков: размер и размер сектора устройства,
+//! запись правок на место целыми секторами
+
+use crate::buffer::PieceTable;
+use anyhow::{Result, bail};
+use std::fs::{File, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::Path;
+
+/// Размер сектора, если устройство его не сообщает
+pub const DEFAULT_SECTOR_SIZE: usize = 512;
+
+/// Сектор образа CD/DVD
+const ISO_SECTOR_SIZE: usize = 2048;
+
+/// Расширения сырых образов дисков, у которых в строке статуса показывается номер сектора
+const IMAGE_EXTENSIONS: &[&str] = &["img", "iso", "dd", "raw"];
+
+#[cfg(unix)]
+pub fn is_block_device(path: &str) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    std::fs::metadata(path).is_ok_and(|metadata| metadata.file_type().is_block_device())
+}
+
+#[cfg(not(unix))]
+pub fn is_block_device(_path: &str) -> bool {
+    false
+}
+
+/// Размер устройства: у блочных устройств длина в метаданных нулевая, она узнается переходом в конец
+pub fn device_len(file: &mut File) -> Result<u64> {
+    let len = file.seek(SeekFrom::End(0))?;
+    file.seek(SeekFrom::Start(0))?;
+    Ok(len)
+}
+
+/// Размер сектора для устройства или образа диска; None у обычных файлов
+pub fn sector_size(path: &str) -> Option<usize> {
+    if is_block_device(path) {
+        return Some(logical_block_size(path).unwrap_or(DEFAULT_SECTOR_SIZE));
+    }
+    let extension = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "iso" => Some(ISO_SECTOR_SIZE),
+        extension if IMAGE_EXTENSIONS.contains(&extension) => Some(DEFAULT_SECTOR_SIZE),
+        _ => None,
+    }
+}
+
+/// Логический размер блока из sysfs; у раздела очередь запросов - у родительского диска
+#[cfg(target_os = "linux")]
+fn logical_block_size(path: &str) -> Option<usize> {
+    use std::os::unix::fs::MetadataExt;
+    let rdev = std::fs::metadata(path).ok()?.rdev();
+    let major = ((rdev >> 8) & 0xFFF) | ((rdev >> 32) & !0xFFF);
+    let minor = (rdev & 0xFF) | ((rdev >> 12) & !0xFF);
+
+    let device = format!("/sys/dev/block/{}:{}", major, minor);
+    ["queue", "../queue"].iter().find_map(|queue| {
+        let text =
+            std::fs::read_to_string(format!("{}/{}/logical_block_size", device, queue)).ok()?;
+        text.trim().parse().ok().filter(|&size: &usize| size > 0)
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn logical_block_size(_path: &str) -> Option<usize> {
+    None
+}
+
+/// Записывает измененные диапазоны `data` в устройство на место, расширяя каждый
+/// до границ секторов: устройство читает и пишет только целые секторы
+pub fn write_sectors(
+    path: &str,
+    data: &PieceTable,
+    ranges: &[Range<usize>],
+    sector_size: usize,
+) -> Result<()> {
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    let len = device_len(&mut file)? as usize;
+    if data.len() != len {
+        bail!(
+            "Device size is {} bytes, the data is {}: block devices cannot grow or shrink",
+            len,
+            data.len()
+        );
+    }
+
+    let mut sectors: Vec<Range<usize>> = Vec::new();
+    for range in ranges {
+        let start = range.start / sector_size * sector_size;
+        let end = range
+            .end
+            .div_ceil(sector_size)
+            .saturating_mul(sector_size)
+            .min(len);
+        match sectors.last_mut() {
+            Some(last) if start <= last.end => last.end = last.end.max(end),
+            _ => sectors.push(start..end),
+        }
+    }
+
+    for range in sectors {
+        file.seek(SeekFrom::Start(range.start as u64))?;
+        file.write_all(&data.range(range))?;
+    }
+    file.sync_all()?;
+    Ok(())
+}
@@ -0,0 +1,172 @@
+//! Дизассемблирование байт под курсором через capstone
+
+use anyhow::{Result, anyhow, bail};
+use capstone::Capstone;
+use capstone::arch::{self, BuildsCapstone, BuildsCapstoneExtraMode, BuildsCapstoneSyntax};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Arch {
+    X86,
+    X86_64,
+    Arm,
+    Thumb,
+    Aarch64,
+    RiscV32,
+    RiscV64,
+}
+
+impl Arch {
+    pub const ALL: [Arch; 7] = [
+        Arch::X86,
+        Arch::X86_64,
+        Arch::Arm,
+        Arch::Thumb,
+        Arch::Aarch64,
+        Arch::RiscV32,
+        Arch::RiscV64,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Arch::X86 => "x86",
+            Arch::X86_64 => "x86-64",
+            Arch::Arm => "arm",
+            Arch::Thumb => "thumb",
+            Arch::Aarch64 => "aarch64",
+            Arch::RiscV32 => "riscv32",
+            Arch::RiscV64 => "riscv64",
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Self> {
+        let name = name.trim().to_ascii_lowercase();
+        match Self::ALL.into_iter().find(|arch| arch.name() == name) {
+            Some(arch) => Ok(arch),
+            None => bail!(
+                "Unknown architecture '{}', expected {}",
+                name,
+                Self::ALL.map(Arch::name).join(", ")
+            ),
+        }
+    }
+
+    /// Архитектура по описанию типа файла из magic::identify ("ELF 64-bit LSB executable, x86-64")
+    pub fn detect(file_type: &str) -> Option<Self> {
+        let machine = file_type.rsplit_once(", ")?.1;
+        let is_64 = file_type.contains("64-bit") || file_type.starts_with("PE32+");
+        Some(match machine {
+            "x86" => Arch::X86,
+            "x86-64" => Arch::X86_64,
+            "ARM" => Arch::Arm,
+            "AArch64" => Arch::Aarch64,
+            "RISC-V" if is_64 => Arch::RiscV64,
+            "RISC-V" => Arch::RiscV32,
+            _ => return None,
+        })
+    }
+
+    /// Следующая архитектура по кругу
+    pub fn next(self) -> Self {
+        let idx = Self::ALL.iter().position(|&arch| arch == self).unwrap_or(0);
+        Self::ALL[(idx + 1) % Self::ALL.len()]
+    }
+
+    /// Шаг, на который сдвигается разбор после байт, не образующих инструкцию
+    fn alignment(self) -> usize {
+        match self {
+            Arch::X86 | Arch::X86_64 => 1,
+            Arch::Thumb | Arch::RiscV32 | Arch::RiscV64 => 2,
+            Arch::Arm | Arch::Aarch64 => 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Instruction {
+    pub address: u64,
+    pub bytes: Vec<u8>,
+    // Мнемоника с операндами; для неразобранных байт `(bad)`
+    pub text: String,
+}
+
+pub struct Disassembler {
+    arch: Arch,
+    capstone: Capstone,
+}
+
+impl Disassembler {
+    pub fn new(arch: Arch) -> Result<Self> {
+        let builder = Capstone::new();
+        let capstone = match arch {
+            Arch::X86 | Arch::X86_64 => builder
+                .x86()
+                .mode(if arch == Arch::X86 {
+                    arch::x86::ArchMode::Mode32
+                } else {
+                    arch::x86::ArchMode::Mode64
+                })
+                .syntax(arch::x86::ArchSyntax::Intel)
+                .build(),
+            Arch::Arm | Arch::Thumb => builder
+                .arm()
+                .mode(if arch == Arch::Arm {
+                    arch::arm::ArchMode::Arm
+                } else {
+                    arch::arm::ArchMode::Thumb
+                })
+                .build(),
+            Arch::Aarch64 => builder.arm64().mode(arch::arm64::ArchMode::Arm).build(),
+            // Сжатые инструкции (расширение C) есть почти во всех реальных прошивках
+            Arch::RiscV32 | Arch::RiscV64 => builder
+                .riscv()
+                .mode(if arch == Arch::RiscV32 {
+                    arch::riscv::ArchMode::RiscV32
+                } else {
+                    arch::riscv::ArchMode::RiscV64
+                })
+                .extra_mode(std::iter::once(arch::riscv::ArchExtraMode::RiscVC))
+                .build(),
+        }
+        .map_err(|err| anyhow!("Cannot initialize disassembler: {}", err))?;
+        Ok(Self { arch, capstone })
+    }
+
+    pub fn arch(&self) -> Arch {
+        self.arch
+    }
+
+    /// До `limit` инструкций подряд с начала `bytes`, который лежит по адресу `address`.
+    /// Байты, которые не разбираются, выводятся как `(bad)`, и разбор продолжается за ними
+    pub fn disassemble(&self, bytes: &[u8], address: u64, limit: usize) -> Vec<Instruction> {
+        let mut instructions = Vec::new();
+        let mut offset = 0;
+        while offset < bytes.len() && instructions.len() < limit {
+            let rest = &bytes[offset..];
+            let address = address + offset as u64;
+            let decoded = self.capstone.disasm_count(rest, address, 1).ok();
+            let instruction = match decoded.as_ref().and_then(|decoded| decoded.iter().next()) {
+                Some(insn) => {
+                    let text = match insn.op_str() {
+                        Some(operands) if !operands.is_empty() => {
+                            format!("{} {}", insn.mnemonic().unwrap_or(""), operands)
+                        }
+                        _ => insn.mnemonic().unwrap_or("").to_string(),
+                    };
+                    Instruction {
+                        address,
+                        bytes: insn.bytes().to_vec(),
+                        text,
+                    }
+                }
+                None => Instruction {
+                    address,
+                    bytes: rest[..self.arch.alignment().min(rest.len())].to_vec(),
+                    text: "(bad)".to_string(),
+                },
+            };
+            offset += instruction.bytes.len().max(1);
+            instructions.push(instruction);
+        }
+        instructions
+    }
+}
@@ -0,0 +1,234 @@
+//! Редактируемые данные вместе с историей правок. Каждая правка записывается в историю,
+//! поэтому ее можно отменить, как в редакторе
+
+use crate::buffer::{Buffer, PieceTable};
+use crate::search::{self, SearchPattern};
+use crate::undo_redo::{Branches, EditOperation, UndoRedoStack};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// Запись журнала правок: операция и направление, в котором она применена к данным.
+/// По журналу правки можно повторить поверх исходного файла после сбоя
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub operation: EditOperation,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub undone: bool,
+}
+
+impl JournalEntry {
+    /// Применяет запись к данным; false, если операция выходит за их конец
+    pub fn apply(&self, data: &mut PieceTable) -> bool {
+        if !self.operation.fits(data.len(), self.undone) {
+            return false;
+        }
+        if self.undone {
+            self.operation.undo(data);
+        } else {
+            self.operation.redo(data);
+        }
+        true
+    }
+}
+
+/// Содержимое файла и дерево отмены. Позиции и диапазоны должны лежать внутри данных,
+/// вставка - не дальше их конца
+pub struct Document {
+    // Arc позволяет фоновому поиску читать снимок без копирования
+    data: Arc<PieceTable>,
+    history: UndoRedoStack,
+    // Примененные с прошлого take_journal() операции; None - журнал не ведется
+    journal: Option<Vec<JournalEntry>>,
+}
+
+impl Document {
+    pub fn new(buffer: Buffer) -> Self {
+        Self {
+            data: Arc::new(PieceTable::new(buffer)),
+            history: UndoRedoStack::default(),
+            journal: None,
+        }
+    }
+
+    /// Открывает файл; большие файлы отображаются в память
+    pub fn open(path: &str) -> Result<Self> {
+        Ok(Self::new(Buffer::open(path)?))
+    }
+
+    pub fn data(&self) -> &PieceTable {
+        &self.data
+    }
+
+    /// Неизменяемый снимок текущих данных для фоновой работы; правки его не меняют
+    pub fn snapshot(&self) -> Arc<PieceTable> {
+        Arc::clone(&self.data)
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Байты диапазона; часть за концом данных отбрасывается
+    pub fn range(&self, range: Range<usize>) -> Cow<'_, [u8]> {
+        self.data.range(range)
+    }
+
+    /// Включает журнал правок
+    pub fn start_journal(&mut self) {
+        self.journal.get_or_insert_with(Vec::new);
+    }
+
+    pub fn stop_journal(&mut self) {
+        self.journal = None;
+    }
+
+    /// Операции, примененные с прошлого вызова
+    pub fn take_journal(&mut self) -> Vec<JournalEntry> {
+        self.journal.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    fn log(&mut self, operation: &EditOperation, undone: bool) {
+        if let Some(journal) = &mut self.journal {
+            journal.push(JournalEntry { operation: operation.clone(), undone });
+        }
+    }
+
+    /// Изменяемый доступ к данным; если снимок читает фоновый поиск, копируется только
+    /// таблица кусков, исходное содержимое общее
+    fn data_mut(&mut self) -> &mut PieceTable {
+        Arc::make_mut(&mut self.data)
+    }
+
+    pub fn insert(&mut self, position: usize, bytes: &[u8]) {
+        if bytes.is_empty() {
+            return;
+        }
+        self.data_mut().insert(position, bytes);
+        let operation = EditOperation::new_insert_bytes(position, bytes.to_vec());
+        self.log(&operation, false);
+        self.history.push(operation);
+    }
+
+    /// Удаляет диапазон и возвращает удаленные байты
+    pub fn delete(&mut self, range: Range<usize>) -> Vec<u8> {
+        let position = range.start;
+        let old_values = self.data_mut().delete(range);
+        if !old_values.is_empty() {
+            let operation = EditOperation::new_delete_bytes(position, old_values.clone());
+            self.log(&operation, false);
+            self.history.push(operation);
+        }
+        old_values
+    }
+
+    /// Заменяет диапазон байтами любой длины и возвращает прежние байты
+    pub fn replace(&mut self, range: Range<usize>, bytes: &[u8]) -> Vec<u8> {
+        let position = range.start;
+        let old_values = self.data_mut().replace(range, bytes);
+        let operation = EditOperation::new_replace_bytes(position, old_values.clone(), bytes.to_vec());
+        self.log(&operation, false);
+        self.history.push(operation);
+        old_values
+    }
+
+    /// Заменяет один байт; `merge` дописывает замену к прошлому шагу отмены, если тот
+    /// менял этот же байт (правки битов одного байта)
+    pub fn set_byte(&mut self, position: usize, value: u8, merge: bool) {
+        let old_value = self.data[position];
+        self.data_mut().replace(position..position + 1, &[value]);
+
+        let operation = EditOperation::new_replace_byte(position, old_value, value);
+        self.log(&operation, false);
+        if merge {
+            self.history.push_merged(operation);
+        } else {
+            self.history.push(operation);
+        }
+    }
+
+    /// Правки до парного end_group() отменяются одним шагом; группы могут вкладываться
+    pub fn begin_group(&mut self) {
+        self.history.begin_group();
+    }
+
+    pub fn end_group(&mut self) {
+        self.history.end_group();
+    }
+
+    /// Отменяет последний шаг; false, если отменять нечего
+    pub fn undo(&mut self) -> bool {
+        let Some(step) = self.history.undo() else {
+            return false;
+        };
+        for operation in step.iter().rev() {
+            operation.undo(self.data_mut());
+            self.log(operation, true);
+        }
+        true
+    }
+
+    /// Повторяет шаг по активной ветке; false, если повторять нечего
+    pub fn redo(&mut self) -> bool {
+        let Some(step) = self.history.redo() else {
+            return false;
+        };
+        for operation in &step {
+            operation.redo(self.data_mut());
+            self.log(operation, false);
+        }
+        true
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.history.can_undo()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.history.can_redo()
+    }
+
+    /// Ветки redo в текущей точке; `None`, если их меньше двух
+    pub fn branches(&self) -> Option<Branches> {
+        self.history.branches()
+    }
+
+    /// Выбирает соседнюю ветку для redo; false, если выбирать не из чего
+    pub fn switch_branch(&mut self, forward: bool) -> bool {
+        self.history.switch_branch(forward)
+    }
+
+    /// Данные записаны на диск: `saved` становится исходным содержимым, а история
+    /// остается, так что отмена снова делает данные измененными
+    pub fn mark_saved(&mut self, saved: Buffer) {
+        self.data = Arc::new(PieceTable::new(saved));
+        self.history.mark_saved();
+    }
+
+    /// История стоит на сохраненном состоянии
+    pub fn is_saved(&self) -> bool {
+        self.history.is_saved()
+    }
+
+    /// Подменяет данные целиком (перечитанный файл, откат), история начинается заново
+    pub fn reset(&mut self, data: PieceTable) {
+        self.data = Arc::new(data);
+        self.history.clear();
+    }
+
+    /// Первое совпадение начиная с `start`
+    pub fn find(&self, pattern: &SearchPattern, start: usize) -> Option<usize> {
+        search::find_from(&*self.data, pattern, start)
+    }
+
+    /// Все совпадения по порядку
+    pub fn find_all(&self, pattern: &SearchPattern) -> Vec<usize> {
+        search::find_all_in(&*self.data, pattern)
+    }
+}
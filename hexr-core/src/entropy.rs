@@ -0,0 +1,93 @@
+//! Энтропия Шеннона участков данных: сжатые и зашифрованные области выделяются почти
+//! предельными 8 битами на байт
+
+use crate::progress::Progress;
+use crate::search::SearchSource;
+use anyhow::Result;
+
+/// Окно, по которому оценивается энтропия при поиске областей
+pub const WINDOW: usize = 512;
+
+/// Окно сдвигается на половину своей длины, чтобы граница области не терялась между окнами
+const STEP: usize = WINDOW / 2;
+
+/// Начиная с этого значения окно считается сжатым или зашифрованным: случайные 512 байт
+/// дают около 7.6 бита, машинный код и текст - не больше 6.5
+pub const HIGH_ENTROPY: f64 = 7.2;
+
+/// Энтропия Шеннона в битах на байт: 0 для одинаковых байт, 8 для случайных
+pub fn entropy(bytes: &[u8]) -> f64 {
+    let mut counts = [0u64; 256];
+    for &byte in bytes {
+        counts[byte as usize] += 1;
+    }
+    from_counts(&counts)
+}
+
+/// Энтропия по частотам значений байт
+pub fn from_counts(counts: &[u64; 256]) -> f64 {
+    let total: u64 = counts.iter().sum();
+    if total == 0 {
+        return 0.0;
+    }
+
+    let total = total as f64;
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / total;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Начало следующей (или предыдущей) области с высокой энтропией и энтропия ее первого
+/// окна; область, в которой стоит `from`, пропускается
+pub fn find_high_entropy<D: SearchSource + ?Sized>(
+    data: &D,
+    from: usize,
+    forward: bool,
+    progress: &mut dyn Progress,
+) -> Result<Option<(usize, f64)>> {
+    let windows = data.len().div_ceil(STEP);
+    let window_entropy = |index: usize| {
+        let start = index * STEP;
+        entropy(&data.range(start..(start + WINDOW).min(data.len())))
+    };
+
+    let mut index = from / STEP;
+    if index >= windows {
+        return Ok(None);
+    }
+    let (first, total) = (index, if forward { windows - index } else { index });
+    let mut high = |index: usize| -> Result<bool> {
+        progress.update(index.abs_diff(first), total)?;
+        Ok(window_entropy(index) >= HIGH_ENTROPY)
+    };
+
+    if forward {
+        while index < windows && high(index)? {
+            index += 1;
+        }
+        while index < windows && !high(index)? {
+            index += 1;
+        }
+        Ok((index < windows).then(|| (index * STEP, window_entropy(index))))
+    } else {
+        while index > 0 && high(index)? {
+            index -= 1;
+        }
+        while index > 0 && !high(index)? {
+            index -= 1;
+        }
+        if !high(index)? {
+            return Ok(None);
+        }
+        // Переход к началу найденной области
+        while index > 0 && high(index - 1)? {
+            index -= 1;
+        }
+        Ok(Some((index * STEP, window_entropy(index))))
+    }
+}
@@ -0,0 +1,746 @@
+//! Встроенные разборщики форматов: заголовки ELF и PE с таблицами секций, блоки PNG
+//! и каталог ZIP. Строят такую же разметку, как шаблоны, но умеют то, чего нет
+//! в языке шаблонов: переходы по смещениям из заголовков и имена секций из таблиц строк
+
+use crate::checksum;
+use crate::magic;
+use crate::search::SearchSource;
+use crate::template::{Layout, LayoutBuilder};
+use anyhow::{Result, anyhow, bail};
+use std::ops::Range;
+
+/// Конец каталога ZIP ищется в стольких последних байтах: запись плюс комментарий
+const ZIP_EOCD_SEARCH: usize = 22 + 0xFFFF;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Elf,
+    Pe,
+    Png,
+    Zip,
+}
+
+impl Format {
+    pub const ALL: [Format; 4] = [Format::Elf, Format::Pe, Format::Png, Format::Zip];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Elf => "elf",
+            Format::Pe => "pe",
+            Format::Png => "png",
+            Format::Zip => "zip",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        let name = name.trim().to_ascii_lowercase();
+        Self::ALL.into_iter().find(|format| format.name() == name)
+    }
+
+    /// Формат данных по сигнатуре в начале `head`
+    pub fn detect(head: &[u8]) -> Option<Self> {
+        let name = magic::identify(head)?;
+        if name.starts_with("ELF") {
+            Some(Format::Elf)
+        } else if name.starts_with("PE32") {
+            Some(Format::Pe)
+        } else if name == "PNG image" {
+            Some(Format::Png)
+        } else if name.starts_with("ZIP archive") {
+            Some(Format::Zip)
+        } else {
+            None
+        }
+    }
+
+    /// Разметка `range` данных; при ошибке - поля до нее
+    pub fn layout<D: SearchSource + ?Sized>(self, data: &D, range: Range<usize>) -> Layout {
+        let mut parser = Parser {
+            data,
+            base: range.start,
+            end: range.end.min(data.len()),
+            little: true,
+            builder: LayoutBuilder::default(),
+        };
+        let result = match self {
+            Format::Elf => parser.elf(),
+            Format::Pe => parser.pe(),
+            Format::Png => parser.png(),
+            Format::Zip => parser.zip(),
+        };
+        let error = result.err().map(|e| format!("{:#}", e));
+        parser.builder.finish(error)
+    }
+}
+
+struct Parser<'a, D: ?Sized> {
+    data: &'a D,
+    // Смещения в заголовках отсчитываются от начала размечаемого участка
+    base: usize,
+    end: usize,
+    little: bool,
+    builder: LayoutBuilder,
+}
+
+impl<D: SearchSource + ?Sized> Parser<'_, D> {
+    fn bytes(&self, offset: usize, len: usize) -> Result<Vec<u8>> {
+        let start = self.base.checked_add(offset);
+        match start.and_then(|start| start.checked_add(len)) {
+            Some(end) if end <= self.end => Ok(self.data.range(end - len..end).into_owned()),
+            _ => bail!(
+                "{} bytes at 0x{:X} are past the end of the data",
+                len,
+                offset
+            ),
+        }
+    }
+
+    fn uint(&self, offset: usize, size: usize) -> Result<u64> {
+        let bytes = self.bytes(offset, size)?;
+        let fold = |value: u64, &byte: &u8| value << 8 | byte as u64;
+        Ok(match self.little {
+            true => bytes.iter().rev().fold(0, fold),
+            false => bytes.iter().fold(0, fold),
+        })
+    }
+
+    /// Сколько из `len` байт от `offset` есть в данных; `None`, если `offset` за их концом
+    fn available(&self, offset: usize, len: usize) -> Option<usize> {
+        let start = self.base.checked_add(offset)?;
+        (start <= self.end).then(|| len.min(self.end - start))
+    }
+
+    fn range(&self, offset: usize, len: usize) -> Range<usize> {
+        self.base + offset..self.base + offset + len
+    }
+
+    /// Целое поле; `names` дает имя значения (тип секции, машина)
+    fn named(
+        &mut self,
+        name: &str,
+        offset: usize,
+        size: usize,
+        names: fn(u64) -> Option<&'static str>,
+    ) -> Result<u64> {
+        let value = self.uint(offset, size)?;
+        let text = match names(value) {
+            Some(label) => format!("{} ({})", value, label),
+            None if value >= 10 => format!("{} (0x{:X})", value, value),
+            None => value.to_string(),
+        };
+        let type_name = format!("u{}{}", size * 8, if self.little { "le" } else { "be" });
+        self.builder
+            .leaf(name, type_name, self.range(offset, size), text)?;
+        Ok(value)
+    }
+
+    fn number(&mut self, name: &str, offset: usize, size: usize) -> Result<u64> {
+        self.named(name, offset, size, |_| None)
+    }
+
+    /// Строка фиксированной длины; нули в конце не показываются
+    fn text(&mut self, name: &str, offset: usize, len: usize) -> Result<String> {
+        let bytes = self.bytes(offset, len)?;
+        let text = String::from_utf8_lossy(&bytes)
+            .trim_end_matches('\0')
+            .to_string();
+        self.builder.leaf(
+            name,
+            format!("char[{}]", len),
+            self.range(offset, len),
+            format!("\"{}\"", text.escape_default()),
+        )?;
+        Ok(text)
+    }
+
+    fn blob(&mut self, name: &str, type_name: &str, offset: usize, len: usize) -> Result<()> {
+        let preview = self.bytes(offset, len.min(16))?;
+        let mut value: Vec<String> = preview.iter().map(|byte| format!("{:02X}", byte)).collect();
+        if len > preview.len() {
+            value.push(format!("... ({} bytes)", len));
+        }
+        self.builder
+            .leaf(name, type_name, self.range(offset, len), value.join(" "))
+    }
+
+    /// Строка с нулем в конце из таблицы строк
+    fn c_string(&self, offset: usize) -> String {
+        let available = self.available(offset, 256).unwrap_or(0);
+        let bytes = self.bytes(offset, available).unwrap_or_default();
+        let len = bytes
+            .iter()
+            .position(|&byte| byte == 0)
+            .unwrap_or(bytes.len());
+        String::from_utf8_lossy(&bytes[..len]).into_owned()
+    }
+
+    fn elf(&mut self) -> Result<()> {
+        if self.bytes(0, 4)? != b"\x7FELF" {
+            bail!("Not an ELF file: no \\x7FELF signature");
+        }
+        let word = match self.bytes(4, 1)?[0] {
+            1 => 4,
+            2 => 8,
+            class => bail!("Unknown ELF class {}", class),
+        };
+        self.little = self.bytes(5, 1)?[0] != 2;
+
+        self.builder.begin("header", "Elf_Ehdr")?;
+        self.blob("magic", "u8[4]", 0, 4)?;
+        self.named("class", 4, 1, |value| match value {
+            1 => Some("ELF32"),
+            2 => Some("ELF64"),
+            _ => None,
+        })?;
+        self.named("data", 5, 1, |value| match value {
+            1 => Some("little-endian"),
+            2 => Some("big-endian"),
+            _ => None,
+        })?;
+        self.number("ident_version", 6, 1)?;
+        self.named("osabi", 7, 1, |value| match value {
+            0 => Some("System V"),
+            3 => Some("Linux"),
+            9 => Some("FreeBSD"),
+            _ => None,
+        })?;
+        self.number("abiversion", 8, 1)?;
+        self.blob("padding", "u8[7]", 9, 7)?;
+        self.named("type", 16, 2, elf_type)?;
+        self.named("machine", 18, 2, elf_machine)?;
+        self.number("version", 20, 4)?;
+        self.number("entry", 24, word)?;
+        let phoff = self.number("phoff", 24 + word, word)? as usize;
+        let shoff = self.number("shoff", 24 + 2 * word, word)? as usize;
+        self.number("flags", 24 + 3 * word, 4)?;
+        self.number("ehsize", 28 + 3 * word, 2)?;
+        let phentsize = self.number("phentsize", 30 + 3 * word, 2)? as usize;
+        let phnum = self.number("phnum", 32 + 3 * word, 2)? as usize;
+        let shentsize = self.number("shentsize", 34 + 3 * word, 2)? as usize;
+        let shnum = self.number("shnum", 36 + 3 * word, 2)? as usize;
+        let shstrndx = self.number("shstrndx", 38 + 3 * word, 2)? as usize;
+        self.builder.end();
+
+        // Дальше смещения таблиц складываются с номерами записей
+        let len = self.end - self.base;
+        if (phnum > 0 && phoff > len) || (shnum > 0 && shoff > len) {
+            bail!(
+                "The header tables at 0x{:X} and 0x{:X} are past the end of the data",
+                phoff,
+                shoff
+            );
+        }
+
+        if phnum > 0 {
+            self.builder
+                .begin("program_headers", format!("Elf_Phdr[{}]", phnum))?;
+            for idx in 0..phnum {
+                let at = phoff + idx * phentsize;
+                let kind = elf_segment_type(self.uint(at, 4)?).unwrap_or("segment");
+                self.builder
+                    .begin(format!("[{}] {}", idx, kind), "Elf_Phdr")?;
+                self.named("type", at, 4, elf_segment_type)?;
+                // В ELF64 флаги стоят сразу за типом, в ELF32 - после размеров
+                let fields = match word {
+                    8 => [
+                        ("flags", 4, 4),
+                        ("offset", 8, 8),
+                        ("vaddr", 16, 8),
+                        ("paddr", 24, 8),
+                        ("filesz", 32, 8),
+                        ("memsz", 40, 8),
+                        ("align", 48, 8),
+                    ],
+                    _ => [
+                        ("offset", 4, 4),
+                        ("vaddr", 8, 4),
+                        ("paddr", 12, 4),
+                        ("filesz", 16, 4),
+                        ("memsz", 20, 4),
+                        ("flags", 24, 4),
+                        ("align", 28, 4),
+                    ],
+                };
+                for (name, offset, size) in fields {
+                    self.number(name, at + offset, size)?;
+                }
+                self.builder.end();
+            }
+            self.builder.end();
+        }
+
+        if shnum == 0 {
+            return Ok(());
+        }
+
+        // Имена секций - в таблице строк, на которую указывает shstrndx
+        let section = |parser: &Self, idx: usize| -> Result<(usize, usize, usize)> {
+            let at = shoff + idx * shentsize;
+            Ok((
+                parser.uint(at, 4)? as usize,
+                parser.uint(at + 8 + 2 * word, word)? as usize,
+                parser.uint(at + 8 + 3 * word, word)? as usize,
+            ))
+        };
+        let strtab = section(self, shstrndx).map(|(_, offset, _)| offset).ok();
+        let names: Vec<String> = (0..shnum)
+            .map(|idx| {
+                // Оба смещения из файла: имя, не помещающееся в адреса, пропускается
+                let name = section(self, idx).ok().zip(strtab);
+                name.and_then(|((name, _, _), strtab)| strtab.checked_add(name))
+                    .map(|offset| self.c_string(offset))
+                    .unwrap_or_default()
+            })
+            .collect();
+        let label = |idx: usize| match names[idx].as_str() {
+            "" => format!("[{}]", idx),
+            name => name.to_string(),
+        };
+
+        self.builder
+            .begin("section_headers", format!("Elf_Shdr[{}]", shnum))?;
+        let mut contents = Vec::new();
+        for idx in 0..shnum {
+            let at = shoff + idx * shentsize;
+            self.builder.begin(label(idx), "Elf_Shdr")?;
+            self.number("name", at, 4)?;
+            let kind = self.named("type", at + 4, 4, elf_section_type)?;
+            self.number("flags", at + 8, word)?;
+            let addr = self.number("addr", at + 8 + word, word)?;
+            let offset = self.number("offset", at + 8 + 2 * word, word)? as usize;
+            let size = self.number("size", at + 8 + 3 * word, word)? as usize;
+            self.number("link", at + 8 + 4 * word, 4)?;
+            self.number("info", at + 12 + 4 * word, 4)?;
+            self.number("addralign", at + 16 + 4 * word, word)?;
+            self.number("entsize", at + 16 + 5 * word, word)?;
+            self.builder.end();
+
+            // SHT_NOBITS (.bss) места в файле не занимает
+            if kind != 8 && size > 0 {
+                contents.push((label(idx), offset, size, addr));
+            }
+        }
+        self.builder.end();
+
+        self.builder.begin("sections", "contents")?;
+        for (name, offset, size, addr) in contents {
+            let Some(size) = self.available(offset, size) else {
+                continue;
+            };
+            let value = format!("address 0x{:X}, {} bytes", addr, size);
+            self.builder
+                .leaf(name, "section", self.range(offset, size), value)?;
+        }
+        self.builder.end();
+        Ok(())
+    }
+
+    fn pe(&mut self) -> Result<()> {
+        if self.bytes(0, 2)? != b"MZ" {
+            bail!("Not a PE file: no MZ signature");
+        }
+        self.little = true;
+
+        self.builder.begin("dos_header", "IMAGE_DOS_HEADER")?;
+        self.text("e_magic", 0, 2)?;
+        let pe = self.number("e_lfanew", 0x3C, 4)? as usize;
+        self.builder.end();
+
+        self.builder.begin("nt_headers", "IMAGE_NT_HEADERS")?;
+        if self.text("signature", pe, 4)? != "PE" {
+            bail!("No PE signature at 0x{:X}", pe);
+        }
+
+        let file = pe + 4;
+        self.builder.begin("file_header", "IMAGE_FILE_HEADER")?;
+        self.named("machine", file, 2, pe_machine)?;
+        let sections = self.number("number_of_sections", file + 2, 2)? as usize;
+        self.number("time_date_stamp", file + 4, 4)?;
+        self.number("pointer_to_symbol_table", file + 8, 4)?;
+        self.number("number_of_symbols", file + 12, 4)?;
+        let optional_size = self.number("size_of_optional_header", file + 16, 2)? as usize;
+        self.number("characteristics", file + 18, 2)?;
+        self.builder.end();
+
+        let optional = file + 20;
+        if optional_size > 0 {
+            self.builder
+                .begin("optional_header", "IMAGE_OPTIONAL_HEADER")?;
+            let magic = self.named("magic", optional, 2, |value| match value {
+                0x10B => Some("PE32"),
+                0x20B => Some("PE32+"),
+                _ => None,
+            })?;
+            // В PE32+ база образа и размеры стека и кучи восьмибайтовые
+            let wide = magic == 0x20B;
+            let word = if wide { 8 } else { 4 };
+            self.number("address_of_entry_point", optional + 16, 4)?;
+            self.number("base_of_code", optional + 20, 4)?;
+            self.number("image_base", optional + if wide { 24 } else { 28 }, word)?;
+            self.number("section_alignment", optional + 32, 4)?;
+            self.number("file_alignment", optional + 36, 4)?;
+            self.number("size_of_image", optional + 56, 4)?;
+            self.number("size_of_headers", optional + 60, 4)?;
+            self.number("checksum", optional + 64, 4)?;
+            self.named("subsystem", optional + 68, 2, pe_subsystem)?;
+            self.number("dll_characteristics", optional + 70, 2)?;
+            let counts = optional + if wide { 108 } else { 92 };
+            let directories = self.number("number_of_rva_and_sizes", counts, 4)? as usize;
+
+            self.builder.begin(
+                "data_directories",
+                format!("IMAGE_DATA_DIRECTORY[{}]", directories),
+            )?;
+            for idx in 0..directories.min(16) {
+                let at = counts + 4 + idx * 8;
+                let name = PE_DIRECTORIES.get(idx).copied().unwrap_or("reserved");
+                self.builder.begin(name, "IMAGE_DATA_DIRECTORY")?;
+                self.number("virtual_address", at, 4)?;
+                self.number("size", at + 4, 4)?;
+                self.builder.end();
+            }
+            self.builder.end();
+            self.builder.end();
+        }
+        self.builder.end();
+
+        let table = optional + optional_size;
+        let mut contents = Vec::new();
+        self.builder.begin(
+            "section_table",
+            format!("IMAGE_SECTION_HEADER[{}]", sections),
+        )?;
+        for idx in 0..sections {
+            let at = table + idx * 40;
+            let name = String::from_utf8_lossy(&self.bytes(at, 8)?)
+                .trim_end_matches('\0')
+                .to_string();
+            let label = if name.is_empty() {
+                format!("[{}]", idx)
+            } else {
+                name
+            };
+            self.builder.begin(label.clone(), "IMAGE_SECTION_HEADER")?;
+            self.text("name", at, 8)?;
+            self.number("virtual_size", at + 8, 4)?;
+            let address = self.number("virtual_address", at + 12, 4)?;
+            let size = self.number("size_of_raw_data", at + 16, 4)? as usize;
+            let offset = self.number("pointer_to_raw_data", at + 20, 4)? as usize;
+            self.number("characteristics", at + 36, 4)?;
+            self.builder.end();
+
+            if size > 0 && offset > 0 {
+                contents.push((label, offset, size, address));
+            }
+        }
+        self.builder.end();
+
+        self.builder.begin("sections", "contents")?;
+        for (name, offset, size, address) in contents {
+            // Последняя секция может быть обрезана
+            let Some(size) = self.available(offset, size) else {
+                continue;
+            };
+            let value = format!("RVA 0x{:X}, {} bytes", address, size);
+            self.builder
+                .leaf(name, "section", self.range(offset, size), value)?;
+        }
+        self.builder.end();
+        Ok(())
+    }
+
+    fn png(&mut self) -> Result<()> {
+        if self.bytes(0, 8)? != b"\x89PNG\r\n\x1A\n" {
+            bail!("Not a PNG file: bad signature");
+        }
+        self.little = false;
+        self.blob("signature", "u8[8]", 0, 8)?;
+
+        let mut at = 8;
+        while self.base + at < self.end {
+            let length = self.uint(at, 4)? as usize;
+            let kind = String::from_utf8_lossy(&self.bytes(at + 4, 4)?).into_owned();
+            self.builder.begin(kind.clone(), "chunk")?;
+            self.number("length", at, 4)?;
+            self.text("type", at + 4, 4)?;
+            let data = at + 8;
+            if kind == "IHDR" && length >= 13 {
+                self.builder.begin("data", "IHDR")?;
+                self.number("width", data, 4)?;
+                self.number("height", data + 4, 4)?;
+                self.number("bit_depth", data + 8, 1)?;
+                self.named("color_type", data + 9, 1, |value| match value {
+                    0 => Some("grayscale"),
+                    2 => Some("RGB"),
+                    3 => Some("palette"),
+                    4 => Some("grayscale + alpha"),
+                    6 => Some("RGBA"),
+                    _ => None,
+                })?;
+                self.number("compression", data + 10, 1)?;
+                self.number("filter", data + 11, 1)?;
+                self.number("interlace", data + 12, 1)?;
+                self.builder.end();
+            } else if length > 0 {
+                self.blob("data", &format!("u8[{}]", length), data, length)?;
+            }
+
+            // CRC считается по типу и данным блока
+            let stored = self.uint(data + length, 4)? as u32;
+            let actual = checksum::crc32(0, &self.bytes(at + 4, length + 4)?);
+            let status = if stored == actual {
+                "ok".to_string()
+            } else {
+                format!("bad, expected {:08X}", actual)
+            };
+            self.builder.leaf(
+                "crc",
+                "u32be",
+                self.range(data + length, 4),
+                format!("{:08X} ({})", stored, status),
+            )?;
+            self.builder.end();
+
+            at = data + length + 4;
+            if kind == "IEND" {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn zip(&mut self) -> Result<()> {
+        self.little = true;
+        let len = self.end - self.base;
+        let tail_start = len.saturating_sub(ZIP_EOCD_SEARCH);
+        let tail = self.bytes(tail_start, len - tail_start)?;
+        let eocd = tail
+            .windows(4)
+            .rposition(|window| window == b"PK\x05\x06")
+            .map(|idx| tail_start + idx)
+            .ok_or_else(|| anyhow!("No end of central directory record: not a ZIP archive"))?;
+
+        let entries = self.uint(eocd + 10, 2)? as usize;
+        let directory = self.uint(eocd + 16, 4)? as usize;
+        if directory == 0xFFFF_FFFF {
+            bail!("ZIP64 archives are not supported");
+        }
+
+        // Сначала каталог: по нему видно, где лежат локальные заголовки
+        let mut files = Vec::new();
+        let mut at = directory;
+        for _ in 0..entries {
+            if self.bytes(at, 4)? != b"PK\x01\x02" {
+                bail!("No central directory entry signature at 0x{:X}", at);
+            }
+            let name_len = self.uint(at + 28, 2)? as usize;
+            let extra_len = self.uint(at + 30, 2)? as usize;
+            let comment_len = self.uint(at + 32, 2)? as usize;
+            let local = self.uint(at + 42, 4)? as usize;
+            let name = String::from_utf8_lossy(&self.bytes(at + 46, name_len)?).into_owned();
+            files.push((name, at, local));
+            at += 46 + name_len + extra_len + comment_len;
+        }
+
+        let mut locals: Vec<&(String, usize, usize)> = files.iter().collect();
+        locals.sort_by_key(|&&(_, _, local)| local);
+        self.builder
+            .begin("local_files", format!("local_file[{}]", locals.len()))?;
+        for (name, _, local) in locals {
+            let local = *local;
+            self.builder.begin(name.clone(), "local_file")?;
+            self.blob("signature", "u8[4]", local, 4)?;
+            self.number("version_needed", local + 4, 2)?;
+            self.number("flags", local + 6, 2)?;
+            self.named("compression", local + 8, 2, zip_method)?;
+            self.number("mod_time", local + 10, 2)?;
+            self.number("mod_date", local + 12, 2)?;
+            self.number("crc32", local + 14, 4)?;
+            let compressed = self.number("compressed_size", local + 18, 4)? as usize;
+            self.number("uncompressed_size", local + 22, 4)?;
+            let name_len = self.number("name_length", local + 26, 2)? as usize;
+            let extra_len = self.number("extra_length", local + 28, 2)? as usize;
+            self.text("name", local + 30, name_len)?;
+            if extra_len > 0 {
+                self.blob(
+                    "extra",
+                    &format!("u8[{}]", extra_len),
+                    local + 30 + name_len,
+                    extra_len,
+                )?;
+            }
+            if compressed > 0 {
+                let data = local + 30 + name_len + extra_len;
+                self.blob("data", &format!("u8[{}]", compressed), data, compressed)?;
+            }
+            self.builder.end();
+        }
+        self.builder.end();
+
+        self.builder.begin(
+            "central_directory",
+            format!("central_file[{}]", files.len()),
+        )?;
+        for (name, at, _) in &files {
+            let at = *at;
+            self.builder.begin(name.clone(), "central_file")?;
+            self.blob("signature", "u8[4]", at, 4)?;
+            self.number("version_made_by", at + 4, 2)?;
+            self.number("version_needed", at + 6, 2)?;
+            self.number("flags", at + 8, 2)?;
+            self.named("compression", at + 10, 2, zip_method)?;
+            self.number("mod_time", at + 12, 2)?;
+            self.number("mod_date", at + 14, 2)?;
+            self.number("crc32", at + 16, 4)?;
+            self.number("compressed_size", at + 20, 4)?;
+            self.number("uncompressed_size", at + 24, 4)?;
+            let name_len = self.number("name_length", at + 28, 2)? as usize;
+            let extra_len = self.number("extra_length", at + 30, 2)? as usize;
+            let comment_len = self.number("comment_length", at + 32, 2)? as usize;
+            self.number("disk_start", at + 34, 2)?;
+            self.number("internal_attributes", at + 36, 2)?;
+            self.number("external_attributes", at + 38, 4)?;
+            self.number("local_header_offset", at + 42, 4)?;
+            self.text("name", at + 46, name_len)?;
+            if extra_len > 0 {
+                self.blob(
+                    "extra",
+                    &format!("u8[{}]", extra_len),
+                    at + 46 + name_len,
+                    extra_len,
+                )?;
+            }
+            if comment_len > 0 {
+                self.text("comment", at + 46 + name_len + extra_len, comment_len)?;
+            }
+            self.builder.end();
+        }
+        self.builder.end();
+
+        self.builder.begin("end_of_central_directory", "eocd")?;
+        self.blob("signature", "u8[4]", eocd, 4)?;
+        self.number("disk", eocd + 4, 2)?;
+        self.number("directory_disk", eocd + 6, 2)?;
+        self.number("disk_entries", eocd + 8, 2)?;
+        self.number("total_entries", eocd + 10, 2)?;
+        self.number("directory_size", eocd + 12, 4)?;
+        self.number("directory_offset", eocd + 16, 4)?;
+        let comment_len = self.number("comment_length", eocd + 20, 2)? as usize;
+        if comment_len > 0 {
+            self.text("comment", eocd + 22, comment_len)?;
+        }
+        self.builder.end();
+        Ok(())
+    }
+}
+
+const PE_DIRECTORIES: [&str; 16] = [
+    "export",
+    "import",
+    "resource",
+    "exception",
+    "certificate",
+    "base_relocation",
+    "debug",
+    "architecture",
+    "global_ptr",
+    "tls",
+    "load_config",
+    "bound_import",
+    "iat",
+    "delay_import",
+    "clr_runtime",
+    "reserved",
+];
+
+fn elf_type(value: u64) -> Option<&'static str> {
+    Some(match value {
+        1 => "REL",
+        2 => "EXEC",
+        3 => "DYN",
+        4 => "CORE",
+        _ => return None,
+    })
+}
+
+fn elf_machine(value: u64) -> Option<&'static str> {
+    Some(match value {
+        0x03 => "x86",
+        0x08 => "MIPS",
+        0x14 => "PowerPC",
+        0x15 => "PowerPC64",
+        0x28 => "ARM",
+        0x3E => "x86-64",
+        0xB7 => "AArch64",
+        0xF3 => "RISC-V",
+        _ => return None,
+    })
+}
+
+fn elf_segment_type(value: u64) -> Option<&'static str> {
+    Some(match value {
+        0 => "NULL",
+        1 => "LOAD",
+        2 => "DYNAMIC",
+        3 => "INTERP",
+        4 => "NOTE",
+        6 => "PHDR",
+        7 => "TLS",
+        0x6474_E550 => "GNU_EH_FRAME",
+        0x6474_E551 => "GNU_STACK",
+        0x6474_E552 => "GNU_RELRO",
+        0x6474_E553 => "GNU_PROPERTY",
+        _ => return None,
+    })
+}
+
+fn elf_section_type(value: u64) -> Option<&'static str> {
+    Some(match value {
+        0 => "NULL",
+        1 => "PROGBITS",
+        2 => "SYMTAB",
+        3 => "STRTAB",
+        4 => "RELA",
+        5 => "HASH",
+        6 => "DYNAMIC",
+        7 => "NOTE",
+        8 => "NOBITS",
+        9 => "REL",
+        11 => "DYNSYM",
+        14 => "INIT_ARRAY",
+        15 => "FINI_ARRAY",
+        _ => return None,
+    })
+}
+
+fn pe_machine(value: u64) -> Option<&'static str> {
+    Some(match value {
+        0x014C => "x86",
+        0x8664 => "x86-64",
+        0x01C0 | 0x01C4 => "ARM",
+        0xAA64 => "AArch64",
+        _ => return None,
+    })
+}
+
+fn pe_subsystem(value: u64) -> Option<&'static str> {
+    Some(match value {
+        1 => "native",
+        2 => "Windows GUI",
+        3 => "Windows console",
+        10 => "EFI application",
+        _ => return None,
+    })
+}
+
+fn zip_method(value: u64) -> Option<&'static str> {
+    Some(match value {
+        0 => "stored",
+        8 => "deflate",
+        9 => "deflate64",
+        12 => "bzip2",
+        14 => "lzma",
+        93 => "zstd",
+        _ => return None,
+    })
+}
@@ -0,0 +1,51 @@
+//! Байты в hex-записи и обратно: ввод пользователя, буфер обмена, шаблоны поиска
+
+use anyhow::Result;
+
+pub fn hex_string_to_bytes(hex: &str) -> Result<Vec<u8>> {
+    let hex = hex.replace(" ", "");
+    let mut bytes = Vec::new();
+
+    for chunk in hex.as_bytes().chunks(2) {
+        if chunk.len() == 2 {
+            let s = std::str::from_utf8(chunk)?;
+            let byte = u8::from_str_radix(s, 16)?;
+            bytes.push(byte);
+        }
+    }
+
+    Ok(bytes)
+}
+
+pub fn bytes_to_hex_string(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Разбор текста как hex-строки: только hex-цифры и пробелы, четное число цифр
+pub fn parse_hex_text(text: &str) -> Option<Vec<u8>> {
+    let digits: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+
+    if digits.is_empty()
+        || !digits.len().is_multiple_of(2)
+        || !digits.chars().all(|c| c.is_ascii_hexdigit())
+    {
+        return None;
+    }
+
+    hex_string_to_bytes(&digits).ok()
+}
+
+/// Шаблон байтов из ввода пользователя: текст в двойных кавычках или hex-строка
+pub fn parse_byte_pattern(input: &str) -> Result<Vec<u8>> {
+    let trimmed = input.trim();
+
+    if let Some(text) = trimmed.strip_prefix('"').and_then(|t| t.strip_suffix('"')) {
+        return Ok(text.as_bytes().to_vec());
+    }
+
+    hex_string_to_bytes(trimmed)
+}
@@ -0,0 +1,397 @@
+//! Текстовые форматы прошивок: Intel HEX и Motorola S-record. Записи раскладываются
+//! в сплошной буфер от наименьшего адреса, промежутки между ними заполняются GAP_FILL
+//! и при записи обратно пропускаются, если их не тронули
+
+use anyhow::{Result, bail};
+use std::ops::Range;
+use std::path::Path;
+
+/// Чем заполняются адреса, которых нет в файле: так выглядит стертая флеш-память
+pub const GAP_FILL: u8 = 0xFF;
+
+/// Больше этого расстояния между первым и последним адресом в буфер не раскладывается
+const MAX_SPAN: u64 = 256 * 1024 * 1024;
+
+/// Байт данных в одной записи при записи файла, как у objcopy
+const RECORD_BYTES: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HexFormat {
+    IntelHex,
+    SRecord,
+}
+
+/// Все, кроме самих данных, что нужно для записи буфера обратно в формат
+#[derive(Debug, Clone, Default)]
+pub struct Image {
+    // Адрес первого байта буфера
+    pub base: u64,
+    // Участки буфера, которых не было в файле
+    pub gaps: Vec<Range<usize>>,
+    // Адрес входа: запись 05 (или 03 как CS*16+IP) в Intel HEX, S7/S8/S9 в S-record
+    pub start: Option<u32>,
+    // Данные записи S0
+    pub header: Option<Vec<u8>>,
+}
+
+impl HexFormat {
+    pub const ALL: [HexFormat; 2] = [HexFormat::IntelHex, HexFormat::SRecord];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            HexFormat::IntelHex => "ihex",
+            HexFormat::SRecord => "srec",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            HexFormat::IntelHex => "Intel HEX",
+            HexFormat::SRecord => "Motorola S-record",
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "ihex" | "hex" | "intel" => Ok(HexFormat::IntelHex),
+            "srec" | "s-record" | "motorola" => Ok(HexFormat::SRecord),
+            name => bail!("Unknown format '{}', expected ihex or srec", name),
+        }
+    }
+
+    /// Формат по расширению файла
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "hex" | "ihex" | "ihx" => Some(HexFormat::IntelHex),
+            "srec" | "s19" | "s28" | "s37" | "mot" => Some(HexFormat::SRecord),
+            _ => None,
+        }
+    }
+
+    /// Похоже ли начало текста на файл этого формата: `.hex` бывает и просто hex-текстом
+    pub fn matches(self, text: &[u8]) -> bool {
+        let first = text.iter().find(|byte| !byte.is_ascii_whitespace());
+        match self {
+            HexFormat::IntelHex => first == Some(&b':'),
+            HexFormat::SRecord => first == Some(&b'S'),
+        }
+    }
+
+    /// Данные файла, разложенные по адресам, и его описание
+    pub fn decode(self, text: &[u8]) -> Result<(Vec<u8>, Image)> {
+        let mut decoder = Decoder::default();
+        for (idx, line) in text.split(|&byte| byte == b'\n').enumerate() {
+            let line = line.trim_ascii();
+            if line.is_empty() {
+                continue;
+            }
+            let result = match self {
+                HexFormat::IntelHex => decoder.intel_record(line),
+                HexFormat::SRecord => decoder.srecord(line),
+            };
+            if let Err(e) = result {
+                bail!("Line {}: {}", idx + 1, e);
+            }
+            if decoder.finished {
+                break;
+            }
+        }
+        decoder.finish()
+    }
+
+    /// Текст файла для буфера `data`, который начинается с адреса `image.base`.
+    /// Промежутки из `image.gaps`, где остались только байты GAP_FILL, не записываются
+    pub fn encode(self, data: &[u8], image: &Image) -> Result<Vec<u8>> {
+        let last = image.base + data.len() as u64;
+        if last > 1 << 32 {
+            bail!("Data ends at 0x{:X}, past the 32-bit address space", last);
+        }
+
+        let segments = segments(data, &image.gaps);
+        let mut out = Vec::new();
+        match self {
+            HexFormat::IntelHex => encode_intel(&mut out, data, image, &segments),
+            HexFormat::SRecord => encode_srecord(&mut out, data, image, &segments, last),
+        }
+        Ok(out)
+    }
+}
+
+/// Записанные в файл участки: все, кроме нетронутых промежутков
+fn segments(data: &[u8], gaps: &[Range<usize>]) -> Vec<Range<usize>> {
+    let mut segments = Vec::new();
+    let mut start = 0;
+    for gap in gaps {
+        let Some(bytes) = data.get(gap.clone()) else {
+            continue;
+        };
+        if gap.start < start || !bytes.iter().all(|&byte| byte == GAP_FILL) {
+            continue;
+        }
+        if gap.start > start {
+            segments.push(start..gap.start);
+        }
+        start = gap.end;
+    }
+    if start < data.len() {
+        segments.push(start..data.len());
+    }
+    segments
+}
+
+#[derive(Default)]
+struct Decoder {
+    // Куски данных по абсолютным адресам в порядке записей
+    chunks: Vec<(u64, Vec<u8>)>,
+    // Старшая часть адреса из записей 02 и 04 Intel HEX
+    offset: u64,
+    start: Option<u32>,
+    header: Option<Vec<u8>>,
+    finished: bool,
+}
+
+impl Decoder {
+    fn intel_record(&mut self, line: &[u8]) -> Result<()> {
+        let Some(hex) = line.strip_prefix(b":") else {
+            bail!("Record does not start with ':'");
+        };
+        let bytes = parse_hex(hex)?;
+        if bytes.len() < 5 || bytes.len() != 5 + bytes[0] as usize {
+            bail!("Record length does not match its byte count");
+        }
+        if bytes.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte)) != 0 {
+            bail!("Checksum mismatch");
+        }
+
+        let address = u16::from_be_bytes([bytes[1], bytes[2]]) as u64;
+        let data = &bytes[4..bytes.len() - 1];
+        let value = || -> Result<u64> {
+            Ok(match *data {
+                [high, low] => u16::from_be_bytes([high, low]) as u64,
+                [a, b, c, d] => u32::from_be_bytes([a, b, c, d]) as u64,
+                _ => bail!("Unexpected length of record type {:02X}", bytes[3]),
+            })
+        };
+        match bytes[3] {
+            0x00 => self.chunks.push((self.offset + address, data.to_vec())),
+            0x01 => self.finished = true,
+            0x02 => self.offset = value()? << 4,
+            0x03 => {
+                let value = value()?;
+                self.start = Some(((value >> 16) * 16 + (value & 0xFFFF)) as u32);
+            }
+            0x04 => self.offset = value()? << 16,
+            0x05 => self.start = Some(value()? as u32),
+            kind => bail!("Unknown record type {:02X}", kind),
+        }
+        Ok(())
+    }
+
+    fn srecord(&mut self, line: &[u8]) -> Result<()> {
+        let (Some(b'S'), Some(&kind)) = (line.first(), line.get(1)) else {
+            bail!("Record does not start with 'S'");
+        };
+        let bytes = parse_hex(&line[2..])?;
+        if bytes.is_empty() || bytes.len() != 1 + bytes[0] as usize {
+            bail!("Record length does not match its byte count");
+        }
+        if bytes.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte)) != 0xFF {
+            bail!("Checksum mismatch");
+        }
+
+        let address_len = match kind {
+            b'0' | b'1' | b'5' | b'9' => 2,
+            b'2' | b'6' | b'8' => 3,
+            b'3' | b'7' => 4,
+            _ => bail!("Unknown record type S{}", kind as char),
+        };
+        let body = &bytes[1..bytes.len() - 1];
+        if body.len() < address_len {
+            bail!("Record is too short for its address");
+        }
+        let address = body[..address_len]
+            .iter()
+            .fold(0u64, |address, &byte| address << 8 | byte as u64);
+        let data = &body[address_len..];
+
+        match kind {
+            b'0' => self.header = Some(data.to_vec()),
+            b'1'..=b'3' => self.chunks.push((address, data.to_vec())),
+            // Число записей проверять незачем: контрольные суммы уже проверены
+            b'5' | b'6' => {}
+            _ => {
+                self.start = Some(address as u32);
+                self.finished = true;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(Vec<u8>, Image)> {
+        let mut image = Image {
+            start: self.start,
+            header: self.header,
+            ..Image::default()
+        };
+        let chunks: Vec<&(u64, Vec<u8>)> = self
+            .chunks
+            .iter()
+            .filter(|(_, data)| !data.is_empty())
+            .collect();
+        let (Some(first), Some(last)) = (
+            chunks.iter().map(|(address, _)| *address).min(),
+            chunks
+                .iter()
+                .map(|(address, data)| address + data.len() as u64)
+                .max(),
+        ) else {
+            return Ok((Vec::new(), image));
+        };
+        if last - first > MAX_SPAN {
+            bail!(
+                "Records span 0x{:X}..0x{:X}, too far apart to lay out in one buffer",
+                first,
+                last
+            );
+        }
+
+        image.base = first;
+        let mut data = vec![GAP_FILL; (last - first) as usize];
+        let mut covered = vec![false; data.len()];
+        for (address, bytes) in chunks {
+            let start = (address - first) as usize;
+            data[start..start + bytes.len()].copy_from_slice(bytes);
+            covered[start..start + bytes.len()].fill(true);
+        }
+
+        let mut idx = 0;
+        while idx < covered.len() {
+            let run = covered[idx..]
+                .iter()
+                .take_while(|&&c| c == covered[idx])
+                .count();
+            if !covered[idx] {
+                image.gaps.push(idx..idx + run);
+            }
+            idx += run;
+        }
+        Ok((data, image))
+    }
+}
+
+fn parse_hex(text: &[u8]) -> Result<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        bail!("Odd number of hex digits");
+    }
+    text.chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| {
+                    anyhow::anyhow!("Invalid hex digits '{}'", String::from_utf8_lossy(pair))
+                })
+        })
+        .collect()
+}
+
+/// Запись `:LLAAAATT...CC` с контрольной суммой
+fn intel_line(out: &mut Vec<u8>, address: u16, kind: u8, data: &[u8]) {
+    let mut bytes = vec![data.len() as u8];
+    bytes.extend(address.to_be_bytes());
+    bytes.push(kind);
+    bytes.extend_from_slice(data);
+    let sum = bytes.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte));
+    bytes.push(sum.wrapping_neg());
+
+    out.push(b':');
+    for byte in bytes {
+        out.extend(format!("{:02X}", byte).into_bytes());
+    }
+    out.push(b'\n');
+}
+
+fn encode_intel(out: &mut Vec<u8>, data: &[u8], image: &Image, segments: &[Range<usize>]) {
+    let mut upper = 0u64;
+    for segment in segments {
+        let mut pos = segment.start;
+        while pos < segment.end {
+            let address = image.base + pos as u64;
+            // Запись не переходит границу 64 KiB: адрес в ней 16-битный
+            let until_boundary = (0x10000 - (address & 0xFFFF)) as usize;
+            let len = RECORD_BYTES.min(segment.end - pos).min(until_boundary);
+            if address >> 16 != upper {
+                upper = address >> 16;
+                intel_line(out, 0, 0x04, &(upper as u16).to_be_bytes());
+            }
+            intel_line(out, address as u16, 0x00, &data[pos..pos + len]);
+            pos += len;
+        }
+    }
+    if let Some(start) = image.start {
+        intel_line(out, 0, 0x05, &start.to_be_bytes());
+    }
+    intel_line(out, 0, 0x01, &[]);
+}
+
+/// Запись `STLLAAAA...CC` с адресом из `address_len` байт
+fn srecord_line(out: &mut Vec<u8>, kind: u8, address_len: usize, address: u64, data: &[u8]) {
+    let mut bytes = vec![(address_len + data.len() + 1) as u8];
+    bytes.extend(&address.to_be_bytes()[8 - address_len..]);
+    bytes.extend_from_slice(data);
+    let sum = bytes.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte));
+    bytes.push(!sum);
+
+    out.extend([b'S', b'0' + kind]);
+    for byte in bytes {
+        out.extend(format!("{:02X}", byte).into_bytes());
+    }
+    out.push(b'\n');
+}
+
+fn encode_srecord(
+    out: &mut Vec<u8>,
+    data: &[u8],
+    image: &Image,
+    segments: &[Range<usize>],
+    last: u64,
+) {
+    // Самые короткие адреса, в которые помещаются все данные и адрес входа
+    let highest = last.max(image.start.map_or(0, |start| start as u64 + 1));
+    let (data_kind, start_kind, address_len) = if highest <= 0x1_0000 {
+        (1, 9, 2)
+    } else if highest <= 0x100_0000 {
+        (2, 8, 3)
+    } else {
+        (3, 7, 4)
+    };
+
+    if let Some(header) = &image.header {
+        srecord_line(out, 0, 2, 0, header);
+    }
+    let mut count = 0usize;
+    for segment in segments {
+        for pos in (segment.start..segment.end).step_by(RECORD_BYTES) {
+            let end = (pos + RECORD_BYTES).min(segment.end);
+            srecord_line(
+                out,
+                data_kind,
+                address_len,
+                image.base + pos as u64,
+                &data[pos..end],
+            );
+            count += 1;
+        }
+    }
+    if let Ok(count) = u16::try_from(count) {
+        srecord_line(out, 5, 2, count as u64, &[]);
+    }
+    srecord_line(
+        out,
+        start_kind,
+        address_len,
+        image.start.unwrap_or(0) as u64,
+        &[],
+    );
+}
@@ -0,0 +1,37 @@
+//! Ядро hexr без терминала: данные файла в таблице кусков, правки с деревом отмены и поиск.
+//!
+//! ```no_run
+//! use hexr_core::document::Document;
+//! use hexr_core::search::{SearchMode, SearchPattern};
+//!
+//! let mut document = Document::open("firmware.bin")?;
+//! let pattern = SearchPattern::parse("DE AD BE EF", SearchMode::Hex, false)?;
+//! if let Some(pos) = document.find(&pattern, 0) {
+//!     document.replace(pos..pos + 4, &[0; 4]);
+//! }
+//! document.undo();
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+pub mod asm;
+pub mod buffer;
+pub mod checksum;
+pub mod codec;
+pub mod compress;
+pub mod device;
+pub mod disasm;
+pub mod document;
+pub mod entropy;
+pub mod formats;
+pub mod hex;
+pub mod hexfile;
+pub mod magic;
+pub mod numeric;
+pub mod process;
+pub mod progress;
+pub mod runs;
+pub mod search;
+pub mod stats;
+pub mod template;
+pub mod transform;
+pub mod undo_redo;
@@ -0,0 +1,241 @@
+//! Тип файла по сигнатуре в начале данных и поиск сигнатур вложенных файлов
+//! (архивы, картинки, исполняемые файлы внутри прошивок и дампов)
+
+use crate::search::SearchSource;
+use memchr::memmem;
+
+/// Сколько байт с начала файла нужно для распознавания: сигнатура tar лежит на 257,
+/// заголовок PE обычно в первых килобайтах
+pub const HEAD: usize = 4096;
+
+/// Сколько байт данных просматривается за раз при поиске вложенных сигнатур
+const SCAN_CHUNK: usize = 16 * 1024 * 1024;
+
+struct Signature {
+    magic: &'static [u8],
+    // Смещение сигнатуры от начала файла
+    offset: usize,
+    name: &'static str,
+    // Короткие сигнатуры часто встречаются в данных случайно, при поиске вложенных
+    // файлов они пропускаются
+    embedded: bool,
+}
+
+const fn signature(magic: &'static [u8], name: &'static str) -> Signature {
+    Signature {
+        magic,
+        offset: 0,
+        name,
+        embedded: magic.len() >= 4,
+    }
+}
+
+const SIGNATURES: &[Signature] = &[
+    signature(b"\x7FELF", "ELF"),
+    // В поиске вложенных файлов учитывается только с заголовком PE
+    Signature {
+        magic: b"MZ",
+        offset: 0,
+        name: "MS-DOS executable",
+        embedded: true,
+    },
+    signature(b"\xCA\xFE\xBA\xBE", "Mach-O universal binary"),
+    signature(b"\xCF\xFA\xED\xFE", "Mach-O 64-bit executable"),
+    signature(b"\xCE\xFA\xED\xFE", "Mach-O 32-bit executable"),
+    signature(b"dex\n", "Android DEX"),
+    signature(b"\0asm", "WebAssembly module"),
+    signature(b"\x89PNG\r\n\x1A\n", "PNG image"),
+    signature(b"\xFF\xD8\xFF", "JPEG image"),
+    signature(b"GIF87a", "GIF image"),
+    signature(b"GIF89a", "GIF image"),
+    signature(b"BM", "BMP image"),
+    signature(b"II*\0", "TIFF image (little-endian)"),
+    signature(b"MM\0*", "TIFF image (big-endian)"),
+    signature(b"\0\0\x01\0", "Windows icon"),
+    signature(b"RIFF", "RIFF container"),
+    signature(b"OggS", "Ogg media"),
+    signature(b"fLaC", "FLAC audio"),
+    signature(b"ID3", "MP3 audio (ID3)"),
+    signature(b"\x1A\x45\xDF\xA3", "Matroska/WebM video"),
+    signature(b"%PDF-", "PDF document"),
+    signature(
+        b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1",
+        "MS Office legacy document",
+    ),
+    signature(b"{\\rtf", "RTF document"),
+    signature(b"PK\x03\x04", "ZIP archive"),
+    signature(b"PK\x05\x06", "ZIP archive (empty)"),
+    signature(b"Rar!\x1A\x07", "RAR archive"),
+    signature(b"7z\xBC\xAF\x27\x1C", "7-Zip archive"),
+    // Сигнатура короткая, но в прошивках gzip встречается часто; в поиске вложенных
+    // файлов проверяются еще и флаги заголовка
+    Signature {
+        magic: b"\x1F\x8B\x08",
+        offset: 0,
+        name: "gzip compressed data",
+        embedded: true,
+    },
+    signature(b"BZh", "bzip2 compressed data"),
+    signature(b"\xFD7zXZ\0", "XZ compressed data"),
+    signature(b"\x28\xB5\x2F\xFD", "Zstandard compressed data"),
+    signature(b"\x04\x22\x4D\x18", "LZ4 compressed data"),
+    signature(b"hsqs", "SquashFS filesystem"),
+    signature(b"\x27\x05\x19\x56", "U-Boot image"),
+    signature(b"\xD0\x0D\xFE\xED", "Device tree blob"),
+    signature(b"SQLite format 3\0", "SQLite database"),
+    signature(b"-----BEGIN ", "PEM text"),
+    Signature {
+        magic: b"ustar",
+        offset: 257,
+        name: "tar archive",
+        embedded: true,
+    },
+];
+
+/// Тип данных, начинающихся с `head` (достаточно первых HEAD байт)
+pub fn identify(head: &[u8]) -> Option<String> {
+    SIGNATURES
+        .iter()
+        .filter(|signature| matches(signature, head))
+        .find_map(|signature| describe(signature, head, false))
+}
+
+/// Все места, где начинается распознаваемый файл, по порядку; не больше `limit`
+pub fn find_signatures<D: SearchSource + ?Sized>(data: &D, limit: usize) -> Vec<(usize, String)> {
+    let finders: Vec<(&Signature, memmem::Finder)> = SIGNATURES
+        .iter()
+        .filter(|signature| signature.embedded)
+        .map(|signature| (signature, memmem::Finder::new(signature.magic)))
+        .collect();
+
+    let mut found = Vec::new();
+    let mut start = 0;
+    while start < data.len() && found.len() < limit {
+        // Куски перекрываются, чтобы сигнатура на границе не потерялась
+        let end = (start + SCAN_CHUNK).min(data.len());
+        let chunk = data.range(start..(end + HEAD).min(data.len()));
+
+        let mut candidates: Vec<(usize, &Signature)> = Vec::new();
+        for (signature, finder) in &finders {
+            for idx in finder.find_iter(&chunk) {
+                let Some(offset) = (start + idx).checked_sub(signature.offset) else {
+                    continue;
+                };
+                if (start..end).contains(&(start + idx)) {
+                    candidates.push((offset, signature));
+                }
+            }
+        }
+        candidates.sort_by_key(|&(offset, _)| offset);
+        candidates.dedup_by_key(|&mut (offset, _)| offset);
+
+        for (offset, signature) in candidates {
+            let head = data.range(offset..offset + HEAD);
+            if let Some(name) = describe(signature, &head, true) {
+                found.push((offset, name));
+                if found.len() == limit {
+                    break;
+                }
+            }
+        }
+        start = end;
+    }
+    found
+}
+
+fn matches(signature: &Signature, head: &[u8]) -> bool {
+    head.get(signature.offset..)
+        .is_some_and(|bytes| bytes.starts_with(signature.magic))
+}
+
+/// Подробности из заголовка для исполняемых форматов; `strict` отбрасывает совпадения
+/// без правильного заголовка, чтобы в поиске вложенных файлов не было случайных
+fn describe(signature: &Signature, head: &[u8], strict: bool) -> Option<String> {
+    match signature.magic {
+        b"\x7FELF" => describe_elf(head).or_else(|| (!strict).then(|| "ELF".to_string())),
+        b"MZ" => match describe_pe(head) {
+            Some(name) => Some(name),
+            None if strict => None,
+            None => Some(signature.name.to_string()),
+        },
+        // Зарезервированные биты флагов gzip всегда нулевые
+        b"\x1F\x8B\x08" if strict && head.get(3).is_none_or(|flags| flags & 0xE0 != 0) => None,
+        _ => Some(signature.name.to_string()),
+    }
+}
+
+/// "ELF 64-bit LSB executable, x86-64"
+fn describe_elf(head: &[u8]) -> Option<String> {
+    let class = match head.get(4)? {
+        1 => "32-bit",
+        2 => "64-bit",
+        _ => return None,
+    };
+    let little = match head.get(5)? {
+        1 => true,
+        2 => false,
+        _ => return None,
+    };
+    let half = |offset: usize| -> Option<u16> {
+        let bytes: [u8; 2] = head.get(offset..offset + 2)?.try_into().ok()?;
+        Some(if little {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    };
+
+    let kind = match half(16)? {
+        1 => "relocatable",
+        2 => "executable",
+        3 => "shared object",
+        4 => "core file",
+        _ => "file",
+    };
+    let machine = match half(18)? {
+        0x03 => ", x86",
+        0x08 => ", MIPS",
+        0x14 => ", PowerPC",
+        0x15 => ", PowerPC64",
+        0x28 => ", ARM",
+        0x3E => ", x86-64",
+        0xB7 => ", AArch64",
+        0xF3 => ", RISC-V",
+        _ => "",
+    };
+    let order = if little { "LSB" } else { "MSB" };
+    Some(format!("ELF {} {} {}{}", class, order, kind, machine))
+}
+
+/// "PE32+ executable, x86-64" по заголовку PE, на который указывает e_lfanew
+fn describe_pe(head: &[u8]) -> Option<String> {
+    let read_u16 = |offset: usize| -> Option<u16> {
+        Some(u16::from_le_bytes(
+            head.get(offset..offset + 2)?.try_into().ok()?,
+        ))
+    };
+    let pe = u32::from_le_bytes(head.get(0x3C..0x40)?.try_into().ok()?) as usize;
+    if head.get(pe..pe + 4)? != b"PE\0\0" {
+        return None;
+    }
+
+    let machine = match read_u16(pe + 4)? {
+        0x014C => ", x86",
+        0x8664 => ", x86-64",
+        0x01C0 | 0x01C4 => ", ARM",
+        0xAA64 => ", AArch64",
+        _ => "",
+    };
+    let format = match read_u16(pe + 24)? {
+        0x10B => "PE32",
+        0x20B => "PE32+",
+        _ => return None,
+    };
+    // IMAGE_FILE_DLL в характеристиках COFF-заголовка
+    let kind = if read_u16(pe + 22)? & 0x2000 != 0 {
+        "DLL"
+    } else {
+        "executable"
+    };
+    Some(format!("{} {}{}", format, kind, machine))
+}
@@ -0,0 +1,172 @@
+use anyhow::{Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Endian {
+    Little,
+    Big,
+}
+
+/// Тип числового значения для поиска и записи
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NumericType {
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+    U64,
+    I64,
+    F32,
+    F64,
+}
+
+impl NumericType {
+    pub fn from_name(name: &str) -> Option<Self> {
+        let ty = match name.to_ascii_lowercase().as_str() {
+            "u8" => NumericType::U8,
+            "i8" => NumericType::I8,
+            "u16" => NumericType::U16,
+            "i16" => NumericType::I16,
+            "u32" => NumericType::U32,
+            "i32" => NumericType::I32,
+            "u64" => NumericType::U64,
+            "i64" => NumericType::I64,
+            "f32" => NumericType::F32,
+            "f64" => NumericType::F64,
+            _ => return None,
+        };
+        Some(ty)
+    }
+
+    pub fn size(self) -> usize {
+        match self {
+            NumericType::U8 | NumericType::I8 => 1,
+            NumericType::U16 | NumericType::I16 => 2,
+            NumericType::U32 | NumericType::I32 | NumericType::F32 => 4,
+            NumericType::U64 | NumericType::I64 | NumericType::F64 => 8,
+        }
+    }
+
+    /// Значение в начале `bytes` в текстовом виде; `None`, если байтов не хватает
+    pub fn decode(self, bytes: &[u8], endian: Endian) -> Option<String> {
+        let bytes = bytes.get(..self.size())?;
+
+        macro_rules! read {
+            ($t:ty) => {{
+                let array = bytes.try_into().ok()?;
+                match endian {
+                    Endian::Little => <$t>::from_le_bytes(array),
+                    Endian::Big => <$t>::from_be_bytes(array),
+                }
+            }};
+        }
+
+        let text = match self {
+            NumericType::U8 => read!(u8).to_string(),
+            NumericType::I8 => read!(i8).to_string(),
+            NumericType::U16 => read!(u16).to_string(),
+            NumericType::I16 => read!(i16).to_string(),
+            NumericType::U32 => read!(u32).to_string(),
+            NumericType::I32 => read!(i32).to_string(),
+            NumericType::U64 => read!(u64).to_string(),
+            NumericType::I64 => read!(i64).to_string(),
+            NumericType::F32 => format_float(read!(f32)),
+            NumericType::F64 => format_float(read!(f64)),
+        };
+
+        Some(text)
+    }
+
+    /// Байтовое представление значения, записанного в десятичном (или 0x hex) виде
+    pub fn encode(self, value: &str, endian: Endian) -> Result<Vec<u8>> {
+        let value = value.trim();
+
+        macro_rules! int {
+            ($t:ty) => {{
+                let parsed = parse_integer(value)?;
+                let v = <$t>::try_from(parsed).map_err(|_| anyhow!("Value {} is out of range for {:?}", value, self))?;
+                match endian {
+                    Endian::Little => v.to_le_bytes().to_vec(),
+                    Endian::Big => v.to_be_bytes().to_vec(),
+                }
+            }};
+        }
+
+        macro_rules! float {
+            ($t:ty) => {{
+                let v: $t = value.parse().map_err(|_| anyhow!("Invalid number: {}", value))?;
+                match endian {
+                    Endian::Little => v.to_le_bytes().to_vec(),
+                    Endian::Big => v.to_be_bytes().to_vec(),
+                }
+            }};
+        }
+
+        let bytes = match self {
+            NumericType::U8 => int!(u8),
+            NumericType::I8 => int!(i8),
+            NumericType::U16 => int!(u16),
+            NumericType::I16 => int!(i16),
+            NumericType::U32 => int!(u32),
+            NumericType::I32 => int!(i32),
+            NumericType::U64 => int!(u64),
+            NumericType::I64 => int!(i64),
+            NumericType::F32 => float!(f32),
+            NumericType::F64 => float!(f64),
+        };
+
+        Ok(bytes)
+    }
+}
+
+/// Очень большие и очень маленькие числа - в экспоненциальной записи, иначе строка
+/// получается длиной в сотни цифр
+fn format_float<T>(value: T) -> String
+where
+    T: Copy + Into<f64> + std::fmt::Display + std::fmt::LowerExp,
+{
+    let wide: f64 = value.into();
+    if wide.is_finite() && wide != 0.0 && !(1e-6..1e16).contains(&wide.abs()) {
+        format!("{:e}", value)
+    } else {
+        value.to_string()
+    }
+}
+
+/// Целое со знаком в десятичной или 0x hex записи
+fn parse_integer(value: &str) -> Result<i128> {
+    let (negative, digits) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+
+    let magnitude = match digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        Some(hex) => i128::from_str_radix(hex, 16),
+        None => digits.parse::<i128>(),
+    }
+    .map_err(|_| anyhow!("Invalid integer: {}", value))?;
+
+    Ok(if negative { -magnitude } else { magnitude })
+}
+
+/// Разбор строки вида `1234 u32`, `-5 i16 be`, `3.5 f64 le` в байты
+pub fn parse_typed_value(input: &str) -> Result<Vec<u8>> {
+    let mut parts = input.split_whitespace();
+
+    let Some(value) = parts.next() else {
+        bail!("Expected: <value> <type> [le|be]");
+    };
+    let Some(ty) = parts.next().and_then(NumericType::from_name) else {
+        bail!("Expected a type: u8/i8/u16/i16/u32/i32/u64/i64/f32/f64");
+    };
+    let endian = match parts.next().map(str::to_ascii_lowercase).as_deref() {
+        None | Some("le") => Endian::Little,
+        Some("be") => Endian::Big,
+        Some(other) => bail!("Unknown endianness '{}', expected le or be", other),
+    };
+
+    ty.encode(value, endian)
+}
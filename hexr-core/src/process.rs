@@ -0,0 +1,169 @@
+//! Память живого процесса Linux: регионы из /proc/<pid>/maps, чтение и запись
+//! через /proc/<pid>/mem. Доступ к памяти требует прав ptrace на процесс
+
+use anyhow::{Context, Result, bail};
+use std::fs::{File, OpenOptions};
+
+/// Регионы больше этого не читаются: регион загружается в память целиком
+pub const MAX_REGION_SIZE: u64 = 1 << 30;
+
+/// Страница памяти: ее можно прочитать только целиком или никак
+const PAGE_SIZE: usize = 4096;
+
+/// Ошибка чтения куска такого размера повторяется по страницам
+const READ_CHUNK: usize = 1024 * 1024;
+
+/// Строка /proc/<pid>/maps: диапазон адресов, права и отображенный файл
+#[derive(Debug, Clone, PartialEq)]
+pub struct Region {
+    pub start: u64,
+    pub end: u64,
+    /// Права в виде `r-xp`
+    pub perms: String,
+    /// Смещение в отображенном файле
+    pub offset: u64,
+    /// Путь файла или псевдоимя (`[heap]`, `[stack]`); у анонимной памяти пусто
+    pub path: String,
+}
+
+impl Region {
+    pub fn len(&self) -> u64 {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    pub fn is_readable(&self) -> bool {
+        self.perms.starts_with('r')
+    }
+
+    pub fn is_writable(&self) -> bool {
+        self.perms.as_bytes().get(1) == Some(&b'w')
+    }
+
+    pub fn name(&self) -> &str {
+        if self.path.is_empty() {
+            "[anon]"
+        } else {
+            &self.path
+        }
+    }
+
+    /// Строка списка регионов: адреса, права, размер и имя
+    pub fn label(&self) -> String {
+        format!(
+            "{:012x}-{:012x} {} {:>10} {}",
+            self.start,
+            self.end,
+            self.perms,
+            self.len(),
+            self.name()
+        )
+    }
+}
+
+/// Регионы процесса по возрастанию адресов
+pub fn regions(pid: u32) -> Result<Vec<Region>> {
+    let path = format!("/proc/{}/maps", pid);
+    let text = std::fs::read_to_string(&path)
+        .with_context(|| format!("Cannot read the memory map of process {}", pid))?;
+    Ok(text.lines().filter_map(parse_region).collect())
+}
+
+/// `55d0c8a4e000-55d0c8a6f000 rw-p 00000000 00:00 0    [heap]`
+fn parse_region(line: &str) -> Option<Region> {
+    let mut fields = line.split_whitespace();
+    let (start, end) = fields.next()?.split_once('-')?;
+    let perms = fields.next()?.to_string();
+    let offset = u64::from_str_radix(fields.next()?, 16).ok()?;
+    // Устройство и inode не нужны; путь может содержать пробелы
+    fields.next()?;
+    fields.next()?;
+    let path = fields.collect::<Vec<_>>().join(" ");
+
+    Some(Region {
+        start: u64::from_str_radix(start, 16).ok()?,
+        end: u64::from_str_radix(end, 16).ok()?,
+        perms,
+        offset,
+        path,
+    })
+}
+
+/// Содержимое региона и число байт, которые не удалось прочитать: такие страницы
+/// (защищенные или не отображенные с момента чтения карты) заполняются нулями
+pub fn read_region(pid: u32, region: &Region) -> Result<(Vec<u8>, usize)> {
+    if region.len() > MAX_REGION_SIZE {
+        bail!(
+            "Region {} is {} bytes, at most {} can be opened",
+            region.name(),
+            region.len(),
+            MAX_REGION_SIZE
+        );
+    }
+    let file = open_memory(pid, false)?;
+
+    let mut data = vec![0u8; region.len() as usize];
+    let mut unreadable = 0;
+    for (idx, chunk) in data.chunks_mut(READ_CHUNK).enumerate() {
+        let address = region.start + (idx * READ_CHUNK) as u64;
+        if read_exact_at(&file, chunk, address).is_ok() {
+            continue;
+        }
+        for (page_idx, page) in chunk.chunks_mut(PAGE_SIZE).enumerate() {
+            if read_exact_at(&file, page, address + (page_idx * PAGE_SIZE) as u64).is_err() {
+                page.fill(0);
+                unreadable += page.len();
+            }
+        }
+    }
+
+    if unreadable == data.len() && !data.is_empty() {
+        bail!("Region {} of process {} cannot be read", region.name(), pid);
+    }
+    Ok((data, unreadable))
+}
+
+/// Пишет байты в память процесса по адресу
+pub fn write(pid: u32, address: u64, bytes: &[u8]) -> Result<()> {
+    let file = open_memory(pid, true)?;
+    write_all_at(&file, bytes, address)
+        .with_context(|| format!("Cannot write {} bytes at 0x{:X}", bytes.len(), address))
+}
+
+fn open_memory(pid: u32, write: bool) -> Result<File> {
+    OpenOptions::new()
+        .read(true)
+        .write(write)
+        .open(format!("/proc/{}/mem", pid))
+        .with_context(|| {
+            format!(
+                "Cannot open the memory of process {} (ptrace permission is required)",
+                pid
+            )
+        })
+}
+
+#[cfg(unix)]
+fn read_exact_at(file: &File, buf: &mut [u8], address: u64) -> std::io::Result<()> {
+    use std::os::unix::fs::FileExt;
+    file.read_exact_at(buf, address)
+}
+
+#[cfg(unix)]
+fn write_all_at(file: &File, bytes: &[u8], address: u64) -> std::io::Result<()> {
+    use std::os::unix::fs::FileExt;
+    file.write_all_at(bytes, address)
+}
+
+#[cfg(not(unix))]
+fn read_exact_at(_file: &File, _buf: &mut [u8], _address: u64) -> std::io::Result<()> {
+    Err(std::io::Error::from(std::io::ErrorKind::Unsupported))
+}
+
+#[cfg(not(unix))]
+fn write_all_at(_file: &File, _bytes: &[u8], _address: u64) -> std::io::Result<()> {
+    Err(std::io::Error::from(std::io::ErrorKind::Unsupported))
+}
@@ -0,0 +1,32 @@
+//! Ход долгих операций: операция сообщает, сколько сделано, и между кусками работы
+//! узнает, не отменил ли ее пользователь
+
+use anyhow::Result;
+use std::fmt;
+
+/// Получатель хода операции; ошибка из `update` прерывает операцию
+pub trait Progress {
+    /// Сделано `done` из `total`
+    fn update(&mut self, done: usize, total: usize) -> Result<()>;
+}
+
+/// Ход никуда не выводится, операцию нельзя отменить: пакетный режим и короткие операции
+pub struct Silent;
+
+impl Progress for Silent {
+    fn update(&mut self, _done: usize, _total: usize) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Ошибка операции, которую отменил пользователь
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
@@ -0,0 +1,87 @@
+//! Серии одинаковых байт: границы серии под курсором и переход через заполнение
+//! (нули и 0xFF стертой флеш-памяти) к следующим данным
+
+use crate::search::SearchSource;
+use std::ops::Range;
+
+/// Серия 0x00 или 0xFF не короче этого считается заполнением, а не данными
+pub const MIN_PADDING: usize = 16;
+
+/// Данные читаются кусками такого размера
+const CHUNK: usize = 64 * 1024;
+
+pub fn is_padding(byte: u8) -> bool {
+    byte == 0x00 || byte == 0xFF
+}
+
+/// Серия одинаковых байт, в которой стоит `pos < len`
+pub fn run_at(data: &impl SearchSource, pos: usize) -> Range<usize> {
+    let value = data.range(pos..pos + 1)[0];
+    run_start(data, pos, value)..run_end(data, pos, value)
+}
+
+/// Первая позиция после `pos` с другим значением, чем `value`
+fn run_end(data: &impl SearchSource, pos: usize, value: u8) -> usize {
+    let mut end = pos;
+    loop {
+        let chunk = data.range(end..end + CHUNK);
+        let same = chunk.iter().take_while(|&&byte| byte == value).count();
+        end += same;
+        if same < chunk.len() || chunk.is_empty() {
+            return end;
+        }
+    }
+}
+
+/// Начало серии `value`, которая заканчивается на `pos`
+fn run_start(data: &impl SearchSource, pos: usize, value: u8) -> usize {
+    let mut start = pos;
+    loop {
+        let chunk = data.range(start.saturating_sub(CHUNK)..start);
+        let same = chunk
+            .iter()
+            .rev()
+            .take_while(|&&byte| byte == value)
+            .count();
+        start -= same;
+        if same < chunk.len() || chunk.is_empty() {
+            return start;
+        }
+    }
+}
+
+/// Начало данных после ближайшего заполнения от `from`; если `from` уже в заполнении -
+/// сразу после него. Соседние серии нулей и 0xFF пропускаются вместе. None, если
+/// заполнение тянется до конца или его больше нет
+pub fn next_data(data: &impl SearchSource, from: usize) -> Option<usize> {
+    let len = data.len();
+    if from >= len {
+        return None;
+    }
+
+    // Серии читаются подряд кусками; серия закрывается на первом другом байте
+    let mut start = run_at(data, from).start;
+    let mut value = data.range(start..start + 1)[0];
+    let mut crossed = false;
+    let mut pos = start;
+    while pos < len {
+        let chunk = data.range(pos..pos + CHUNK);
+        for (idx, &byte) in chunk.iter().enumerate() {
+            if byte == value {
+                continue;
+            }
+            if is_padding(value) && pos + idx - start >= MIN_PADDING {
+                crossed = true;
+            } else if crossed {
+                return Some(start);
+            }
+            start = pos + idx;
+            value = byte;
+        }
+        pos += chunk.len();
+    }
+
+    // Последняя серия доходит до конца данных
+    let tail_is_data = !is_padding(value) || len - start < MIN_PADDING;
+    (crossed && tail_is_data).then_some(start)
+}
@@ -0,0 +1,604 @@
+use crate::hex;
+use crate::numeric;
+use anyhow::{Result, bail};
+use memchr::memmem;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::ops::Range;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+/// Размер блока, после которого фоновый поиск проверяет отмену и обновляет прогресс
+const SEARCH_CHUNK: usize = 16 * 1024 * 1024;
+
+/// Данные для поиска. Поиск запрашивает их окнами, поэтому данные не обязаны лежать
+/// в памяти одним срезом (таблица кусков, отображенный файл)
+pub trait SearchSource {
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Байты диапазона; часть за концом данных отбрасывается
+    fn range(&self, range: Range<usize>) -> Cow<'_, [u8]>;
+}
+
+impl SearchSource for [u8] {
+    fn len(&self) -> usize {
+        <[u8]>::len(self)
+    }
+
+    fn range(&self, range: Range<usize>) -> Cow<'_, [u8]> {
+        let end = range.end.min(self.len());
+        Cow::Borrowed(&self[range.start.min(end)..end])
+    }
+}
+
+/// Область поиска внутри источника; координаты относительно ее начала
+struct Scope<'a, D: SearchSource + ?Sized> {
+    data: &'a D,
+    start: usize,
+    end: usize,
+}
+
+impl<D: SearchSource + ?Sized> Scope<'_, D> {
+    fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    fn window(&self, range: Range<usize>) -> Cow<'_, [u8]> {
+        self.data.range(self.start + range.start..self.start + range.end)
+    }
+}
+
+/// Как интерпретируется строка, введенная в поиске
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    Hex,
+    Text,
+    Utf16Le,
+    Utf16Be,
+    /// Числовое значение заданного типа: `1234 u32 le`
+    Value,
+}
+
+impl SearchMode {
+    pub fn next(self) -> Self {
+        match self {
+            SearchMode::Hex => SearchMode::Text,
+            SearchMode::Text => SearchMode::Utf16Le,
+            SearchMode::Utf16Le => SearchMode::Utf16Be,
+            SearchMode::Utf16Be => SearchMode::Value,
+            SearchMode::Value => SearchMode::Hex,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SearchMode::Hex => "hex",
+            SearchMode::Text => "text",
+            SearchMode::Utf16Le => "UTF-16LE",
+            SearchMode::Utf16Be => "UTF-16BE",
+            SearchMode::Value => "value: <number> <type> [le|be]",
+        }
+    }
+
+    pub fn is_text(self) -> bool {
+        matches!(self, SearchMode::Text | SearchMode::Utf16Le | SearchMode::Utf16Be)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchPattern {
+    bytes: Vec<u8>,
+    /// Маска сравнения по байтам: 0xFF - точное совпадение, 0x00 - любой байт (`??`)
+    mask: Option<Vec<u8>>,
+    ignore_case: bool,
+}
+
+impl SearchPattern {
+    /// Точный байтовый шаблон
+    pub fn exact(bytes: Vec<u8>) -> Self {
+        Self {
+            bytes,
+            mask: None,
+            ignore_case: false,
+        }
+    }
+
+    /// Разбор ввода пользователя в соответствии с режимом поиска.
+    /// Регистр игнорируется только в текстовых режимах и только для ASCII букв
+    pub fn parse(input: &str, mode: SearchMode, ignore_case: bool) -> Result<Self> {
+        let trimmed = input.trim();
+        if mode == SearchMode::Hex && !trimmed.starts_with('"') {
+            return Self::parse_masked_hex(trimmed);
+        }
+
+        let bytes = match mode {
+            SearchMode::Hex => hex::parse_byte_pattern(input)?,
+            SearchMode::Text => input.as_bytes().to_vec(),
+            SearchMode::Utf16Le => input.encode_utf16().flat_map(u16::to_le_bytes).collect(),
+            SearchMode::Utf16Be => input.encode_utf16().flat_map(u16::to_be_bytes).collect(),
+            SearchMode::Value => numeric::parse_typed_value(input)?,
+        };
+
+        if bytes.is_empty() {
+            bail!("Search pattern is empty");
+        }
+
+        Ok(Self {
+            bytes,
+            mask: None,
+            ignore_case: ignore_case && mode.is_text(),
+        })
+    }
+
+    /// Hex-шаблон с подстановочными полубайтами: `DE ?? BE ??`, `4?`, `?F`
+    pub fn parse_masked_hex(input: &str) -> Result<Self> {
+        let digits: Vec<char> = input.chars().filter(|c| !c.is_whitespace()).collect();
+
+        if digits.is_empty() {
+            bail!("Search pattern is empty");
+        }
+        if !digits.len().is_multiple_of(2) {
+            bail!("Odd number of hex digits in search pattern");
+        }
+
+        let mut bytes = Vec::with_capacity(digits.len() / 2);
+        let mut mask = Vec::with_capacity(digits.len() / 2);
+
+        for pair in digits.chunks(2) {
+            let mut value = 0u8;
+            let mut nibble_mask = 0u8;
+
+            for &c in pair {
+                value <<= 4;
+                nibble_mask <<= 4;
+                if c != '?' {
+                    let Some(digit) = c.to_digit(16) else {
+                        bail!("Invalid character '{}' in search pattern", c);
+                    };
+                    value |= digit as u8;
+                    nibble_mask |= 0x0F;
+                }
+            }
+
+            bytes.push(value);
+            mask.push(nibble_mask);
+        }
+
+        // Без подстановок маска не нужна, сравнение идет напрямую
+        let mask = mask.iter().any(|&m| m != 0xFF).then_some(mask);
+
+        Ok(Self {
+            bytes,
+            mask,
+            ignore_case: false,
+        })
+    }
+
+    pub fn matches_at(&self, data: &[u8], pos: usize) -> bool {
+        let Some(window) = data.get(pos..pos + self.bytes.len()) else {
+            return false;
+        };
+
+        if let Some(mask) = &self.mask {
+            window
+                .iter()
+                .zip(&self.bytes)
+                .zip(mask)
+                .all(|((&byte, &expected), &m)| byte & m == expected & m)
+        } else if self.ignore_case {
+            window.eq_ignore_ascii_case(&self.bytes)
+        } else {
+            window == self.bytes.as_slice()
+        }
+    }
+
+    /// Первое вхождение начиная с `start`
+    pub fn find_forward(&self, data: &[u8], start: usize) -> Option<usize> {
+        if self.bytes.is_empty() || self.bytes.len() > data.len() || start > data.len() - self.bytes.len() {
+            return None;
+        }
+
+        // Точный шаблон - SIMD two-way поиск из memchr
+        if self.is_exact() {
+            return memmem::find(&data[start..], &self.bytes).map(|pos| start + pos);
+        }
+
+        // Иначе быстро ищем кандидатов по первому байту и проверяем их целиком
+        let last_start = data.len() - self.bytes.len();
+        let mut pos = start;
+        while pos <= last_start {
+            let candidate = pos + self.next_candidate(&data[pos..=last_start])?;
+            if self.matches_at(data, candidate) {
+                return Some(candidate);
+            }
+            pos = candidate + 1;
+        }
+
+        None
+    }
+
+    /// Последнее вхождение, начинающееся строго до `before`
+    pub fn find_backward(&self, data: &[u8], before: usize) -> Option<usize> {
+        if self.bytes.is_empty() || self.bytes.len() > data.len() {
+            return None;
+        }
+
+        let last_start = (data.len() - self.bytes.len()).min(before.checked_sub(1)?);
+
+        if self.is_exact() {
+            return memmem::rfind(&data[..last_start + self.bytes.len()], &self.bytes);
+        }
+
+        let mut end = last_start + 1;
+        while end > 0 {
+            let candidate = self.prev_candidate(&data[..end])?;
+            if self.matches_at(data, candidate) {
+                return Some(candidate);
+            }
+            end = candidate;
+        }
+
+        None
+    }
+
+    /// Все вхождения (включая перекрывающиеся) по возрастанию смещения
+    pub fn find_all(&self, data: &[u8]) -> Vec<usize> {
+        let mut offsets = Vec::new();
+        let mut start = 0;
+
+        while let Some(pos) = self.find_forward(data, start) {
+            offsets.push(pos);
+            start = pos + 1;
+        }
+
+        offsets
+    }
+
+    fn is_exact(&self) -> bool {
+        self.mask.is_none() && !self.ignore_case
+    }
+
+    /// Варианты первого байта шаблона, пригодные для memchr; None - первый байт произвольный
+    fn first_byte_variants(&self) -> Option<(u8, u8)> {
+        let first = self.bytes[0];
+
+        if let Some(mask) = &self.mask
+            && mask[0] != 0xFF
+        {
+            return None;
+        }
+
+        if self.ignore_case {
+            Some((first.to_ascii_lowercase(), first.to_ascii_uppercase()))
+        } else {
+            Some((first, first))
+        }
+    }
+
+    fn next_candidate(&self, haystack: &[u8]) -> Option<usize> {
+        match self.first_byte_variants() {
+            Some((a, b)) if a == b => memchr::memchr(a, haystack),
+            Some((a, b)) => memchr::memchr2(a, b, haystack),
+            None if haystack.is_empty() => None,
+            None => Some(0),
+        }
+    }
+
+    fn prev_candidate(&self, haystack: &[u8]) -> Option<usize> {
+        match self.first_byte_variants() {
+            Some((a, b)) if a == b => memchr::memrchr(a, haystack),
+            Some((a, b)) => memchr::memrchr2(a, b, haystack),
+            None => haystack.len().checked_sub(1),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+}
+
+/// Список результатов "найти все" для панели совпадений
+#[derive(Debug, Clone)]
+pub struct MatchList {
+    pub offsets: Vec<usize>,
+    pub selected: usize,
+    pub pattern_len: usize,
+    // Подписи строк (тип найденного файла); пусто - показывается только контекст
+    pub labels: Vec<String>,
+}
+
+impl MatchList {
+    pub fn new(offsets: Vec<usize>, pattern_len: usize) -> Self {
+        Self {
+            offsets,
+            selected: 0,
+            pattern_len,
+            labels: Vec::new(),
+        }
+    }
+
+    pub fn current(&self) -> Option<usize> {
+        self.offsets.get(self.selected).copied()
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.offsets.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// Выбор ближайшего совпадения не левее `offset`
+    pub fn select_nearest(&mut self, offset: usize) {
+        self.selected = self
+            .offsets
+            .iter()
+            .position(|&o| o >= offset)
+            .unwrap_or(self.offsets.len().saturating_sub(1));
+    }
+}
+
+/// Что именно ищем относительно курсора
+#[derive(Debug, Clone, Copy)]
+pub enum SearchRequest {
+    /// Следующее вхождение после `cursor` с переходом через конец буфера
+    Next { cursor: usize },
+    /// Предыдущее вхождение до `cursor` с переходом через начало буфера
+    Previous { cursor: usize },
+    /// Все вхождения
+    All,
+}
+
+#[derive(Debug, Clone)]
+pub enum SearchOutcome {
+    Found { pos: usize, wrapped: bool },
+    All(Vec<usize>),
+    NotFound,
+    Cancelled,
+}
+
+/// Общее состояние между редактором и потоком поиска
+#[derive(Debug, Default)]
+pub struct SearchControl {
+    cancelled: AtomicBool,
+    scanned: AtomicUsize,
+}
+
+impl SearchControl {
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    fn advance(&self, bytes: usize) {
+        self.scanned.fetch_add(bytes, Ordering::Relaxed);
+    }
+}
+
+/// Поиск блоками с проверкой отмены; используется и синхронно, и в фоновом потоке.
+/// Вхождения ищутся только целиком внутри `within`, смещения возвращаются абсолютные
+pub fn execute<D: SearchSource + ?Sized>(
+    data: &D,
+    pattern: &SearchPattern,
+    request: SearchRequest,
+    within: Range<usize>,
+    control: &SearchControl,
+) -> SearchOutcome {
+    let end = within.end.min(data.len());
+    let start = within.start.min(end);
+
+    // Границы сканирования относительно области поиска; курсор может быть и вне ее
+    let scan = match request {
+        SearchRequest::Next { cursor } => Scan::Forward {
+            from: (cursor + 1).saturating_sub(start),
+        },
+        SearchRequest::Previous { cursor } => Scan::Backward {
+            before: cursor.saturating_sub(start),
+        },
+        SearchRequest::All => Scan::All,
+    };
+
+    match scan_scope(&Scope { data, start, end }, pattern, scan, control) {
+        SearchOutcome::Found { pos, wrapped } => SearchOutcome::Found {
+            pos: start + pos,
+            wrapped,
+        },
+        SearchOutcome::All(offsets) => SearchOutcome::All(offsets.into_iter().map(|pos| start + pos).collect()),
+        other => other,
+    }
+}
+
+/// Первое вхождение не раньше `start`, без перехода в начало
+pub fn find_from<D: SearchSource + ?Sized>(data: &D, pattern: &SearchPattern, start: usize) -> Option<usize> {
+    if pattern.is_empty() {
+        return None;
+    }
+
+    let scope = Scope { data, start: 0, end: data.len() };
+    forward_in(&scope, pattern, start.min(scope.len()), scope.len(), &SearchControl::default()).flatten()
+}
+
+/// Все вхождения (включая перекрывающиеся) по возрастанию смещения
+pub fn find_all_in<D: SearchSource + ?Sized>(data: &D, pattern: &SearchPattern) -> Vec<usize> {
+    if pattern.is_empty() {
+        return Vec::new();
+    }
+
+    let scope = Scope { data, start: 0, end: data.len() };
+    all_in(&scope, pattern, &SearchControl::default()).unwrap_or_default()
+}
+
+/// Запрос поиска в координатах области поиска
+enum Scan {
+    /// Первое вхождение не раньше `from`, затем с начала среза
+    Forward { from: usize },
+    /// Последнее вхождение раньше `before`, затем с конца среза
+    Backward { before: usize },
+    All,
+}
+
+fn scan_scope<D: SearchSource + ?Sized>(data: &Scope<D>, pattern: &SearchPattern, scan: Scan, control: &SearchControl) -> SearchOutcome {
+    if pattern.is_empty() {
+        return SearchOutcome::NotFound;
+    }
+
+    let result = match scan {
+        Scan::Forward { from } => {
+            let start = from.min(data.len());
+            match forward_in(data, pattern, start, data.len(), control) {
+                Some(Some(pos)) => Some(SearchOutcome::Found { pos, wrapped: false }),
+                Some(None) => forward_in(data, pattern, 0, start, control).map(|found| match found {
+                    Some(pos) => SearchOutcome::Found { pos, wrapped: true },
+                    None => SearchOutcome::NotFound,
+                }),
+                None => None,
+            }
+        }
+        Scan::Backward { before } => {
+            let before = before.min(data.len());
+            match backward_in(data, pattern, 0, before, control) {
+                Some(Some(pos)) => Some(SearchOutcome::Found { pos, wrapped: false }),
+                Some(None) => backward_in(data, pattern, before, data.len(), control).map(|found| match found {
+                    Some(pos) => SearchOutcome::Found { pos, wrapped: true },
+                    None => SearchOutcome::NotFound,
+                }),
+                None => None,
+            }
+        }
+        Scan::All => all_in(data, pattern, control).map(SearchOutcome::All),
+    };
+
+    result.unwrap_or(SearchOutcome::Cancelled)
+}
+
+/// Первое вхождение, начинающееся в [start, end); None - поиск отменен
+fn forward_in<D: SearchSource + ?Sized>(data: &Scope<D>, pattern: &SearchPattern, start: usize, end: usize, control: &SearchControl) -> Option<Option<usize>> {
+    let mut chunk_start = start;
+
+    while chunk_start < end {
+        if control.is_cancelled() {
+            return None;
+        }
+
+        let chunk_end = (chunk_start + SEARCH_CHUNK).min(end);
+        // Шаблон может начинаться в блоке и заканчиваться за его границей
+        let window_end = (chunk_end + pattern.len() - 1).min(data.len());
+        if let Some(pos) = pattern.find_forward(&data.window(chunk_start..window_end), 0) {
+            return Some(Some(chunk_start + pos));
+        }
+
+        control.advance(chunk_end - chunk_start);
+        chunk_start = chunk_end;
+    }
+
+    Some(None)
+}
+
+/// Последнее вхождение, начинающееся в [start, end); None - поиск отменен
+fn backward_in<D: SearchSource + ?Sized>(data: &Scope<D>, pattern: &SearchPattern, start: usize, end: usize, control: &SearchControl) -> Option<Option<usize>> {
+    let mut chunk_end = end;
+
+    while chunk_end > start {
+        if control.is_cancelled() {
+            return None;
+        }
+
+        let chunk_start = chunk_end.saturating_sub(SEARCH_CHUNK).max(start);
+        let window_end = (chunk_end + pattern.len() - 1).min(data.len());
+        if let Some(pos) = pattern.find_backward(&data.window(chunk_start..window_end), chunk_end - chunk_start) {
+            return Some(Some(chunk_start + pos));
+        }
+
+        control.advance(chunk_end - chunk_start);
+        chunk_end = chunk_start;
+    }
+
+    Some(None)
+}
+
+fn all_in<D: SearchSource + ?Sized>(data: &Scope<D>, pattern: &SearchPattern, control: &SearchControl) -> Option<Vec<usize>> {
+    let mut offsets = Vec::new();
+    let mut chunk_start = 0;
+
+    while chunk_start < data.len() {
+        if control.is_cancelled() {
+            return None;
+        }
+
+        let chunk_end = (chunk_start + SEARCH_CHUNK).min(data.len());
+        let window_end = (chunk_end + pattern.len() - 1).min(data.len());
+        // Окно заканчивается так, что в нем начинаются только вхождения из этого блока
+        let window = data.window(chunk_start..window_end);
+        offsets.extend(pattern.find_all(&window).into_iter().map(|pos| chunk_start + pos));
+
+        control.advance(chunk_end - chunk_start);
+        chunk_start = chunk_end;
+    }
+
+    Some(offsets)
+}
+
+/// Поиск в фоновом потоке над снимком данных
+pub struct SearchJob {
+    control: Arc<SearchControl>,
+    receiver: Receiver<SearchOutcome>,
+    total: usize,
+}
+
+impl SearchJob {
+    pub fn spawn<D>(data: Arc<D>, pattern: SearchPattern, request: SearchRequest, within: Range<usize>) -> Self
+    where
+        D: SearchSource + Send + Sync + 'static,
+    {
+        let control = Arc::new(SearchControl::default());
+        let (sender, receiver) = mpsc::channel();
+        let total = within.end.min(data.len()).saturating_sub(within.start);
+
+        let worker_control = Arc::clone(&control);
+        thread::spawn(move || {
+            let outcome = execute(&*data, &pattern, request, within, &worker_control);
+            // Получатель мог быть уже удален, если поиск отменили
+            let _ = sender.send(outcome);
+        });
+
+        Self {
+            control,
+            receiver,
+            total,
+        }
+    }
+
+    /// Результат, если поиск завершился
+    pub fn poll(&self) -> Option<SearchOutcome> {
+        match self.receiver.try_recv() {
+            Ok(outcome) => Some(outcome),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(SearchOutcome::Cancelled),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.control.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn progress_percent(&self) -> usize {
+        if self.total == 0 {
+            return 100;
+        }
+        let scanned = self.control.scanned.load(Ordering::Relaxed).min(self.total);
+        scanned * 100 / self.total
+    }
+}
@@ -0,0 +1,84 @@
+//! Частоты значений байт участка: по ним видны заполнение, ключи и кодировки
+
+use crate::entropy;
+use crate::progress::Progress;
+use crate::search::SearchSource;
+use anyhow::Result;
+use std::ops::Range;
+
+/// Сколько байт читается за раз: таблица кусков не собирается в один буфер
+const CHUNK: usize = 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    pub counts: [u64; 256],
+    pub total: u64,
+}
+
+impl Histogram {
+    /// Частоты байт участка `range`; часть за концом данных не считается
+    pub fn of<D: SearchSource + ?Sized>(
+        data: &D,
+        range: Range<usize>,
+        progress: &mut dyn Progress,
+    ) -> Result<Self> {
+        let mut histogram = Self {
+            counts: [0; 256],
+            total: 0,
+        };
+        let end = range.end.min(data.len());
+        let mut offset = range.start;
+        while offset < end {
+            progress.update(offset - range.start, end - range.start)?;
+            let chunk_end = (offset + CHUNK).min(end);
+            for &byte in data.range(offset..chunk_end).iter() {
+                histogram.counts[byte as usize] += 1;
+            }
+            offset = chunk_end;
+        }
+        histogram.total = histogram.counts.iter().sum();
+        Ok(histogram)
+    }
+
+    fn count(&self, bytes: impl Iterator<Item = u8>) -> u64 {
+        bytes.map(|byte| self.counts[byte as usize]).sum()
+    }
+
+    pub fn nulls(&self) -> u64 {
+        self.counts[0]
+    }
+
+    /// Печатаемые ASCII, включая пробел
+    pub fn printable(&self) -> u64 {
+        self.count(0x20..=0x7E)
+    }
+
+    /// Управляющие символы без нуля, включая 0x7F
+    pub fn control(&self) -> u64 {
+        self.count((0x01..0x20).chain([0x7F]))
+    }
+
+    pub fn high_bit(&self) -> u64 {
+        self.count(0x80..=0xFF)
+    }
+
+    /// Сколько разных значений встречается
+    pub fn distinct(&self) -> usize {
+        self.counts.iter().filter(|&&count| count > 0).count()
+    }
+
+    pub fn entropy(&self) -> f64 {
+        entropy::from_counts(&self.counts)
+    }
+
+    /// Самые частые значения по убыванию частоты
+    pub fn most_common(&self, limit: usize) -> Vec<(u8, u64)> {
+        let mut values: Vec<(u8, u64)> = (0..=255u8)
+            .map(|byte| (byte, self.counts[byte as usize]))
+            .filter(|&(_, count)| count > 0)
+            .collect();
+        values.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        values.truncate(limit);
+        values
+    }
+}
@@ -0,0 +1,617 @@
+//! Шаблоны двоичных форматов: описание полей на простом языке и разметка данных
+//! по нему в дерево полей с диапазонами и значениями.
+//!
+//! ```text
+//! # комментарий до конца строки
+//! endian big                 # порядок байт по умолчанию, иначе little
+//! struct Chunk {
+//!     u32 length
+//!     char type[4]
+//!     u8 data[length]        # длина из уже прочитанного поля
+//!     u32le crc @red         # свой порядок байт и цвет подсветки
+//! }
+//! struct Png {
+//!     u8 signature[8]
+//!     Chunk chunks[..]       # повторяется до конца данных
+//! }
+//! root Png                   # корень; без него - последняя структура
+//! ```
+
+use crate::numeric::{Endian, NumericType};
+use crate::search::SearchSource;
+use anyhow::{Context, Result, anyhow, bail};
+use std::ops::Range;
+
+/// Больше полей разметка не создает: ошибка в шаблоне не должна съесть всю память
+const MAX_FIELDS: usize = 100_000;
+
+/// Глубина вложенности структур; ограничивает рекурсивные шаблоны
+const MAX_DEPTH: usize = 32;
+
+/// Сколько элементов числового массива показывается в значении поля
+const PREVIEW_ITEMS: usize = 8;
+
+/// Длина превью строки char[N]
+const PREVIEW_CHARS: usize = 48;
+
+#[derive(Debug, Clone)]
+enum FieldType {
+    // Порядок байт `None` - как задано директивой endian
+    Number(NumericType, Option<Endian>),
+    Char,
+    Struct(usize),
+}
+
+#[derive(Debug, Clone)]
+enum Count {
+    Fixed(usize),
+    // Значение прочитанного ранее целого поля этой или объемлющей структуры
+    Field(String),
+    // До конца данных
+    Rest,
+}
+
+#[derive(Debug, Clone)]
+struct FieldDef {
+    ty: FieldType,
+    type_name: String,
+    name: String,
+    count: Option<Count>,
+    color: Option<String>,
+    line: usize,
+}
+
+#[derive(Debug, Clone)]
+struct StructDef {
+    name: String,
+    fields: Vec<FieldDef>,
+}
+
+/// Разобранный шаблон
+#[derive(Debug, Clone)]
+pub struct Template {
+    structs: Vec<StructDef>,
+    root: usize,
+    endian: Endian,
+}
+
+/// Поле разметки. Поля идут в порядке обхода дерева: потомки поля - индексы
+/// от него до `end`
+#[derive(Debug, Clone)]
+pub struct Field {
+    // Имя поля; у элементов массива структур - "[N]"
+    pub name: String,
+    pub type_name: String,
+    pub range: Range<usize>,
+    pub depth: usize,
+    pub parent: Option<usize>,
+    pub end: usize,
+    // Значение листа в текстовом виде; у структур и массивов структур `None`
+    pub value: Option<String>,
+    pub color: Option<String>,
+}
+
+/// Результат разметки: поля до первой ошибки и сама ошибка, если данные не совпали
+/// с шаблоном
+#[derive(Debug, Clone, Default)]
+pub struct Layout {
+    pub fields: Vec<Field>,
+    pub error: Option<String>,
+}
+
+/// Разметка, которую строит код, а не шаблон (встроенные разборщики форматов).
+/// Диапазон узла охватывает диапазоны всех его потомков
+#[derive(Debug, Default)]
+pub struct LayoutBuilder {
+    fields: Vec<Field>,
+    open: Vec<usize>,
+}
+
+impl LayoutBuilder {
+    /// Открывает узел; следующие поля до парного end() - его потомки
+    pub fn begin(&mut self, name: impl Into<String>, type_name: impl Into<String>) -> Result<()> {
+        let field = self.field(name.into(), type_name.into(), 0..0, None)?;
+        self.open.push(self.fields.len());
+        self.fields.push(field);
+        Ok(())
+    }
+
+    pub fn end(&mut self) {
+        let Some(idx) = self.open.pop() else {
+            return;
+        };
+        let children = self.fields[idx + 1..]
+            .iter()
+            .map(|field| field.range.clone())
+            .filter(|range| !range.is_empty());
+        let range =
+            children.reduce(|all, range| all.start.min(range.start)..all.end.max(range.end));
+        self.fields[idx].range = range.unwrap_or(0..0);
+        self.fields[idx].end = self.fields.len();
+    }
+
+    pub fn leaf(
+        &mut self,
+        name: impl Into<String>,
+        type_name: impl Into<String>,
+        range: Range<usize>,
+        value: String,
+    ) -> Result<()> {
+        let field = self.field(name.into(), type_name.into(), range, Some(value))?;
+        self.fields.push(field);
+        Ok(())
+    }
+
+    fn field(
+        &self,
+        name: String,
+        type_name: String,
+        range: Range<usize>,
+        value: Option<String>,
+    ) -> Result<Field> {
+        if self.fields.len() == MAX_FIELDS {
+            bail!("The layout has more than {} fields", MAX_FIELDS);
+        }
+        Ok(Field {
+            name,
+            type_name,
+            range,
+            depth: self.open.len(),
+            parent: self.open.last().copied(),
+            end: self.fields.len() + 1,
+            value,
+            color: None,
+        })
+    }
+
+    /// Закрывает незаконченные после ошибки узлы
+    pub fn finish(mut self, error: Option<String>) -> Layout {
+        while !self.open.is_empty() {
+            self.end();
+        }
+        Layout {
+            fields: self.fields,
+            error,
+        }
+    }
+}
+
+impl Template {
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut structs: Vec<StructDef> = Vec::new();
+        let mut endian = Endian::Little;
+        let mut root = None;
+        let mut open: Option<StructDef> = None;
+
+        for (idx, line) in text.lines().enumerate() {
+            let number = idx + 1;
+            let line = line
+                .split('#')
+                .next()
+                .unwrap_or("")
+                .trim()
+                .trim_end_matches(';');
+            if line.is_empty() {
+                continue;
+            }
+            let words: Vec<&str> = line.split_whitespace().collect();
+
+            if let Some(def) = &mut open {
+                if line == "}" {
+                    structs.extend(open.take());
+                } else {
+                    let field = parse_field(&words, number)?;
+                    if def.fields.iter().any(|other| other.name == field.name) {
+                        bail!("line {}: field '{}' is already defined", number, field.name);
+                    }
+                    def.fields.push(field);
+                }
+                continue;
+            }
+
+            match words.as_slice() {
+                ["endian", "big"] => endian = Endian::Big,
+                ["endian", "little"] => endian = Endian::Little,
+                ["root", name] => root = Some(name.to_string()),
+                ["struct", name, "{"] => {
+                    if structs.iter().any(|def| def.name == *name) {
+                        bail!("line {}: struct '{}' is already defined", number, name);
+                    }
+                    open = Some(StructDef {
+                        name: name.to_string(),
+                        fields: Vec::new(),
+                    });
+                }
+                _ => bail!(
+                    "line {}: expected 'struct NAME {{', 'endian big|little' or 'root NAME'",
+                    number
+                ),
+            }
+        }
+
+        if let Some(def) = open {
+            bail!("struct '{}' is not closed with '}}'", def.name);
+        }
+        if structs.is_empty() {
+            bail!("The template defines no structs");
+        }
+
+        // Типы-структуры разрешаются после разбора: структура может ссылаться на
+        // описанную ниже
+        let names: Vec<String> = structs.iter().map(|def| def.name.clone()).collect();
+        for field in structs.iter_mut().flat_map(|def| def.fields.iter_mut()) {
+            if let FieldType::Struct(idx) = &mut field.ty {
+                *idx = names
+                    .iter()
+                    .position(|name| *name == field.type_name)
+                    .ok_or_else(|| {
+                        anyhow!("line {}: unknown type '{}'", field.line, field.type_name)
+                    })?;
+            }
+        }
+        let root = match root {
+            Some(root) => names
+                .iter()
+                .position(|name| *name == root)
+                .ok_or_else(|| anyhow!("root struct '{}' is not defined", root))?,
+            None => structs.len() - 1,
+        };
+
+        Ok(Self {
+            structs,
+            root,
+            endian,
+        })
+    }
+
+    /// Имя корневой структуры
+    pub fn root_name(&self) -> &str {
+        &self.structs[self.root].name
+    }
+
+    /// Размечает `range` данных: корневая структура начинается с его начала
+    pub fn apply<D: SearchSource + ?Sized>(&self, data: &D, range: Range<usize>) -> Layout {
+        let mut evaluator = Evaluator {
+            template: self,
+            data,
+            end: range.end.min(data.len()),
+            fields: Vec::new(),
+            scopes: Vec::new(),
+        };
+        let root = &self.structs[self.root];
+        let error = evaluator
+            .eval_struct(self.root, root.name.clone(), range.start, None, None)
+            .err()
+            .map(|e| format!("{:#}", e));
+
+        // После ошибки у незаконченных структур остался конец-заглушка
+        let mut fields = evaluator.fields;
+        for idx in (0..fields.len()).rev() {
+            if fields[idx].end == 0 {
+                fields[idx].end = fields.len();
+                let last = fields[idx + 1..].iter().map(|field| field.range.end).max();
+                fields[idx].range.end = last.unwrap_or(fields[idx].range.start);
+            }
+        }
+        Layout { fields, error }
+    }
+}
+
+fn parse_field(words: &[&str], line: usize) -> Result<FieldDef> {
+    let (declaration, color) = match words {
+        [ty, name] => ([*ty, *name], None),
+        [ty, name, color] if color.starts_with('@') => ([*ty, *name], Some(color[1..].to_string())),
+        _ => bail!(
+            "line {}: expected a field 'TYPE NAME', 'TYPE NAME[COUNT]' or '}}'",
+            line
+        ),
+    };
+    let [type_name, declarator] = declaration;
+
+    let (name, count) = match declarator.split_once('[') {
+        Some((name, count)) => {
+            let count = count
+                .strip_suffix(']')
+                .ok_or_else(|| anyhow!("line {}: missing ']' after the count", line))?;
+            (name, Some(parse_count(count, line)?))
+        }
+        None => (declarator, None),
+    };
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+    {
+        bail!("line {}: bad field name '{}'", line, name);
+    }
+
+    Ok(FieldDef {
+        ty: parse_type(type_name),
+        type_name: type_name.to_string(),
+        name: name.to_string(),
+        count,
+        color,
+        line,
+    })
+}
+
+fn parse_count(count: &str, line: usize) -> Result<Count> {
+    if count == ".." {
+        return Ok(Count::Rest);
+    }
+    let number = match count.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => count.parse().ok(),
+    };
+    match number {
+        Some(number) => Ok(Count::Fixed(number)),
+        None if count
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '_') =>
+        {
+            Ok(Count::Field(count.to_string()))
+        }
+        None => bail!(
+            "line {}: bad count '{}', expected a number, a field or ..",
+            line,
+            count
+        ),
+    }
+}
+
+/// `u16`, `u32be`, `f64le`, `char`; все остальное - имя структуры
+fn parse_type(name: &str) -> FieldType {
+    if name == "char" {
+        return FieldType::Char;
+    }
+    let (base, endian) = if let Some(base) = name.strip_suffix("le") {
+        (base, Some(Endian::Little))
+    } else if let Some(base) = name.strip_suffix("be") {
+        (base, Some(Endian::Big))
+    } else {
+        (name, None)
+    };
+    match NumericType::from_name(base) {
+        Some(ty) => FieldType::Number(ty, endian),
+        None => FieldType::Struct(0),
+    }
+}
+
+struct Evaluator<'a, D: ?Sized> {
+    template: &'a Template,
+    data: &'a D,
+    end: usize,
+    fields: Vec<Field>,
+    // Целые значения прочитанных полей по структурам от корня
+    scopes: Vec<Vec<(String, i128)>>,
+}
+
+impl<D: SearchSource + ?Sized> Evaluator<'_, D> {
+    /// Добавляет поле; `end` равен нулю, пока у поля не закончились потомки
+    fn push(&mut self, field: Field) -> Result<usize> {
+        if self.fields.len() == MAX_FIELDS {
+            bail!("The template produced more than {} fields", MAX_FIELDS);
+        }
+        self.fields.push(field);
+        Ok(self.fields.len() - 1)
+    }
+
+    fn finish(&mut self, idx: usize, pos: usize) {
+        self.fields[idx].range.end = pos;
+        self.fields[idx].end = self.fields.len();
+    }
+
+    fn node(&self, name: String, type_name: String, pos: usize, parent: Option<usize>) -> Field {
+        Field {
+            name,
+            type_name,
+            range: pos..pos,
+            depth: self.scopes.len(),
+            parent,
+            end: 0,
+            value: None,
+            color: None,
+        }
+    }
+
+    fn eval_struct(
+        &mut self,
+        def: usize,
+        name: String,
+        pos: usize,
+        parent: Option<usize>,
+        color: Option<String>,
+    ) -> Result<usize> {
+        if self.scopes.len() == MAX_DEPTH {
+            bail!("Structs are nested deeper than {} levels", MAX_DEPTH);
+        }
+        let type_name = self.template.structs[def].name.clone();
+        let node = Field {
+            color,
+            ..self.node(name, type_name, pos, parent)
+        };
+        let idx = self.push(node)?;
+
+        self.scopes.push(Vec::new());
+        let mut pos = pos;
+        for field in &self.template.structs[def].fields {
+            pos = self
+                .eval_field(field, pos, idx)
+                .with_context(|| format!("line {}: field '{}'", field.line, field.name))?;
+        }
+        self.scopes.pop();
+
+        self.finish(idx, pos);
+        Ok(pos)
+    }
+
+    fn eval_field(&mut self, field: &FieldDef, pos: usize, parent: usize) -> Result<usize> {
+        let count = match &field.count {
+            None => None,
+            Some(Count::Fixed(count)) => Some(*count),
+            Some(Count::Field(name)) => Some(self.lookup(name)?),
+            Some(Count::Rest) => match field.ty {
+                FieldType::Number(ty, _) => Some((self.end.saturating_sub(pos)) / ty.size()),
+                FieldType::Char => Some(self.end.saturating_sub(pos)),
+                FieldType::Struct(_) => None,
+            },
+        };
+
+        match (&field.ty, count) {
+            (&FieldType::Struct(def), _) if field.count.is_none() => self.eval_struct(
+                def,
+                field.name.clone(),
+                pos,
+                Some(parent),
+                field.color.clone(),
+            ),
+            (&FieldType::Struct(def), count) => {
+                let array = Field {
+                    color: field.color.clone(),
+                    ..self.node(
+                        field.name.clone(),
+                        format!("{}[..]", field.type_name),
+                        pos,
+                        Some(parent),
+                    )
+                };
+                let array = self.push(array)?;
+
+                self.scopes.push(Vec::new());
+                let mut pos = pos;
+                let mut item = 0;
+                while count.map_or(pos < self.end, |count| item < count) {
+                    let next = self.eval_struct(
+                        def,
+                        format!("[{}]", item),
+                        pos,
+                        Some(array),
+                        field.color.clone(),
+                    )?;
+                    // Пустая структура в массиве до конца данных зациклила бы разбор
+                    if next == pos && count.is_none() {
+                        break;
+                    }
+                    pos = next;
+                    item += 1;
+                }
+                self.scopes.pop();
+
+                // Для массива до конца данных число элементов известно только теперь
+                self.fields[array].type_name = format!("{}[{}]", field.type_name, item);
+                self.finish(array, pos);
+                Ok(pos)
+            }
+            (FieldType::Number(..) | FieldType::Char, count) => {
+                self.eval_leaf(field, pos, count, parent)
+            }
+        }
+    }
+
+    fn eval_leaf(
+        &mut self,
+        field: &FieldDef,
+        pos: usize,
+        count: Option<usize>,
+        parent: usize,
+    ) -> Result<usize> {
+        let item_size = match field.ty {
+            FieldType::Number(ty, _) => ty.size(),
+            _ => 1,
+        };
+        let size = item_size
+            .checked_mul(count.unwrap_or(1))
+            .filter(|&size| pos + size <= self.end)
+            .ok_or_else(|| {
+                anyhow!(
+                    "{} bytes at 0x{:X} run past the end of the data",
+                    item_size.saturating_mul(count.unwrap_or(1)),
+                    pos
+                )
+            })?;
+        let bytes = self.data.range(pos..pos + size);
+
+        let value = match (&field.ty, count) {
+            (&FieldType::Number(ty, endian), None) => {
+                let value = ty.decode(&bytes, endian.unwrap_or(self.template.endian));
+                let value = value.unwrap_or_default();
+                if let Ok(number) = value.parse::<i128>() {
+                    self.scopes
+                        .last_mut()
+                        .expect("leaf fields are read inside a struct")
+                        .push((field.name.clone(), number));
+                    if number >= 10 {
+                        format!("{} (0x{:X})", value, number)
+                    } else {
+                        value
+                    }
+                } else {
+                    value
+                }
+            }
+            (FieldType::Number(NumericType::U8, _), Some(_)) => {
+                let preview: Vec<String> = bytes
+                    .iter()
+                    .take(PREVIEW_ITEMS * 2)
+                    .map(|byte| format!("{:02X}", byte))
+                    .collect();
+                let more = if bytes.len() > PREVIEW_ITEMS * 2 {
+                    " ..."
+                } else {
+                    ""
+                };
+                format!("{}{}", preview.join(" "), more)
+            }
+            (&FieldType::Number(ty, endian), Some(count)) => {
+                let endian = endian.unwrap_or(self.template.endian);
+                let preview: Vec<String> = bytes
+                    .chunks_exact(item_size)
+                    .take(PREVIEW_ITEMS)
+                    .filter_map(|item| ty.decode(item, endian))
+                    .collect();
+                let more = if count > PREVIEW_ITEMS { ", ..." } else { "" };
+                format!("[{}{}]", preview.join(", "), more)
+            }
+            (_, _) => {
+                let text: String = bytes
+                    .iter()
+                    .take(PREVIEW_CHARS)
+                    .flat_map(|&byte| std::ascii::escape_default(byte))
+                    .map(char::from)
+                    .collect();
+                let more = if bytes.len() > PREVIEW_CHARS {
+                    "..."
+                } else {
+                    ""
+                };
+                format!("\"{}\"{}", text, more)
+            }
+        };
+
+        let type_name = match count {
+            Some(count) => format!("{}[{}]", field.type_name, count),
+            None => field.type_name.clone(),
+        };
+        self.push(Field {
+            value: Some(value),
+            color: field.color.clone(),
+            end: self.fields.len() + 1,
+            range: pos..pos + size,
+            ..self.node(field.name.clone(), type_name, pos, Some(parent))
+        })?;
+        Ok(pos + size)
+    }
+
+    /// Значение целого поля по имени: сначала в текущей структуре, потом в объемлющих
+    fn lookup(&self, name: &str) -> Result<usize> {
+        let value = self
+            .scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.iter().rev())
+            .find(|(field, _)| field == name)
+            .map(|&(_, value)| value)
+            .ok_or_else(|| anyhow!("count '{}' is not an integer field read before", name))?;
+        usize::try_from(value).map_err(|_| anyhow!("count '{}' is negative: {}", name, value))
+    }
+}
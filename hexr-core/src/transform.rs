@@ -0,0 +1,111 @@
+//! Побайтовые преобразования выделения: снятие простого XOR-шифрования, сдвиги значений
+
+use crate::hex;
+use anyhow::{Result, anyhow, bail};
+
+/// Подсказка для ввода операции
+pub const SYNTAX: &str = "xor/and/or KEY, add/sub N, rol/ror BITS, not, swap 16/32/64";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Transform {
+    // Ключ повторяется с начала участка
+    Xor(Vec<u8>),
+    And(Vec<u8>),
+    Or(Vec<u8>),
+    Not,
+    // Сложение и вычитание по модулю 256
+    Add(u8),
+    Sub(u8),
+    RotateLeft(u32),
+    RotateRight(u32),
+    // Смена порядка байт в каждом слове из стольких байт: таблица big-endian чисел
+    // становится little-endian
+    SwapWords(usize),
+}
+
+impl Transform {
+    /// `xor DEADBEEF`, `xor "key"`, `add 1`, `rol 3`, `not`, `swap 32`
+    pub fn parse(input: &str) -> Result<Self> {
+        let input = input.trim();
+        let (operation, argument) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+        let argument = argument.trim();
+
+        let key = || -> Result<Vec<u8>> {
+            let key = hex::parse_byte_pattern(argument)?;
+            if key.is_empty() {
+                bail!("{} needs a key: hex bytes or \"text\"", operation);
+            }
+            Ok(key)
+        };
+        let number = |max: u32| -> Result<u32> {
+            let value = match argument
+                .strip_prefix("0x")
+                .or_else(|| argument.strip_prefix("0X"))
+            {
+                Some(hex) => u32::from_str_radix(hex, 16),
+                None => argument.parse(),
+            };
+            match value {
+                Ok(value) if value <= max => Ok(value),
+                _ => Err(anyhow!("{} needs a number from 0 to {}", operation, max)),
+            }
+        };
+
+        Ok(match operation.to_ascii_lowercase().as_str() {
+            "xor" => Transform::Xor(key()?),
+            "and" => Transform::And(key()?),
+            "or" => Transform::Or(key()?),
+            "not" => Transform::Not,
+            "add" => Transform::Add(number(0xFF)? as u8),
+            "sub" => Transform::Sub(number(0xFF)? as u8),
+            "rol" => Transform::RotateLeft(number(7)?),
+            "ror" => Transform::RotateRight(number(7)?),
+            "swap" => match argument {
+                "" | "16" => Transform::SwapWords(2),
+                "32" => Transform::SwapWords(4),
+                "64" => Transform::SwapWords(8),
+                _ => bail!("swap works on 16, 32 or 64-bit words, got '{}'", argument),
+            },
+            _ => bail!("Unknown operation '{}', expected {}", operation, SYNTAX),
+        })
+    }
+
+    /// Преобразует байты на месте
+    pub fn apply(&self, bytes: &mut [u8]) -> Result<()> {
+        let with_key = |bytes: &mut [u8], key: &[u8], op: fn(u8, u8) -> u8| {
+            for (byte, &k) in bytes.iter_mut().zip(key.iter().cycle()) {
+                *byte = op(*byte, k);
+            }
+        };
+
+        match self {
+            Transform::Xor(key) => with_key(bytes, key, |byte, k| byte ^ k),
+            Transform::And(key) => with_key(bytes, key, |byte, k| byte & k),
+            Transform::Or(key) => with_key(bytes, key, |byte, k| byte | k),
+            Transform::Not => bytes.iter_mut().for_each(|byte| *byte = !*byte),
+            Transform::Add(n) => bytes
+                .iter_mut()
+                .for_each(|byte| *byte = byte.wrapping_add(*n)),
+            Transform::Sub(n) => bytes
+                .iter_mut()
+                .for_each(|byte| *byte = byte.wrapping_sub(*n)),
+            Transform::RotateLeft(bits) => bytes
+                .iter_mut()
+                .for_each(|byte| *byte = byte.rotate_left(*bits)),
+            Transform::RotateRight(bits) => bytes
+                .iter_mut()
+                .for_each(|byte| *byte = byte.rotate_right(*bits)),
+            Transform::SwapWords(size) => {
+                if !bytes.len().is_multiple_of(*size) {
+                    bail!(
+                        "{} bytes are not a whole number of {}-bit words",
+                        bytes.len(),
+                        size * 8
+                    );
+                }
+                bytes.chunks_exact_mut(*size).for_each(<[u8]>::reverse);
+            }
+        }
+        Ok(())
+    }
+}
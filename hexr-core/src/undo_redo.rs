@@ -0,0 +1,381 @@
+use crate::buffer::PieceTable;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum EditOperation {
+    /// Вставка байта: позиция, старое значение, новое значение
+    InsertByte { position: usize, old_value: Option<u8>, new_value: u8 },
+    /// Удаление байта: позиция, старое значение
+    DeleteByte { position: usize, old_value: u8 },
+    /// Замена байта: позиция, старое значение, новое значение
+    ReplaceByte { position: usize, old_value: u8, new_value: u8 },
+    /// Вставка блока байтов: позиция, старые значения, новые значения
+    InsertBytes { position: usize, old_values: Vec<u8>, new_values: Vec<u8> },
+    /// Удаление блока байтов: позиция, старые значения
+    DeleteBytes { position: usize, old_values: Vec<u8> },
+    /// Замена блока байтов: позиция, старые значения, новые значения
+    ReplaceBytes { position: usize, old_values: Vec<u8>, new_values: Vec<u8> },
+}
+
+impl EditOperation {
+    pub fn new_replace_byte(position: usize, old_value: u8, new_value: u8) -> Self {
+        Self::ReplaceByte { position, old_value, new_value }
+    }
+
+    pub fn new_insert_byte(position: usize, new_value: u8) -> Self {
+        Self::InsertByte {
+            position,
+            old_value: None,
+            new_value,
+        }
+    }
+
+    pub fn new_insert_bytes(position: usize, new_values: Vec<u8>) -> Self {
+        Self::InsertBytes {
+            position,
+            old_values: Vec::new(),
+            new_values,
+        }
+    }
+
+    pub fn new_replace_bytes(position: usize, old_values: Vec<u8>, new_values: Vec<u8>) -> Self {
+        Self::ReplaceBytes { position, old_values, new_values }
+    }
+
+    pub fn new_delete_byte(position: usize, old_value: u8) -> Self {
+        Self::DeleteByte { position, old_value }
+    }
+
+    pub fn new_delete_bytes(position: usize, old_values: Vec<u8>) -> Self {
+        Self::DeleteBytes { position, old_values }
+    }
+
+    pub fn undo(&self, data: &mut PieceTable) {
+        match self {
+            EditOperation::InsertByte { position, old_value, .. } => {
+                if let Some(old_val) = old_value {
+                    data.replace(*position..*position + 1, &[*old_val]);
+                } else {
+                    data.delete(*position..*position + 1);
+                }
+            }
+            EditOperation::DeleteByte { position, old_value } => {
+                data.insert(*position, &[*old_value]);
+            }
+            EditOperation::ReplaceByte { position, old_value, .. } => {
+                data.replace(*position..*position + 1, &[*old_value]);
+            }
+            EditOperation::InsertBytes { position, old_values, new_values } => {
+                if old_values.is_empty() {
+                    // Удаляем вставленные байты
+                    data.delete(*position..*position + new_values.len());
+                } else {
+                    // Восстанавливаем старые значения
+                    data.replace(*position..*position + old_values.len(), old_values);
+                }
+            }
+            EditOperation::DeleteBytes { position, old_values } => {
+                data.insert(*position, old_values);
+            }
+            EditOperation::ReplaceBytes { position, old_values, new_values } => {
+                // Длины могут различаться (например, вставка поверх конца файла)
+                data.replace(*position..*position + new_values.len(), old_values);
+            }
+        }
+    }
+
+    /// Помещается ли операция в данные длины `len`: байты, которые заменяет повтор
+    /// (или отмена при `undo`), лежат до конца данных
+    pub fn fits(&self, len: usize, undo: bool) -> bool {
+        let (position, before, after) = match self {
+            EditOperation::InsertByte { position, .. } => (*position, 0, 1),
+            EditOperation::DeleteByte { position, .. } => (*position, 1, 0),
+            EditOperation::ReplaceByte { position, .. } => (*position, 1, 1),
+            EditOperation::InsertBytes { position, new_values, .. } => (*position, 0, new_values.len()),
+            EditOperation::DeleteBytes { position, old_values } => (*position, old_values.len(), 0),
+            EditOperation::ReplaceBytes { position, old_values, new_values } => (*position, old_values.len(), new_values.len()),
+        };
+        // Повтор заменяет прежние байты новыми, отмена - наоборот
+        let touched = if undo { after } else { before };
+        position.checked_add(touched).is_some_and(|end| end <= len)
+    }
+
+    pub fn redo(&self, data: &mut PieceTable) {
+        match self {
+            EditOperation::InsertByte { position, new_value, .. } => {
+                data.insert(*position, &[*new_value]);
+            }
+            EditOperation::DeleteByte { position, .. } => {
+                data.delete(*position..*position + 1);
+            }
+            EditOperation::ReplaceByte { position, new_value, .. } => {
+                if *position < data.len() {
+                    data.replace(*position..*position + 1, &[*new_value]);
+                }
+            }
+            EditOperation::InsertBytes { position, new_values, .. } => {
+                data.insert(*position, new_values);
+            }
+            EditOperation::DeleteBytes { position, old_values } => {
+                data.delete(*position..*position + old_values.len());
+            }
+            EditOperation::ReplaceBytes { position, old_values, new_values } => {
+                data.replace(*position..*position + old_values.len(), new_values);
+            }
+        }
+    }
+}
+
+/// Шаг истории: одна или несколько операций, которые отменяются вместе
+pub type UndoStep = Vec<EditOperation>;
+
+/// Узел дерева истории: шаг, который ведет к нему от родителя
+struct Node {
+    parent: usize,
+    step: UndoStep,
+    children: Vec<usize>,
+    // Ветка, по которой пойдет redo; новая правка делает активной себя
+    active_child: Option<usize>,
+}
+
+/// Ветки redo в текущей точке истории
+pub struct Branches {
+    pub active: usize,
+    // Число шагов по каждой ветке до ее конца по активным потомкам
+    pub lengths: Vec<usize>,
+}
+
+/// История правок деревом: правка после undo открывает новую ветку,
+/// а прежняя остается доступной для redo
+pub struct UndoRedoStack {
+    // Узел 0 - исходное состояние, у него нет шага
+    nodes: Vec<Node>,
+    current: usize,
+    // Узел, который совпадает с содержимым файла на диске
+    saved: Option<usize>,
+    max_operations: usize,
+    // Открытая группа и глубина вложенности begin_group()
+    group: Option<UndoStep>,
+    group_depth: usize,
+}
+
+impl UndoRedoStack {
+    pub fn new(max_operations: usize) -> Self {
+        Self {
+            nodes: vec![Node::root()],
+            current: 0,
+            saved: Some(0),
+            max_operations,
+            group: None,
+            group_depth: 0,
+        }
+    }
+
+    pub fn push(&mut self, operation: EditOperation) {
+        match &mut self.group {
+            Some(group) => group.push(operation),
+            None => self.push_step(vec![operation]),
+        }
+    }
+
+    /// Замена байта, который только что менялся (биты одного байта), дополняет прежний шаг
+    pub fn push_merged(&mut self, operation: EditOperation) {
+        let node = &mut self.nodes[self.current];
+        if self.group.is_none()
+            && self.current != 0
+            && self.saved != Some(self.current)
+            && node.children.is_empty()
+            && let EditOperation::ReplaceByte { position, new_value, .. } = &operation
+            && let [EditOperation::ReplaceByte { position: last, new_value: last_value, .. }] =
+                node.step.as_mut_slice()
+            && last == position
+        {
+            *last_value = *new_value;
+            return;
+        }
+
+        self.push(operation);
+    }
+
+    fn push_step(&mut self, step: UndoStep) {
+        let index = self.nodes.len();
+        self.nodes.push(Node {
+            parent: self.current,
+            step,
+            children: Vec::new(),
+            active_child: None,
+        });
+
+        let parent = &mut self.nodes[self.current];
+        parent.children.push(index);
+        parent.active_child = Some(index);
+        self.current = index;
+
+        // Ограничиваем размер истории
+        if self.nodes.len() > self.max_operations + 1 {
+            self.drop_oldest();
+        }
+    }
+
+    /// Первый шаг на пути к текущему узлу становится новым исходным состоянием;
+    /// ветки, которые расходились до него, отбрасываются
+    fn drop_oldest(&mut self) {
+        let mut new_root = self.current;
+        while self.nodes[new_root].parent != 0 {
+            new_root = self.nodes[new_root].parent;
+        }
+
+        // Перенумерация оставшегося поддерева обходом от нового корня
+        let mut order = vec![new_root];
+        let mut idx = 0;
+        while idx < order.len() {
+            order.extend(self.nodes[order[idx]].children.iter().copied());
+            idx += 1;
+        }
+        let mut remap = vec![usize::MAX; self.nodes.len()];
+        for (new_index, &old_index) in order.iter().enumerate() {
+            remap[old_index] = new_index;
+        }
+
+        let mut old_nodes: Vec<Option<Node>> = std::mem::take(&mut self.nodes).into_iter().map(Some).collect();
+        self.nodes = order
+            .iter()
+            .map(|&old_index| {
+                let node = old_nodes[old_index].take().expect("node visited once");
+                Node {
+                    parent: remap[node.parent],
+                    step: node.step,
+                    children: node.children.iter().map(|&child| remap[child]).collect(),
+                    active_child: node.active_child.map(|child| remap[child]),
+                }
+            })
+            .collect();
+        // Шаг нового корня уже применен и отменить его больше нельзя
+        self.nodes[0].step.clear();
+        self.nodes[0].parent = 0;
+        self.current = remap[self.current];
+        // Сохраненное состояние могло остаться в отброшенной ветке
+        self.saved = self.saved.map(|saved| remap[saved]).filter(|&saved| saved != usize::MAX);
+    }
+
+    /// Операции до парного end_group() отменяются и повторяются одним шагом;
+    /// вложенные группы сливаются во внешнюю
+    pub fn begin_group(&mut self) {
+        self.group_depth += 1;
+        self.group.get_or_insert_with(Vec::new);
+    }
+
+    pub fn end_group(&mut self) {
+        self.group_depth = self.group_depth.saturating_sub(1);
+        if self.group_depth > 0 {
+            return;
+        }
+
+        if let Some(group) = self.group.take().filter(|group| !group.is_empty()) {
+            self.push_step(group);
+        }
+    }
+
+    /// Операции шага в порядке выполнения; отменять их нужно с конца
+    pub fn undo(&mut self) -> Option<UndoStep> {
+        if self.current == 0 {
+            return None;
+        }
+
+        let node = &self.nodes[self.current];
+        let step = node.step.clone();
+        self.current = node.parent;
+        Some(step)
+    }
+
+    /// Шаг по активной ветке
+    pub fn redo(&mut self) -> Option<UndoStep> {
+        let child = self.nodes[self.current].active_child?;
+        self.current = child;
+        Some(self.nodes[child].step.clone())
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.current != 0
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.nodes[self.current].active_child.is_some()
+    }
+
+    /// Ветки redo в текущей точке; `None`, если их меньше двух
+    pub fn branches(&self) -> Option<Branches> {
+        let node = &self.nodes[self.current];
+        if node.children.len() < 2 {
+            return None;
+        }
+
+        let active = node
+            .active_child
+            .and_then(|child| node.children.iter().position(|&c| c == child))
+            .unwrap_or(0);
+        let lengths = node.children.iter().map(|&child| self.branch_length(child)).collect();
+        Some(Branches { active, lengths })
+    }
+
+    fn branch_length(&self, mut node: usize) -> usize {
+        let mut length = 1;
+        while let Some(child) = self.nodes[node].active_child {
+            node = child;
+            length += 1;
+        }
+        length
+    }
+
+    /// Делает активной соседнюю ветку redo; false, если выбирать не из чего
+    pub fn switch_branch(&mut self, forward: bool) -> bool {
+        let node = &mut self.nodes[self.current];
+        let count = node.children.len();
+        if count < 2 {
+            return false;
+        }
+
+        let active = node
+            .active_child
+            .and_then(|child| node.children.iter().position(|&c| c == child))
+            .unwrap_or(0);
+        let next = if forward { (active + 1) % count } else { (active + count - 1) % count };
+        node.active_child = Some(node.children[next]);
+        true
+    }
+
+    /// Текущее состояние записано на диск
+    pub fn mark_saved(&mut self) {
+        self.saved = Some(self.current);
+    }
+
+    /// История стоит на сохраненном состоянии, например после отмены правок, сделанных после записи
+    pub fn is_saved(&self) -> bool {
+        self.saved == Some(self.current)
+    }
+
+    pub fn clear(&mut self) {
+        self.nodes = vec![Node::root()];
+        self.current = 0;
+        self.saved = Some(0);
+        self.group = None;
+        self.group_depth = 0;
+    }
+}
+
+impl Node {
+    fn root() -> Self {
+        Self {
+            parent: 0,
+            step: Vec::new(),
+            children: Vec::new(),
+            active_child: None,
+        }
+    }
+}
+
+impl Default for UndoRedoStack {
+    fn default() -> Self {
+        Self::new(1000) // Максимум 1000 операций
+    }
+}
@@ -0,0 +1,78 @@
+use crate::sidecar;
+use anyhow::{Result, bail};
+use crossterm::style::Color;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+const ANNOTATIONS_FILE: &str = "annotations.toml";
+
+/// Цвет аннотации по умолчанию
+pub const DEFAULT_ANNOTATION_COLOR: &str = "dark_yellow";
+
+/// Заметка к диапазону байт [start, end)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Annotation {
+    pub start: usize,
+    pub end: usize,
+    pub note: String,
+    pub color: String,
+}
+
+impl Annotation {
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// Цвет подсветки; неизвестное имя цвета заменяется цветом по умолчанию
+    pub fn background(&self) -> Color {
+        Color::try_from(self.color.as_str()).unwrap_or(Color::DarkYellow)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Annotations {
+    items: Vec<Annotation>,
+}
+
+impl Annotations {
+    pub fn load(file_path: &str) -> Result<Self> {
+        let mut items: Vec<Annotation> = sidecar::load(ANNOTATIONS_FILE, file_path)?.unwrap_or_default();
+        items.sort_by_key(|a| a.start);
+        Ok(Self { items })
+    }
+
+    pub fn save(&self, file_path: &str) -> Result<()> {
+        let items = (!self.items.is_empty()).then(|| self.items.clone());
+        sidecar::store(ANNOTATIONS_FILE, file_path, items)
+    }
+
+    pub fn add(&mut self, range: Range<usize>, note: String, color: String) -> Result<()> {
+        if range.is_empty() {
+            bail!("Cannot annotate an empty range");
+        }
+        if Color::try_from(color.as_str()).is_err() {
+            bail!("Unknown color '{}' (e.g. red, dark_green, blue, magenta)", color);
+        }
+
+        let idx = self.items.partition_point(|a| a.start <= range.start);
+        self.items.insert(idx, Annotation { start: range.start, end: range.end, note, color });
+        Ok(())
+    }
+
+    /// Самая внутренняя (последняя начавшаяся) аннотация, покрывающая позицию
+    pub fn at(&self, pos: usize) -> Option<&Annotation> {
+        self.items
+            .iter()
+            .take_while(|a| a.start <= pos)
+            .filter(|a| pos < a.end)
+            .last()
+    }
+
+    pub fn remove_at(&mut self, pos: usize) -> Option<Annotation> {
+        let idx = self
+            .items
+            .iter()
+            .rposition(|a| a.range().contains(&pos))?;
+        Some(self.items.remove(idx))
+    }
+}
@@ -0,0 +1,187 @@
+use crate::config::Config;
+use crate::editor::HexEditor;
+use crate::pipe;
+use crate::utils;
+use anyhow::{Context, Result, anyhow, bail};
+use clap::ArgMatches;
+use std::io::stdout;
+use std::ops::Range;
+
+/// Правка `hexr edit`; смещения считаются от данных после предыдущих правок
+#[derive(Debug, Clone)]
+pub enum Edit {
+    WriteAt {
+        offset: usize,
+        bytes: Vec<u8>,
+    },
+    Fill {
+        range: Range<usize>,
+        pattern: Vec<u8>,
+    },
+    Truncate(usize),
+}
+
+/// Правки из файла сценария, по строке на правку: `write-at 0x1F0:DEADBEEF`,
+/// `fill 0x200..0x300:00`, `truncate 0x1000`; `#` начинает комментарий
+#[derive(Debug, Clone)]
+pub struct Script(pub Vec<Edit>);
+
+/// Аргументы с правками: порядок в командной строке - порядок применения
+const EDIT_ARGS: [&str; 3] = ["write_at", "fill", "truncate"];
+
+/// Аргумент со сценарием: его правки встают на место аргумента
+const SCRIPT_ARG: &str = "script";
+
+/// `0x1F0:DEADBEEF`
+pub fn parse_write_at(input: &str) -> Result<Edit> {
+    let (offset, hex) = input
+        .split_once(':')
+        .ok_or_else(|| anyhow!("Expected OFFSET:HEX, got '{}'", input))?;
+    let bytes = utils::hex_string_to_bytes(hex)?;
+    if bytes.is_empty() {
+        bail!("No bytes to write in '{}'", input);
+    }
+
+    Ok(Edit::WriteAt {
+        offset: utils::parse_size(offset)?,
+        bytes,
+    })
+}
+
+/// `0x200..0x300:00`: конец не включается, шаблон повторяется
+pub fn parse_fill(input: &str) -> Result<Edit> {
+    let (range, hex) = input
+        .split_once(':')
+        .ok_or_else(|| anyhow!("Expected START..END:HEX, got '{}'", input))?;
+    let range = utils::parse_range(range)?;
+
+    let pattern = utils::hex_string_to_bytes(hex)?;
+    if pattern.is_empty() {
+        bail!("Fill pattern is empty in '{}'", input);
+    }
+    Ok(Edit::Fill { range, pattern })
+}
+
+pub fn parse_truncate(input: &str) -> Result<Edit> {
+    Ok(Edit::Truncate(utils::parse_size(input)?))
+}
+
+pub fn parse_script_file(path: &str) -> Result<Script> {
+    // clap показывает только верхнюю ошибку, поэтому причины склеиваются в одну строку
+    std::fs::read_to_string(path)
+        .with_context(|| format!("Cannot read {}", path))
+        .and_then(|text| parse_script(&text).with_context(|| format!("In {}", path)))
+        .map_err(|e| anyhow!("{:#}", e))
+}
+
+fn parse_script(text: &str) -> Result<Script> {
+    let mut edits = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.split_once('#').map_or(line, |(code, _)| code).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let (command, argument) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let argument = argument.trim();
+        let edit = match command {
+            "write-at" => parse_write_at(argument),
+            "fill" => parse_fill(argument),
+            "truncate" => parse_truncate(argument),
+            _ => Err(anyhow!(
+                "Unknown command '{}': expected write-at, fill or truncate",
+                command
+            )),
+        };
+        edits.push(edit.with_context(|| format!("Line {}", index + 1))?);
+    }
+    Ok(Script(edits))
+}
+
+impl Script {
+    /// Применяет сценарий к открытому буферу одним шагом отмены
+    pub fn apply(&self, editor: &mut HexEditor) -> Result<()> {
+        editor.undo_group(|editor| self.0.iter().try_for_each(|edit| apply(editor, edit)))
+    }
+}
+
+/// Правки из аргументов подкоманды в том порядке, в котором они заданы
+pub fn edits_in_order(matches: &ArgMatches) -> Vec<Edit> {
+    let mut edits = Vec::new();
+    for id in EDIT_ARGS {
+        if let (Some(indices), Some(values)) =
+            (matches.indices_of(id), matches.get_many::<Edit>(id))
+        {
+            edits.extend(indices.zip(values.cloned()));
+        }
+    }
+    if let (Some(indices), Some(scripts)) = (
+        matches.indices_of(SCRIPT_ARG),
+        matches.get_many::<Script>(SCRIPT_ARG),
+    ) {
+        for (index, script) in indices.zip(scripts) {
+            edits.extend(script.0.iter().map(|edit| (index, edit.clone())));
+        }
+    }
+
+    // Сортировка устойчива: правки одного сценария сохраняют свой порядок
+    edits.sort_by_key(|&(index, _)| index);
+    edits.into_iter().map(|(_, edit)| edit).collect()
+}
+
+/// Применяет правки через операции редактора и сохраняет результат: в `output`,
+/// в сам файл или, для `-`, в stdout
+pub fn run(file: &str, edits: &[Edit], output: Option<&str>, config: Config) -> Result<()> {
+    if edits.is_empty() {
+        bail!("No edits given: use --write-at, --fill, --truncate or --script");
+    }
+
+    let mut editor = if file == pipe::STDIO_PATH {
+        HexEditor::from_pipe(pipe::read_stdin()?, output.is_none(), config)?
+    } else {
+        HexEditor::open_headless(file, false, config)?
+    };
+
+    for edit in edits {
+        apply(&mut editor, edit)?;
+    }
+
+    match output {
+        Some(path) => editor.write_to_path(path)?,
+        None => {
+            editor.save()?;
+            editor.write_stdout_output(&mut stdout().lock())?;
+        }
+    }
+    Ok(())
+}
+
+fn apply(editor: &mut HexEditor, edit: &Edit) -> Result<()> {
+    let len = editor.data_len();
+    match edit {
+        // Запись с самого конца дописывает байты, дальше конца - ошибка
+        Edit::WriteAt { offset, bytes } => {
+            if *offset > len {
+                bail!(
+                    "--write-at 0x{:X} is past the end of the data ({} bytes)",
+                    offset,
+                    len
+                );
+            }
+            editor.replace_bytes(*offset, bytes)
+        }
+        Edit::Fill { range, pattern } => {
+            if range.end > len {
+                bail!(
+                    "--fill 0x{:X}..0x{:X} is past the end of the data ({} bytes)",
+                    range.start,
+                    range.end,
+                    len
+                );
+            }
+            editor.fill_range(range.start, range.end, pattern)
+        }
+        // Как truncate(1): больший размер дописывает нули
+        Edit::Truncate(size) => editor.resize(*size, 0),
+    }
+}
@@ -0,0 +1,74 @@
+use crate::sidecar;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub offset: usize,
+    #[serde(default)]
+    pub label: String,
+}
+
+/// Закладки открытого файла и выбранная строка в панели
+#[derive(Debug, Default)]
+pub struct Bookmarks {
+    pub items: Vec<Bookmark>,
+    pub selected: usize,
+}
+
+const BOOKMARKS_FILE: &str = "bookmarks.toml";
+
+impl Bookmarks {
+    /// Загружает закладки файла
+    pub fn load(file_path: &str) -> Result<Self> {
+        let mut items: Vec<Bookmark> = sidecar::load(BOOKMARKS_FILE, file_path)?.unwrap_or_default();
+        items.sort_by_key(|b| b.offset);
+
+        Ok(Self { items, selected: 0 })
+    }
+
+    pub fn save(&self, file_path: &str) -> Result<()> {
+        let items = (!self.items.is_empty()).then(|| self.items.clone());
+        sidecar::store(BOOKMARKS_FILE, file_path, items)
+    }
+
+    /// Добавляет закладку или переименовывает существующую на том же смещении
+    pub fn set(&mut self, offset: usize, label: String) {
+        match self.items.binary_search_by_key(&offset, |b| b.offset) {
+            Ok(idx) => {
+                self.items[idx].label = label;
+                self.selected = idx;
+            }
+            Err(idx) => {
+                self.items.insert(idx, Bookmark { offset, label });
+                self.selected = idx;
+            }
+        }
+    }
+
+    pub fn remove_selected(&mut self) -> Option<Bookmark> {
+        if self.selected >= self.items.len() {
+            return None;
+        }
+
+        let removed = self.items.remove(self.selected);
+        self.selected = self.selected.min(self.items.len().saturating_sub(1));
+        Some(removed)
+    }
+
+    pub fn current(&self) -> Option<&Bookmark> {
+        self.items.get(self.selected)
+    }
+
+    pub fn select_next(&mut self) {
+        if !self.items.is_empty() {
+            self.selected = (self.selected + 1) % self.items.len();
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        if !self.items.is_empty() {
+            self.selected = (self.selected + self.items.len() - 1) % self.items.len();
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use serde::{Deserialize, Serialize};
+use unicode_width::UnicodeWidthChar;
+
+/// Кодировка текстовой колонки
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Charset {
+    #[default]
+    Ascii,
+    Latin1,
+    Utf8,
+    Utf16le,
+    Utf16be,
+    Cp437,
+    Ebcdic,
+}
+
+/// Символ на месте байта, который нельзя показать
+const UNPRINTABLE: char = '.';
+/// Продолжение многобайтового символа: сам символ показан на месте его первого байта
+const CONTINUATION: char = ' ';
+
+impl Charset {
+    pub fn label(self) -> &'static str {
+        match self {
+            Charset::Ascii => "ASCII",
+            Charset::Latin1 => "Latin-1",
+            Charset::Utf8 => "UTF-8",
+            Charset::Utf16le => "UTF-16LE",
+            Charset::Utf16be => "UTF-16BE",
+            Charset::Cp437 => "CP437",
+            Charset::Ebcdic => "EBCDIC",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Charset::Ascii => Charset::Latin1,
+            Charset::Latin1 => Charset::Utf8,
+            Charset::Utf8 => Charset::Utf16le,
+            Charset::Utf16le => Charset::Utf16be,
+            Charset::Utf16be => Charset::Cp437,
+            Charset::Cp437 => Charset::Ebcdic,
+            Charset::Ebcdic => Charset::Ascii,
+        }
+    }
+
+    /// По символу на каждый байт `bytes`, которые начинаются со смещения `offset` в файле
+    /// (от него зависит выравнивание UTF-16)
+    pub fn decode(self, bytes: &[u8], offset: usize) -> Vec<char> {
+        match self {
+            Charset::Ascii => bytes.iter().map(|&b| ascii(b)).collect(),
+            Charset::Latin1 => bytes.iter().map(|&b| printable(b as char)).collect(),
+            Charset::Cp437 => bytes.iter().map(|&b| cp437(b)).collect(),
+            Charset::Ebcdic => bytes.iter().map(|&b| printable(EBCDIC[b as usize])).collect(),
+            Charset::Utf8 => decode_utf8(bytes),
+            Charset::Utf16le => decode_utf16(bytes, offset, u16::from_le_bytes),
+            Charset::Utf16be => decode_utf16(bytes, offset, u16::from_be_bytes),
+        }
+    }
+}
+
+fn ascii(byte: u8) -> char {
+    if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { UNPRINTABLE }
+}
+
+/// Управляющие символы и символы шире одной колонки сбили бы выравнивание строки
+fn printable(c: char) -> char {
+    if c.is_control() || c.width() != Some(1) { UNPRINTABLE } else { c }
+}
+
+fn cp437(byte: u8) -> char {
+    match byte {
+        0x7F => '⌂',
+        0x80.. => printable(CP437_HIGH[(byte - 0x80) as usize]),
+        _ => ascii(byte),
+    }
+}
+
+fn decode_utf8(bytes: &[u8]) -> Vec<char> {
+    let mut chars = Vec::with_capacity(bytes.len());
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        let len = match bytes[pos] {
+            0xC2..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF4 => 4,
+            _ => 1,
+        };
+
+        // Неполная или некорректная последовательность показывается побайтно
+        let decoded = bytes
+            .get(pos..pos + len)
+            .filter(|_| len > 1)
+            .and_then(|seq| std::str::from_utf8(seq).ok())
+            .and_then(|text| text.chars().next());
+
+        match decoded {
+            Some(c) => {
+                chars.push(printable(c));
+                chars.extend(std::iter::repeat_n(CONTINUATION, len - 1));
+                pos += len;
+            }
+            None => {
+                chars.push(ascii(bytes[pos]));
+                pos += 1;
+            }
+        }
+    }
+
+    chars
+}
+
+fn decode_utf16(bytes: &[u8], offset: usize, unit: fn([u8; 2]) -> u16) -> Vec<char> {
+    let mut chars = Vec::with_capacity(bytes.len());
+    let read = |pos: usize| bytes.get(pos..pos + 2).map(|pair| unit([pair[0], pair[1]]));
+
+    // Кодовые единицы выровнены по четным смещениям файла
+    let mut pos = (offset % 2).min(bytes.len());
+    chars.extend(std::iter::repeat_n(UNPRINTABLE, pos));
+
+    while pos < bytes.len() {
+        let Some(first) = read(pos) else {
+            chars.push(UNPRINTABLE);
+            break;
+        };
+
+        // Суррогатная пара занимает четыре байта
+        let (c, len) = match (first, read(pos + 2)) {
+            (0xD800..=0xDBFF, Some(second @ 0xDC00..=0xDFFF)) => {
+                (char::decode_utf16([first, second]).next(), 4)
+            }
+            _ => (char::decode_utf16([first]).next(), 2),
+        };
+
+        chars.push(c.and_then(Result::ok).map_or(UNPRINTABLE, printable));
+        chars.extend(std::iter::repeat_n(CONTINUATION, len - 1));
+        pos += len;
+    }
+
+    chars.truncate(bytes.len());
+    chars
+}
+
+/// CP437 0x80..0xFF: латиница DOS и псевдографика
+const CP437_HIGH: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å',
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ',
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»',
+    '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐',
+    '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧',
+    '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀',
+    'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩',
+    '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', ' ',
+];
+
+/// EBCDIC, кодовая страница 037; управляющие коды записаны как '\0'
+const EBCDIC: [char; 256] = [
+    '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+    '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+    '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+    '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+    ' ', ' ', 'â', 'ä', 'à', 'á', 'ã', 'å', 'ç', 'ñ', '¢', '.', '<', '(', '+', '|',
+    '&', 'é', 'ê', 'ë', 'è', 'í', 'î', 'ï', 'ì', 'ß', '!', '$', '*', ')', ';', '¬',
+    '-', '/', 'Â', 'Ä', 'À', 'Á', 'Ã', 'Å', 'Ç', 'Ñ', '¦', ',', '%', '_', '>', '?',
+    'ø', 'É', 'Ê', 'Ë', 'È', 'Í', 'Î', 'Ï', 'Ì', '`', ':', '#', '@', '\'', '=', '"',
+    'Ø', 'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', '«', '»', 'ð', 'ý', 'þ', '±',
+    '°', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r', 'ª', 'º', 'æ', '¸', 'Æ', '¤',
+    'µ', '~', 's', 't', 'u', 'v', 'w', 'x', 'y', 'z', '¡', '¿', 'Ð', 'Ý', 'Þ', '®',
+    '^', '£', '¥', '·', '©', '§', '¶', '¼', '½', '¾', '[', ']', '¯', '¨', '´', '×',
+    '{', 'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', '\0', 'ô', 'ö', 'ò', 'ó', 'õ',
+    '}', 'J', 'K', 'L', 'M', 'N', 'O', 'P', 'Q', 'R', '¹', 'û', 'ü', 'ù', 'ú', 'ÿ',
+    '\\', '÷', 'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z', '²', 'Ô', 'Ö', 'Ò', 'Ó', 'Õ',
+    '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', '³', 'Û', 'Ü', 'Ù', 'Ú', '\0',
+];
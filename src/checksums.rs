@@ -0,0 +1,15 @@
+use crate::sidecar;
+use anyhow::Result;
+use hexr_core::checksum::Recipe;
+
+const CHECKSUMS_FILE: &str = "checksums.toml";
+
+/// Запомненные для файла поля сумм
+pub fn load(file_path: &str) -> Result<Vec<Recipe>> {
+    Ok(sidecar::load(CHECKSUMS_FILE, file_path)?.unwrap_or_default())
+}
+
+pub fn save(file_path: &str, recipes: &[Recipe]) -> Result<()> {
+    let recipes = (!recipes.is_empty()).then(|| recipes.to_vec());
+    sidecar::store(CHECKSUMS_FILE, file_path, recipes)
+}
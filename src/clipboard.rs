@@ -0,0 +1,41 @@
+use anyhow::{Result, anyhow};
+
+/// Системный буфер обмена. Подключение создается лениво при первом обращении,
+/// поэтому на headless терминале редактор работает, а ошибка видна только при использовании
+pub struct SystemClipboard {
+    inner: Option<arboard::Clipboard>,
+}
+
+impl SystemClipboard {
+    pub fn new() -> Self {
+        Self { inner: None }
+    }
+
+    fn connection(&mut self) -> Result<&mut arboard::Clipboard> {
+        if self.inner.is_none() {
+            let clipboard = arboard::Clipboard::new()
+                .map_err(|e| anyhow!("System clipboard unavailable: {}", e))?;
+            self.inner = Some(clipboard);
+        }
+
+        Ok(self.inner.as_mut().expect("clipboard connection initialized above"))
+    }
+
+    pub fn set_text(&mut self, text: String) -> Result<()> {
+        self.connection()?
+            .set_text(text)
+            .map_err(|e| anyhow!("Failed to write system clipboard: {}", e))
+    }
+
+    pub fn get_text(&mut self) -> Result<String> {
+        self.connection()?
+            .get_text()
+            .map_err(|e| anyhow!("Failed to read system clipboard: {}", e))
+    }
+}
+
+impl Default for SystemClipboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
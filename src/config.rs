@@ -1,48 +1,172 @@
-use serde::{Deserialize, Serialize};
+use crate::charset::Charset;
+use crate::display::Minimap;
+use crate::highlight::HighlightRule;
+use crate::hexdump::OffsetRadix;
+use crate::keymap::KeyPreset;
+use crate::plugin::PluginConfig;
+use crate::utils;
+use anyhow::Context;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fmt;
 use std::path::PathBuf;
+use std::str::FromStr;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Config {
     pub editor: EditorConfig,
     pub display: DisplayConfig,
     pub colors: ColorConfig,
+    // Переназначения клавиш: "ctrl+s" = "save"
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub keys: BTreeMap<String, String>,
+    // Внешние программы с командами, строками инспектора и раскраской: [plugins.<имя>]
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub plugins: BTreeMap<String, PluginConfig>,
+    // Правила подсветки по шаблону или серии байт: [[highlight]]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub highlight: Vec<HighlightRule>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct EditorConfig {
-    pub bytes_per_line: usize,
+    pub bytes_per_line: BytesPerLine,
+    // Значение из -w/--bytes-per-line: действует в этой сессии и не сохраняется
+    #[serde(skip)]
+    pub bytes_per_line_override: Option<BytesPerLine>,
     pub tab_size: usize,
     pub auto_save: bool,
     pub auto_save_interval: u64, // в секундах
+    pub insert_mode: bool,       // true - ввод вставляет байты, false - заменяет
+    pub backup_on_save: bool,    // сохранять прежнее содержимое в <файл>.bak
+    pub keymap: KeyPreset,
+    // Запоминать курсор, режим и последний поиск для каждого файла
+    pub restore_session: bool,
+}
+
+/// Байт в строке: одно из допустимых значений или `auto` - по ширине терминала
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BytesPerLine {
+    Auto,
+    Fixed(usize),
+}
+
+impl BytesPerLine {
+    pub const ALLOWED: [usize; 4] = [8, 16, 24, 32];
+}
+
+impl FromStr for BytesPerLine {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("auto") {
+            return Ok(BytesPerLine::Auto);
+        }
+        match s.parse::<usize>() {
+            Ok(n) if Self::ALLOWED.contains(&n) => Ok(BytesPerLine::Fixed(n)),
+            _ => Err(format!("bytes per line must be 8, 16, 24, 32 or auto, got '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for BytesPerLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BytesPerLine::Auto => write!(f, "auto"),
+            BytesPerLine::Fixed(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+// В конфигурации записывается числом или строкой "auto"
+impl Serialize for BytesPerLine {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            BytesPerLine::Auto => serializer.serialize_str("auto"),
+            BytesPerLine::Fixed(n) => serializer.serialize_u64(*n as u64),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for BytesPerLine {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Number(u64),
+            Text(String),
+        }
+
+        let text = match Raw::deserialize(deserializer)? {
+            Raw::Number(n) => n.to_string(),
+            Raw::Text(text) => text,
+        };
+        text.parse().map_err(serde::de::Error::custom)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct DisplayConfig {
     pub show_line_numbers: bool,
     pub show_ascii: bool,
     pub highlight_current_line: bool,
     pub show_status_bar: bool,
+    pub scroll_margin: usize, // строк контекста над/под курсором при прокрутке
+    pub charset: Charset,     // кодировка текстовой колонки
+    pub byte_group: usize,    // байт в группе hex-колонки: 1, 2, 4 или 8
+    pub offset_radix: OffsetRadix,
+    pub colorize_bytes: bool, // раскраска байт по классам
+    pub show_scrollbar: bool,
+    pub minimap: Minimap,
+    pub squeeze: bool, // одинаковые строки подряд сворачиваются в "*", как в xxd
 }
 
+/// Имена цветов, `#rrggbb` или номера 256-цветной палитры (см. theme::parse_color)
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ColorConfig {
     pub background: String,
     pub foreground: String,
     pub cursor: String,
     pub selection: String,
+    // Фон строки курсора при highlight_current_line
+    pub current_line: String,
     pub header: String,
+    pub offset: String,
     pub status_bar: String,
     pub modified_indicator: String,
+    // Классы байт в hex- и текстовой колонках
+    pub null_byte: String,
+    pub printable_byte: String,
+    pub high_byte: String,
+    pub ff_byte: String,
+    // Имя файла темы в themes/ каталога конфигурации, без .toml
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
 }
 
 
+impl EditorConfig {
+    /// Действующая ширина строки: из командной строки, если задана, иначе из конфигурации
+    pub fn bytes_per_line(&self) -> BytesPerLine {
+        self.bytes_per_line_override.unwrap_or(self.bytes_per_line)
+    }
+}
+
 impl Default for EditorConfig {
     fn default() -> Self {
         Self {
-            bytes_per_line: 16,
+            bytes_per_line: BytesPerLine::Fixed(16),
+            bytes_per_line_override: None,
             tab_size: 4,
             auto_save: false,
             auto_save_interval: 30,
+            insert_mode: false,
+            backup_on_save: false,
+            keymap: KeyPreset::Default,
+            restore_session: true,
         }
     }
 }
@@ -54,6 +178,14 @@ impl Default for DisplayConfig {
             show_ascii: true,
             highlight_current_line: true,
             show_status_bar: true,
+            scroll_margin: 3,
+            charset: Charset::default(),
+            byte_group: 1,
+            offset_radix: OffsetRadix::default(),
+            colorize_bytes: true,
+            show_scrollbar: true,
+            minimap: Minimap::default(),
+            squeeze: false,
         }
     }
 }
@@ -63,11 +195,18 @@ impl Default for ColorConfig {
         Self {
             background: "black".to_string(),
             foreground: "white".to_string(),
-            cursor: "green".to_string(),
+            cursor: "dark_green".to_string(),
             selection: "blue".to_string(),
-            header: "blue".to_string(),
-            status_bar: "grey".to_string(),
+            current_line: "236".to_string(),
+            header: "dark_blue".to_string(),
+            offset: "yellow".to_string(),
+            status_bar: "dark_grey".to_string(),
             modified_indicator: "red".to_string(),
+            null_byte: "dark_grey".to_string(),
+            printable_byte: "cyan".to_string(),
+            high_byte: "yellow".to_string(),
+            ff_byte: "magenta".to_string(),
+            theme: None,
         }
     }
 }
@@ -102,32 +241,58 @@ impl Config {
         Self::default()
     }
 
-    pub fn save(&self) -> anyhow::Result<()> {
+    /// Меняет настройки в файле конфигурации и возвращает записанное. Файл перечитывается:
+    /// в памяти вкладки есть настройки сессии и вид после ошибок загрузки, а другие вкладки
+    /// могли сохранить свое. Файл, который не удалось разобрать, не перезаписывается
+    pub fn update(change: impl FnOnce(&mut Config)) -> anyhow::Result<Config> {
+        let config_path = Self::get_config_path();
+        let mut config: Config = match std::fs::read_to_string(&config_path) {
+            Ok(content) => toml::from_str(&content).with_context(|| {
+                format!("{} is damaged, so it is left as is; fix it to save settings", config_path.display())
+            })?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(e).with_context(|| format!("Cannot read {}", config_path.display())),
+        };
+        change(&mut config);
+        config.save()?;
+        Ok(config)
+    }
+
+    fn save(&self) -> anyhow::Result<()> {
         let config_path = Self::get_config_path();
+        if let Some(parent) = config_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
         let content = toml::to_string_pretty(self)?;
-        std::fs::write(config_path, content)?;
-        Ok(())
+        utils::write_file_atomic(&config_path.to_string_lossy(), [content.as_bytes()], false)
     }
 
     fn get_config_path() -> PathBuf {
+        Self::config_dir().join("config.toml")
+    }
+
+    /// Каталог с файлами тем
+    pub fn themes_dir() -> PathBuf {
+        Self::config_dir().join("themes")
+    }
+
+    /// Каталог с шаблонами двоичных форматов
+    pub fn templates_dir() -> PathBuf {
+        Self::config_dir().join("templates")
+    }
+
+    fn config_dir() -> PathBuf {
         let mut path = dirs::config_dir()
             .unwrap_or_else(|| PathBuf::from("."));
         path.push("hexr");
-        path.push("config.toml");
         path
     }
 
     fn create_default_config() -> anyhow::Result<()> {
-        let config = Self::default();
         let config_path = Self::get_config_path();
-
-        // Создаем директорию если её нет
-        if let Some(parent) = config_path.parent() {
-            std::fs::create_dir_all(parent)?;
-        }
-
-        config.save()?;
-        println!("Created default config file at: {:?}", config_path);
+        Self::default().save()?;
+        // В stderr: stdout может быть выводом dump или данными из канала
+        eprintln!("Created default config file at: {:?}", config_path);
         Ok(())
     }
 
@@ -0,0 +1,210 @@
+use crate::utils;
+use anyhow::Result;
+use hexr_core::search::SearchSource;
+use serde::Serialize;
+use std::io::Write;
+use std::ops::Range;
+
+/// Сколько байт сравнивается за один запрос к буферам
+const DIFF_CHUNK: usize = 1024 * 1024;
+
+/// Отличается ли байт `pos` в двух буферах. Байты за концом более короткого буфера
+/// считаются отличающимися, за концом обоих - нет
+pub fn differs_at<A, B>(a: &A, b: &B, pos: usize) -> bool
+where
+    A: SearchSource + ?Sized,
+    B: SearchSource + ?Sized,
+{
+    let common = a.len().min(b.len());
+    if pos >= common {
+        return pos < a.len().max(b.len());
+    }
+
+    a.range(pos..pos + 1) != b.range(pos..pos + 1)
+}
+
+/// Участки различий по порядку: каждый - максимальный диапазон отличающихся байт
+pub fn differing_runs<'a, A, B>(a: &'a A, b: &'a B) -> impl Iterator<Item = Range<usize>> + 'a
+where
+    A: SearchSource + ?Sized,
+    B: SearchSource + ?Sized,
+{
+    let total = a.len().max(b.len());
+    let mut pos = 0;
+
+    std::iter::from_fn(move || {
+        let start = scan_forward(a, b, pos, true)?;
+        let end = scan_forward(a, b, start, false).unwrap_or(total);
+        pos = end;
+        Some(start..end)
+    })
+}
+
+/// Начало следующего участка различий после участка, на котором стоит `from`
+pub fn next_difference<A, B>(a: &A, b: &B, from: usize) -> Option<usize>
+where
+    A: SearchSource + ?Sized,
+    B: SearchSource + ?Sized,
+{
+    let run_end = scan_forward(a, b, from, false)?;
+    scan_forward(a, b, run_end, true)
+}
+
+/// Начало предыдущего участка различий перед участком, на котором стоит `from`
+pub fn previous_difference<A, B>(a: &A, b: &B, from: usize) -> Option<usize>
+where
+    A: SearchSource + ?Sized,
+    B: SearchSource + ?Sized,
+{
+    let current = if differs_at(a, b, from) { run_start(a, b, from) } else { from };
+    let last = scan_backward(a, b, current, true)?;
+    Some(run_start(a, b, last))
+}
+
+/// Начало участка различий, которому принадлежит `pos`
+fn run_start<A, B>(a: &A, b: &B, pos: usize) -> usize
+where
+    A: SearchSource + ?Sized,
+    B: SearchSource + ?Sized,
+{
+    scan_backward(a, b, pos, false).map_or(0, |equal| equal + 1)
+}
+
+/// Первая позиция `>= from`, где различие равно `want`
+fn scan_forward<A, B>(a: &A, b: &B, from: usize, want: bool) -> Option<usize>
+where
+    A: SearchSource + ?Sized,
+    B: SearchSource + ?Sized,
+{
+    let common = a.len().min(b.len());
+    let total = a.len().max(b.len());
+
+    let mut start = from;
+    while start < common {
+        let end = (start + DIFF_CHUNK).min(common);
+        let (left, right) = (a.range(start..end), b.range(start..end));
+        if let Some(idx) = left.iter().zip(right.iter()).position(|(x, y)| (x != y) == want) {
+            return Some(start + idx);
+        }
+        start = end;
+    }
+
+    // Хвост длинного буфера целиком отличается
+    let start = from.max(common);
+    (want && start < total).then_some(start)
+}
+
+/// Последняя позиция `< before`, где различие равно `want`
+fn scan_backward<A, B>(a: &A, b: &B, before: usize, want: bool) -> Option<usize>
+where
+    A: SearchSource + ?Sized,
+    B: SearchSource + ?Sized,
+{
+    let common = a.len().min(b.len());
+    let before = before.min(a.len().max(b.len()));
+
+    if before > common && want {
+        return Some(before - 1);
+    }
+
+    let mut end = before.min(common);
+    while end > 0 {
+        let start = end.saturating_sub(DIFF_CHUNK);
+        let (left, right) = (a.range(start..end), b.range(start..end));
+        if let Some(idx) = left.iter().zip(right.iter()).rposition(|(x, y)| (x != y) == want) {
+            return Some(start + idx);
+        }
+        end = start;
+    }
+
+    None
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum ReportFormat {
+    Text,
+    Json,
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    first: &'a str,
+    second: &'a str,
+    first_len: usize,
+    second_len: usize,
+    ranges: Vec<ReportRange>,
+}
+
+#[derive(Serialize)]
+struct ReportRange {
+    offset: usize,
+    length: usize,
+    old: String,
+    new: String,
+}
+
+/// Отчет о различиях для `hexr diff --summary`: смещение, длина и байты обеих сторон.
+/// Показывается не больше `max_bytes` байт каждой стороны (0 - все). Возвращает число участков
+pub fn write_report<A, B>(
+    out: &mut impl Write,
+    names: (&str, &str),
+    a: &A,
+    b: &B,
+    format: ReportFormat,
+    max_bytes: usize,
+) -> Result<usize>
+where
+    A: SearchSource + ?Sized,
+    B: SearchSource + ?Sized,
+{
+    match format {
+        ReportFormat::Text => {
+            writeln!(out, "--- {} ({} bytes)", names.0, a.len())?;
+            writeln!(out, "+++ {} ({} bytes)", names.1, b.len())?;
+
+            let mut count = 0;
+            for range in differing_runs(a, b) {
+                let truncated = max_bytes != 0 && range.len() > max_bytes;
+                let more = if truncated { " ..." } else { "" };
+                writeln!(out, "0x{:08X}  {} bytes", range.start, range.len())?;
+                for (sign, bytes) in [("-", shown_bytes(a, &range, max_bytes)), ("+", shown_bytes(b, &range, max_bytes))] {
+                    // Участок за концом одного из файлов
+                    let bytes = if bytes.is_empty() { "(none)".to_string() } else { bytes + more };
+                    writeln!(out, "  {} {}", sign, bytes)?;
+                }
+                count += 1;
+            }
+
+            writeln!(out, "{} differing range(s)", count)?;
+            Ok(count)
+        }
+        ReportFormat::Json => {
+            let ranges: Vec<ReportRange> = differing_runs(a, b)
+                .map(|range| ReportRange {
+                    offset: range.start,
+                    length: range.len(),
+                    old: shown_bytes(a, &range, max_bytes),
+                    new: shown_bytes(b, &range, max_bytes),
+                })
+                .collect();
+            let count = ranges.len();
+
+            let report = Report {
+                first: names.0,
+                second: names.1,
+                first_len: a.len(),
+                second_len: b.len(),
+                ranges,
+            };
+            serde_json::to_writer_pretty(&mut *out, &report)?;
+            writeln!(out)?;
+            Ok(count)
+        }
+    }
+}
+
+/// Начало диапазона в hex, не больше `max_bytes` байт (0 - все)
+fn shown_bytes<D: SearchSource + ?Sized>(data: &D, range: &Range<usize>, max_bytes: usize) -> String {
+    let end = if max_bytes == 0 { range.end } else { range.end.min(range.start + max_bytes) };
+    utils::bytes_to_hex_string(&data.range(range.start..end))
+}
@@ -1,63 +1,540 @@
-use crate::config::Config;
-use crate::editor::{EditMode, HexEditor};
+use crate::editor::{EditMode, HexEditor, ScreenLine, StatsPanel, Viewport};
+use crate::hexdump::is_group_end;
+use crate::help::{Help, HelpLine};
+use crate::inspect;
+use crate::keymap::Keymap;
+use crate::notify::Severity;
+use crate::plugin::Plugins;
+use crate::screen::{self, Screen};
+use crate::tabs::Tabs;
+use crate::theme::Theme;
 use anyhow::Result;
 use crossterm::{
-    ExecutableCommand, cursor, execute,
+    cursor,
+    event::{self, Event, KeyCode, KeyEventKind},
+    execute,
     style::{Color, ResetColor, SetBackgroundColor, SetForegroundColor},
     terminal::{self, Clear, ClearType},
 };
-use std::io::{BufWriter, Stdout, Write, stdout};
+use hexr_core::entropy;
+use hexr_core::progress::{Cancelled, Progress};
+use hexr_core::numeric::{Endian, NumericType};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::io::{BufWriter, Write, stdout};
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+/// Высота панели совпадений вместе со строкой заголовка
+pub const MATCH_PANEL_HEIGHT: usize = 8;
+
+/// Высота инспектора данных: заголовок, пять строк типов, строка с битами
+/// и строки расшифровок по две в строке
+pub const INSPECTOR_PANEL_HEIGHT: usize = 7 + inspect::DECODERS.len() / 2;
+
+/// Высота панели дизассемблера вместе со строкой заголовка
+pub const DISASM_PANEL_HEIGHT: usize = 8;
+
+/// Строки инспектора: беззнаковый и знаковый тип рядом
+pub const INSPECTOR_ROWS: [(NumericType, NumericType); 5] = [
+    (NumericType::U8, NumericType::I8),
+    (NumericType::U16, NumericType::I16),
+    (NumericType::U32, NumericType::I32),
+    (NumericType::U64, NumericType::I64),
+    (NumericType::F32, NumericType::F64),
+];
+
+/// Столбцы инспектора: у каждого из двух типов строки LE и BE
+pub const INSPECTOR_COLUMNS: usize = 4;
+
+/// Сколько байтов контекста показывать в строке панели совпадений
+const MATCH_CONTEXT_BYTES: usize = 16;
+
+/// Строк гистограммы в панели статистики; каждая строка - восемь уровней столбца
+const HISTOGRAM_ROWS: usize = 4;
+
+/// Сколько самых частых значений перечисляет панель статистики
+const STATS_COMMON_VALUES: usize = 8;
+
+/// Полоса-миникарта рядом с полосой прокрутки: каждая строка - участок файла
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Minimap {
+    #[default]
+    Off,
+    Entropy,
+    Class,
+}
+
+impl Minimap {
+    pub fn label(self) -> &'static str {
+        match self {
+            Minimap::Off => "off",
+            Minimap::Entropy => "entropy",
+            Minimap::Class => "byte class",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Minimap::Off => Minimap::Entropy,
+            Minimap::Entropy => Minimap::Class,
+            Minimap::Class => Minimap::Off,
+        }
+    }
+}
+
+/// Сколько байт участка читается для строки миникарты: оценка вместо чтения всего файла
+const MINIMAP_SAMPLE_BYTES: usize = 4096;
+
+/// Строки данных в каждой половине окна: верхняя, нижняя; между ними строка-разделитель
+pub fn split_lines(lines: usize) -> (usize, usize) {
+    let rows = lines.saturating_sub(1);
+    (rows / 2, rows - rows / 2)
+}
+
+/// Область данных на экране и положение, которое в ней показывается
+struct PaneView {
+    top: usize,
+    lines: usize,
+    cursor_pos: usize,
+    view_offset: usize,
+    focused: bool,
+}
+
+/// Байт под курсором мыши
+#[derive(Debug, Clone, Copy)]
+pub struct ScreenHit {
+    pub offset: usize,
+    // Текстовая колонка, а не hex
+    pub text: bool,
+    // Бит в битовом режиме, 0 - старший
+    pub bit: Option<u8>,
+    // Половина окна, в которой стоит курсор
+    pub focused: bool,
+}
+
+/// Строка одной стороны в режиме сравнения
+struct CompareLine {
+    offset: usize,
+    bytes_per_line: usize,
+    // Начало строки в окне данных
+    start: usize,
+    cursor_pos: usize,
+    mode: EditMode,
+}
+
+/// Строки экрана подряд, без "*" между ними, с раскраской их байт
+struct Segment<'a> {
+    start: usize,
+    window: Cow<'a, [u8]>,
+    text: Vec<char>,
+    // Несохраненные изменения выделяются цветом поверх раскраски по классам
+    modified: Vec<Range<usize>>,
+    // Шаблоны плагинов важнее классов байт
+    plugin_colors: Vec<(Range<usize>, Color)>,
+    // Правила подсветки важнее шаблонов
+    highlights: Vec<(Range<usize>, Color)>,
+}
+
+impl Segment<'_> {
+    fn is_modified(&self, pos: usize) -> bool {
+        self.modified.iter().any(|range| range.contains(&pos))
+    }
+
+    fn plugin_color(&self, pos: usize) -> Option<Color> {
+        span_color(&self.plugin_colors, pos)
+    }
+
+    fn highlight_color(&self, pos: usize) -> Option<Color> {
+        span_color(&self.highlights, pos)
+    }
+}
+
+fn span_color(spans: &[(Range<usize>, Color)], pos: usize) -> Option<Color> {
+    spans.iter().find(|(range, _)| range.contains(&pos)).map(|&(_, color)| color)
+}
+
+impl CompareLine {
+    /// Байты `window`, которые отличаются от `other` или отсутствуют в нем, выделяются красным
+    fn draw(
+        &self,
+        stdout: &mut Screen,
+        theme: &Theme,
+        window: &[u8],
+        other: &[u8],
+        focused: bool,
+    ) -> Result<()> {
+        let cursor_color = if focused { theme.cursor } else { Color::DarkGrey };
+
+        execute!(stdout, SetForegroundColor(theme.offset))?;
+        write!(stdout, "{:08X}  ", self.offset)?;
+        execute!(stdout, ResetColor)?;
+
+        for ascii in [false, true] {
+            for byte_idx in 0..self.bytes_per_line {
+                let idx = self.start + byte_idx;
+                let pos = self.offset + byte_idx;
+
+                let Some(&byte) = window.get(idx) else {
+                    write!(stdout, "{}", if ascii { " " } else { "   " })?;
+                    continue;
+                };
+
+                let is_cursor = pos == self.cursor_pos && (self.mode == EditMode::Ascii) == ascii;
+                if is_cursor {
+                    execute!(stdout, SetBackgroundColor(cursor_color))?;
+                    execute!(stdout, SetForegroundColor(theme.foreground))?;
+                } else if pos == self.cursor_pos {
+                    // Тот же байт во второй колонке
+                    execute!(stdout, SetBackgroundColor(Color::DarkGrey))?;
+                    execute!(stdout, SetForegroundColor(theme.foreground))?;
+                } else if other.get(idx) != Some(&byte) {
+                    execute!(stdout, SetForegroundColor(Color::Red))?;
+                }
+
+                if ascii {
+                    let ch = if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' };
+                    write!(stdout, "{}", ch)?;
+                } else {
+                    write!(stdout, "{:02X}", byte)?;
+                    execute!(stdout, ResetColor)?;
+                    write!(stdout, " ")?;
+                }
+                execute!(stdout, ResetColor)?;
+            }
+
+            if !ascii {
+                write!(stdout, " ")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Размер окна, когда терминала нет
+const HEADLESS_SIZE: (u16, u16) = (80, 24);
+
+/// Операция короче этого завершается без полосы прогресса, чтобы строка состояния не мигала
+const PROGRESS_DELAY: Duration = Duration::from_millis(200);
+
+/// Полоса прогресса перерисовывается и проверяет Esc не чаще этого
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Ширина полосы в символах
+const PROGRESS_BAR_WIDTH: usize = 20;
+
+/// Полоса вида `█████░░░░░` для `done` из `total`
+pub fn progress_bar(done: usize, total: usize) -> String {
+    let filled = (done.min(total) * PROGRESS_BAR_WIDTH).checked_div(total).unwrap_or(PROGRESS_BAR_WIDTH);
+    format!("{}{}", "█".repeat(filled), "░".repeat(PROGRESS_BAR_WIDTH - filled))
+}
+
+/// Ход долгой операции в строке состояния. Операция идет в основном потоке и экран
+/// между кадрами не обновляется, поэтому полоса рисуется прямо из `update`, а Esc
+/// проверяется там же. Без терминала (пакетный режим) ничего не выводится
+pub struct ProgressBar {
+    label: String,
+    cancellable: bool,
+    started: Instant,
+    drawn: Option<Instant>,
+}
+
+impl ProgressBar {
+    /// Операция, которую Esc прерывает без вреда для данных
+    pub fn new(label: impl Into<String>) -> Self {
+        Self { label: label.into(), cancellable: true, started: Instant::now(), drawn: None }
+    }
+
+    /// Операция, которую нельзя бросить на середине, например запись файла
+    pub fn uncancellable(label: impl Into<String>) -> Self {
+        Self { cancellable: false, ..Self::new(label) }
+    }
+
+    fn draw(&self, done: usize, total: usize) -> Result<()> {
+        let (width, height) = terminal::size()?;
+        let percent = (done.min(total) * 100).checked_div(total).unwrap_or(100);
+        let hint = if self.cancellable { " (Esc: cancel)" } else { "" };
+        let text = format!(" {}... {} {}%{}", self.label, progress_bar(done, total), percent, hint);
+
+        screen::invalidate();
+        let mut stdout = stdout();
+        execute!(stdout, cursor::MoveTo(0, height.saturating_sub(1)))?;
+        execute!(stdout, SetBackgroundColor(Color::DarkGrey), SetForegroundColor(Color::White))?;
+        write!(stdout, "{:width$}", text, width = width as usize)?;
+        execute!(stdout, ResetColor)?;
+        stdout.flush()?;
+        Ok(())
+    }
+}
+
+impl Progress for ProgressBar {
+    fn update(&mut self, done: usize, total: usize) -> Result<()> {
+        let now = Instant::now();
+        if now - self.started < PROGRESS_DELAY || self.drawn.is_some_and(|drawn| now - drawn < PROGRESS_INTERVAL) {
+            return Ok(());
+        }
+        if !terminal::is_raw_mode_enabled()? {
+            return Ok(());
+        }
+        self.drawn = Some(now);
+
+        // Остальные нажатия во время операции отбрасываются: экран все равно занят ею
+        while self.cancellable && event::poll(Duration::ZERO)? {
+            if let Event::Key(key) = event::read()?
+                && key.code == KeyCode::Esc
+                && key.kind == KeyEventKind::Press
+            {
+                return Err(Cancelled.into());
+            }
+        }
+        self.draw(done, total)
+    }
+}
 
 pub struct Display {
     width: u16,
     height: u16,
-    config: Config,
+    theme: Theme,
+    // Открытая справка закрывает собой весь экран
+    help: Option<Help>,
+    // Режим ввода vim и набранный счетчик
+    key_status: Option<String>,
+    // Строки инспектора и раскраска от плагинов
+    plugins: Plugins,
+    // Прошлый кадр: в терминал выводятся только отличия от него
+    screen: Screen,
 }
 
 impl Display {
     pub fn new() -> Result<Self> {
-        let (width, height) = terminal::size()?;
+        // Без терминала (пакетные правки, CI) размер окна ни на что не влияет
+        let (width, height) = terminal::size().unwrap_or(HEADLESS_SIZE);
         Ok(Self {
             width,
             height,
-            config: Config::default(),
+            theme: Theme::default(),
+            help: None,
+            key_status: None,
+            plugins: Plugins::default(),
+            screen: Screen::default(),
         })
     }
 
-    pub fn draw(&mut self, editor: &HexEditor) -> Result<()> {
-        // Обновление размеров терминала
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    pub fn set_key_status(&mut self, status: Option<String>) {
+        self.key_status = status;
+    }
+
+    pub fn set_plugins(&mut self, plugins: Plugins) {
+        self.plugins = plugins;
+    }
+
+    pub fn plugins(&self) -> &Plugins {
+        &self.plugins
+    }
+
+    pub fn open_help(&mut self, keymap: &Keymap) {
+        self.help = Some(Help::new(keymap, &self.plugins));
+    }
+
+    pub fn close_help(&mut self) {
+        self.help = None;
+    }
+
+    pub fn is_help_open(&self) -> bool {
+        self.help.is_some()
+    }
+
+    pub fn scroll_help(&mut self, lines: isize) {
+        let rows = self.help_rows();
+        if let Some(help) = &mut self.help {
+            help.scroll(lines, rows);
+        }
+    }
+
+    /// Строки справки между заголовком и строкой подсказки
+    pub fn help_rows(&self) -> usize {
+        (self.height as usize).saturating_sub(2)
+    }
+
+    /// Перечитывает размер терминала; вызывается перед отрисовкой, чтобы редакторы
+    /// получили новый размер до расчета строк
+    pub fn resize(&mut self) -> Result<Viewport> {
         let (width, height) = terminal::size()?;
         self.width = width;
         self.height = height;
+        Ok(self.viewport())
+    }
+
+    pub fn viewport(&self) -> Viewport {
+        Viewport {
+            width: self.width(),
+            lines: self.get_visible_lines(),
+        }
+    }
 
-        // Используем буферизированный вывод для уменьшения мерцания
-        let mut stdout = BufWriter::new(stdout());
+    pub fn draw(&mut self, tabs: &Tabs) -> Result<()> {
+        // Кадр собирается в памяти, в терминал уходят только изменившиеся ячейки
+        let mut frame = std::mem::take(&mut self.screen);
+        frame.begin(self.width, self.height);
+        let drawn = self.draw_frame(&mut frame, tabs);
+        let presented = frame.present(&mut BufWriter::new(stdout()));
+        self.screen = frame;
+        drawn?;
+        Ok(presented?)
+    }
 
-        // Перемещаемся в начало, но НЕ очищаем весь экран
-        execute!(stdout, cursor::MoveTo(0, 0))?;
+    fn draw_frame(&mut self, stdout: &mut Screen, tabs: &Tabs) -> Result<()> {
+        let editor = tabs.active();
 
-        // Отрисовка компонентов
-        self.draw_header_buffered(&mut stdout, editor)?;
-        self.draw_content_buffered(&mut stdout, editor)?;
-        self.draw_status_bar_buffered(&mut stdout, editor)?;
+        if let Some(help) = &self.help {
+            return self.draw_help_buffered(stdout, help);
+        }
 
-        // Сбрасываем буфер один раз
-        stdout.flush()?;
+        // Отрисовка компонентов
+        self.draw_header_buffered(stdout, editor)?;
+        self.draw_tab_bar_buffered(stdout, tabs)?;
+        if let Some((left, right)) = tabs.compare_pair() {
+            self.draw_compare_buffered(stdout, editor, left, right)?;
+        } else {
+            let panes = self.pane_views(editor);
+            self.draw_content_buffered(stdout, editor, &panes[0])?;
+            if let Some(lower) = panes.get(1) {
+                self.draw_split_separator_buffered(stdout, lower.top - 1)?;
+                self.draw_content_buffered(stdout, editor, lower)?;
+            }
+        }
+        if editor.get_match_list().is_some() {
+            self.draw_match_panel_buffered(stdout, editor)?;
+        } else if editor.is_bookmark_panel_open() {
+            self.draw_bookmark_panel_buffered(stdout, editor)?;
+        } else if let Some(stats) = editor.stats_panel() {
+            self.draw_stats_panel_buffered(stdout, editor, stats)?;
+        } else if editor.is_template_panel_open() {
+            self.draw_template_panel_buffered(stdout, editor)?;
+        } else if editor.is_region_panel_open() {
+            self.draw_region_panel_buffered(stdout, editor)?;
+        }
+        if editor.disasm_height() > 0 {
+            self.draw_disasm_buffered(stdout, editor)?;
+        }
+        if editor.inspector_height() > 0 {
+            let plugin_rows = self.plugins.inspect(editor);
+            self.draw_inspector_buffered(stdout, editor, &plugin_rows)?;
+        }
+        self.draw_status_bar_buffered(stdout, editor)?;
         Ok(())
     }
 
+    /// Области данных сверху вниз: одна или две при разделенном окне
+    fn pane_views(&self, editor: &HexEditor) -> Vec<PaneView> {
+        let lines = self.content_lines(editor);
+        match editor.split_panes() {
+            Some([(upper, upper_focused), (lower, lower_focused)]) => {
+                let (upper_lines, lower_lines) = split_lines(lines);
+                vec![
+                    PaneView {
+                        top: 3,
+                        lines: upper_lines,
+                        cursor_pos: upper.cursor_pos,
+                        view_offset: upper.view_offset,
+                        focused: upper_focused,
+                    },
+                    PaneView {
+                        top: 3 + upper_lines + 1,
+                        lines: lower_lines,
+                        cursor_pos: lower.cursor_pos,
+                        view_offset: lower.view_offset,
+                        focused: lower_focused,
+                    },
+                ]
+            }
+            None => vec![PaneView {
+                top: 3,
+                lines,
+                cursor_pos: editor.get_cursor_pos(),
+                view_offset: editor.get_view_offset(),
+                focused: true,
+            }],
+        }
+    }
+
+    /// Байт под точкой экрана (для мыши); раскладка та же, что у draw_content_buffered.
+    /// `None` вне данных и в режиме сравнения
+    pub fn hit_test(&self, tabs: &Tabs, column: u16, row: u16) -> Option<ScreenHit> {
+        if tabs.compare_pair().is_some() {
+            return None;
+        }
+
+        let editor = tabs.active();
+        let (column, row) = (column as usize, row as usize);
+        let pane = self
+            .pane_views(editor)
+            .into_iter()
+            .find(|pane| (pane.top..pane.top + pane.lines).contains(&row))?;
+
+        let bytes_per_line = editor.line_len();
+        // Щелчок по "*" свернутых строк не попадает в байт
+        let ScreenLine::Data(line_offset) = editor
+            .screen_lines(pane.view_offset, row - pane.top + 1, pane.cursor_pos)
+            .pop()?
+        else {
+            return None;
+        };
+        let group = editor.byte_group();
+        let bits = editor.is_bit_mode();
+
+        // Hex-колонка: ячейки по 3 колонки (9 в битовом режиме) и разделители групп,
+        // щелчок по разделителю относится к байту слева
+        let mut x = editor.offset_radix().digits() + 2;
+        if column < x {
+            return None;
+        }
+        let mut hit = None;
+        for byte_idx in 0..bytes_per_line {
+            let cell = if bits { 9 } else { 3 };
+            let gap = usize::from(!bits && is_group_end(byte_idx, group, bytes_per_line));
+            if column < x + cell + gap {
+                let bit = (bits && column - x < 8).then(|| (column - x) as u8);
+                hit = Some((byte_idx, false, bit));
+                break;
+            }
+            x += cell + gap;
+        }
+
+        // Текстовая колонка после пробела-разделителя
+        let text_start = x + 1;
+        if hit.is_none() && (text_start..text_start + bytes_per_line).contains(&column) {
+            hit = Some((column - text_start, true, None));
+        }
+
+        let (byte_idx, text, bit) = hit?;
+        let offset = line_offset + byte_idx;
+        (offset < editor.data_len()).then_some(ScreenHit { offset, text, bit, focused: pane.focused })
+    }
+
     fn draw_header_buffered(
         &self,
-        stdout: &mut BufWriter<Stdout>,
+        stdout: &mut Screen,
         editor: &HexEditor,
     ) -> Result<()> {
         execute!(stdout, cursor::MoveTo(0, 0))?;
-        execute!(stdout, SetBackgroundColor(Color::DarkBlue))?;
-        execute!(stdout, SetForegroundColor(Color::White))?;
+        execute!(stdout, SetBackgroundColor(self.theme.header))?;
+        execute!(stdout, SetForegroundColor(self.theme.foreground))?;
 
+        let file_type = match (editor.hex_file_label().or_else(|| editor.process_label()), editor.file_type()) {
+            (Some(label), Some(name)) => Some(format!("({}, {})", label, name)),
+            (label, name) => label.or(name).map(|name| format!("({})", name)),
+        };
         let header = format!(
-            " HEX EDITOR - {} {} {} {}",
+            " HEX EDITOR - {} {} {} {} {}",
             editor.get_file_path(),
+            file_type.as_deref().unwrap_or(""),
             if editor.is_modified() {
                 "[Modified]"
             } else {
@@ -81,109 +558,297 @@ impl Display {
         // Динамический заголовок колонок
         execute!(stdout, cursor::MoveTo(0, 2))?;
         execute!(stdout, SetForegroundColor(Color::DarkGrey))?;
-        write!(stdout, "  Offset  ")?;
-
-        // Расчет динамического количества байтов на строку
-        let available_width = self.width as usize;
-        let offset_width = 10;
-        let ascii_label_width = 8;
-        let separator_width = 2;
+        write!(stdout, "  {:<width$}", "Offset", width = editor.offset_radix().digits())?;
 
-        let bytes_per_line = ((available_width
-            .saturating_sub(offset_width + separator_width + ascii_label_width))
-            / 4)
-        .max(8)
-        .min(32);
+        let bytes_per_line = editor.line_len();
+        let group = editor.byte_group();
 
+        // Номер столбца курсора выделяется вместе с адресом строки курсора
+        let cursor_column = editor
+            .highlight_current_line()
+            .then(|| editor.get_cursor_pos() % bytes_per_line);
         for i in 0..bytes_per_line {
-            write!(stdout, "{:02X} ", i)?;
+            if cursor_column == Some(i) {
+                execute!(stdout, SetBackgroundColor(self.theme.offset), SetForegroundColor(Color::Black))?;
+                write!(stdout, "{:02X}", i)?;
+                execute!(stdout, ResetColor, SetForegroundColor(Color::DarkGrey))?;
+            } else {
+                write!(stdout, "{:02X}", i)?;
+            }
+
+            if editor.is_bit_mode() {
+                write!(stdout, "{:7}", "")?;
+            } else {
+                write!(stdout, " ")?;
+                if is_group_end(i, group, bytes_per_line) {
+                    write!(stdout, " ")?;
+                }
+            }
         }
-        write!(stdout, "  ASCII")?;
+        write!(stdout, "  {}", editor.charset().label())?;
         execute!(stdout, ResetColor)?;
 
         Ok(())
     }
 
+    /// Строка вкладок под заголовком; с одним файлом остается пустой
+    fn draw_tab_bar_buffered(&self, stdout: &mut Screen, tabs: &Tabs) -> Result<()> {
+        execute!(stdout, cursor::MoveTo(0, 1))?;
+        execute!(stdout, Clear(ClearType::CurrentLine))?;
+
+        if tabs.len() < 2 {
+            return Ok(());
+        }
+
+        for (index, editor) in tabs.editors().iter().enumerate() {
+            let name = std::path::Path::new(editor.get_file_path())
+                .file_name()
+                .map_or_else(|| editor.get_file_path().to_string(), |name| name.to_string_lossy().into_owned());
+            let modified = if editor.is_modified() { "*" } else { "" };
+
+            if index == tabs.active_index() {
+                execute!(stdout, SetBackgroundColor(self.theme.header))?;
+                execute!(stdout, SetForegroundColor(self.theme.foreground))?;
+            } else {
+                execute!(stdout, SetForegroundColor(Color::DarkGrey))?;
+            }
+            write!(stdout, " {}:{}{} ", index + 1, name, modified)?;
+            execute!(stdout, ResetColor)?;
+            write!(stdout, " ")?;
+        }
+
+        Ok(())
+    }
+
+    fn draw_split_separator_buffered(&self, stdout: &mut Screen, y: usize) -> Result<()> {
+        execute!(stdout, cursor::MoveTo(0, y as u16))?;
+        execute!(stdout, SetForegroundColor(Color::DarkGrey))?;
+        write!(stdout, "{:─<width$}", "── F6: switch pane | Alt+P: unsplit ", width = self.width as usize)?;
+        execute!(stdout, ResetColor)?;
+        Ok(())
+    }
+
     fn draw_content_buffered(
         &self,
-        stdout: &mut BufWriter<Stdout>,
+        stdout: &mut Screen,
         editor: &HexEditor,
+        pane: &PaneView,
     ) -> Result<()> {
-        let data = editor.get_data();
-        let cursor_pos = editor.get_cursor_pos();
-        let view_offset = editor.get_view_offset();
+        let data_len = editor.data_len();
+        let cursor_pos = pane.cursor_pos;
+        let view_offset = pane.view_offset;
         let mode = editor.get_mode();
+        // Курсор неактивной половины показывается приглушенным
+        let cursor_color = if pane.focused { self.theme.cursor } else { Color::DarkGrey };
+        // Тот же байт в другой колонке отмечается приглушенно, чтобы hex и текст были связаны
+        let secondary_cursor = Color::DarkGrey;
+        // Набранный первый полубайт показывается на месте байта под курсором: "A_"
+        let pending_nibble = editor.pending_nibble().filter(|_| pane.focused);
 
-        // Динамический расчет bytes_per_line на основе ширины терминала
-        let available_width = self.width as usize;
-        let offset_width = 10;
-        let ascii_label_width = 8;
-        let separator_width = 2;
+        let bytes_per_line = editor.line_len();
+        let group = editor.byte_group();
+        let radix = editor.offset_radix();
+        // Цвета по классам байт, если раскраска не отключена (Alt+Y)
+        let byte_colors = editor.colorize_bytes().then_some(&self.theme);
 
-        let bytes_per_line = ((available_width
-            .saturating_sub(offset_width + separator_width + ascii_label_width))
-            / 4)
-        .max(8)
-        .min(32);
+        let visible_lines = pane.lines;
+        let screen_lines = editor.screen_lines(view_offset, visible_lines, cursor_pos);
+        // Строка курсора выделяется фоном (highlight_current_line)
+        let cursor_line = editor
+            .highlight_current_line()
+            .then(|| cursor_pos - cursor_pos % bytes_per_line);
+        let mut segment = None;
 
-        let visible_lines = self.get_visible_lines();
+        // В режиме вставки курсор может стоять сразу за последним байтом
+        let cursor_past_end = cursor_pos >= data_len;
+        let rendered_len = data_len.max(cursor_pos + 1);
 
-        for line_idx in 0..visible_lines {
-            let y = 3 + line_idx;
+        for (line_idx, line) in screen_lines.iter().enumerate() {
+            let y = pane.top + line_idx;
             execute!(stdout, cursor::MoveTo(0, y as u16))?;
 
-            let offset = view_offset + line_idx * bytes_per_line;
-            if offset >= data.len() {
+            let offset = match line {
+                ScreenLine::Data(offset) => *offset,
+                ScreenLine::Squeezed(hidden) => {
+                    execute!(stdout, SetForegroundColor(self.theme.offset))?;
+                    write!(stdout, "*")?;
+                    execute!(stdout, SetForegroundColor(Color::DarkGrey))?;
+                    write!(stdout, "  {} identical lines", hidden.len() / bytes_per_line)?;
+                    execute!(stdout, ResetColor, Clear(ClearType::UntilNewLine))?;
+                    segment = None;
+                    continue;
+                }
+            };
+            if offset >= rendered_len {
                 // Очищаем оставшиеся строки
                 execute!(stdout, Clear(ClearType::CurrentLine))?;
                 continue;
             }
+            // Строки до следующей "*" читаются одним окном
+            let segment = segment.get_or_insert_with(|| {
+                let rows = screen_lines[line_idx..]
+                    .iter()
+                    .take_while(|line| matches!(line, ScreenLine::Data(_)))
+                    .count();
+                self.read_segment(editor, offset, rows * bytes_per_line)
+            });
 
-            // Адрес
-            execute!(stdout, SetForegroundColor(Color::Yellow))?;
-            write!(stdout, "{:08X}  ", offset)?;
-            execute!(stdout, ResetColor)?;
+            // Адрес; у строки курсора он выделен, как номер столбца курсора в заголовке
+            let current_line = cursor_line == Some(offset);
+            if current_line {
+                execute!(stdout, SetBackgroundColor(self.theme.offset), SetForegroundColor(Color::Black))?;
+                write!(stdout, "{}", radix.format(offset))?;
+                execute!(stdout, ResetColor)?;
+                write!(stdout, "  ")?;
+            } else {
+                execute!(stdout, SetForegroundColor(self.theme.offset))?;
+                write!(stdout, "{}  ", radix.format(offset))?;
+                execute!(stdout, ResetColor)?;
+            }
 
             // Hex данные
             for byte_idx in 0..bytes_per_line {
                 let pos = offset + byte_idx;
 
-                if pos < data.len() {
-                    // Подсветка курсора
+                if pos < data_len && mode == EditMode::Bits {
+                    // Биты от старшего к младшему; курсор подсвечивает один бит
+                    let byte = segment.window[pos - segment.start];
+                    for bit in 0..8 {
+                        if current_line {
+                            execute!(stdout, SetBackgroundColor(self.theme.current_line))?;
+                        }
+                        if pos == cursor_pos && bit == editor.bit_index() {
+                            execute!(stdout, SetBackgroundColor(cursor_color))?;
+                            execute!(stdout, SetForegroundColor(self.theme.foreground))?;
+                        } else if editor.is_selected(pos) {
+                            execute!(stdout, SetBackgroundColor(self.theme.selection))?;
+                            execute!(stdout, SetForegroundColor(self.theme.foreground))?;
+                        } else if let Some(annotation) = editor.annotation_at(pos) {
+                            execute!(stdout, SetBackgroundColor(annotation.background()))?;
+                            execute!(stdout, SetForegroundColor(Color::Black))?;
+                        } else if segment.is_modified(pos) {
+                            execute!(stdout, SetForegroundColor(self.theme.modified))?;
+                        } else if let Some(color) = segment.highlight_color(pos) {
+                            execute!(stdout, SetForegroundColor(color))?;
+                        } else if let Some(color) = editor.template_color_at(pos) {
+                            execute!(stdout, SetForegroundColor(color))?;
+                        } else if let Some(color) = segment.plugin_color(pos) {
+                            execute!(stdout, SetForegroundColor(color))?;
+                        } else if let Some(color) = byte_colors.and_then(|theme| theme.byte_color(byte)) {
+                            execute!(stdout, SetForegroundColor(color))?;
+                        }
+
+                        write!(stdout, "{}", (byte >> (7 - bit)) & 1)?;
+                        execute!(stdout, ResetColor)?;
+                    }
+                    write!(stdout, " ")?;
+                } else if pos < data_len {
+                    let byte = segment.window[pos - segment.start];
+
+                    // Подсветка курсора и выделения поверх фона строки курсора
+                    if current_line {
+                        execute!(stdout, SetBackgroundColor(self.theme.current_line))?;
+                    }
                     if pos == cursor_pos && mode == EditMode::Hex {
-                        execute!(stdout, SetBackgroundColor(Color::DarkGreen))?;
-                        execute!(stdout, SetForegroundColor(Color::White))?;
+                        execute!(stdout, SetBackgroundColor(cursor_color))?;
+                        execute!(stdout, SetForegroundColor(self.theme.foreground))?;
+                    } else if pos == cursor_pos {
+                        execute!(stdout, SetBackgroundColor(secondary_cursor))?;
+                        execute!(stdout, SetForegroundColor(self.theme.foreground))?;
+                    } else if editor.is_selected(pos) {
+                        execute!(stdout, SetBackgroundColor(self.theme.selection))?;
+                        execute!(stdout, SetForegroundColor(self.theme.foreground))?;
+                    } else if let Some(annotation) = editor.annotation_at(pos) {
+                        execute!(stdout, SetBackgroundColor(annotation.background()))?;
+                        execute!(stdout, SetForegroundColor(Color::Black))?;
+                    } else if segment.is_modified(pos) {
+                        execute!(stdout, SetForegroundColor(self.theme.modified))?;
+                    } else if let Some(color) = segment.highlight_color(pos) {
+                        execute!(stdout, SetForegroundColor(color))?;
+                    } else if let Some(color) = editor.template_color_at(pos) {
+                        execute!(stdout, SetForegroundColor(color))?;
+                    } else if let Some(color) = segment.plugin_color(pos) {
+                        execute!(stdout, SetForegroundColor(color))?;
+                    } else if let Some(color) = byte_colors.and_then(|theme| theme.byte_color(byte)) {
+                        execute!(stdout, SetForegroundColor(color))?;
                     }
 
-                    write!(stdout, "{:02X} ", data[pos])?;
+                    match pending_nibble.filter(|_| pos == cursor_pos) {
+                        Some(nibble) => {
+                            execute!(stdout, SetForegroundColor(self.theme.modified))?;
+                            write!(stdout, "{:X}_ ", nibble)?;
+                        }
+                        None => write!(stdout, "{:02X} ", byte)?,
+                    }
+                    execute!(stdout, ResetColor)?;
+                } else if pos == cursor_pos && cursor_past_end && mode != EditMode::Bits {
+                    let color = if mode == EditMode::Hex { cursor_color } else { secondary_cursor };
+                    execute!(stdout, SetBackgroundColor(color))?;
+                    match pending_nibble {
+                        Some(nibble) => {
+                            execute!(stdout, SetForegroundColor(self.theme.modified))?;
+                            write!(stdout, "{:X}_", nibble)?;
+                        }
+                        None => write!(stdout, "  ")?,
+                    }
                     execute!(stdout, ResetColor)?;
+                    write!(stdout, " ")?;
+                } else if mode == EditMode::Bits {
+                    write!(stdout, "{:9}", "")?;
                 } else {
                     write!(stdout, "   ")?;
                 }
+
+                if mode != EditMode::Bits && is_group_end(byte_idx, group, bytes_per_line) {
+                    write!(stdout, " ")?;
+                }
             }
 
             write!(stdout, " ")?;
 
-            // ASCII представление
+            // Текстовое представление в выбранной кодировке
             for byte_idx in 0..bytes_per_line {
                 let pos = offset + byte_idx;
 
-                if pos < data.len() {
-                    let byte = data[pos];
-                    let ch = if byte.is_ascii_graphic() || byte == b' ' {
-                        byte as char
-                    } else {
-                        '.'
-                    };
+                if pos < data_len {
+                    let byte = segment.window[pos - segment.start];
+                    let ch = segment.text[pos - segment.start];
 
-                    // Подсветка курсора
+                    // Подсветка курсора и выделения поверх фона строки курсора
+                    if current_line {
+                        execute!(stdout, SetBackgroundColor(self.theme.current_line))?;
+                    }
                     if pos == cursor_pos && mode == EditMode::Ascii {
-                        execute!(stdout, SetBackgroundColor(Color::DarkGreen))?;
-                        execute!(stdout, SetForegroundColor(Color::White))?;
+                        execute!(stdout, SetBackgroundColor(cursor_color))?;
+                        execute!(stdout, SetForegroundColor(self.theme.foreground))?;
+                    } else if pos == cursor_pos {
+                        execute!(stdout, SetBackgroundColor(secondary_cursor))?;
+                        execute!(stdout, SetForegroundColor(self.theme.foreground))?;
+                    } else if editor.is_selected(pos) {
+                        execute!(stdout, SetBackgroundColor(self.theme.selection))?;
+                        execute!(stdout, SetForegroundColor(self.theme.foreground))?;
+                    } else if let Some(annotation) = editor.annotation_at(pos) {
+                        execute!(stdout, SetBackgroundColor(annotation.background()))?;
+                        execute!(stdout, SetForegroundColor(Color::Black))?;
+                    } else if segment.is_modified(pos) {
+                        execute!(stdout, SetForegroundColor(self.theme.modified))?;
+                    } else if let Some(color) = segment.highlight_color(pos) {
+                        execute!(stdout, SetForegroundColor(color))?;
+                    } else if let Some(color) = editor.template_color_at(pos) {
+                        execute!(stdout, SetForegroundColor(color))?;
+                    } else if let Some(color) = segment.plugin_color(pos) {
+                        execute!(stdout, SetForegroundColor(color))?;
+                    } else if let Some(color) = byte_colors.and_then(|theme| theme.byte_color(byte)) {
+                        execute!(stdout, SetForegroundColor(color))?;
                     }
 
                     write!(stdout, "{}", ch)?;
                     execute!(stdout, ResetColor)?;
+                } else if pos == cursor_pos && cursor_past_end {
+                    let color = if mode == EditMode::Ascii { cursor_color } else { secondary_cursor };
+                    execute!(stdout, SetBackgroundColor(color))?;
+                    write!(stdout, " ")?;
+                    execute!(stdout, ResetColor)?;
                 } else {
                     write!(stdout, " ")?;
                 }
@@ -193,30 +858,772 @@ impl Display {
             execute!(stdout, Clear(ClearType::UntilNewLine))?;
         }
 
+        self.draw_scrollbar_buffered(stdout, editor, pane)?;
+        Ok(())
+    }
+
+    /// Окно `len` байт с `start` и его раскраска: запрашиваются только видимые байты
+    fn read_segment<'a>(&self, editor: &'a HexEditor, start: usize, len: usize) -> Segment<'a> {
+        let window = editor.get_range(start, len);
+        let range = start..start + window.len();
+        Segment {
+            start,
+            text: editor.charset().decode(&window, start),
+            modified: editor.modified_ranges(range.clone()),
+            plugin_colors: self.plugins.colors(editor, range.clone()),
+            highlights: editor.highlight_spans(range),
+            window,
+        }
+    }
+
+    /// Полоса прокрутки у правого края и миникарта левее нее
+    fn draw_scrollbar_buffered(&self, stdout: &mut Screen, editor: &HexEditor, pane: &PaneView) -> Result<()> {
+        let minimap = editor.minimap();
+        if (!editor.show_scrollbar() && minimap == Minimap::Off) || pane.lines == 0 || self.width < 2 {
+            return Ok(());
+        }
+
+        let bytes_per_line = editor.line_len();
+        let data_len = editor.data_len();
+        let total_lines = data_len.div_ceil(bytes_per_line).max(1);
+        let view_line = pane.view_offset / bytes_per_line;
+
+        // Ползунок пропорционален видимой части файла
+        let thumb_len = (pane.lines * pane.lines / total_lines).clamp(1, pane.lines);
+        let thumb_start = (view_line * pane.lines / total_lines).min(pane.lines - thumb_len);
+
+        for row in 0..pane.lines {
+            let y = (pane.top + row) as u16;
+
+            if minimap != Minimap::Off && data_len > 0 {
+                let start = row * data_len / pane.lines;
+                let end = ((row + 1) * data_len / pane.lines).max(start + 1);
+                let sample = editor.get_range(start, (end - start).min(MINIMAP_SAMPLE_BYTES));
+
+                execute!(stdout, cursor::MoveTo(self.width - 2, y))?;
+                execute!(stdout, SetBackgroundColor(self.minimap_color(minimap, &sample)))?;
+                write!(stdout, " ")?;
+                execute!(stdout, ResetColor)?;
+            }
+
+            if editor.show_scrollbar() {
+                execute!(stdout, cursor::MoveTo(self.width - 1, y))?;
+                execute!(stdout, SetForegroundColor(Color::DarkGrey))?;
+                let thumb = (thumb_start..thumb_start + thumb_len).contains(&row);
+                write!(stdout, "{}", if thumb { '█' } else { '│' })?;
+                execute!(stdout, ResetColor)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn minimap_color(&self, minimap: Minimap, sample: &[u8]) -> Color {
+        match minimap {
+            // От однородных данных к сжатым и зашифрованным
+            Minimap::Entropy => match entropy::entropy(sample) {
+                e if e < 2.0 => Color::DarkGrey,
+                e if e < 4.0 => Color::DarkBlue,
+                e if e < 6.0 => Color::DarkGreen,
+                e if e < 7.5 => Color::DarkYellow,
+                _ => Color::Red,
+            },
+            // Цвет самого частого класса байт
+            Minimap::Class => {
+                let representatives = [0x00, 0xFF, b'A', 0x80, 0x01];
+                let mut counts = [0usize; 5];
+                for &byte in sample {
+                    let class = match byte {
+                        0x00 => 0,
+                        0xFF => 1,
+                        0x20..=0x7E => 2,
+                        0x80.. => 3,
+                        _ => 4,
+                    };
+                    counts[class] += 1;
+                }
+                let (class, _) = counts.iter().enumerate().max_by_key(|&(_, count)| count).unwrap_or((0, &0));
+                self.theme.byte_color(representatives[class]).unwrap_or(self.theme.foreground)
+            }
+            Minimap::Off => Color::Reset,
+        }
+    }
+
+    /// Режим сравнения: файлы рядом, байты на одинаковых смещениях, различия выделены.
+    /// Курсор и прокрутка берутся из активной вкладки
+    fn draw_compare_buffered(
+        &self,
+        stdout: &mut Screen,
+        editor: &HexEditor,
+        left: &HexEditor,
+        right: &HexEditor,
+    ) -> Result<()> {
+        let side_width = (self.width as usize).saturating_sub(1) / 2;
+        // Адрес (10) и пробел между hex и ASCII; остаток делится на байты по 4 колонки
+        let bytes_per_line = (side_width.saturating_sub(11) / 4).clamp(4, 32);
+        let visible_lines = self.content_lines(editor);
+        let view_offset = editor.get_view_offset();
+        let cursor_pos = editor.get_cursor_pos();
+        let left_focused = std::ptr::eq(editor, left);
+
+        // Заголовок колонок заменяется именами файлов
+        execute!(stdout, cursor::MoveTo(0, 2))?;
+        execute!(stdout, Clear(ClearType::CurrentLine))?;
+        execute!(stdout, SetForegroundColor(Color::DarkGrey))?;
+        let left_title = format!("  {}", left.get_file_path());
+        let right_title = format!("  {}", right.get_file_path());
+        write!(stdout, "{:<side$}│{}", left_title, right_title, side = side_width)?;
+        execute!(stdout, ResetColor)?;
+
+        let len = visible_lines * bytes_per_line;
+        let left_window = left.get_range(view_offset, len);
+        let right_window = right.get_range(view_offset, len);
+        let rendered_len = left.data_len().max(right.data_len()).max(cursor_pos + 1);
+
+        for line_idx in 0..visible_lines {
+            execute!(stdout, cursor::MoveTo(0, (3 + line_idx) as u16))?;
+
+            let offset = view_offset + line_idx * bytes_per_line;
+            if offset >= rendered_len {
+                execute!(stdout, Clear(ClearType::CurrentLine))?;
+                continue;
+            }
+
+            let start = line_idx * bytes_per_line;
+            let side = CompareLine {
+                offset,
+                bytes_per_line,
+                start,
+                cursor_pos,
+                mode: editor.get_mode(),
+            };
+
+            side.draw(stdout, &self.theme, &left_window, &right_window, left_focused)?;
+            // Дополняем левую половину до разделителя
+            let drawn = 10 + bytes_per_line * 4 + 1;
+            write!(stdout, "{:pad$}│", "", pad = side_width.saturating_sub(drawn))?;
+            side.draw(stdout, &self.theme, &right_window, &left_window, !left_focused)?;
+            execute!(stdout, Clear(ClearType::UntilNewLine))?;
+        }
+
+        Ok(())
+    }
+
+    fn draw_match_panel_buffered(
+        &self,
+        stdout: &mut Screen,
+        editor: &HexEditor,
+    ) -> Result<()> {
+        let Some(list) = editor.get_match_list() else {
+            return Ok(());
+        };
+
+        let top = 3 + self.content_lines(editor);
+        let rows = (self.height as usize).saturating_sub(1 + top + editor.docked_height());
+        if rows == 0 {
+            return Ok(());
+        }
+
+        // Заголовок панели
+        execute!(stdout, cursor::MoveTo(0, top as u16))?;
+        execute!(stdout, SetBackgroundColor(self.theme.header))?;
+        execute!(stdout, SetForegroundColor(self.theme.foreground))?;
+        let title = format!(
+            " Matches: {}/{} | n/p or Up/Down: move | Enter/Esc: close",
+            list.selected + 1,
+            list.offsets.len()
+        );
+        write!(stdout, "{:width$}", title, width = self.width as usize)?;
+        execute!(stdout, ResetColor)?;
+
+        // Прокручиваем список так, чтобы выбранное совпадение было видно
+        let list_rows = rows - 1;
+        let first = list.selected.saturating_sub(list_rows.saturating_sub(1));
+
+        for row in 0..list_rows {
+            execute!(stdout, cursor::MoveTo(0, (top + 1 + row) as u16))?;
+            execute!(stdout, Clear(ClearType::CurrentLine))?;
+
+            let idx = first + row;
+            let Some(&offset) = list.offsets.get(idx) else {
+                continue;
+            };
+
+            if idx == list.selected {
+                execute!(stdout, SetBackgroundColor(self.theme.cursor))?;
+                execute!(stdout, SetForegroundColor(self.theme.foreground))?;
+            }
+
+            let context = editor.get_range(offset, MATCH_CONTEXT_BYTES.max(list.pattern_len));
+            let hex: String = context.iter().map(|b| format!("{:02X} ", b)).collect();
+            let ascii: String = context
+                .iter()
+                .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+                .collect();
+
+            match list.labels.get(idx) {
+                Some(label) => write!(stdout, " {:>5}  {:08X}  {:<28} {}", idx + 1, offset, label, hex)?,
+                None => write!(stdout, " {:>5}  {:08X}  {} {}", idx + 1, offset, hex, ascii)?,
+            }
+            execute!(stdout, ResetColor)?;
+        }
+
+        Ok(())
+    }
+
+    fn draw_bookmark_panel_buffered(
+        &self,
+        stdout: &mut Screen,
+        editor: &HexEditor,
+    ) -> Result<()> {
+        let bookmarks = editor.get_bookmarks();
+        let top = 3 + self.content_lines(editor);
+        let rows = (self.height as usize).saturating_sub(1 + top + editor.docked_height());
+        if rows == 0 {
+            return Ok(());
+        }
+
+        execute!(stdout, cursor::MoveTo(0, top as u16))?;
+        execute!(stdout, SetBackgroundColor(self.theme.header))?;
+        execute!(stdout, SetForegroundColor(self.theme.foreground))?;
+        let title = format!(
+            " Bookmarks: {} | Up/Down: move | 1-9/Enter: jump | Del: remove | Esc: close",
+            bookmarks.items.len()
+        );
+        write!(stdout, "{:width$}", title, width = self.width as usize)?;
+        execute!(stdout, ResetColor)?;
+
+        let list_rows = rows - 1;
+        let first = bookmarks.selected.saturating_sub(list_rows.saturating_sub(1));
+
+        for row in 0..list_rows {
+            execute!(stdout, cursor::MoveTo(0, (top + 1 + row) as u16))?;
+            execute!(stdout, Clear(ClearType::CurrentLine))?;
+
+            let idx = first + row;
+            let Some(bookmark) = bookmarks.items.get(idx) else {
+                continue;
+            };
+
+            if idx == bookmarks.selected {
+                execute!(stdout, SetBackgroundColor(self.theme.cursor))?;
+                execute!(stdout, SetForegroundColor(self.theme.foreground))?;
+            }
+
+            let context = editor.get_range(bookmark.offset, MATCH_CONTEXT_BYTES / 2);
+            let hex: String = context.iter().map(|b| format!("{:02X} ", b)).collect();
+
+            write!(stdout, " {:>5}  {:08X}  {:<24} {}", idx + 1, bookmark.offset, hex, bookmark.label)?;
+            execute!(stdout, ResetColor)?;
+        }
+
+        Ok(())
+    }
+
+    /// Регионы памяти процесса из /proc/<pid>/maps; открытый отмечен звездочкой
+    fn draw_region_panel_buffered(
+        &self,
+        stdout: &mut Screen,
+        editor: &HexEditor,
+    ) -> Result<()> {
+        let Some((regions, selected, current)) = editor.process_regions() else {
+            return Ok(());
+        };
+        let top = 3 + self.content_lines(editor);
+        let rows = (self.height as usize).saturating_sub(1 + top + editor.docked_height());
+        if rows == 0 {
+            return Ok(());
+        }
+
+        execute!(stdout, cursor::MoveTo(0, top as u16))?;
+        execute!(stdout, SetBackgroundColor(self.theme.header))?;
+        execute!(stdout, SetForegroundColor(self.theme.foreground))?;
+        let title = format!(" Memory regions: {} | Up/Down: move | Enter: open | Esc: close", regions.len());
+        write!(stdout, "{:width$}", title, width = self.width as usize)?;
+        execute!(stdout, ResetColor)?;
+
+        let list_rows = rows - 1;
+        let first = selected.saturating_sub(list_rows.saturating_sub(1));
+
+        for row in 0..list_rows {
+            execute!(stdout, cursor::MoveTo(0, (top + 1 + row) as u16))?;
+            execute!(stdout, Clear(ClearType::CurrentLine))?;
+
+            let idx = first + row;
+            let Some(region) = regions.get(idx) else {
+                continue;
+            };
+
+            if idx == selected {
+                execute!(stdout, SetBackgroundColor(self.theme.cursor))?;
+                execute!(stdout, SetForegroundColor(self.theme.foreground))?;
+            }
+
+            let mark = if region.start == current { '*' } else { ' ' };
+            let line = format!(" {} {}", mark, region.label());
+            write!(stdout, "{}", line.chars().take(self.width as usize).collect::<String>())?;
+            execute!(stdout, ResetColor)?;
+        }
+
+        Ok(())
+    }
+
+    /// Дерево полей шаблона: отступ по вложенности, тип, смещение, длина и значение
+    fn draw_template_panel_buffered(
+        &self,
+        stdout: &mut Screen,
+        editor: &HexEditor,
+    ) -> Result<()> {
+        let Some(view) = editor.template_view() else {
+            return Ok(());
+        };
+        let top = 3 + self.content_lines(editor);
+        let rows = (self.height as usize).saturating_sub(1 + top + editor.docked_height());
+        if rows == 0 {
+            return Ok(());
+        }
+
+        execute!(stdout, cursor::MoveTo(0, top as u16))?;
+        execute!(stdout, SetBackgroundColor(self.theme.header))?;
+        execute!(stdout, SetForegroundColor(self.theme.foreground))?;
+        let title = format!(
+            " Template {}: {} fields | Up/Down: move | Left/Right: fold | Enter: select | /: find | r: reapply | a: apply | d: remove | Esc: close",
+            view.name,
+            view.layout.fields.len()
+        );
+        write!(stdout, "{:width$}", title, width = self.width as usize)?;
+        execute!(stdout, ResetColor)?;
+
+        let visible = view.visible();
+        let selected = visible.iter().position(|&idx| idx == view.selected).unwrap_or(0);
+        let list_rows = rows - 1;
+        let first = selected.saturating_sub(list_rows.saturating_sub(1));
+
+        for row in 0..list_rows {
+            execute!(stdout, cursor::MoveTo(0, (top + 1 + row) as u16))?;
+            execute!(stdout, Clear(ClearType::CurrentLine))?;
+
+            let Some(&idx) = visible.get(first + row) else {
+                continue;
+            };
+            let Some(field) = view.field(idx) else {
+                continue;
+            };
+
+            if idx == view.selected {
+                execute!(stdout, SetBackgroundColor(self.theme.cursor))?;
+                execute!(stdout, SetForegroundColor(self.theme.foreground))?;
+            }
+
+            let marker = match (view.has_children(idx), view.is_collapsed(idx)) {
+                (false, _) => ' ',
+                (true, true) => '+',
+                (true, false) => '-',
+            };
+            let name = format!("{:indent$}{} {}", "", marker, field.name, indent = field.depth * 2);
+            let line = format!(
+                " {:<28} {:<14} {:08X} {:>8}  {}",
+                name,
+                field.type_name,
+                field.range.start,
+                field.range.len(),
+                field.value.as_deref().unwrap_or("")
+            );
+            write!(stdout, "{}", line.chars().take(self.width as usize).collect::<String>())?;
+            execute!(stdout, ResetColor)?;
+        }
+
+        Ok(())
+    }
+
+    /// Статистика байт: доли классов, самые частые значения и гистограмма всех 256 значений.
+    /// Высота столбцов логарифмическая, иначе нули заполнения скрывают остальные значения
+    fn draw_stats_panel_buffered(
+        &self,
+        stdout: &mut Screen,
+        editor: &HexEditor,
+        stats: &StatsPanel,
+    ) -> Result<()> {
+        let top = 3 + self.content_lines(editor);
+        let rows = (self.height as usize).saturating_sub(1 + top + editor.docked_height());
+        if rows == 0 {
+            return Ok(());
+        }
+        let histogram = &stats.histogram;
+        let width = self.width as usize;
+
+        execute!(stdout, cursor::MoveTo(0, top as u16))?;
+        execute!(stdout, SetBackgroundColor(self.theme.header))?;
+        execute!(stdout, SetForegroundColor(self.theme.foreground))?;
+        let title = format!(
+            " Byte statistics: 0x{:X}..0x{:X} ({} bytes) | Alt+Shift+H: refresh | Esc: close",
+            stats.range.start, stats.range.end, histogram.total
+        );
+        write!(stdout, "{:width$}", title, width = width)?;
+        execute!(stdout, ResetColor)?;
+
+        let percent = |count: u64| count as f64 * 100.0 / histogram.total.max(1) as f64;
+        let summary = format!(
+            " Nulls {:.1}% | Printable {:.1}% | Control {:.1}% | High-bit {:.1}% | Distinct {} | Entropy {:.2} bits/byte",
+            percent(histogram.nulls()),
+            percent(histogram.printable()),
+            percent(histogram.control()),
+            percent(histogram.high_bit()),
+            histogram.distinct(),
+            histogram.entropy()
+        );
+        let common: String = histogram
+            .most_common(STATS_COMMON_VALUES)
+            .iter()
+            .map(|&(byte, count)| format!(" {:02X} {:.1}%", byte, percent(count)))
+            .collect();
+        let mut lines = [summary, format!(" Most common:{}", common)];
+
+        // Столбец гистограммы объединяет соседние значения, если все 256 не помещаются
+        let bins = [256, 128, 64, 32, 16]
+            .into_iter()
+            .find(|&bins| bins + 2 <= width)
+            .unwrap_or(16);
+        let per_bin = 256 / bins;
+        let bin_counts: Vec<u64> = histogram
+            .counts
+            .chunks(per_bin)
+            .map(|chunk| chunk.iter().sum())
+            .collect();
+        let max = bin_counts.iter().copied().max().unwrap_or(0);
+        let scale = (HISTOGRAM_ROWS * 8) as f64 / (max as f64).ln_1p();
+        let levels: Vec<usize> = bin_counts
+            .iter()
+            .map(|&count| match count {
+                0 => 0,
+                _ => (((count as f64).ln_1p() * scale).round() as usize).max(1),
+            })
+            .collect();
+
+        let mut axis = vec![' '; bins + 1];
+        for value in [0x00, 0x40, 0x80, 0xC0] {
+            let column = 1 + value / per_bin;
+            for (idx, ch) in format!("{:02X}", value).chars().enumerate() {
+                axis[column + idx] = ch;
+            }
+        }
+
+        for row in 0..rows - 1 {
+            execute!(stdout, cursor::MoveTo(0, (top + 1 + row) as u16))?;
+            execute!(stdout, Clear(ClearType::CurrentLine))?;
+
+            if let Some(line) = lines.get_mut(row) {
+                line.truncate(width);
+                write!(stdout, "{}", line)?;
+                continue;
+            }
+
+            let bar_row = row - lines.len();
+            if bar_row == HISTOGRAM_ROWS {
+                write!(stdout, "{}FF", axis.iter().collect::<String>())?;
+                continue;
+            }
+            if bar_row > HISTOGRAM_ROWS {
+                continue;
+            }
+
+            // Строки снизу вверх: в нижней строке первые восемь уровней столбца
+            let floor = (HISTOGRAM_ROWS - 1 - bar_row) * 8;
+            write!(stdout, " ")?;
+            for (bin, &level) in levels.iter().enumerate() {
+                let block = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█']
+                    [level.saturating_sub(floor).min(8)];
+                let color = self
+                    .theme
+                    .byte_color((bin * per_bin) as u8)
+                    .unwrap_or(self.theme.foreground);
+                execute!(stdout, SetForegroundColor(color))?;
+                write!(stdout, "{}", block)?;
+            }
+            execute!(stdout, ResetColor)?;
+        }
+
+        Ok(())
+    }
+
+    /// Дизассемблер над инспектором: инструкции с позиции курсора, первая подсвечена
+    fn draw_disasm_buffered(&self, stdout: &mut Screen, editor: &HexEditor) -> Result<()> {
+        let Some(top) = (self.height as usize).checked_sub(1 + editor.docked_height()) else {
+            return Ok(());
+        };
+        let Some(arch) = editor.disasm_arch() else {
+            return Ok(());
+        };
+
+        execute!(stdout, cursor::MoveTo(0, top as u16))?;
+        execute!(stdout, SetBackgroundColor(self.theme.header))?;
+        execute!(stdout, SetForegroundColor(self.theme.foreground))?;
+        let title = format!(
+            " Disassembly ({}) @ 0x{:08X} | Alt+Shift+A: architecture | Alt+Shift+D: close",
+            arch.name(),
+            editor.get_cursor_pos()
+        );
+        write!(stdout, "{:width$}", title, width = self.width as usize)?;
+        execute!(stdout, ResetColor)?;
+
+        let instructions = editor.disassemble_at_cursor(DISASM_PANEL_HEIGHT - 1);
+        for row in 0..DISASM_PANEL_HEIGHT - 1 {
+            execute!(stdout, cursor::MoveTo(0, (top + 1 + row) as u16))?;
+            execute!(stdout, Clear(ClearType::CurrentLine))?;
+            let Some(instruction) = instructions.get(row) else {
+                continue;
+            };
+
+            if row == 0 {
+                execute!(stdout, SetBackgroundColor(self.theme.cursor))?;
+                execute!(stdout, SetForegroundColor(self.theme.foreground))?;
+            } else if instruction.text == "(bad)" {
+                execute!(stdout, SetForegroundColor(Color::DarkGrey))?;
+            }
+            let bytes: Vec<String> = instruction.bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+            let line = format!(" {:08X}  {:<30} {}", instruction.address, bytes.join(" "), instruction.text);
+            write!(stdout, "{}", line.chars().take(self.width as usize).collect::<String>())?;
+            execute!(stdout, ResetColor)?;
+        }
+        Ok(())
+    }
+
+    /// Инспектор данных над строкой статуса: байты под курсором как числа в обоих порядках байт,
+    /// под ними строки плагинов
+    fn draw_inspector_buffered(
+        &self,
+        stdout: &mut Screen,
+        editor: &HexEditor,
+        plugin_rows: &[(String, String)],
+    ) -> Result<()> {
+        let Some(top) = (self.height as usize).checked_sub(1 + editor.inspector_height()) else {
+            return Ok(());
+        };
+        let cursor_pos = editor.get_cursor_pos();
+        let bytes = editor.get_range(cursor_pos, 16);
+
+        execute!(stdout, cursor::MoveTo(0, top as u16))?;
+        execute!(stdout, SetBackgroundColor(self.theme.header))?;
+        execute!(stdout, SetForegroundColor(self.theme.foreground))?;
+        let title = format!(
+            " Inspector @ 0x{:08X} | {:>21} {:>21} | Ctrl+arrows: select | Enter: edit | Alt+K: close",
+            cursor_pos, "little endian", "big endian"
+        );
+        write!(stdout, "{:width$}", title, width = self.width as usize)?;
+        execute!(stdout, ResetColor)?;
+
+        let value = |ty: NumericType, endian| ty.decode(&bytes, endian).unwrap_or_else(|| "-".to_string());
+
+        for (row, (left, right)) in INSPECTOR_ROWS.iter().enumerate() {
+            execute!(stdout, cursor::MoveTo(0, (top + 1 + row) as u16))?;
+            execute!(stdout, Clear(ClearType::CurrentLine))?;
+
+            for (side, ty) in [left, right].into_iter().enumerate() {
+                execute!(stdout, SetForegroundColor(Color::DarkGrey))?;
+                write!(stdout, " {:<4}", format!("{:?}", ty).to_lowercase())?;
+                execute!(stdout, ResetColor)?;
+
+                for (offset, endian) in [Endian::Little, Endian::Big].into_iter().enumerate() {
+                    if editor.inspector_cell() == (row, side * 2 + offset) {
+                        execute!(stdout, SetBackgroundColor(self.theme.cursor))?;
+                        execute!(stdout, SetForegroundColor(self.theme.foreground))?;
+                    }
+                    write!(stdout, "{:>21}", value(*ty, endian))?;
+                    execute!(stdout, ResetColor)?;
+                    write!(stdout, " ")?;
+                }
+                write!(stdout, " ")?;
+            }
+        }
+
+        // Биты и восьмеричное значение байта под курсором
+        execute!(stdout, cursor::MoveTo(0, (top + 1 + INSPECTOR_ROWS.len()) as u16))?;
+        execute!(stdout, Clear(ClearType::CurrentLine))?;
+        if let Some(&byte) = bytes.first() {
+            execute!(stdout, SetForegroundColor(Color::DarkGrey))?;
+            write!(stdout, " bin ")?;
+            execute!(stdout, ResetColor)?;
+            write!(stdout, "{:04b} {:04b}", byte >> 4, byte & 0x0F)?;
+            execute!(stdout, SetForegroundColor(Color::DarkGrey))?;
+            write!(stdout, "   oct ")?;
+            execute!(stdout, ResetColor)?;
+            write!(stdout, "{:03o}", byte)?;
+        }
+
+        // Время, GUID и адреса - по две расшифровки в строке
+        let first_row = top + 2 + INSPECTOR_ROWS.len();
+        for (row, pair) in inspect::DECODERS.chunks(2).enumerate() {
+            execute!(stdout, cursor::MoveTo(0, (first_row + row) as u16))?;
+            execute!(stdout, Clear(ClearType::CurrentLine))?;
+
+            for (label, decode) in pair {
+                execute!(stdout, SetForegroundColor(Color::DarkGrey))?;
+                write!(stdout, " {:<9}", label)?;
+                execute!(stdout, ResetColor)?;
+                write!(stdout, "{:<39}", decode(&bytes).unwrap_or_else(|| "-".to_string()))?;
+            }
+        }
+
+        for (row, (label, value)) in plugin_rows.iter().enumerate() {
+            execute!(stdout, cursor::MoveTo(0, (top + INSPECTOR_PANEL_HEIGHT + row) as u16))?;
+            execute!(stdout, Clear(ClearType::CurrentLine))?;
+            execute!(stdout, SetForegroundColor(Color::DarkGrey))?;
+            write!(stdout, " {:<9}", label)?;
+            execute!(stdout, ResetColor)?;
+            let value: String = value.chars().take((self.width as usize).saturating_sub(10)).collect();
+            write!(stdout, "{}", value)?;
+        }
+
+        Ok(())
+    }
+
+    fn draw_help_buffered(&self, stdout: &mut Screen, help: &Help) -> Result<()> {
+        let width = self.width as usize;
+
+        execute!(stdout, SetBackgroundColor(self.theme.header))?;
+        execute!(stdout, SetForegroundColor(self.theme.foreground))?;
+        write!(stdout, "{:width$}", " hexr key bindings", width = width)?;
+        execute!(stdout, ResetColor)?;
+
+        let rows = self.help_rows();
+        let lines = help.lines().iter().skip(help.scroll_offset());
+        for row in 0..rows {
+            execute!(stdout, cursor::MoveTo(0, (row + 1) as u16))?;
+            execute!(stdout, Clear(ClearType::CurrentLine))?;
+        }
+        for (row, line) in lines.take(rows).enumerate() {
+            execute!(stdout, cursor::MoveTo(0, (row + 1) as u16))?;
+            let text = match line {
+                HelpLine::Heading(title) => {
+                    execute!(stdout, SetForegroundColor(self.theme.offset))?;
+                    format!(" {}", title)
+                }
+                HelpLine::Entry(chords, description, name) => format!(
+                    "   {:chord_width$}  {:40}  {}",
+                    chords,
+                    description,
+                    name,
+                    chord_width = help.chord_width()
+                ),
+                HelpLine::Blank => String::new(),
+            };
+            let text: String = text.chars().take(width).collect();
+            write!(stdout, "{}", text)?;
+            execute!(stdout, ResetColor)?;
+        }
+
+        // Строка подсказки на месте строки состояния
+        let percent = match help.lines().len().saturating_sub(rows) {
+            0 => 100,
+            max => help.scroll_offset() * 100 / max,
+        };
+        execute!(stdout, cursor::MoveTo(0, self.height - 1))?;
+        execute!(stdout, SetBackgroundColor(self.theme.status_bar))?;
+        execute!(stdout, SetForegroundColor(self.theme.foreground))?;
+        let hint = format!(" Up/Down/PgUp/PgDn: scroll | Esc/F1/Q: close | {}% ", percent);
+        write!(stdout, "{:width$}", hint, width = width)?;
+        execute!(stdout, ResetColor)?;
+
         Ok(())
     }
 
     fn draw_status_bar_buffered(
         &self,
-        stdout: &mut BufWriter<Stdout>,
+        stdout: &mut Screen,
         editor: &HexEditor,
     ) -> Result<()> {
         let y = self.height - 1;
+        // Предупреждения и ошибки выделяются фоном всей строки состояния
+        let background = match editor.get_message().map(|message| message.severity) {
+            Some(Severity::Warning) => Color::DarkYellow,
+            Some(Severity::Error) => Color::DarkRed,
+            Some(Severity::Info) | None => self.theme.status_bar,
+        };
         execute!(stdout, cursor::MoveTo(0, y))?;
-        execute!(stdout, SetBackgroundColor(Color::DarkGrey))?;
-        execute!(stdout, SetForegroundColor(Color::White))?;
+        execute!(stdout, SetBackgroundColor(background))?;
+        execute!(stdout, SetForegroundColor(self.theme.foreground))?;
 
         let cursor_pos = editor.get_cursor_pos();
-        let file_size = editor.get_data().len();
-        let mode_str = match editor.get_mode() {
-            EditMode::Hex => "HEX",
-            EditMode::Ascii => "ASCII",
+        let file_size = editor.data_len();
+        let mode_str = match (editor.get_mode(), editor.is_visual_mode()) {
+            (EditMode::Hex, false) => "HEX",
+            (EditMode::Ascii, false) => "ASCII",
+            (EditMode::Hex, true) => "HEX VISUAL",
+            (EditMode::Ascii, true) => "ASCII VISUAL",
+            (EditMode::Bits, false) => "BITS",
+            (EditMode::Bits, true) => "BITS VISUAL",
         };
 
-        let status = format!(
-            " Pos: 0x{:08X} ({}/{}) | Mode: {} | Ctrl+Q: Quit | Ctrl+S: Save | Ctrl+Z: Undo | Ctrl+Y: Redo | Tab: Switch Mode ",
-            cursor_pos, cursor_pos, file_size, mode_str
-        );
+        let typing_str = if editor.is_insert_mode() { "INS" } else { "OVR" };
+        let typing_str = match &self.key_status {
+            Some(status) => format!("{} {}", typing_str, status),
+            None => typing_str.to_string(),
+        };
+
+        let selection_str = match editor.selection_range() {
+            Some(range) => format!(" | Sel: {} bytes", range.len()),
+            None => String::new(),
+        };
+
+        let jumps_str = match editor.jump_depth() {
+            (0, 0) => String::new(),
+            (back, forward) => format!(" | Jumps: -{} +{}", back, forward),
+        };
+        let branch_str = match editor.undo_branch() {
+            Some((active, count)) => format!(" | Redo branch: {}/{}", active, count),
+            None => String::new(),
+        };
+        let changed_str = match editor.modified_count() {
+            0 => String::new(),
+            count => format!(" | Changed: {}", count),
+        };
+        let lock_str = match editor.lock_at(cursor_pos) {
+            Some(lock) => format!(" | Locked 0x{:X}-0x{:X}", lock.start, lock.end),
+            None => String::new(),
+        };
+        let note_str = match editor.annotation_at(cursor_pos) {
+            Some(annotation) => format!(" | Note: {}", annotation.note),
+            None => String::new(),
+        };
+        let field_str = match editor.template_field_at(cursor_pos) {
+            Some(field) => format!(" | {}", field),
+            None => String::new(),
+        };
+        let sector_str = match editor.sector_size() {
+            Some(size) => format!(" | Sector: {} +0x{:X}", cursor_pos / size, cursor_pos % size),
+            None => String::new(),
+        };
+        let address_str = match editor.process_address(cursor_pos) {
+            Some(address) => format!(" | Addr: 0x{:X}", address),
+            None => String::new(),
+        };
+        let pending_str = match editor.pending_nibble() {
+            Some(nibble) => format!(" | Pending: {:X}_ (Esc: cancel)", nibble),
+            None => String::new(),
+        };
+        let selection_str = pending_str + &address_str + &sector_str + &selection_str + &changed_str + &branch_str + &jumps_str + &lock_str + &note_str + &field_str;
+
+        let status = match (editor.search_progress(), editor.get_message()) {
+            (Some(percent), _) => format!(
+                " Pos: 0x{:08X} ({}/{}) | Mode: {} {}{} | Searching... {} {}% (Esc: cancel) ",
+                cursor_pos,
+                cursor_pos,
+                file_size,
+                mode_str,
+                typing_str,
+                selection_str,
+                progress_bar(percent, 100),
+                percent
+            ),
+            (None, Some(message)) => format!(
+                " Pos: 0x{:08X} ({}/{}) | Mode: {} {}{} | {} ",
+                cursor_pos, cursor_pos, file_size, mode_str, typing_str, selection_str, message.text
+            ),
+            (None, None) => format!(
+                " Pos: 0x{:08X} ({}/{}) | Mode: {} {}{} | F1: Help | Ctrl+Q: Quit | Ctrl+S: Save | Ctrl+Z: Undo | Ctrl+Y: Redo | Tab: Switch Mode | Ins: OVR/INS ",
+                cursor_pos, cursor_pos, file_size, mode_str, typing_str, selection_str
+            ),
+        };
 
         write!(stdout, "{:width$}", status, width = self.width as usize)?;
         execute!(stdout, ResetColor)?;
@@ -224,6 +1631,15 @@ impl Display {
         Ok(())
     }
 
+    /// Строки данных с учетом панелей внизу экрана
+    fn content_lines(&self, editor: &HexEditor) -> usize {
+        self.get_visible_lines().saturating_sub(editor.panels_height())
+    }
+
+    pub fn width(&self) -> usize {
+        self.width as usize
+    }
+
     pub fn get_visible_lines(&self) -> usize {
         // Высота минус: заголовок (1), пустая строка (1), заголовок колонок (1), статус бар (1)
         (self.height as usize).saturating_sub(4)
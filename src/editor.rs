@@ -1,32 +1,226 @@
-use crate::config::Config;
-use crate::display::Display;
-use crate::undo_redo::{EditOperation, UndoRedoStack};
+use crate::annotations::{self, Annotation, Annotations};
+use crate::bookmarks::Bookmarks;
+use crate::charset::Charset;
+use crate::checksums;
+use crate::clipboard::SystemClipboard;
+use crate::config::{BytesPerLine, Config};
+use crate::diff;
+use crate::display::{self, Minimap, ProgressBar, ScreenHit};
+use crate::export::ExportFormat;
+use crate::hexdump::{self, OffsetRadix};
+use crate::highlight::{HighlightRule, Highlights};
+use crate::history::JumpHistory;
+use crate::locks::{Lock, Locks};
+use crate::notify::{Notification, Notifications, Severity};
+use crate::patch::{self, PatchFormat};
+use crate::pipe;
+use crate::plugin;
+use crate::recent;
+use crate::recovery;
+use crate::session::{LastSearch, Session};
+use crate::templates::{self, Source, TemplateView};
 use crate::utils;
-use anyhow::{Result, bail};
-use std::fs::{File, OpenOptions};
-use std::io::{Read, Write};
-
-#[derive(Debug, Clone, Copy, PartialEq)]
+use crate::watch::FileWatcher;
+use anyhow::{Context, Result, anyhow, bail};
+use hexr_core::buffer::{self, Buffer, PieceTable};
+use hexr_core::asm;
+use hexr_core::checksum::{Algorithm, Recipe};
+use hexr_core::codec::Codec;
+use hexr_core::compress::Format;
+use hexr_core::device;
+use hexr_core::disasm::{Arch, Disassembler, Instruction};
+use hexr_core::document::Document;
+use hexr_core::entropy;
+use hexr_core::formats;
+use hexr_core::hexfile::{HexFormat, Image};
+use hexr_core::magic;
+use hexr_core::numeric::{Endian, NumericType};
+use hexr_core::process::{self, Region};
+use hexr_core::progress::{Cancelled, Progress};
+use hexr_core::runs;
+use hexr_core::search::{
+    self, MatchList, SearchControl, SearchJob, SearchMode, SearchOutcome, SearchPattern, SearchRequest,
+};
+use hexr_core::stats::Histogram;
+use hexr_core::transform::{self, Transform};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fs::OpenOptions;
+use std::io::{Seek, SeekFrom, Write};
+use std::sync::Arc;
+
+/// Начиная с этого размера поиск выполняется в фоновом потоке
+const BACKGROUND_SEARCH_THRESHOLD: usize = 4 * 1024 * 1024;
+
+/// Как часто обновляется ход фонового поиска в строке состояния
+const SEARCH_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Начиная с этого размера при неизменной длине записываются только измененные блоки
+const DELTA_SAVE_THRESHOLD: usize = 16 * 1024 * 1024;
+
+/// Куски таблицы при записи делятся на части такого размера, чтобы был виден ход записи
+const SAVE_CHUNK: usize = 1024 * 1024;
+
+/// Сколько байт результата кодирования показывается перед заменой
+const CODEC_PREVIEW_BYTES: usize = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum EditMode {
     Hex,
     Ascii,
+    Bits,
+}
+
+/// Байт в строке битового режима: 32 бита, как в диаграммах форматов пакетов
+pub const BIT_VIEW_BYTES_PER_LINE: usize = 4;
+
+/// Размер области данных на экране. Терминал опрашивает отображение и передает размер
+/// редактору, поэтому без терминала (пакетные правки) редактор работает с размером по умолчанию
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    // Ширина экрана в символах: по ней считается автоматическая длина строки
+    pub width: usize,
+    // Строки данных без заголовков, строки состояния и панелей
+    pub lines: usize,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self { width: 80, lines: 20 }
+    }
+}
+
+/// Строка области данных: байты строки с этого смещения или свернутые одинаковые строки
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScreenLine {
+    Data(usize),
+    // Байты скрытых строк, повторяющих строку над "*"
+    Squeezed(std::ops::Range<usize>),
+}
+
+/// Меньше стольких одинаковых строк подряд не сворачиваются: "*" их не короче
+const MIN_SQUEEZED_LINES: usize = 2;
+
+/// Откуда распакована вкладка: файл-источник, сжатый поток в нем и его формат.
+/// По нему вкладка сжимается обратно на место потока
+#[derive(Debug, Clone)]
+pub struct Packed {
+    pub source: String,
+    pub range: std::ops::Range<usize>,
+    pub format: Format,
+}
+
+/// Частоты байт файла или выделения на момент открытия панели статистики
+pub struct StatsPanel {
+    pub range: std::ops::Range<usize>,
+    pub histogram: Histogram,
+}
+
+/// Курсор и прокрутка одной половины разделенного окна
+#[derive(Debug, Clone, Copy)]
+pub struct Pane {
+    pub cursor_pos: usize,
+    pub view_offset: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PaneSlot {
+    Top,
+    Bottom,
+}
+
+/// Память процесса (`hexr --pid`): открытый регион и карта регионов на момент открытия панели
+struct ProcessView {
+    pid: u32,
+    region: Region,
+    regions: Vec<Region>,
+    // Выбранная строка панели регионов
+    selected: usize,
+}
+
+/// Разделенное окно: активная половина использует cursor_pos/view_offset редактора,
+/// здесь хранится состояние второй
+struct Split {
+    other: Pane,
+    focus: PaneSlot,
 }
 
 pub struct HexEditor {
     pub file_path: String,
-    data: Vec<u8>,
-    original_data: Vec<u8>,
+    // Данные и история правок
+    document: Document,
     cursor_pos: usize,
     view_offset: usize,
     mode: EditMode,
     readonly: bool,
+    // Блочное устройство: открывается только для чтения, сохраняется секторами на место
+    device: bool,
+    // Размер сектора устройства или образа диска, для номера сектора в строке статуса
+    sector_size: Option<usize>,
     modified: bool,
-    bytes_per_line: usize,
+    bytes_per_line: BytesPerLine,
     half_byte: Option<u8>,
-    display: Display,
-    undo_redo_stack: UndoRedoStack,
+    // Бит под курсором в битовом режиме, 0 - старший
+    bit_index: u8,
+    viewport: Viewport,
     config: Config,
     is_new_file: bool,
+    // Буфер со стандартного ввода сохраняется в stdout, когда тот перенаправлен
+    writes_stdout: bool,
+    // Содержимое последнего сохранения такого буфера, пишется в stdout при выходе
+    stdout_output: Option<Arc<PieceTable>>,
+    selection_start: Option<usize>,
+    selection_end: Option<usize>,
+    visual_mode: bool,
+    insert_mode: bool,
+    clipboard: Vec<u8>,
+    system_clipboard: SystemClipboard,
+    search_mode: SearchMode,
+    search_ignore_case: bool,
+    last_search: Option<SearchPattern>,
+    // Строка и режим последнего поиска, чтобы запомнить их в сессии
+    last_search_query: Option<LastSearch>,
+    search_scope: Option<std::ops::Range<usize>>,
+    search_job: Option<SearchJob>,
+    match_list: Option<MatchList>,
+    jump_history: JumpHistory,
+    file_watcher: FileWatcher,
+    bookmarks: Bookmarks,
+    bookmark_panel: bool,
+    stats_panel: Option<StatsPanel>,
+    // Наложенный шаблон остается и при закрытой панели дерева
+    template: Option<TemplateView>,
+    template_panel: bool,
+    annotations: Annotations,
+    // Диапазоны, правки в которых отклоняются
+    locks: Locks,
+    // Разобранные правила [[highlight]] конфигурации
+    highlights: Highlights,
+    // Запомненные поля контрольных сумм, пересчитываются одной клавишей
+    checksums: Vec<Recipe>,
+    // Сжатый поток, из которого распакована вкладка
+    packed: Option<Packed>,
+    // Открыт Intel HEX или S-record: формат и раскладка по адресам для записи обратно
+    hex_file: Option<(HexFormat, Image)>,
+    // Буфер - регион памяти процесса; сохранение пишет правки в процесс
+    process: Option<ProcessView>,
+    region_panel: bool,
+    split: Option<Split>,
+    inspector: bool,
+    // Панель дизассемблера под курсором; закрыта - None
+    disasm: Option<Disassembler>,
+    // Выбранная ячейка инспектора: строка и столбец (тип слева/справа, LE/BE)
+    inspector_cell: (usize, usize),
+    messages: Notifications,
+    // Данные, записанные в файл восстановления последним, и когда это было
+    auto_saved: Option<Arc<PieceTable>>,
+    auto_saved_at: std::time::Instant,
+    // Журнал правок этого запуска начат на диске
+    journaling: bool,
+    // Журнал прошлого запуска отложен ("Keep for later"): перед тем как его переписать,
+    // нужно спросить
+    kept_journal: bool,
 }
 
 impl HexEditor {
@@ -44,52 +238,342 @@ impl HexEditor {
 
         // Создаем данные с указанным размером и заполнителем
         let data = vec![fill_byte; size];
-        let original_data = data.clone();
-
-        let display = Display::new()?;
+        let highlights = Highlights::load(&config.highlight).unwrap_or_default();
 
         Ok(Self {
             file_path: "untitled".to_string(),
-            data,
-            original_data,
+            document: Document::new(Buffer::from(data)),
             cursor_pos: 0,
             view_offset: 0,
             mode: EditMode::Hex,
             readonly: false,
+            device: false,
+            sector_size: None,
             modified: size > 0, // Если размер > 0, то файл считается измененным
-            bytes_per_line: config.editor.bytes_per_line,
+            bytes_per_line: config.editor.bytes_per_line(),
+            insert_mode: config.editor.insert_mode,
             half_byte: None,
-            display,
-            undo_redo_stack: UndoRedoStack::default(),
+            bit_index: 0,
+            viewport: Viewport::default(),
             config,
             is_new_file: true,
+            writes_stdout: false,
+            stdout_output: None,
+            selection_start: None,
+            selection_end: None,
+            visual_mode: false,
+            clipboard: Vec::new(),
+            system_clipboard: SystemClipboard::new(),
+            search_mode: SearchMode::Hex,
+            search_ignore_case: false,
+            last_search: None,
+            last_search_query: None,
+            search_scope: None,
+            search_job: None,
+            match_list: None,
+            jump_history: JumpHistory::default(),
+            file_watcher: FileWatcher::new(""),
+            bookmarks: Bookmarks::default(),
+            bookmark_panel: false,
+            stats_panel: None,
+            template: None,
+            template_panel: false,
+            annotations: Annotations::default(),
+            locks: Locks::default(),
+            highlights,
+            checksums: Vec::new(),
+            packed: None,
+            hex_file: None,
+            process: None,
+            region_panel: false,
+            split: None,
+            inspector: false,
+            disasm: None,
+            inspector_cell: (0, 0),
+            messages: Notifications::default(),
+            auto_saved: None,
+            auto_saved_at: std::time::Instant::now(),
+            journaling: false,
+            kept_journal: false,
         })
     }
 
+    /// Данные из канала (`hexr -`); при `writes_stdout` сохранение отдает их в stdout
+    pub fn from_pipe(data: Vec<u8>, writes_stdout: bool, config: Config) -> Result<Self> {
+        let mut editor = Self::new(config)?;
+        editor.file_path = pipe::STDIO_PATH.to_string();
+        editor.document = Document::new(Buffer::from(data));
+        editor.is_new_file = false;
+        editor.writes_stdout = writes_stdout;
+        Ok(editor)
+    }
+
+    /// Память процесса (`hexr --pid`): открывается куча или первый читаемый регион,
+    /// только для чтения - запись разрешается явно, с подтверждением
+    pub fn attach(pid: u32, config: Config) -> Result<Self> {
+        let regions = process::regions(pid)?;
+        let initial = regions
+            .iter()
+            .find(|region| region.name() == "[heap]" && region.is_readable())
+            .or_else(|| regions.iter().find(|region| region.is_readable()))
+            .cloned()
+            .ok_or_else(|| anyhow!("Process {} has no readable memory", pid))?;
+
+        let mut editor = Self::new(config)?;
+        editor.is_new_file = false;
+        editor.readonly = true;
+        editor.process = Some(ProcessView { pid, region: initial.clone(), regions, selected: 0 });
+        editor.set_message("Process memory opened read-only (Alt+Shift+G: regions, Alt+Shift+R: allow writes)");
+        editor.load_region(initial)?;
+        Ok(editor)
+    }
+
+    /// Распакованный поток в своей вкладке; имя файла спрашивается при сохранении
+    pub fn unpacked(data: Vec<u8>, packed: Packed, config: Config) -> Result<Self> {
+        let mut editor = Self::new(config)?;
+        editor.file_path = format!("{}@0x{:X}.{}", packed.source, packed.range.start, packed.format.name());
+        editor.document = Document::new(Buffer::from(data));
+        editor.packed = Some(packed);
+        Ok(editor)
+    }
+
     pub fn open(file_path: &str, readonly: bool, config: Config) -> Result<Self> {
-        // Открываем существующий файл
-        let mut file = File::open(file_path)?;
-        let mut data = Vec::new();
-        file.read_to_end(&mut data)?;
+        let mut editor = Self::open_headless(file_path, readonly, config)?;
 
-        let display = Display::new()?;
+        if editor.config.editor.restore_session {
+            match Session::load(file_path) {
+                Ok(Some(session)) => editor.restore_session(session),
+                Ok(None) => {}
+                Err(e) => editor.set_warning(format!("{:#}", e)),
+            }
+        }
+        // Правки журналируются с самого открытия, в том числе восстановленные
+        if editor.can_recover() {
+            editor.document.start_journal();
+            if let Err(e) = editor.offer_recovery() {
+                editor.report_error(&e);
+            }
+        }
 
-        Ok(Self {
+        // Список недавних файлов не важнее самого открытия
+        if let Err(e) = recent::record(file_path) {
+            editor.set_warning(format!("Recent files not updated: {:#}", e));
+        }
+        Ok(editor)
+    }
+
+    /// Журнал правок или файл восстановления остался от аварийного выхода: его правки
+    /// можно вернуть. Журнал полнее: в нем каждая правка до самого сбоя
+    fn offer_recovery(&mut self) -> Result<()> {
+        if let Some(journal) = recovery::read_journal(&self.file_path) {
+            return self.offer_journal(journal);
+        }
+        let Some(path) = recovery::find(&self.file_path) else {
+            return Ok(());
+        };
+
+        let prompt = format!("Unsaved changes found in {}: [R]estore, [D]iscard, [K]eep for later? ", path);
+        match utils::prompt_choice(&prompt, "rdk")? {
+            Some('r') => {
+                let count = self.restore_recovered(&PieceTable::new(Buffer::open(&path)?));
+                // Файл восстановления совпадает с буфером, пока в нем нет новых правок
+                self.auto_saved = Some(self.document.snapshot());
+                self.set_message(format!("Restored {} changed ranges from {} (Ctrl+S saves them)", count, path));
+            }
+            Some('d') => {
+                recovery::remove(&self.file_path);
+                self.set_message("Recovery file discarded");
+            }
+            _ => self.set_message(format!("Recovery file kept: {}", path)),
+        }
+        Ok(())
+    }
+
+    /// Повторяет правки журнала поверх файла; файл должен быть таким же, как при записи журнала
+    fn offer_journal(&mut self, journal: recovery::Journal) -> Result<()> {
+        let count = journal.entries.len();
+        let prompt = format!("Unsaved edits found in {} ({} edits): [R]eplay, [D]iscard, [K]eep for later? ", journal.path, count);
+        match utils::prompt_choice(&prompt, "rdk")? {
+            Some('r') => {
+                // Журнал, который не удалось повторить, остается на диске, как отложенный
+                self.kept_journal = true;
+                if !journal.matches(&self.file_path, self.document.data().original()) {
+                    bail!("{} does not match the file: it was changed after the edits", journal.path);
+                }
+                let mut replayed = self.document.data().clone();
+                for (idx, entry) in journal.entries.iter().enumerate() {
+                    if !entry.apply(&mut replayed) {
+                        bail!("{}: edit #{} does not fit the file", journal.path, idx + 1);
+                    }
+                }
+                self.restore_recovered(&replayed);
+                // Журнал начнется заново с этих правок, а файл восстановления отстает от него
+                recovery::remove(&self.file_path);
+                self.kept_journal = false;
+                self.set_message(format!("Replayed {} edits from {} (Ctrl+S saves them)", count, journal.path));
+            }
+            Some('d') => {
+                recovery::remove(&self.file_path);
+                self.set_message("Journal discarded");
+            }
+            _ => {
+                self.kept_journal = true;
+                self.set_message(format!("Journal kept: {}", journal.path));
+            }
+        }
+        Ok(())
+    }
+
+    /// Применяет отличия восстановленных данных одной правкой, которую можно отменить;
+    /// возвращает число измененных участков
+    fn restore_recovered(&mut self, recovered: &PieceTable) -> usize {
+        let runs: Vec<_> = diff::differing_runs(self.document.data(), recovered).collect();
+
+        // Длины различаются только в последнем участке, поэтому замены не сдвигают следующие
+        let len = self.document.len();
+        self.document.begin_group();
+        for run in &runs {
+            let bytes = recovered.range(run.start..run.end.min(recovered.len()));
+            self.document.replace(run.start..run.end.min(len), &bytes);
+        }
+        self.document.end_group();
+
+        if !runs.is_empty() {
+            self.modified = true;
+            self.clamp_cursor();
+            self.adjust_view();
+        }
+        runs.len()
+    }
+
+    /// Открытие без сессии и списка недавних файлов: для пакетных правок
+    pub fn open_headless(file_path: &str, readonly: bool, config: Config) -> Result<Self> {
+        // Открываем существующий файл; большие файлы отображаются в память
+        let (buffer, hex_file) = Self::load_file(file_path)?;
+        let document = Document::new(buffer);
+        let device = device::is_block_device(file_path);
+        let highlights = Highlights::load(&config.highlight).unwrap_or_default();
+        // Испорченные файлы закладок и пометок не мешают открытию: файл открывается без
+        // них, а ошибка показывается предупреждением
+        let mut warnings = Vec::new();
+        let bookmarks = or_warn(Bookmarks::load(file_path), &mut warnings);
+        let annotations = or_warn(Annotations::load(file_path), &mut warnings);
+        let locks = or_warn(Locks::load(file_path), &mut warnings);
+        let checksums = or_warn(checksums::load(file_path), &mut warnings);
+
+        let mut editor = Self {
             file_path: file_path.to_string(),
-            data: data.clone(),
-            original_data: data,
+            document,
             cursor_pos: 0,
             view_offset: 0,
             mode: EditMode::Hex,
-            readonly,
+            // Запись на устройство разрешается только явно, с подтверждением
+            readonly: readonly || device,
+            device,
+            sector_size: device::sector_size(file_path),
             modified: false,
-            bytes_per_line: config.editor.bytes_per_line,
+            bytes_per_line: config.editor.bytes_per_line(),
+            insert_mode: config.editor.insert_mode,
             half_byte: None,
-            display,
-            undo_redo_stack: UndoRedoStack::default(),
+            bit_index: 0,
+            viewport: Viewport::default(),
             config,
             is_new_file: false,
-        })
+            writes_stdout: false,
+            stdout_output: None,
+            selection_start: None,
+            selection_end: None,
+            visual_mode: false,
+            clipboard: Vec::new(),
+            system_clipboard: SystemClipboard::new(),
+            search_mode: SearchMode::Hex,
+            search_ignore_case: false,
+            last_search: None,
+            last_search_query: None,
+            search_scope: None,
+            search_job: None,
+            match_list: None,
+            jump_history: JumpHistory::default(),
+            file_watcher: FileWatcher::new(file_path),
+            bookmarks,
+            bookmark_panel: false,
+            stats_panel: None,
+            template: None,
+            template_panel: false,
+            annotations,
+            locks,
+            highlights,
+            checksums,
+            packed: None,
+            hex_file,
+            process: None,
+            region_panel: false,
+            split: None,
+            inspector: false,
+            disasm: None,
+            inspector_cell: (0, 0),
+            messages: Notifications::default(),
+            auto_saved: None,
+            auto_saved_at: std::time::Instant::now(),
+            journaling: false,
+            kept_journal: false,
+        };
+        for e in warnings {
+            editor.set_warning(format!("{:#}", e));
+        }
+        if device {
+            editor.set_message("Block device opened read-only (Alt+Shift+R: allow writes)");
+        }
+        Ok(editor)
+    }
+
+    /// Данные файла с диска; Intel HEX и S-record раскладываются по адресам. Файл с таким
+    /// расширением, но другим содержимым (например, hex-текст) открывается как есть
+    fn load_file(path: &str) -> Result<(Buffer, Option<(HexFormat, Image)>)> {
+        if let Some(format) = HexFormat::from_path(path) {
+            let text = std::fs::read(path)?;
+            if format.matches(&text) {
+                let (data, image) = format
+                    .decode(&text)
+                    .with_context(|| format!("{} is not valid {}", path, format.label()))?;
+                return Ok((Buffer::from(data), Some((format, image))));
+            }
+        }
+        Ok((Buffer::open(path)?, None))
+    }
+
+    /// Файл мог укоротиться с прошлого раза, поэтому позиции проверяются по длине
+    fn restore_session(&mut self, session: Session) {
+        self.set_mode(session.mode);
+        self.cursor_pos = session.cursor_pos;
+        self.clamp_cursor();
+        self.view_offset = session.view_offset.min(self.cursor_pos);
+        self.realign_view();
+
+        if let Some(search) = session.search {
+            // Шаблон, который больше не разбирается, просто забывается
+            if let Ok(pattern) = SearchPattern::parse(&search.input, search.mode, search.ignore_case) {
+                self.search_mode = search.mode;
+                self.search_ignore_case = search.ignore_case;
+                self.last_search = Some(pattern);
+                self.last_search_query = Some(search);
+            }
+        }
+    }
+
+    /// Запоминает позицию, режим и последний поиск до следующего открытия файла
+    pub fn save_session(&self) -> Result<()> {
+        if !self.config.editor.restore_session || self.is_new_file || self.is_pipe() || self.process.is_some() {
+            return Ok(());
+        }
+
+        let session = Session {
+            cursor_pos: self.cursor_pos,
+            view_offset: self.view_offset,
+            mode: self.mode,
+            search: self.last_search_query.clone(),
+        };
+        session.save(&self.file_path)
     }
 
 
@@ -98,34 +582,251 @@ impl HexEditor {
             bail!("File is opened in read-only mode");
         }
 
+        // Данные из канала отдаются в stdout и без правок: так hexr можно поставить в конвейер
+        if self.is_pipe() {
+            return self.save_to_stdout();
+        }
+
         if !self.modified {
             return Ok(());
         }
 
-        let mut file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(&self.file_path)?;
+        if self.process.is_some() {
+            self.save_to_process()?;
+        } else if self.device {
+            self.save_to_device()?;
+        } else if self.can_save_delta() {
+            self.save_delta()?;
+        } else {
+            self.write_to_path(&self.file_path)?;
+        }
+        self.mark_saved();
+
+        Ok(())
+    }
+
+    /// Новый файл без имени (создан через -n без пути) - имя нужно спросить.
+    /// Данные из канала тоже, если stdout - терминал
+    pub fn needs_file_name(&self) -> bool {
+        (self.is_new_file && (self.file_path == "untitled" || self.packed.is_some()))
+            || (self.is_pipe() && !self.writes_stdout)
+    }
 
-        file.write_all(&self.data)?;
-        file.flush()?;
+    /// Буфер со стандартного ввода
+    pub fn is_pipe(&self) -> bool {
+        self.file_path == pipe::STDIO_PATH
+    }
 
-        self.original_data = self.data.clone();
-        self.modified = false;
-        self.undo_redo_stack.clear(); // Очищаем историю после сохранения
+    /// Снимок запоминается сейчас, а пишется при выходе: stdout не перезаписать дважды
+    fn save_to_stdout(&mut self) -> Result<()> {
+        self.mark_saved();
+        self.stdout_output = Some(self.document.snapshot());
+        self.set_message(format!("{} bytes will be written to stdout on exit", self.document.len()));
+        Ok(())
+    }
+
+    /// Пишет сохраненное содержимое буфера из канала; у остальных буферов ничего не делает
+    pub fn write_stdout_output(&self, out: &mut impl Write) -> Result<()> {
+        if let Some(data) = &self.stdout_output {
+            for chunk in data.chunks() {
+                out.write_all(chunk)?;
+            }
+            out.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Ctrl+S: сохранение, для безымянного буфера - через "Save As"
+    pub fn save_or_prompt(&mut self) -> Result<()> {
+        if self.needs_file_name() {
+            self.save_as_from_input()
+        } else {
+            self.save()
+        }
+    }
+
+    /// Сохраняет буфер под новым именем и делает его текущим файлом
+    pub fn save_as_from_input(&mut self) -> Result<()> {
+        let path = utils::get_user_input("Save as: ")?;
+        let path = path.trim();
+
+        if path.is_empty() {
+            return Ok(());
+        }
+
+        self.write_to_path(path)?;
+        self.discard_recovery();
+        self.hex_file = self.hex_file_for(path);
+        self.file_path = path.to_string();
+        self.is_new_file = false;
+        // Копия принадлежит пользователю, дальше её можно редактировать: это обычный файл,
+        // и сохранение больше не пишет в память процесса или на устройство
+        self.readonly = false;
+        self.process = None;
+        self.region_panel = false;
+        self.device = false;
+        self.sector_size = None;
+        self.mark_saved();
+        self.set_message(format!("Saved as {}", path));
+
+        Ok(())
+    }
+
+    /// Сохраняет копию буфера, не меняя текущий файл
+    pub fn save_copy_from_input(&mut self) -> Result<()> {
+        let path = utils::get_user_input("Save copy to: ")?;
+        let path = path.trim();
+
+        if path.is_empty() {
+            return Ok(());
+        }
+
+        self.write_to_path(path)?;
+        self.set_message(format!("Copy saved to {} ({} bytes)", path, self.document.len()));
+
+        Ok(())
+    }
+
+    pub fn write_to_path(&self, path: &str) -> Result<()> {
+        let backup = self.config.editor.backup_on_save;
+        match self.hex_file_for(path) {
+            Some((format, image)) => {
+                let text = format.encode(&self.document.data().to_vec(), &image)?;
+                utils::write_file_atomic(path, [&text[..]], backup)
+            }
+            None => {
+                // Запись не прерывается на середине; полоса только показывает ход
+                let mut progress = ProgressBar::uncancellable("Saving");
+                let (total, mut written) = (self.document.len(), 0);
+                let chunks = self.document.data().chunks().flat_map(|chunk| chunk.chunks(SAVE_CHUNK)).inspect(|chunk| {
+                    let _ = progress.update(written, total);
+                    written += chunk.len();
+                });
+                utils::write_file_atomic(path, chunks, backup)
+            }
+        }
+    }
+
+    /// В каком формате писать в `path`: свой файл - как он был открыт, другой - по расширению
+    /// (`.hex`, `.srec`), с адресами открытого файла. None - сырые байты
+    fn hex_file_for(&self, path: &str) -> Option<(HexFormat, Image)> {
+        if path == self.file_path {
+            return self.hex_file.clone();
+        }
+        let format = HexFormat::from_path(path)?;
+        let image = self.hex_file.as_ref().map(|(_, image)| image.clone()).unwrap_or_default();
+        Some((format, image))
+    }
+
+    /// Intel HEX или S-record, из которого открыт буфер, и адрес его первого байта
+    pub fn hex_file_label(&self) -> Option<String> {
+        let (format, image) = self.hex_file.as_ref()?;
+        Some(format!("{} @ 0x{:08X}", format.label(), image.base))
+    }
+
+    /// Адрес и права открытого региона памяти процесса
+    pub fn process_label(&self) -> Option<String> {
+        let view = self.process.as_ref()?;
+        Some(format!("0x{:X} {}", view.region.start, view.region.perms))
+    }
+
+    /// Адрес в памяти процесса для смещения в буфере
+    pub fn process_address(&self, pos: usize) -> Option<u64> {
+        Some(self.process.as_ref()?.region.start + pos as u64)
+    }
+
+    /// Частичная запись возможна, если все правки были заменой на месте и файл на диске
+    /// той же длины. Она идет поверх файла на месте, поэтому не используется вместе с резервной копией
+    fn can_save_delta(&self) -> bool {
+        !self.is_new_file
+            && !self.config.editor.backup_on_save
+            && self.document.len() >= DELTA_SAVE_THRESHOLD
+            && self.document.data().overwritten_ranges().is_some()
+            && self.hex_file.is_none()
+            && std::fs::metadata(&self.file_path).is_ok_and(|m| m.len() == self.document.len() as u64)
+    }
+
+    /// Устройство не меняет размер и не заменяется целиком: на место пишутся секторы с правками
+    fn save_to_device(&self) -> Result<()> {
+        let Some(ranges) = self.document.data().overwritten_ranges() else {
+            bail!("Insertions and deletions cannot be saved to a block device; undo them first");
+        };
+        let sector_size = self.sector_size.unwrap_or(device::DEFAULT_SECTOR_SIZE);
+        device::write_sectors(&self.file_path, self.document.data(), &ranges, sector_size)
+    }
+
+    /// В память процесса пишутся только замененные байты: регион не меняет размер
+    fn save_to_process(&self) -> Result<()> {
+        let Some(view) = &self.process else {
+            return Ok(());
+        };
+        let Some(ranges) = self.document.data().overwritten_ranges() else {
+            bail!("Insertions and deletions cannot be saved to process memory; undo them first");
+        };
+        for range in ranges {
+            let address = view.region.start + range.start as u64;
+            process::write(view.pid, address, &self.document.range(range))?;
+        }
+        Ok(())
+    }
+
+    /// Записывает в файл только замененные диапазоны
+    fn save_delta(&self) -> Result<()> {
+        let mut file = OpenOptions::new().write(true).open(&self.file_path)?;
+
+        for range in self.document.data().overwritten_ranges().unwrap_or_default() {
+            file.seek(SeekFrom::Start(range.start as u64))?;
+            file.write_all(&self.document.range(range))?;
+        }
+        file.sync_all()?;
 
         Ok(())
     }
 
+    fn mark_saved(&mut self) {
+        // Сохраненное содержимое становится исходным: большой файл заново отображаем с диска
+        // Маленький файл не перечитывается: его содержимое уже в памяти
+        let reopen = self.document.len() >= buffer::MMAP_THRESHOLD && !self.is_pipe() && self.hex_file.is_none();
+        let saved = reopen
+            .then(|| Buffer::open(&self.file_path).ok())
+            .flatten()
+            .unwrap_or_else(|| Buffer::from(self.document.data().to_vec()));
+        // История остается: отмена после сохранения снова делает файл измененным
+        self.document.mark_saved(saved);
+        self.file_watcher.refresh(&self.file_path);
+        self.modified = false;
+        self.discard_recovery();
+    }
+
+    /// Спрашивает, что делать с несохраненными изменениями перед выходом.
+    /// Возвращает true, если можно выходить
+    pub fn confirm_quit(&mut self) -> Result<bool> {
+        if !self.modified {
+            return Ok(true);
+        }
+
+        match utils::prompt_choice("Unsaved changes: [S]ave, [D]iscard, [C]ancel? ", "sdc")? {
+            Some('s') => {
+                self.save_or_prompt()?;
+                // Отмена "Save As" оставляет изменения несохраненными
+                Ok(!self.modified)
+            }
+            Some('d') => Ok(true),
+            _ => Ok(false),
+        }
+    }
+
     pub fn undo(&mut self) -> Result<()> {
         if self.readonly {
             bail!("Cannot undo in read-only mode");
         }
 
-        if let Some(operation) = self.undo_redo_stack.undo() {
-            operation.undo(&mut self.data);
-            self.modified = true;
+        if !self.can_undo() {
+            bail!("Nothing to undo");
+        }
+
+        if self.document.undo() {
+            self.refresh_modified();
         }
         Ok(())
     }
@@ -135,60 +836,130 @@ impl HexEditor {
             bail!("Cannot redo in read-only mode");
         }
 
-        if let Some(operation) = self.undo_redo_stack.redo() {
-            operation.redo(&mut self.data);
-            self.modified = true;
+        if !self.can_redo() {
+            bail!("Nothing to redo");
+        }
+
+        if self.document.redo() {
+            self.refresh_modified();
         }
         Ok(())
     }
 
+    /// После undo/redo файл не изменен, если история вернулась к сохраненному состоянию
+    fn refresh_modified(&mut self) {
+        // Несохраненный новый файл по-прежнему нужно записать, как сразу после создания
+        self.modified = !self.document.is_saved() || (self.is_new_file && !self.document.is_empty());
+    }
+
     pub fn can_undo(&self) -> bool {
-        self.undo_redo_stack.can_undo()
+        self.document.can_undo()
     }
 
-    pub fn can_redo(&self) -> bool {
-        self.undo_redo_stack.can_redo()
+    /// Выбор ветки redo там, где после отмены была сделана другая правка
+    pub fn switch_undo_branch(&mut self, forward: bool) {
+        if !self.document.switch_branch(forward) {
+            self.set_warning("No other redo branch here");
+            return;
+        }
+
+        if let Some(branches) = self.document.branches() {
+            let list: Vec<String> = branches
+                .lengths
+                .iter()
+                .enumerate()
+                .map(|(idx, len)| {
+                    let mark = if idx == branches.active { "*" } else { "" };
+                    format!("{}{}: {} step{}", mark, idx + 1, len, if *len == 1 { "" } else { "s" })
+                })
+                .collect();
+            self.set_message(format!("Redo branch {}/{} ({})", branches.active + 1, list.len(), list.join(", ")));
+        }
+    }
+
+    /// Активная ветка redo и их число, если в этой точке история ветвится
+    pub fn undo_branch(&self) -> Option<(usize, usize)> {
+        self.document
+            .branches()
+            .map(|branches| (branches.active + 1, branches.lengths.len()))
+    }
+
+    /// Выполняет составное действие так, что все его правки отменяются одним шагом
+    pub fn undo_group<T>(&mut self, action: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.document.begin_group();
+        let result = action(self);
+        self.document.end_group();
+        result
     }
 
-    pub fn get_config(&self) -> &Config {
-        &self.config
+    pub fn can_redo(&self) -> bool {
+        self.document.can_redo()
     }
 
     pub fn move_cursor_up(&mut self) {
-        if self.cursor_pos >= self.bytes_per_line {
-            self.cursor_pos -= self.bytes_per_line;
+        let line_len = self.line_len();
+        if self.cursor_pos >= line_len {
+            // Свернутые строки проходятся за один шаг, к первой строке серии
+            let line = self.cursor_pos - self.cursor_pos % line_len;
+            let mut step = line_len;
+            if self.squeeze() && line <= self.document.len() {
+                let start = self.repeated_lines_start(line - line_len);
+                if (line - line_len - start) / line_len >= MIN_SQUEEZED_LINES {
+                    step = line - start;
+                }
+            }
+            self.cursor_pos -= step;
             self.adjust_view();
         }
     }
 
     pub fn move_cursor_down(&mut self) {
-        if self.cursor_pos + self.bytes_per_line < self.data.len() {
-            self.cursor_pos += self.bytes_per_line;
+        let line_len = self.line_len();
+        let line = self.cursor_pos - self.cursor_pos % line_len;
+        let mut step = line_len;
+        if self.squeeze() && line < self.document.len() {
+            let end = self.repeated_lines_end(line);
+            if (end - line) / line_len > MIN_SQUEEZED_LINES {
+                step = end - line;
+            }
+        }
+
+        if self.cursor_pos + step < self.document.len() {
+            self.cursor_pos += step;
             self.adjust_view();
-        } else if self.cursor_pos < self.data.len() {
+        } else if self.cursor_pos < self.document.len() {
             // Перемещаемся к концу файла
-            self.cursor_pos = self.data.len().saturating_sub(1);
+            self.cursor_pos = self.document.len().saturating_sub(1);
             self.adjust_view();
         }
     }
 
     pub fn move_cursor_left(&mut self) {
-        if self.cursor_pos > 0 {
+        // В битовом режиме курсор сначала проходит биты текущего байта
+        if self.mode == EditMode::Bits && self.bit_index > 0 {
+            self.bit_index -= 1;
+        } else if self.cursor_pos > 0 {
             self.cursor_pos -= 1;
+            if self.mode == EditMode::Bits {
+                self.bit_index = 7;
+            }
             self.adjust_view();
         }
     }
 
     pub fn move_cursor_right(&mut self) {
-        if self.cursor_pos < self.data.len().saturating_sub(1) {
+        if self.mode == EditMode::Bits && self.bit_index < 7 {
+            self.bit_index += 1;
+        } else if self.cursor_pos < self.document.len().saturating_sub(1) {
             self.cursor_pos += 1;
+            self.bit_index = 0;
             self.adjust_view();
         }
     }
 
     pub fn page_up(&mut self) {
-        let lines_per_page = self.display.get_visible_lines();
-        let jump = lines_per_page * self.bytes_per_line;
+        let lines_per_page = self.visible_lines();
+        let jump = lines_per_page * self.line_len();
 
         if self.cursor_pos > jump {
             self.cursor_pos -= jump;
@@ -199,219 +970,2304 @@ impl HexEditor {
     }
 
     pub fn page_down(&mut self) {
-        let lines_per_page = self.display.get_visible_lines();
-        let jump = lines_per_page * self.bytes_per_line;
+        let lines_per_page = self.visible_lines();
+        let jump = lines_per_page * self.line_len();
 
-        self.cursor_pos = (self.cursor_pos + jump).min(self.data.len().saturating_sub(1));
+        self.cursor_pos = (self.cursor_pos + jump).min(self.document.len().saturating_sub(1));
         self.adjust_view();
     }
 
     pub fn move_to_line_start(&mut self) {
-        self.cursor_pos = (self.cursor_pos / self.bytes_per_line) * self.bytes_per_line;
+        self.cursor_pos = (self.cursor_pos / self.line_len()) * self.line_len();
     }
 
     pub fn move_to_line_end(&mut self) {
-        let line_start = (self.cursor_pos / self.bytes_per_line) * self.bytes_per_line;
-        let line_end = (line_start + self.bytes_per_line).saturating_sub(1).min(self.data.len().saturating_sub(1));
+        let line_start = (self.cursor_pos / self.line_len()) * self.line_len();
+        let line_end = (line_start + self.line_len()).saturating_sub(1).min(self.document.len().saturating_sub(1));
         self.cursor_pos = line_end;
     }
 
     pub fn toggle_mode(&mut self) {
-        self.mode = match self.mode {
+        let mode = match self.mode {
             EditMode::Hex => EditMode::Ascii,
-            EditMode::Ascii => EditMode::Hex,
+            EditMode::Ascii | EditMode::Bits => EditMode::Hex,
         };
-        self.half_byte = None;
+        self.set_mode(mode);
     }
 
-    pub fn input_hex_char(&mut self, c: char) -> Result<()> {
-        if self.readonly || self.mode != EditMode::Hex {
-            return Ok(());
-        }
-
-        let value = c.to_digit(16).unwrap() as u8;
-
-        if let Some(high) = self.half_byte {
-            // Второй полубайт
-            if self.cursor_pos >= self.data.len() {
-                self.half_byte = None;
-                return Ok(());
-            }
+    /// Битовый режим (Alt+J): каждый байт показан восемью битами
+    pub fn toggle_bit_mode(&mut self) {
+        let mode = if self.mode == EditMode::Bits { EditMode::Hex } else { EditMode::Bits };
+        self.set_mode(mode);
+    }
 
-            let old_value = self.data[self.cursor_pos];
-            let new_value = (high << 4) | value;
-            self.data[self.cursor_pos] = new_value;
-            self.modified = true;
-            self.half_byte = None;
+    fn set_mode(&mut self, mode: EditMode) {
+        self.mode = mode;
+        self.half_byte = None;
+        self.bit_index = 0;
 
-            // Сохраняем операцию для undo/redo
-            self.undo_redo_stack.push(EditOperation::new_replace_byte(self.cursor_pos, old_value, new_value));
+        // Длина строки зависит от режима
+        self.realign_view();
+    }
 
-            if self.cursor_pos + 1 < self.data.len() {
-                self.cursor_pos += 1;
+    /// Байт в строке; по нему считаются и перемещения курсора, и отрисовка
+    pub fn line_len(&self) -> usize {
+        match (self.mode, self.bytes_per_line) {
+            (EditMode::Bits, _) => BIT_VIEW_BYTES_PER_LINE,
+            (_, BytesPerLine::Fixed(bytes)) => bytes,
+            (_, BytesPerLine::Auto) => {
+                hexdump::auto_bytes_per_line(self.viewport.width, self.offset_radix(), self.byte_group())
             }
-        } else {
-            // Первый полубайт
-            self.half_byte = Some(value);
         }
-
-        Ok(())
     }
 
-    pub fn input_ascii_char(&mut self, c: char) -> Result<()> {
-        if self.readonly || self.mode != EditMode::Ascii {
-            return Ok(());
+    /// Новый размер окна; курсор остается в видимой области, а если окно выросло и файл
+    /// кончается раньше, окно сдвигается вверх, чтобы под концом файла не оставалось пустых строк
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        if self.viewport != viewport {
+            self.viewport = viewport;
+            let line_len = self.line_len();
+            let last_line = self.document.len().saturating_sub(1).max(self.cursor_pos) / line_len;
+            let max_view_line = (last_line + 1).saturating_sub(self.visible_lines());
+            self.view_offset = self.view_offset.min(max_view_line * line_len);
+            self.realign_view();
         }
+    }
 
-        if self.cursor_pos >= self.data.len() {
-            return Ok(());
-        }
+    /// После смены длины строки начало окна выравнивается по новой строке
+    fn realign_view(&mut self) {
+        self.view_offset -= self.view_offset % self.line_len();
+        self.adjust_view();
+    }
 
-        let old_value = self.data[self.cursor_pos];
-        let new_value = c as u8;
-        self.data[self.cursor_pos] = new_value;
-        self.modified = true;
+    /// Следующая кодировка текстовой колонки; выбор действует до выхода, постоянная
+    /// задается в конфигурации
+    pub fn cycle_charset(&mut self) {
+        self.config.display.charset = self.config.display.charset.next();
+    }
 
-        // Сохраняем операцию для undo/redo
-        self.undo_redo_stack.push(EditOperation::new_replace_byte(self.cursor_pos, old_value, new_value));
+    pub fn charset(&self) -> Charset {
+        self.config.display.charset
+    }
 
-        if self.cursor_pos + 1 < self.data.len() {
-            self.cursor_pos += 1;
-        }
+    /// Следующий размер группы байт в hex-колонке (1, 2, 4, 8)
+    pub fn cycle_byte_group(&mut self) {
+        let current = hexdump::BYTE_GROUPS.iter().position(|&group| group == self.byte_group());
+        let next = current.map_or(0, |idx| (idx + 1) % hexdump::BYTE_GROUPS.len());
+        self.config.display.byte_group = hexdump::BYTE_GROUPS[next];
+        self.realign_view();
+    }
 
-        Ok(())
+    pub fn byte_group(&self) -> usize {
+        hexdump::byte_group(self.config.display.byte_group)
     }
 
-    pub fn start_search(&mut self) -> Result<()> {
-        // Упрощенная версия поиска
-        let pattern = utils::get_user_input("Search (hex): ")?;
-        let bytes = utils::hex_string_to_bytes(&pattern)?;
+    pub fn cycle_offset_radix(&mut self) {
+        self.config.display.offset_radix = self.config.display.offset_radix.next();
+        self.realign_view();
+    }
 
-        if let Some(pos) = self.find_pattern(&bytes, self.cursor_pos + 1) {
-            self.cursor_pos = pos;
-            self.adjust_view();
-        }
+    pub fn offset_radix(&self) -> OffsetRadix {
+        self.config.display.offset_radix
+    }
 
-        Ok(())
+    /// Раскраска байт по классам (Alt+Y)
+    pub fn toggle_byte_colors(&mut self) {
+        self.config.display.colorize_bytes = !self.config.display.colorize_bytes;
     }
 
-    pub fn goto_address(&mut self) -> Result<()> {
-        let input = utils::get_user_input("Go to address (hex): ")?;
+    pub fn colorize_bytes(&self) -> bool {
+        self.config.display.colorize_bytes
+    }
 
-        if input.trim().is_empty() {
-            return Ok(());
-        }
+    /// Сворачивание одинаковых строк подряд (Alt+Shift+Z)
+    pub fn toggle_squeeze(&mut self) {
+        self.config.display.squeeze = !self.config.display.squeeze;
+        self.adjust_view();
+    }
 
-        match usize::from_str_radix(&input, 16) {
-            Ok(address) => {
-                if address < self.data.len() {
-                    self.cursor_pos = address;
-                    self.adjust_view();
-                } else {
-                    // Адрес за пределами файла - переходим к концу
-                    self.cursor_pos = self.data.len().saturating_sub(1);
-                    self.adjust_view();
+    pub fn squeeze(&self) -> bool {
+        self.config.display.squeeze
+    }
+
+    /// Строки экрана с начала окна `view_offset`. Со сворачиванием серии одинаковых
+    /// строк показываются первой строкой и "*"; строка курсора не скрывается никогда
+    pub fn screen_lines(&self, view_offset: usize, rows: usize, cursor_pos: usize) -> Vec<ScreenLine> {
+        let line_len = self.line_len();
+        let cursor_line = cursor_pos - cursor_pos % line_len;
+        let mut lines = Vec::with_capacity(rows);
+        let mut offset = view_offset;
+        while lines.len() < rows {
+            lines.push(ScreenLine::Data(offset));
+            let mut next = offset + line_len;
+            if self.squeeze() && offset < self.document.len() {
+                let mut end = self.repeated_lines_end(offset);
+                if (next..end).contains(&cursor_line) {
+                    end = cursor_line;
+                }
+                if end - next >= MIN_SQUEEZED_LINES * line_len {
+                    lines.push(ScreenLine::Squeezed(next..end));
+                    next = end;
                 }
             }
-            Err(_) => {
-                // Неверный формат адреса - игнорируем
-            }
+            offset = next;
         }
-
-        Ok(())
+        lines.truncate(rows);
+        lines
     }
 
-    fn find_pattern(&self, pattern: &[u8], start: usize) -> Option<usize> {
-        if pattern.is_empty() {
-            return None;
+    /// Конец серии строк, одинаковых со строкой `line`; неполная последняя строка
+    /// ни с чем не совпадает
+    fn repeated_lines_end(&self, line: usize) -> usize {
+        let line_len = self.line_len();
+        let data = self.document.data();
+        let first = data.range(line..line + line_len);
+        if first.len() < line_len {
+            return line + line_len;
         }
+        // Строки из одного значения: серия байт находится быстрее, чем сравнением строк
+        if first.iter().all(|&byte| byte == first[0]) {
+            let run = runs::run_at(data, line);
+            return line + (run.end - line) / line_len * line_len;
+        }
+        let mut end = line + line_len;
+        while data.range(end..end + line_len) == first {
+            end += line_len;
+        }
+        end
+    }
 
-        let data_len = self.data.len();
-        let pattern_len = pattern.len();
-
-        if pattern_len > data_len {
-            return None;
+    /// Первая строка серии строк, одинаковых со строкой `line`
+    fn repeated_lines_start(&self, line: usize) -> usize {
+        let line_len = self.line_len();
+        let data = self.document.data();
+        let first = data.range(line..line + line_len);
+        if first.len() < line_len {
+            return line;
+        }
+        if first.iter().all(|&byte| byte == first[0]) {
+            return runs::run_at(data, line).start.next_multiple_of(line_len);
         }
+        let mut start = line;
+        while start >= line_len && data.range(start - line_len..start) == first {
+            start -= line_len;
+        }
+        start
+    }
 
-        (start..=data_len.saturating_sub(pattern_len))
-            .find(|&i| &self.data[i..i + pattern_len] == pattern)
+    pub fn highlight_current_line(&self) -> bool {
+        self.config.display.highlight_current_line
     }
 
-    fn adjust_view(&mut self) {
-        let visible_lines = self.display.get_visible_lines();
-        let cursor_line = self.cursor_pos / self.bytes_per_line;
-        let view_line = self.view_offset / self.bytes_per_line;
+    pub fn show_scrollbar(&self) -> bool {
+        self.config.display.show_scrollbar
+    }
 
-        if cursor_line < view_line {
-            self.view_offset = cursor_line * self.bytes_per_line;
-        } else if cursor_line >= view_line + visible_lines {
-            self.view_offset = (cursor_line - visible_lines + 1) * self.bytes_per_line;
-        }
+    /// Следующий режим миникарты (Alt+Z)
+    pub fn cycle_minimap(&mut self) {
+        self.config.display.minimap = self.config.display.minimap.next();
     }
 
-    // Getters для display
-    pub fn get_data(&self) -> &[u8] {
-        &self.data
+    pub fn minimap(&self) -> Minimap {
+        self.config.display.minimap
     }
-    pub fn get_cursor_pos(&self) -> usize {
-        self.cursor_pos
+
+    pub fn toggle_insert_mode(&mut self) -> Result<()> {
+        self.insert_mode = !self.insert_mode;
+        self.half_byte = None;
+        if !self.insert_mode {
+            self.clamp_cursor();
+        }
+
+        // Режим запоминается в конфигурации между сессиями; в файле меняется только он
+        let insert_mode = self.insert_mode;
+        self.config.editor.insert_mode = insert_mode;
+        Config::update(|config| config.editor.insert_mode = insert_mode)?;
+        Ok(())
+    }
+
+    pub fn input_hex_char(&mut self, c: char) -> Result<()> {
+        if self.readonly || self.mode != EditMode::Hex {
+            return Ok(());
+        }
+
+        let value = c.to_digit(16).unwrap() as u8;
+
+        if let Some(high) = self.half_byte {
+            // Второй полубайт
+            self.half_byte = None;
+            self.write_typed_byte((high << 4) | value)?;
+        } else {
+            // Первый полубайт: о замке сообщается сразу, а не после второго
+            self.check_locks(self.typed_range(), 1)?;
+            self.half_byte = Some(value);
+        }
+
+        Ok(())
+    }
+
+    /// Набранный первый полубайт, ожидающий второго
+    pub fn pending_nibble(&self) -> Option<u8> {
+        self.half_byte
+    }
+
+    pub fn input_ascii_char(&mut self, c: char) -> Result<()> {
+        if self.readonly || self.mode != EditMode::Ascii {
+            return Ok(());
+        }
+
+        self.write_typed_byte(c as u8)
+    }
+
+    /// Ввод в битовом режиме: '0' и '1' записывают бит и переходят к следующему,
+    /// пробел инвертирует бит под курсором
+    pub fn input_bit(&mut self, c: char) -> Result<()> {
+        if self.readonly || self.mode != EditMode::Bits {
+            return Ok(());
+        }
+
+        let position = self.cursor_pos;
+        if position >= self.document.len() {
+            return Ok(());
+        }
+
+        let mask = 0x80 >> self.bit_index;
+        let old_value = self.document.data()[position];
+        let new_value = match c {
+            '0' => old_value & !mask,
+            '1' => old_value | mask,
+            _ => old_value ^ mask,
+        };
+
+        if new_value != old_value {
+            self.check_locks(position..position + 1, 1)?;
+            // Правки битов одного байта подряд отменяются одним шагом
+            self.document.set_byte(position, new_value, true);
+            self.modified = true;
+        }
+
+        if c != ' ' {
+            self.move_cursor_right();
+        }
+        Ok(())
+    }
+
+    /// Диапазон, который заменит набранный байт: пустой в режиме вставки
+    fn typed_range(&self) -> std::ops::Range<usize> {
+        let end = if self.insert_mode { self.cursor_pos } else { self.cursor_pos + 1 };
+        self.cursor_pos..end
+    }
+
+    /// Запись набранного байта: замена под курсором (OVR) или вставка со сдвигом (INS)
+    fn write_typed_byte(&mut self, new_value: u8) -> Result<()> {
+        let position = self.cursor_pos;
+        self.check_locks(self.typed_range(), 1)?;
+
+        if self.insert_mode {
+            if position > self.document.len() {
+                return Ok(());
+            }
+
+            self.document.insert(position, &[new_value]);
+
+            // В режиме вставки курсор может стоять сразу за последним байтом
+            self.cursor_pos = position + 1;
+        } else {
+            if position >= self.document.len() {
+                return Ok(());
+            }
+
+            self.document.set_byte(position, new_value, false);
+
+            if position + 1 < self.document.len() {
+                self.cursor_pos += 1;
+            }
+        }
+
+        self.modified = true;
+        self.adjust_view();
+        Ok(())
+    }
+
+    pub fn start_search(&mut self) -> Result<()> {
+        let scope = self.ask_search_scope()?;
+
+        let prompt = format!(
+            "Search{} ({}{}): ",
+            Self::scope_label(&scope),
+            self.search_mode.label(),
+            self.search_case_label()
+        );
+        let input = utils::get_user_input(&prompt)?;
+
+        if input.is_empty() {
+            return Ok(());
+        }
+
+        self.search_scope = scope;
+
+        let pattern = SearchPattern::parse(&input, self.search_mode, self.search_ignore_case)?;
+        self.last_search = Some(pattern);
+        self.remember_search_query(input);
+        self.search_next();
+
+        Ok(())
+    }
+
+    // Режим запоминается вместе со строкой: его можно сменить уже после поиска
+    fn remember_search_query(&mut self, input: String) {
+        self.last_search_query = Some(LastSearch {
+            input,
+            mode: self.search_mode,
+            ignore_case: self.search_ignore_case,
+        });
+    }
+
+    /// Следующее вхождение последнего шаблона (F3), с переходом через конец буфера
+    pub fn search_next(&mut self) {
+        self.repeat_search(SearchRequest::Next { cursor: self.cursor_pos });
+    }
+
+    /// Предыдущее вхождение последнего шаблона (Shift+F3), с переходом через начало
+    pub fn search_previous(&mut self) {
+        self.repeat_search(SearchRequest::Previous { cursor: self.cursor_pos });
+    }
+
+    /// Ближайший байт со значением байта под курсором за пределами серии, в которой он стоит:
+    /// так переходят от одной области заполнения (0xFF, нули) к следующей. Значение становится
+    /// последним поиском, F3 и Shift+F3 продолжают с ним
+    pub fn find_same_byte(&mut self, forward: bool) {
+        if self.cursor_pos >= self.document.len() {
+            return;
+        }
+        let value = self.document.data()[self.cursor_pos];
+        let run = runs::run_at(self.document.data(), self.cursor_pos);
+        let request = if forward {
+            SearchRequest::Next { cursor: run.end - 1 }
+        } else {
+            SearchRequest::Previous { cursor: run.start }
+        };
+        self.search_byte(value, request);
+    }
+
+    /// Следующее вхождение введенного значения байта от курсора
+    pub fn find_byte_value_from_input(&mut self) -> Result<()> {
+        let input = utils::get_user_input("Find byte value (hex): ")?;
+        let input = input.trim();
+        if input.is_empty() {
+            return Ok(());
+        }
+        let value = u8::from_str_radix(input.trim_start_matches("0x"), 16)
+            .map_err(|_| anyhow!("Invalid byte value '{}': expected hex 00-FF", input))?;
+        self.search_byte(value, SearchRequest::Next { cursor: self.cursor_pos });
+        Ok(())
+    }
+
+    fn search_byte(&mut self, value: u8, request: SearchRequest) {
+        let Ok(pattern) = SearchPattern::parse(&format!("{:02X}", value), SearchMode::Hex, false) else {
+            return;
+        };
+        self.search_scope = None;
+        self.last_search = Some(pattern.clone());
+        self.run_search(pattern, request);
+    }
+
+    fn repeat_search(&mut self, request: SearchRequest) {
+        let Some(pattern) = self.last_search.clone() else {
+            self.set_warning("No previous search");
+            return;
+        };
+        self.run_search(pattern, request);
+    }
+
+    /// Небольшие буферы ищутся сразу, большие - в фоне, чтобы не блокировать интерфейс
+    fn run_search(&mut self, pattern: SearchPattern, request: SearchRequest) {
+        if self.search_job.is_some() {
+            self.set_warning("Search already in progress");
+            return;
+        }
+
+        let within = self.search_scope.clone().unwrap_or(0..self.document.len());
+
+        if within.len() < BACKGROUND_SEARCH_THRESHOLD {
+            let outcome = search::execute(self.document.data(), &pattern, request, within, &SearchControl::default());
+            self.apply_search_outcome(outcome, pattern.len());
+        } else {
+            self.search_job = Some(SearchJob::spawn(self.document.snapshot(), pattern, request, within));
+        }
+    }
+
+    /// При активном выделении предлагает искать только в нем
+    fn ask_search_scope(&mut self) -> Result<Option<std::ops::Range<usize>>> {
+        let Some(range) = self.selection_range().filter(|range| !range.is_empty()) else {
+            return Ok(None);
+        };
+
+        let answer = utils::get_user_input("Search in selection? [Y/n]: ")?;
+        match answer.trim() {
+            "n" | "N" => Ok(None),
+            _ => Ok(Some(range)),
+        }
+    }
+
+    fn scope_label(scope: &Option<std::ops::Range<usize>>) -> &'static str {
+        if scope.is_some() { " in selection" } else { "" }
+    }
+
+    /// Проверка фонового поиска; вызывается из основного цикла. true, пока поиск идет
+    /// или только что закончился: его ход виден в строке состояния
+    pub fn poll_search(&mut self) -> bool {
+        let Some(job) = &self.search_job else {
+            return false;
+        };
+        let Some(outcome) = job.poll() else {
+            return true;
+        };
+
+        self.search_job = None;
+        let pattern_len = self.last_search.as_ref().map_or(1, SearchPattern::len);
+        self.apply_search_outcome(outcome, pattern_len);
+        true
+    }
+
+    /// Проверка, не изменила ли файл другая программа; вызывается из основного цикла
+    pub fn check_file_changed(&mut self) -> Result<()> {
+        if self.is_new_file || !self.file_watcher.changed(&self.file_path) {
+            return Ok(());
+        }
+
+        // Отвечаем один раз на каждое внешнее изменение
+        self.file_watcher.refresh(&self.file_path);
+
+        let prompt = if self.modified {
+            "File changed on disk: [R]eload (lose edits), [K]eep mine, [D]iff? "
+        } else {
+            "File changed on disk: [R]eload, [K]eep current view, [D]iff? "
+        };
+
+        match utils::prompt_choice(prompt, "rkd")? {
+            Some('r') => {
+                self.reload_from_disk()?;
+                self.set_message("Reloaded from disk");
+            }
+            Some('d') => self.show_disk_diff()?,
+            _ => self.set_message("Keeping the version in the editor"),
+        }
+        Ok(())
+    }
+
+    /// Заменяет буфер содержимым файла на диске; правки и история отмены теряются
+    pub fn reload_from_disk(&mut self) -> Result<()> {
+        // У процесса регион перечитывается из его памяти
+        if let Some(region) = self.process.as_ref().map(|view| view.region.clone()) {
+            return self.load_region(region);
+        }
+        let (buffer, hex_file) = Self::load_file(&self.file_path)?;
+        let data = PieceTable::new(buffer);
+        self.hex_file = hex_file;
+        self.file_watcher.refresh(&self.file_path);
+        self.replace_buffer(data);
+        Ok(())
+    }
+
+    /// Отбрасывает все правки и возвращает содержимое на момент открытия или сохранения
+    pub fn revert_to_saved(&mut self) -> Result<()> {
+        if !self.modified {
+            self.set_message("No changes to revert");
+            return Ok(());
+        }
+
+        if utils::prompt_choice("Revert to saved? All edits will be lost [y/N]: ", "yn")? != Some('y') {
+            return Ok(());
+        }
+
+        let data = self.document.data().reverted();
+        self.replace_buffer(data);
+        // Несохраненный новый файл по-прежнему нужно записать, как сразу после создания
+        self.modified = self.is_new_file && !self.document.is_empty();
+        self.set_message("Reverted to saved version");
+        Ok(())
+    }
+
+    fn replace_buffer(&mut self, data: PieceTable) {
+        self.cancel_search();
+        self.discard_recovery();
+        self.document.reset(data);
+        self.modified = false;
+        self.half_byte = None;
+        self.match_list = None;
+        if let Some(view) = &mut self.template {
+            view.reapply(self.document.data());
+        }
+        self.clear_selection();
+        self.clamp_cursor();
+        self.adjust_view();
+    }
+
+    /// Панели внизу окна открываются по одной: перед открытием закрываются остальные
+    fn close_panels(&mut self) {
+        self.match_list = None;
+        self.bookmark_panel = false;
+        self.stats_panel = None;
+        self.template_panel = false;
+        self.region_panel = false;
+    }
+
+    /// Места, где буфер отличается от файла на диске, в панели совпадений
+    fn show_disk_diff(&mut self) -> Result<()> {
+        const DIFF_LIMIT: usize = 10_000;
+
+        let (disk, _) = Self::load_file(&self.file_path)?;
+        let runs = self.document.data().diff_runs(&disk, DIFF_LIMIT);
+        if runs.is_empty() {
+            self.set_message("File on disk has the same contents");
+            return Ok(());
+        }
+
+        let count = runs.len();
+        let mut list = MatchList::new(runs, 1);
+        list.select_nearest(self.cursor_pos);
+        self.close_panels();
+        self.match_list = Some(list);
+        self.jump_to_selected_match();
+
+        let more = if count == DIFF_LIMIT { "+" } else { "" };
+        self.set_message(format!("{}{} differences from the file on disk", count, more));
+        Ok(())
+    }
+
+    pub fn cancel_search(&mut self) {
+        if let Some(job) = self.search_job.take() {
+            job.cancel();
+            self.set_message("Search cancelled");
+        }
+    }
+
+    pub fn is_searching(&self) -> bool {
+        self.search_job.is_some()
+    }
+
+    /// Когда основному циклу нужно проснуться без нажатия: показать ход поиска, убрать
+    /// сообщение или записать файл восстановления. None - ждать событий без предела
+    pub fn next_wakeup(&self) -> Option<std::time::Duration> {
+        let search = self.is_searching().then_some(SEARCH_POLL_INTERVAL);
+        let message = self.messages.expires_in(std::time::Instant::now());
+        let auto_save = (self.config.editor.auto_save && self.modified && self.can_recover()).then(|| {
+            std::time::Duration::from_secs(self.config.editor.auto_save_interval).saturating_sub(self.auto_saved_at.elapsed())
+        });
+        [search, message, auto_save].into_iter().flatten().min()
+    }
+
+    /// Когда снова проверить файл на диске (см. check_file_changed)
+    pub fn next_file_check(&self) -> Option<std::time::Duration> {
+        (!self.is_new_file).then(|| self.file_watcher.next_check())
+    }
+
+    pub fn search_progress(&self) -> Option<usize> {
+        self.search_job.as_ref().map(SearchJob::progress_percent)
+    }
+
+    fn apply_search_outcome(&mut self, outcome: SearchOutcome, pattern_len: usize) {
+        match outcome {
+            SearchOutcome::Found { pos, wrapped } => self.jump_to_match(Some((pos, wrapped))),
+            SearchOutcome::NotFound => self.jump_to_match(None),
+            SearchOutcome::All(offsets) if offsets.is_empty() => self.jump_to_match(None),
+            SearchOutcome::All(offsets) => {
+                let mut list = MatchList::new(offsets, pattern_len);
+                list.select_nearest(self.cursor_pos);
+                self.set_message(format!("{} matches", list.offsets.len()));
+                self.close_panels();
+                self.match_list = Some(list);
+                self.jump_to_selected_match();
+            }
+            SearchOutcome::Cancelled => self.set_message("Search cancelled"),
+        }
+    }
+
+    /// "Найти все": открывает панель со списком совпадений
+    pub fn find_all_from_input(&mut self) -> Result<()> {
+        let scope = self.ask_search_scope()?;
+
+        let prompt = format!(
+            "Find all{} ({}{}): ",
+            Self::scope_label(&scope),
+            self.search_mode.label(),
+            self.search_case_label()
+        );
+        let input = utils::get_user_input(&prompt)?;
+
+        if input.is_empty() {
+            return Ok(());
+        }
+
+        self.search_scope = scope;
+
+        let pattern = SearchPattern::parse(&input, self.search_mode, self.search_ignore_case)?;
+        self.last_search = Some(pattern.clone());
+        self.remember_search_query(input);
+        self.run_search(pattern, SearchRequest::All);
+        Ok(())
+    }
+
+    pub fn match_list_next(&mut self) {
+        if let Some(list) = &mut self.match_list {
+            list.select_next();
+        }
+        self.jump_to_selected_match();
+    }
+
+    pub fn match_list_previous(&mut self) {
+        if let Some(list) = &mut self.match_list {
+            list.select_previous();
+        }
+        self.jump_to_selected_match();
+    }
+
+    pub fn close_match_list(&mut self) {
+        self.match_list = None;
+        self.adjust_view();
+    }
+
+    pub fn get_match_list(&self) -> Option<&MatchList> {
+        self.match_list.as_ref()
+    }
+
+    fn jump_to_selected_match(&mut self) {
+        if let Some(offset) = self.match_list.as_ref().and_then(MatchList::current) {
+            self.jump_to(offset.min(self.document.len().saturating_sub(1)));
+        }
+    }
+
+    /// Открыта ли панель внизу экрана (совпадения или закладки)
+    pub fn is_panel_open(&self) -> bool {
+        self.match_list.is_some()
+            || self.bookmark_panel
+            || self.stats_panel.is_some()
+            || self.template_panel
+            || self.region_panel
+    }
+
+    /// Тип файла по сигнатуре в начале данных; пересчитывается при правке заголовка
+    pub fn file_type(&self) -> Option<String> {
+        magic::identify(&self.document.range(0..magic::HEAD))
+    }
+
+    /// Тип данных с начала выделения, без выделения - с позиции курсора
+    pub fn identify_at_cursor(&mut self) {
+        let start = match self.selection_range().filter(|range| !range.is_empty()) {
+            Some(range) => range.start,
+            None => self.cursor_pos,
+        };
+        match magic::identify(&self.document.range(start..start + magic::HEAD)) {
+            Some(name) => self.set_message(format!("0x{:X}: {}", start, name)),
+            None => self.set_warning(format!("0x{:X}: no known file signature", start)),
+        }
+    }
+
+    /// Начала вложенных файлов в панели совпадений, с типом каждого
+    pub fn find_file_signatures(&mut self) {
+        const SIGNATURE_LIMIT: usize = 10_000;
+
+        let found = magic::find_signatures(self.document.data(), SIGNATURE_LIMIT);
+        if found.is_empty() {
+            self.set_message("No embedded file signatures found");
+            return;
+        }
+
+        let count = found.len();
+        let (offsets, labels) = found.into_iter().unzip();
+        let mut list = MatchList::new(offsets, 1);
+        list.labels = labels;
+        list.select_nearest(self.cursor_pos);
+        self.close_panels();
+        self.match_list = Some(list);
+        self.jump_to_selected_match();
+
+        let more = if count == SIGNATURE_LIMIT { "+" } else { "" };
+        self.set_message(format!("{}{} file signatures", count, more));
+    }
+
+    /// Панель статистики байт выделения или всего файла; частоты считаются при открытии
+    pub fn toggle_stats_panel(&mut self) -> Result<()> {
+        if self.stats_panel.take().is_none() {
+            let range = match self.selection_range().filter(|range| !range.is_empty()) {
+                Some(range) => range,
+                None => 0..self.document.len(),
+            };
+            let mut progress = ProgressBar::new("Counting bytes");
+            let histogram = Histogram::of(self.document.data(), range.clone(), &mut progress)?;
+            self.close_panels();
+            self.stats_panel = Some(StatsPanel { range, histogram });
+        }
+        self.adjust_view();
+        Ok(())
+    }
+
+    pub fn stats_panel(&self) -> Option<&StatsPanel> {
+        self.stats_panel.as_ref()
+    }
+
+    pub fn close_stats_panel(&mut self) {
+        self.stats_panel = None;
+        self.adjust_view();
+    }
+
+    /// Шаблон по имени или пути размечает выделение или весь файл; открывает дерево полей
+    pub fn apply_template_from_input(&mut self) -> Result<()> {
+        let input = utils::get_user_input("Template (name in the templates directory or path): ")?;
+        if input.trim().is_empty() {
+            return Ok(());
+        }
+
+        let (name, source) = templates::load(input.trim())?;
+        self.apply_template(name, source);
+        Ok(())
+    }
+
+    fn apply_template(&mut self, name: String, source: Source) {
+        let range = match self.selection_range().filter(|range| !range.is_empty()) {
+            Some(range) => range,
+            None => 0..self.document.len(),
+        };
+        let view = TemplateView::new(name, source, self.document.data(), range);
+        self.report_template(&view);
+        self.template = Some(view);
+        self.open_template_panel();
+    }
+
+    fn report_template(&mut self, view: &TemplateView) {
+        let count = view.layout.fields.len();
+        match &view.layout.error {
+            Some(error) => self.set_warning(format!("{}: {} fields, stopped: {}", view.name, count, error)),
+            None => self.set_message(format!("{}: {} fields", view.name, count)),
+        }
+    }
+
+    /// Дерево полей наложенного шаблона. Без шаблона формат выбирается по сигнатуре
+    /// из встроенных, а для остальных файлов спрашивается шаблон
+    pub fn toggle_template_panel(&mut self) -> Result<()> {
+        if self.template_panel {
+            self.close_template_panel();
+        } else if self.template.is_some() {
+            self.open_template_panel();
+        } else {
+            let start = self.selection_range().map_or(0, |range| range.start);
+            match formats::Format::detect(&self.document.range(start..start + magic::HEAD)) {
+                Some(format) => self.apply_template(format.name().to_string(), Source::Builtin(format)),
+                None => self.apply_template_from_input()?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Поиск поля по имени в дереве шаблона: секция `.text`, блок `IEND`
+    pub fn find_template_field_from_input(&mut self) -> Result<()> {
+        let input = utils::get_user_input("Find field: ")?;
+        if input.trim().is_empty() {
+            return Ok(());
+        }
+        if let Some(view) = &mut self.template
+            && !view.find(input.trim())
+        {
+            bail!("No field matching '{}'", input.trim());
+        }
+        self.jump_to_template_field(false);
+        Ok(())
+    }
+
+    fn open_template_panel(&mut self) {
+        self.close_panels();
+        self.template_panel = true;
+        self.adjust_view();
+    }
+
+    pub fn close_template_panel(&mut self) {
+        self.template_panel = false;
+        self.adjust_view();
+    }
+
+    pub fn is_template_panel_open(&self) -> bool {
+        self.template_panel && self.template.is_some()
+    }
+
+    pub fn template_view(&self) -> Option<&TemplateView> {
+        self.template.as_ref()
+    }
+
+    /// Снимает шаблон вместе с подсветкой полей
+    pub fn remove_template(&mut self) {
+        if let Some(view) = self.template.take() {
+            self.set_message(format!("Template {} removed", view.name));
+        }
+        self.close_template_panel();
+    }
+
+    /// Размечает данные заново после правок
+    pub fn reapply_template(&mut self) {
+        if let Some(mut view) = self.template.take() {
+            view.reapply(self.document.data());
+            self.report_template(&view);
+            self.template = Some(view);
+        }
+    }
+
+    pub fn template_next(&mut self) {
+        if let Some(view) = &mut self.template {
+            view.select_next();
+        }
+        self.jump_to_template_field(false);
+    }
+
+    pub fn template_previous(&mut self) {
+        if let Some(view) = &mut self.template {
+            view.select_previous();
+        }
+        self.jump_to_template_field(false);
+    }
+
+    pub fn template_expand(&mut self) {
+        if let Some(view) = &mut self.template {
+            view.expand();
+        }
+    }
+
+    pub fn template_collapse(&mut self) {
+        if let Some(view) = &mut self.template {
+            view.collapse();
+        }
+        self.jump_to_template_field(false);
+    }
+
+    /// Курсор на начало выбранного поля; `select` выделяет поле целиком
+    pub fn jump_to_template_field(&mut self, select: bool) {
+        let Some(range) = self
+            .template
+            .as_ref()
+            .and_then(|view| view.field(view.selected))
+            .map(|field| field.range.clone())
+        else {
+            return;
+        };
+        if select && !range.is_empty() {
+            self.select_range(range);
+        } else {
+            self.jump_to(range.start.min(self.document.len().saturating_sub(1)));
+        }
+    }
+
+    /// Полное имя, тип и значение поля шаблона под байтом
+    pub fn template_field_at(&self, pos: usize) -> Option<String> {
+        let view = self.template.as_ref()?;
+        let leaf = view.leaf_at(pos)?;
+        let field = view.field(leaf)?;
+        Some(format!(
+            "{} {} = {}",
+            field.type_name,
+            view.path(leaf),
+            field.value.as_deref().unwrap_or("")
+        ))
+    }
+
+    pub fn template_color_at(&self, pos: usize) -> Option<crossterm::style::Color> {
+        self.template.as_ref()?.color_at(pos)
+    }
+
+    /// Высота панелей внизу экрана: список совпадений или закладок, дизассемблер и инспектор
+    pub fn panels_height(&self) -> usize {
+        let list = if self.is_panel_open() { display::MATCH_PANEL_HEIGHT } else { 0 };
+        list + self.docked_height()
+    }
+
+    /// Высота панелей, которые открываются вместе со списками: дизассемблер и инспектор
+    pub fn docked_height(&self) -> usize {
+        self.disasm_height() + self.inspector_height()
+    }
+
+    pub fn disasm_height(&self) -> usize {
+        if self.disasm.is_some() { display::DISASM_PANEL_HEIGHT } else { 0 }
+    }
+
+    /// Панель дизассемблера с инструкциями от курсора (Alt+Shift+D); архитектура
+    /// по типу файла, иначе x86-64
+    pub fn toggle_disasm(&mut self) -> Result<()> {
+        if self.disasm.take().is_none() {
+            self.disasm = Some(Disassembler::new(self.default_arch())?);
+        }
+        self.adjust_view();
+        Ok(())
+    }
+
+    pub fn close_disasm(&mut self) {
+        self.disasm = None;
+        self.adjust_view();
+    }
+
+    /// Выбор архитектуры дизассемблера по имени; открывает панель, если она закрыта
+    pub fn choose_disasm_arch(&mut self) -> Result<()> {
+        let current = self.disasm.as_ref().map_or(Arch::X86_64, Disassembler::arch);
+        let prompt = format!(
+            "Architecture ({}) [{}]: ",
+            Arch::ALL.map(Arch::name).join(", "),
+            current.name()
+        );
+        let arch = match utils::get_user_input(&prompt)?.trim() {
+            "" => return Ok(()),
+            name => Arch::parse(name)?,
+        };
+        self.disasm = Some(Disassembler::new(arch)?);
+        self.adjust_view();
+        self.set_message(format!("Disassembling as {}", arch.name()));
+        Ok(())
+    }
+
+    pub fn disasm_arch(&self) -> Option<Arch> {
+        self.disasm.as_ref().map(Disassembler::arch)
+    }
+
+    /// Архитектура открытой панели дизассемблера, иначе по типу файла, иначе x86-64
+    fn default_arch(&self) -> Arch {
+        self.disasm_arch()
+            .or_else(|| self.file_type().as_deref().and_then(Arch::detect))
+            .unwrap_or(Arch::X86_64)
+    }
+
+    /// Ассемблирует введенные инструкции и записывает их поверх инструкции под курсором
+    /// (или с начала выделения) одним шагом отмены; предупреждает, если код не поместился
+    pub fn assemble_at_cursor(&mut self) -> Result<()> {
+        let arch = self.default_arch();
+        let selection = self.selection_range().filter(|range| !range.is_empty());
+        let start = selection.as_ref().map_or(self.cursor_pos, |range| range.start);
+
+        let prompt = format!("Assemble at 0x{:X} ({}, `;` between instructions): ", start, arch.name());
+        let source = utils::get_user_input(&prompt)?;
+        if source.trim().is_empty() {
+            return Ok(());
+        }
+        let bytes = asm::assemble(arch, &source, start as u64)?;
+
+        // Заменяемое место: выделение или инструкция, которая сейчас лежит под курсором
+        let replaced = match selection {
+            Some(range) => range.len(),
+            None => {
+                let disasm = Disassembler::new(arch)?;
+                let old = self.document.range(start..start + 16);
+                disasm.disassemble(&old, start as u64, 1).first().map_or(0, |insn| insn.bytes.len())
+            }
+        };
+
+        self.replace_bytes(start, &bytes)?;
+        let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+        if bytes.len() > replaced {
+            self.set_warning(format!(
+                "Assembled {} bytes at 0x{:X}, {} more than the {} replaced: {}",
+                bytes.len(),
+                start,
+                bytes.len() - replaced,
+                replaced,
+                hex.join(" ")
+            ));
+        } else {
+            self.set_message(format!("Assembled {} bytes at 0x{:X}: {}", bytes.len(), start, hex.join(" ")));
+        }
+        Ok(())
+    }
+
+    /// До `limit` инструкций с позиции курсора; адреса - смещения в файле
+    pub fn disassemble_at_cursor(&self, limit: usize) -> Vec<Instruction> {
+        let Some(disasm) = &self.disasm else {
+            return Vec::new();
+        };
+        // Самая длинная инструкция x86 - 15 байт
+        let bytes = self.document.range(self.cursor_pos..self.cursor_pos + limit * 16);
+        disasm.disassemble(&bytes, self.cursor_pos as u64, limit)
+    }
+
+    pub fn inspector_height(&self) -> usize {
+        if self.inspector {
+            display::INSPECTOR_PANEL_HEIGHT + plugin::inspector_rows(&self.config.plugins)
+        } else {
+            0
+        }
+    }
+
+    /// Панель с байтами под курсором в виде чисел разных типов (Alt+K)
+    pub fn toggle_inspector(&mut self) {
+        self.inspector = !self.inspector;
+        self.adjust_view();
+    }
+
+    pub fn is_inspector_open(&self) -> bool {
+        self.inspector
+    }
+
+    pub fn inspector_cell(&self) -> (usize, usize) {
+        self.inspector_cell
+    }
+
+    /// Перемещение по ячейкам инспектора (Ctrl+стрелки), с переходом через край
+    pub fn move_inspector_cell(&mut self, rows: isize, columns: isize) {
+        let (row, column) = self.inspector_cell;
+        let row_count = display::INSPECTOR_ROWS.len() as isize;
+        let column_count = display::INSPECTOR_COLUMNS as isize;
+
+        self.inspector_cell = (
+            (row as isize + rows).rem_euclid(row_count) as usize,
+            (column as isize + columns).rem_euclid(column_count) as usize,
+        );
+    }
+
+    /// Тип и порядок байт выбранной ячейки инспектора
+    pub fn inspector_value_type(&self) -> (NumericType, Endian) {
+        let (row, column) = self.inspector_cell;
+        let (left, right) = display::INSPECTOR_ROWS[row];
+        let ty = if column < 2 { left } else { right };
+        let endian = if column % 2 == 0 { Endian::Little } else { Endian::Big };
+        (ty, endian)
+    }
+
+    /// Запись числа в выбранную ячейку инспектора: байты значения заменяют байты под курсором
+    pub fn edit_inspector_value(&mut self) -> Result<()> {
+        let (ty, endian) = self.inspector_value_type();
+        let label = format!("{:?} {}", ty, if endian == Endian::Little { "LE" } else { "BE" }).to_lowercase();
+
+        let available = self.document.len().saturating_sub(self.cursor_pos);
+        if available < ty.size() {
+            bail!("{} needs {} bytes, only {} left before the end of the file", label, ty.size(), available);
+        }
+
+        let prompt = format!("{} at 0x{:08X}: ", label, self.cursor_pos);
+        let input = utils::get_user_input(&prompt)?;
+        if input.trim().is_empty() {
+            return Ok(());
+        }
+
+        let bytes = ty.encode(&input, endian)?;
+        self.replace_bytes(self.cursor_pos, &bytes)?;
+        self.set_message(format!("Wrote {} {} at 0x{:08X}", label, input.trim(), self.cursor_pos));
+
+        Ok(())
+    }
+
+    /// Пересчет поля контрольной суммы: участок - выделение или ввод, поле по умолчанию
+    /// сразу за участком. Рецепт можно запомнить для повторного пересчета после правок
+    pub fn fix_checksum_from_input(&mut self) -> Result<()> {
+        let range = match self.selection_range().filter(|range| !range.is_empty()) {
+            Some(range) => range,
+            None => {
+                let input = utils::get_user_input("Checksum range (START..END): ")?;
+                if input.trim().is_empty() {
+                    return Ok(());
+                }
+                utils::parse_range(input.trim())?
+            }
+        };
+
+        let names = Algorithm::ALL.map(Algorithm::name).join("/");
+        let prompt = format!("Checksum of 0x{:X}..0x{:X} ({}) [crc32]: ", range.start, range.end, names);
+        let algorithm = match utils::get_user_input(&prompt)?.trim() {
+            "" => Algorithm::Crc32,
+            name => Algorithm::parse(name)?,
+        };
+
+        let prompt = format!("Write {} at offset [0x{:X}]: ", algorithm.name(), range.end);
+        let target = match utils::get_user_input(&prompt)?.trim() {
+            "" => range.end,
+            offset => utils::parse_size(offset)?,
+        };
+
+        let endian = if algorithm.size() == 1 {
+            Endian::Little
+        } else {
+            match utils::prompt_choice("Byte order: [l]ittle or [b]ig endian: ", "lb")? {
+                Some('l') => Endian::Little,
+                Some(_) => Endian::Big,
+                None => return Ok(()),
+            }
+        };
+
+        let recipe = Recipe::new(algorithm, range, target, endian)?;
+        let (value, bytes) = recipe.compute(self.document.data(), &mut ProgressBar::new("Computing checksum"))?;
+        self.replace_bytes(target, &bytes)?;
+        self.set_message(format!("Wrote {} 0x{:X} at 0x{:08X}", algorithm.name(), value, target));
+
+        if utils::prompt_choice("Remember this checksum for the file? [y/N]: ", "yn")? == Some('y') {
+            self.checksums.retain(|known| known.target != recipe.target);
+            self.checksums.push(recipe);
+            checksums::save(&self.file_path, &self.checksums)?;
+            self.set_message(format!(
+                "Wrote {} 0x{:X} at 0x{:08X}, remembered ({} for this file)",
+                algorithm.name(),
+                value,
+                target,
+                self.checksums.len()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Пересчитывает запомненные суммы по порядку одним шагом отмены: сумма, которая
+    /// покрывает поле другой, видит уже новое значение
+    pub fn refix_checksums(&mut self) -> Result<()> {
+        if self.checksums.is_empty() {
+            bail!("No checksums remembered for this file");
+        }
+
+        let recipes = self.checksums.clone();
+        let changed = self.undo_group(|editor| {
+            let mut changed = 0;
+            for recipe in &recipes {
+                let mut progress = ProgressBar::new(format!("Computing {}", recipe.algorithm.name()));
+                let (_, bytes) = recipe.compute(editor.document.data(), &mut progress)?;
+                if editor.document.range(recipe.target..recipe.target + bytes.len()) != bytes.as_slice() {
+                    editor.replace_bytes(recipe.target, &bytes)?;
+                    changed += 1;
+                }
+            }
+            Ok(changed)
+        })?;
+        self.set_message(format!("Checksums: {} of {} updated", changed, recipes.len()));
+        Ok(())
+    }
+
+    fn visible_lines(&self) -> usize {
+        let lines = self.viewport.lines.saturating_sub(self.panels_height());
+
+        match &self.split {
+            Some(split) => {
+                let (top, bottom) = display::split_lines(lines);
+                if split.focus == PaneSlot::Top { top } else { bottom }
+            }
+            None => lines,
+        }
+    }
+
+    /// Разделение окна по горизонтали (Alt+P): обе половины начинают с текущей позиции
+    pub fn toggle_split(&mut self) {
+        if self.split.take().is_none() {
+            self.split = Some(Split {
+                other: Pane {
+                    cursor_pos: self.cursor_pos,
+                    view_offset: self.view_offset,
+                },
+                focus: PaneSlot::Top,
+            });
+        }
+        self.adjust_view();
+    }
+
+    /// Переключение между половинами окна (F6)
+    pub fn switch_pane(&mut self) {
+        let Some(split) = &mut self.split else {
+            return;
+        };
+
+        let current = Pane {
+            cursor_pos: self.cursor_pos,
+            view_offset: self.view_offset,
+        };
+        let other = std::mem::replace(&mut split.other, current);
+        split.focus = match split.focus {
+            PaneSlot::Top => PaneSlot::Bottom,
+            PaneSlot::Bottom => PaneSlot::Top,
+        };
+
+        self.cursor_pos = other.cursor_pos;
+        self.view_offset = other.view_offset;
+        self.half_byte = None;
+        // Выделение относится к половине, в которой его начали
+        self.clear_selection();
+        // Пока половина была неактивна, данные могли укоротиться
+        self.clamp_cursor();
+        self.adjust_view();
+    }
+
+    /// Половины окна сверху вниз, с признаком активной; `None` без разделения
+    pub fn split_panes(&self) -> Option<[(Pane, bool); 2]> {
+        let split = self.split.as_ref()?;
+        let current = Pane {
+            cursor_pos: self.cursor_pos,
+            view_offset: self.view_offset,
+        };
+
+        Some(match split.focus {
+            PaneSlot::Top => [(current, true), (split.other, false)],
+            PaneSlot::Bottom => [(split.other, false), (current, true)],
+        })
+    }
+
+    fn jump_to_match(&mut self, found: Option<(usize, bool)>) {
+        match found {
+            Some((pos, wrapped)) => {
+                self.jump_to(pos);
+                if wrapped {
+                    self.set_message("Search wrapped");
+                }
+            }
+            None => self.set_warning("Pattern not found"),
+        }
+    }
+
+    pub fn cycle_search_mode(&mut self) {
+        self.search_mode = self.search_mode.next();
+        self.set_message(format!("Search mode: {}{}", self.search_mode.label(), self.search_case_label()));
+    }
+
+    pub fn toggle_search_ignore_case(&mut self) {
+        self.search_ignore_case = !self.search_ignore_case;
+        let state = if self.search_ignore_case { "ignored" } else { "matched" };
+        self.set_message(format!("Search: case {} in text modes", state));
+    }
+
+    fn search_case_label(&self) -> &'static str {
+        if self.search_ignore_case && self.search_mode.is_text() {
+            ", ignore case"
+        } else {
+            ""
+        }
+    }
+
+    pub fn goto_address(&mut self) -> Result<()> {
+        let input = utils::get_user_input("Go to offset (0x hex, decimal, +/- relative, end-N): ")?;
+
+        if input.trim().is_empty() {
+            return Ok(());
+        }
+
+        let address = utils::eval_offset_expression(&input, self.cursor_pos, self.document.len())?;
+
+        if address < self.document.len() {
+            self.jump_to(address);
+        } else {
+            // Адрес за пределами файла - переходим к концу
+            self.jump_to(self.document.len().saturating_sub(1));
+            self.set_message(format!("Offset 0x{:X} is past the end, moved to the last byte", address));
+        }
+
+        Ok(())
+    }
+
+    /// Переход к началу строки `line` (с нуля), не дальше последнего байта
+    pub fn goto_line(&mut self, line: usize) {
+        let pos = line.saturating_mul(self.line_len());
+        self.jump_to(pos.min(self.document.len().saturating_sub(1)));
+    }
+
+    /// Переход курсора с записью в историю переходов
+    pub fn jump_to(&mut self, pos: usize) {
+        if pos != self.cursor_pos {
+            self.jump_history.record(self.cursor_pos);
+        }
+        self.cursor_pos = pos;
+        self.adjust_view();
+    }
+
+    /// Начальная позиция из командной строки (`--offset`): курсор и верх экрана на смещении.
+    /// С `--length` окно выделяется, и правки по выделению остаются внутри него
+    pub fn open_at(&mut self, offset: usize, length: Option<usize>) -> Result<()> {
+        let len = self.document.len();
+        if offset > len || (offset == len && len > 0) {
+            bail!("Offset 0x{:X} is past the end of the file ({} bytes)", offset, len);
+        }
+
+        if let Some(length) = length.filter(|&length| length > 0) {
+            let end = offset.saturating_add(length).min(len);
+            self.select_range(offset..end);
+            if end - offset < length {
+                self.set_warning(format!("Window cut to {} bytes at the end of the file", end - offset));
+            }
+        }
+        self.cursor_pos = offset;
+        self.view_offset = offset;
+        self.realign_view();
+        Ok(())
+    }
+
+    pub fn jump_back(&mut self) {
+        match self.jump_history.back(self.cursor_pos) {
+            Some(pos) => self.move_to_history_entry(pos),
+            None => self.set_warning("Already at the oldest jump"),
+        }
+    }
+
+    pub fn jump_forward(&mut self) {
+        match self.jump_history.forward(self.cursor_pos) {
+            Some(pos) => self.move_to_history_entry(pos),
+            None => self.set_warning("Already at the newest jump"),
+        }
+    }
+
+    fn move_to_history_entry(&mut self, pos: usize) {
+        self.cursor_pos = pos.min(self.document.len().saturating_sub(1));
+        self.adjust_view();
+    }
+
+    /// Панель регионов памяти процесса; карта регионов перечитывается при каждом открытии
+    pub fn toggle_region_panel(&mut self) -> Result<()> {
+        if self.region_panel {
+            self.close_region_panel();
+            return Ok(());
+        }
+        let Some(view) = &mut self.process else {
+            bail!("Not attached to a process (hexr --pid PID)");
+        };
+
+        view.regions = process::regions(view.pid)?;
+        view.selected = view.regions.iter().position(|region| region.start == view.region.start).unwrap_or(0);
+        self.close_panels();
+        self.region_panel = true;
+        self.adjust_view();
+        Ok(())
+    }
+
+    pub fn close_region_panel(&mut self) {
+        self.region_panel = false;
+        self.adjust_view();
+    }
+
+    pub fn is_region_panel_open(&self) -> bool {
+        self.region_panel
+    }
+
+    /// Регионы процесса, выбранная строка и начало открытого региона
+    pub fn process_regions(&self) -> Option<(&[Region], usize, u64)> {
+        let view = self.process.as_ref()?;
+        Some((&view.regions, view.selected, view.region.start))
+    }
+
+    pub fn region_next(&mut self) {
+        if let Some(view) = &mut self.process
+            && view.selected + 1 < view.regions.len()
+        {
+            view.selected += 1;
+        }
+    }
+
+    pub fn region_previous(&mut self) {
+        if let Some(view) = &mut self.process {
+            view.selected = view.selected.saturating_sub(1);
+        }
+    }
+
+    /// Открывает выбранный в панели регион вместо текущего; правки текущего нужно сохранить
+    pub fn open_selected_region(&mut self) -> Result<()> {
+        let Some(region) = self.process.as_ref().and_then(|view| view.regions.get(view.selected).cloned()) else {
+            return Ok(());
+        };
+        if self.modified {
+            bail!("Save or revert the changes to this region first");
+        }
+        if !region.is_readable() {
+            bail!("Region {} is not readable", region.name());
+        }
+
+        self.load_region(region)?;
+        self.close_region_panel();
+        Ok(())
+    }
+
+    /// Читает регион из памяти процесса в новый буфер; история правок начинается заново
+    fn load_region(&mut self, region: Region) -> Result<()> {
+        let Some(view) = &mut self.process else {
+            return Ok(());
+        };
+        let (data, unreadable) = process::read_region(view.pid, &region)?;
+        self.file_path = format!("pid {}: {}", view.pid, region.name());
+        view.region = region;
+
+        self.replace_buffer(PieceTable::new(Buffer::from(data)));
+        self.cursor_pos = 0;
+        self.view_offset = 0;
+        if unreadable > 0 {
+            self.set_warning(format!("{} bytes of the region could not be read and show as zeros", unreadable));
+        }
+        Ok(())
+    }
+
+    /// Закладка на позиции курсора с необязательной подписью
+    pub fn add_bookmark_from_input(&mut self) -> Result<()> {
+        let offset = self.cursor_pos;
+        let prompt = format!("Bookmark 0x{:08X} label (optional): ", offset);
+        let label = utils::get_user_input(&prompt)?.trim().to_string();
+
+        self.bookmarks.set(offset, label);
+        self.bookmarks.save(&self.file_path)?;
+        self.set_message(format!("Bookmark {} set at 0x{:08X}", self.bookmarks.selected + 1, offset));
+        Ok(())
+    }
+
+    pub fn toggle_bookmark_panel(&mut self) {
+        if self.bookmark_panel {
+            self.close_bookmark_panel();
+        } else if self.bookmarks.items.is_empty() {
+            self.set_warning("No bookmarks (Ctrl+B: add)");
+        } else {
+            self.close_panels();
+            self.bookmark_panel = true;
+            self.adjust_view();
+        }
+    }
+
+    pub fn close_bookmark_panel(&mut self) {
+        self.bookmark_panel = false;
+        self.adjust_view();
+    }
+
+    pub fn is_bookmark_panel_open(&self) -> bool {
+        self.bookmark_panel
+    }
+
+    pub fn get_bookmarks(&self) -> &Bookmarks {
+        &self.bookmarks
+    }
+
+    pub fn bookmark_next(&mut self) {
+        self.bookmarks.select_next();
+    }
+
+    pub fn bookmark_previous(&mut self) {
+        self.bookmarks.select_previous();
+    }
+
+    pub fn jump_to_selected_bookmark(&mut self) {
+        if let Some(offset) = self.bookmarks.current().map(|b| b.offset) {
+            self.jump_to(offset.min(self.document.len().saturating_sub(1)));
+        }
+    }
+
+    /// Переход к закладке по номеру (с единицы, как в панели)
+    pub fn jump_to_bookmark(&mut self, number: usize) {
+        if number == 0 || number > self.bookmarks.items.len() {
+            self.set_message(format!("No bookmark {}", number));
+            return;
+        }
+
+        self.bookmarks.selected = number - 1;
+        self.jump_to_selected_bookmark();
+    }
+
+    pub fn remove_selected_bookmark(&mut self) -> Result<()> {
+        if self.bookmarks.remove_selected().is_none() {
+            return Ok(());
+        }
+
+        if self.bookmarks.items.is_empty() {
+            self.close_bookmark_panel();
+        }
+        self.bookmarks.save(&self.file_path)?;
+        self.set_message("Bookmark removed");
+        Ok(())
+    }
+
+    /// Заметка к выделению (или к байту под курсором) с цветом подсветки
+    pub fn annotate_from_input(&mut self) -> Result<()> {
+        let range = match self.selection_range() {
+            Some(range) => range,
+            None if self.cursor_pos < self.document.len() => self.cursor_pos..self.cursor_pos + 1,
+            None => bail!("Nothing to annotate"),
+        };
+
+        let note = utils::get_user_input(&format!("Note for {} bytes at 0x{:08X}: ", range.len(), range.start))?;
+        if note.trim().is_empty() {
+            return Ok(());
+        }
+
+        let prompt = format!("Color [{}]: ", annotations::DEFAULT_ANNOTATION_COLOR);
+        let color = match utils::get_user_input(&prompt)?.trim() {
+            "" => annotations::DEFAULT_ANNOTATION_COLOR.to_string(),
+            color => color.to_lowercase(),
+        };
+
+        let len = range.len();
+        self.annotations.add(range, note.trim().to_string(), color)?;
+        self.annotations.save(&self.file_path)?;
+        self.set_message(format!("Annotated {} bytes", len));
+        Ok(())
+    }
+
+    pub fn remove_annotation_at_cursor(&mut self) -> Result<()> {
+        if self.annotations.remove_at(self.cursor_pos).is_none() {
+            bail!("No annotation at cursor");
+        }
+
+        self.annotations.save(&self.file_path)?;
+        self.set_message("Annotation removed");
+        Ok(())
+    }
+
+    pub fn annotation_at(&self, pos: usize) -> Option<&Annotation> {
+        self.annotations.at(pos)
+    }
+
+    /// Замок на выделение (или байт под курсором); на замке под курсором без выделения - снятие
+    pub fn toggle_lock(&mut self) -> Result<()> {
+        let selection = self.selection_range().filter(|range| !range.is_empty());
+        if selection.is_none()
+            && let Some(lock) = self.locks.remove_at(self.cursor_pos)
+        {
+            self.locks.save(&self.file_path)?;
+            self.set_message(format!("Unlocked 0x{:08X}-0x{:08X}", lock.start, lock.end));
+            return Ok(());
+        }
+
+        let range = match selection {
+            Some(range) => range,
+            None if self.cursor_pos < self.document.len() => self.cursor_pos..self.cursor_pos + 1,
+            None => bail!("Nothing to lock"),
+        };
+        let len = range.len();
+        self.locks.add(range)?;
+        self.locks.save(&self.file_path)?;
+        self.set_message(format!("Locked {} bytes", len));
+        Ok(())
+    }
+
+    /// Новое правило подсветки из запроса; сохраняется в конфигурации. `clear` удаляет все
+    pub fn add_highlight_from_input(&mut self) -> Result<()> {
+        let input = utils::get_user_input("Highlight (hex pattern, run:BYTE[:MIN_LEN] or clear): ")?;
+        let input = input.trim();
+        if input.is_empty() {
+            return Ok(());
+        }
+
+        if input.eq_ignore_ascii_case("clear") {
+            let mut count = 0;
+            Config::update(|config| count = std::mem::take(&mut config.highlight).len())?;
+            self.config.highlight.clear();
+            self.highlights = Highlights::default();
+            self.set_message(format!("{} highlight rules removed", count));
+            return Ok(());
+        }
+
+        let color = match utils::get_user_input("Color [red]: ")?.trim() {
+            "" => "red".to_string(),
+            color => color.to_lowercase(),
+        };
+        let rule = HighlightRule::parse(input, &color)?;
+        // Правило проверяется до записи; правила из файла включают добавленные в других вкладках
+        Highlights::load(std::slice::from_ref(&rule))?;
+        let rules = Config::update(|config| config.highlight.push(rule))?.highlight;
+        self.highlights = Highlights::load(&rules)?;
+        self.config.highlight = rules;
+        self.set_message(format!("Highlighting {} in {}", input, color));
+        Ok(())
+    }
+
+    /// Участки окна, подсвеченные правилами [[highlight]]
+    pub fn highlight_spans(&self, window: std::ops::Range<usize>) -> Vec<(std::ops::Range<usize>, crossterm::style::Color)> {
+        self.highlights.spans(self.document.data(), window)
+    }
+
+    pub fn lock_at(&self, pos: usize) -> Option<&Lock> {
+        self.locks.at(pos)
+    }
+
+    /// Отклоняет замену `range` на `new_len` байт, если она тронет или сдвинет замок
+    fn check_locks(&self, range: std::ops::Range<usize>, new_len: usize) -> Result<()> {
+        match self.locks.conflict(range.clone(), new_len) {
+            Some(lock) if lock.start < range.end && range.start < lock.end => bail!(
+                "0x{:08X}-0x{:08X} is locked (Alt+Shift+K on it: unlock)",
+                lock.start,
+                lock.end
+            ),
+            Some(lock) => bail!(
+                "The edit would shift locked bytes 0x{:08X}-0x{:08X} (Alt+Shift+K on them: unlock)",
+                lock.start,
+                lock.end
+            ),
+            None => Ok(()),
+        }
+    }
+
+    /// Глубина истории переходов: (назад, вперед)
+    pub fn jump_depth(&self) -> (usize, usize) {
+        (self.jump_history.back_depth(), self.jump_history.forward_depth())
+    }
+
+    fn find_pattern(&self, pattern: &[u8], start: usize) -> Option<usize> {
+        search::find_from(self.document.data(), &SearchPattern::exact(pattern.to_vec()), start)
+    }
+
+    /// Все вхождения шаблона (включая перекрывающиеся) по возрастанию смещения
+    pub fn find_all(&self, pattern: &[u8]) -> Vec<usize> {
+        search::find_all_in(self.document.data(), &SearchPattern::exact(pattern.to_vec()))
+    }
+
+    pub fn replace_from_input(&mut self) -> Result<()> {
+        if self.readonly {
+            bail!("Cannot replace in read-only mode");
+        }
+
+        let input = utils::get_user_input("Find (hex or \"text\"): ")?;
+        if input.trim().is_empty() {
+            return Ok(());
+        }
+        let pattern = utils::parse_byte_pattern(&input)?;
+        if pattern.is_empty() {
+            bail!("Search pattern is empty");
+        }
+
+        // Пустая замена допустима и означает удаление найденного
+        let input = utils::get_user_input("Replace with (hex or \"text\"): ")?;
+        let replacement = utils::parse_byte_pattern(&input)?;
+
+        let choice = utils::get_user_input("Replace [n]ext or [a]ll: ")?;
+        match choice.trim() {
+            "a" | "A" => {
+                let count = self.replace_all(&pattern, &replacement)?;
+                self.set_message(format!("Replaced {} occurrences", count));
+            }
+            "n" | "N" | "" => {
+                if self.replace_next(&pattern, &replacement)? {
+                    self.set_message("Replaced 1 occurrence");
+                } else {
+                    self.set_warning("Pattern not found");
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Замена ближайшего вхождения начиная с курсора; курсор встает за заменой
+    pub fn replace_next(&mut self, pattern: &[u8], replacement: &[u8]) -> Result<bool> {
+        let Some(pos) = self.find_pattern(pattern, self.cursor_pos) else {
+            return Ok(false);
+        };
+
+        self.replace_range(pos..pos + pattern.len(), replacement)?;
+        self.cursor_pos = pos + replacement.len();
+        self.clamp_cursor();
+        self.adjust_view();
+        Ok(true)
+    }
+
+    /// Замена всех непересекающихся вхождений; весь охваченный участок
+    /// записывается одной операцией ReplaceBytes, поэтому отменяется за один шаг
+    pub fn replace_all(&mut self, pattern: &[u8], replacement: &[u8]) -> Result<usize> {
+        let mut matches = Vec::new();
+        let mut next_allowed = 0;
+        for pos in self.find_all(pattern) {
+            if pos >= next_allowed {
+                matches.push(pos);
+                next_allowed = pos + pattern.len();
+            }
+        }
+
+        let (Some(&first), Some(&last)) = (matches.first(), matches.last()) else {
+            return Ok(0);
+        };
+        let span_end = last + pattern.len();
+
+        let mut new_span = Vec::with_capacity(span_end - first);
+        let mut copied_up_to = first;
+        for &pos in &matches {
+            new_span.extend_from_slice(&self.document.range(copied_up_to..pos));
+            new_span.extend_from_slice(replacement);
+            copied_up_to = pos + pattern.len();
+        }
+
+        self.replace_range(first..span_end, &new_span)?;
+        self.clamp_cursor();
+        self.adjust_view();
+        Ok(matches.len())
+    }
+
+    fn adjust_view(&mut self) {
+        let visible_lines = self.visible_lines();
+        if visible_lines == 0 {
+            return;
+        }
+
+        let line_len = self.line_len();
+        let cursor_line = self.cursor_pos / line_len;
+        let view_line = self.view_offset / line_len;
+        // Курсор в режиме вставки может стоять за концом данных
+        let last_line = self.document.len().saturating_sub(1).max(self.cursor_pos) / line_len;
+
+        // Отступ не может превышать половину видимой области, иначе курсор "зажат" с двух сторон
+        let margin = self
+            .config
+            .display
+            .scroll_margin
+            .min(visible_lines.saturating_sub(1) / 2);
+
+        // Со сворачиванием на экран помещается больше строк, чем visible_lines
+        let squeezed_into_view = self.squeeze()
+            && self
+                .screen_lines(self.view_offset, visible_lines - margin, self.cursor_pos)
+                .contains(&ScreenLine::Data(cursor_line * line_len));
+
+        if cursor_line < view_line + margin {
+            let new_view_line = cursor_line.saturating_sub(margin);
+            self.view_offset = new_view_line * line_len;
+        } else if cursor_line + margin >= view_line + visible_lines && !squeezed_into_view {
+            // Не прокручиваем дальше последней строки файла
+            let max_view_line = (last_line + 1).saturating_sub(visible_lines);
+            let new_view_line = (cursor_line + margin + 1 - visible_lines).min(max_view_line);
+            self.view_offset = new_view_line.max(view_line) * line_len;
+        }
+    }
+
+    // Getters для display
+    /// Измененные с открытия или сохранения участки `range`
+    pub fn modified_ranges(&self, range: std::ops::Range<usize>) -> Vec<std::ops::Range<usize>> {
+        self.document.data().modified_ranges(range)
+    }
+
+    /// Сколько байт изменено с открытия или сохранения
+    pub fn modified_count(&self) -> usize {
+        self.document.data().modified_ranges(0..self.document.len()).iter().map(|range| range.len()).sum()
+    }
+
+    /// Переход к началу следующего (F7) или предыдущего (Shift+F7) участка изменений
+    pub fn jump_to_modified(&mut self, forward: bool) {
+        let cursor = self.cursor_pos;
+        let target = if forward {
+            // Участок под курсором пропускается
+            self.document.data().modified_ranges(cursor..self.document.len()).into_iter().find(|range| range.start > cursor)
+        } else {
+            let mut ranges = self.document.data().modified_ranges(0..cursor + 1);
+            if ranges.last().is_some_and(|range| range.contains(&cursor)) {
+                ranges.pop();
+            }
+            ranges.pop()
+        };
+
+        match target {
+            Some(range) => self.jump_to(range.start),
+            None => self.set_warning("No more changes"),
+        }
+    }
+
+    /// Переход к следующей (предыдущей) области с высокой энтропией: сжатые
+    /// и зашифрованные данные
+    pub fn jump_to_high_entropy(&mut self, forward: bool) -> Result<()> {
+        let mut progress = ProgressBar::new("Scanning entropy");
+        match entropy::find_high_entropy(self.document.data(), self.cursor_pos, forward, &mut progress)? {
+            Some((pos, bits)) => {
+                self.jump_to(pos);
+                self.set_message(format!("High entropy at 0x{:08X}: {:.2} bits/byte", pos, bits));
+            }
+            None => self.set_warning("No more high-entropy regions"),
+        }
+        Ok(())
+    }
+
+    /// Переход за серию одинаковых байт под курсором (вперед) или на байт перед ней (назад)
+    pub fn skip_run(&mut self, forward: bool) {
+        if self.cursor_pos >= self.document.len() {
+            return;
+        }
+        let run = runs::run_at(self.document.data(), self.cursor_pos);
+        let value = self.document.data()[self.cursor_pos];
+        let target = if forward {
+            (run.end < self.document.len()).then_some(run.end)
+        } else {
+            run.start.checked_sub(1)
+        };
+        match target {
+            Some(pos) => {
+                self.jump_to(pos);
+                let plural = if run.len() == 1 { "" } else { "s" };
+                self.set_message(format!("Skipped {} byte{} of 0x{:02X}", run.len(), plural, value));
+            }
+            None if forward => self.set_warning(format!("0x{:02X} runs to the end of the file", value)),
+            None => self.set_warning(format!("0x{:02X} runs from the start of the file", value)),
+        }
+    }
+
+    /// Начало данных после ближайшей области заполнения (серии нулей или 0xFF)
+    pub fn jump_to_next_data(&mut self) {
+        match runs::next_data(self.document.data(), self.cursor_pos) {
+            Some(pos) => {
+                let skipped = pos - self.cursor_pos;
+                self.jump_to(pos);
+                self.set_message(format!("Data at 0x{:08X}, {} bytes ahead", pos, skipped));
+            }
+            None => self.set_warning("No data after padding"),
+        }
+    }
+
+    pub fn data(&self) -> &PieceTable {
+        self.document.data()
+    }
+
+    pub fn data_len(&self) -> usize {
+        self.document.len()
+    }
+
+    /// Байты окна [offset, offset + len) для отрисовки; читается только запрошенный участок
+    pub fn get_range(&self, offset: usize, len: usize) -> Cow<'_, [u8]> {
+        self.document.range(offset..offset.saturating_add(len))
+    }
+    pub fn get_cursor_pos(&self) -> usize {
+        self.cursor_pos
     }
     pub fn get_view_offset(&self) -> usize {
         self.view_offset
     }
-    pub fn get_mode(&self) -> EditMode {
-        self.mode
+    pub fn get_mode(&self) -> EditMode {
+        self.mode
+    }
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+    pub fn is_insert_mode(&self) -> bool {
+        self.insert_mode
+    }
+    pub fn is_ascii_mode(&self) -> bool {
+        self.mode == EditMode::Ascii
+    }
+    pub fn is_bit_mode(&self) -> bool {
+        self.mode == EditMode::Bits
+    }
+    pub fn bit_index(&self) -> u8 {
+        self.bit_index
+    }
+    pub fn get_file_path(&self) -> &str {
+        &self.file_path
+    }
+    pub fn is_readonly(&self) -> bool {
+        self.readonly
+    }
+
+    /// Включает или выключает режим только для чтения; запись разрешается после
+    /// подтверждения, для блочного устройства - вводом `yes`
+    pub fn toggle_readonly(&mut self) -> Result<()> {
+        if !self.readonly {
+            self.readonly = true;
+            self.set_message("Read-only mode on");
+            return Ok(());
+        }
+
+        let confirmed = if let Some(view) = &self.process {
+            let prompt = format!("Saving writes into the memory of process {}. Type yes to allow writes: ", view.pid);
+            utils::get_user_input(&prompt)?.trim().eq_ignore_ascii_case("yes")
+        } else if self.device {
+            let prompt = format!("Saving writes straight to the device {}. Type yes to allow writes: ", self.file_path);
+            utils::get_user_input(&prompt)?.trim().eq_ignore_ascii_case("yes")
+        } else {
+            utils::prompt_choice("Allow writes to this file? [y/N]: ", "yn")? == Some('y')
+        };
+        if confirmed {
+            self.readonly = false;
+            self.set_warning("Writes allowed");
+        } else {
+            self.set_message("Still read-only");
+        }
+        Ok(())
+    }
+
+    pub fn sector_size(&self) -> Option<usize> {
+        self.sector_size
+    }
+
+    pub fn is_new_file(&self) -> bool {
+        self.is_new_file
+    }
+
+    /// Раз в auto_save_interval несохраненные правки пишутся в файл восстановления (см.
+    /// recovery), сам файл не меняется. true, если файл восстановления записан
+    pub fn check_auto_save(&mut self) -> Result<bool> {
+        let interval = std::time::Duration::from_secs(self.config.editor.auto_save_interval);
+        if !self.config.editor.auto_save || !self.can_recover() || self.auto_saved_at.elapsed() < interval {
+            return Ok(false);
+        }
+        // Отметка ставится до записи: неудачная запись повторится через интервал, а не на
+        // каждом шаге цикла
+        self.auto_saved_at = std::time::Instant::now();
+
+        // Отмена вернула данные к сохраненным: восстанавливать больше нечего
+        if !self.modified {
+            self.discard_recovery();
+            return Ok(false);
+        }
+        let snapshot = self.document.snapshot();
+        if self.auto_saved.as_ref().is_some_and(|saved| Arc::ptr_eq(saved, &snapshot)) {
+            return Ok(false);
+        }
+        recovery::write(&self.file_path, &snapshot)?;
+        self.auto_saved = Some(snapshot);
+        Ok(true)
+    }
+
+    /// Файл восстановления нужен только буферу с настоящим файлом на диске
+    fn can_recover(&self) -> bool {
+        !self.readonly
+            && !self.device
+            && !self.needs_file_name()
+            && !self.is_pipe()
+            && self.process.is_none()
+            && self.packed.is_none()
+    }
+
+    /// Дописывает новые правки в журнал (см. recovery); вызывается из основного цикла
+    pub fn write_journal(&mut self) -> Result<()> {
+        let entries = self.document.take_journal();
+        if entries.is_empty() {
+            return Ok(());
+        }
+        if !self.journaling && std::mem::take(&mut self.kept_journal) {
+            let prompt = "The journal kept for later will be replaced by the edits of this session. Replace it? [y/N] ";
+            if utils::prompt_choice(prompt, "yn")? != Some('y') {
+                self.document.stop_journal();
+                let path = recovery::journal_path(&self.file_path);
+                self.set_warning(format!("Edits of this session are not journaled; {} is kept", path));
+                return Ok(());
+            }
+        }
+        if !self.journaling {
+            // Правки журнала относятся к содержимому на момент открытия или сохранения
+            recovery::start_journal(&self.file_path, self.document.data().original())?;
+            self.journaling = true;
+        }
+        recovery::append_journal(&self.file_path, &entries)
+    }
+
+    /// Правки сохранены или отброшены: журнал и файл восстановления удаляются. Отложенные
+    /// при открытии ("Keep for later") остаются, пока их не перепишут правки этого запуска
+    pub fn discard_recovery(&mut self) {
+        self.document.take_journal();
+        let journaling = std::mem::take(&mut self.journaling);
+        if self.auto_saved.take().is_some() || journaling {
+            recovery::remove(&self.file_path);
+        }
+    }
+
+    pub fn insert_byte(&mut self, value: u8) -> Result<()> {
+        if self.readonly {
+            bail!("Cannot insert in read-only mode");
+        }
+
+        let position = self.cursor_pos;
+        self.check_locks(position..position, 1)?;
+
+        // Вставляем байт в текущую позицию курсора
+        self.document.insert(position, &[value]);
+        self.modified = true;
+
+        // Перемещаем курсор на следующую позицию
+        if position < self.document.len() - 1 {
+            self.cursor_pos += 1;
+        }
+
+        self.adjust_view();
+        Ok(())
+    }
+
+    /// Перемещение курсора с учетом выделения: `extend` (Shift) или visual режим
+    /// расширяют выделение, обычное перемещение сбрасывает его
+    pub fn move_cursor(&mut self, movement: impl FnOnce(&mut Self), extend: bool) {
+        if extend && self.selection_start.is_none() && !self.document.is_empty() {
+            self.selection_start = Some(self.cursor_pos);
+        }
+
+        movement(self);
+
+        if extend || self.visual_mode {
+            if self.selection_start.is_some() {
+                self.selection_end = Some(self.cursor_pos);
+            }
+        } else {
+            self.clear_selection();
+        }
+    }
+
+    /// Щелчок мышью по байту; `extend` продлевает выделение (Shift или перетаскивание)
+    pub fn click(&mut self, hit: ScreenHit, extend: bool) {
+        if !hit.focused {
+            self.switch_pane();
+        }
+
+        // Активной становится колонка под мышью; битовый режим при этом сохраняется
+        if self.mode != EditMode::Bits {
+            self.mode = if hit.text { EditMode::Ascii } else { EditMode::Hex };
+            self.half_byte = None;
+        }
+        if let Some(bit) = hit.bit {
+            self.bit_index = bit;
+        }
+
+        self.move_cursor(|editor| editor.cursor_pos = hit.offset, extend);
+        self.adjust_view();
+    }
+
+    /// Прокрутка колесом мыши на `lines` строк; курсор остается в видимой области
+    pub fn scroll_view(&mut self, lines: isize) {
+        let visible_lines = self.visible_lines();
+        if visible_lines == 0 || self.document.is_empty() {
+            return;
+        }
+
+        let line_len = self.line_len();
+        let last_line = (self.document.len() - 1) / line_len;
+        let max_view_line = (last_line + 1).saturating_sub(visible_lines);
+        let view_line = (self.view_offset / line_len).saturating_add_signed(lines).min(max_view_line);
+        self.view_offset = view_line * line_len;
+
+        let cursor_line = self.cursor_pos / line_len;
+        let target_line = cursor_line.clamp(view_line, view_line + visible_lines - 1);
+        if target_line != cursor_line {
+            let column = self.cursor_pos % line_len;
+            self.cursor_pos = (target_line * line_len + column).min(self.document.len() - 1);
+        }
+    }
+
+    pub fn toggle_visual_mode(&mut self) {
+        if self.visual_mode {
+            // Выход из visual режима оставляет выделение для последующих операций
+            self.visual_mode = false;
+        } else if !self.document.is_empty() {
+            self.visual_mode = true;
+            self.selection_start = Some(self.cursor_pos);
+            self.selection_end = Some(self.cursor_pos);
+        }
+    }
+
+    /// Выделяет участок так же, как Shift+стрелки: следующее перемещение сбросит выделение
+    fn select_range(&mut self, range: std::ops::Range<usize>) {
+        if range.is_empty() {
+            self.clear_selection();
+            return;
+        }
+        self.selection_start = Some(range.start);
+        self.selection_end = Some(range.end - 1);
+        self.visual_mode = false;
+        self.cursor_pos = range.end - 1;
+        self.adjust_view();
+    }
+
+    /// Отмена по Esc, по одному состоянию за раз: фоновый поиск, набранный полубайт,
+    /// открытая панель, инспектор, дизассемблер, выделение. false, если отменять нечего
+    pub fn cancel(&mut self) -> bool {
+        if self.is_searching() {
+            self.cancel_search();
+        } else if self.half_byte.is_some() {
+            self.half_byte = None;
+        } else if self.match_list.is_some() {
+            self.close_match_list();
+        } else if self.bookmark_panel {
+            self.close_bookmark_panel();
+        } else if self.stats_panel.is_some() {
+            self.close_stats_panel();
+        } else if self.template_panel {
+            self.close_template_panel();
+        } else if self.region_panel {
+            self.close_region_panel();
+        } else if self.inspector {
+            self.toggle_inspector();
+        } else if self.disasm.is_some() {
+            self.close_disasm();
+        } else if self.visual_mode || self.selection_range().is_some() {
+            self.clear_selection();
+        } else {
+            return false;
+        }
+        true
+    }
+
+    pub fn clear_selection(&mut self) {
+        self.selection_start = None;
+        self.selection_end = None;
+        self.visual_mode = false;
+    }
+
+    /// Выделенный диапазон (полуинтервал), обе границы выделения включительно
+    pub fn selection_range(&self) -> Option<std::ops::Range<usize>> {
+        let (start, end) = (self.selection_start?, self.selection_end?);
+        let (low, high) = if start <= end { (start, end) } else { (end, start) };
+        let end = (high + 1).min(self.document.len());
+        Some(low.min(end)..end)
+    }
+
+    pub fn is_selected(&self, pos: usize) -> bool {
+        self.selection_range().is_some_and(|range| range.contains(&pos))
+    }
+
+    pub fn is_visual_mode(&self) -> bool {
+        self.visual_mode
+    }
+
+    pub fn write_selection_to(&self, path: &str) -> Result<usize> {
+        let Some(range) = self.selection_range() else {
+            bail!("No selection");
+        };
+
+        let bytes = self.document.range(range);
+        utils::write_file_atomic(path, [&bytes[..]], self.config.editor.backup_on_save)?;
+
+        Ok(bytes.len())
+    }
+
+    pub fn write_selection_from_input(&mut self) -> Result<()> {
+        let Some(range) = self.selection_range() else {
+            self.set_warning("No selection to write");
+            return Ok(());
+        };
+
+        let path = utils::get_user_input("Write selection to: ")?;
+        let path = path.trim();
+
+        if path.is_empty() {
+            return Ok(());
+        }
+
+        // Вырезаемый фрагмент не должен молча затирать существующий файл
+        if std::path::Path::new(path).exists() {
+            let prompt = format!("{} exists. Overwrite? [y/N]: ", path);
+            if utils::prompt_choice(&prompt, "yn")? != Some('y') {
+                self.set_message("Write cancelled");
+                return Ok(());
+            }
+        }
+
+        match self.write_selection_to(path) {
+            Ok(written) => self.set_message(format!(
+                "Wrote {} bytes (0x{:X}..0x{:X}) to {}",
+                written, range.start, range.end, path
+            )),
+            Err(e) => self.report_error(&e.context("Write failed")),
+        }
+
+        Ok(())
+    }
+
+    /// Изменения относительно файла на диске в виде IPS- или BPS-патча (по расширению)
+    pub fn export_patch_from_input(&mut self) -> Result<()> {
+        let path = utils::get_user_input("Export changes as patch (.ips/.bps): ")?;
+        let path = path.trim();
+
+        if path.is_empty() {
+            return Ok(());
+        }
+
+        let format = PatchFormat::from_path(path)?;
+        if std::path::Path::new(path).exists() {
+            let prompt = format!("{} exists. Overwrite? [y/N]: ", path);
+            if utils::prompt_choice(&prompt, "yn")? != Some('y') {
+                self.set_message("Export cancelled");
+                return Ok(());
+            }
+        }
+
+        let mut progress = ProgressBar::new("Creating patch");
+        let patch = patch::create(format, self.document.data().original(), self.document.data(), &mut progress)?;
+        utils::write_file_atomic(path, [&patch[..]], false)?;
+        self.set_message(format!("Wrote {} byte patch to {}", patch.len(), path));
+
+        Ok(())
     }
-    pub fn is_modified(&self) -> bool {
-        self.modified
+
+    pub fn set_message(&mut self, message: impl Into<String>) {
+        self.messages.push(message, Severity::Info);
     }
-    pub fn get_bytes_per_line(&self) -> usize {
-        self.bytes_per_line
+
+    pub fn set_warning(&mut self, message: impl Into<String>) {
+        self.messages.push(message, Severity::Warning);
     }
-    pub fn is_ascii_mode(&self) -> bool {
-        self.mode == EditMode::Ascii
+
+    /// Ошибка операции вместе с цепочкой причин; отмена долгой операции по Esc - не ошибка,
+    /// о ней сообщает предупреждение
+    pub fn report_error(&mut self, error: &anyhow::Error) {
+        let severity = if error.is::<Cancelled>() { Severity::Warning } else { Severity::Error };
+        self.messages.push(format!("{:#}", error), severity);
     }
-    pub fn get_file_path(&self) -> &str {
-        &self.file_path
+
+    pub fn clear_message(&mut self) {
+        self.messages.clear();
     }
-    pub fn is_readonly(&self) -> bool {
-        self.readonly
+
+    pub fn expire_messages(&mut self) -> bool {
+        self.messages.expire(std::time::Instant::now())
     }
 
-    pub fn is_new_file(&self) -> bool {
-        self.is_new_file
+    pub fn get_message(&self) -> Option<&Notification> {
+        self.messages.current()
     }
 
-    pub fn check_auto_save(&mut self) -> Result<()> {
-        if self.config.editor.auto_save && self.modified && !self.readonly {
-            self.save()?;
+    /// Удаление байта под курсором (Delete) или выделенного диапазона
+    pub fn delete_at_cursor(&mut self) -> Result<()> {
+        if self.readonly {
+            bail!("Cannot delete in read-only mode");
+        }
+
+        self.half_byte = None;
+
+        if self.selection_range().is_some() {
+            return self.delete_selection();
+        }
+
+        if self.cursor_pos >= self.document.len() {
+            return Ok(());
         }
+
+        let position = self.cursor_pos;
+        self.check_locks(position..position + 1, 0)?;
+        self.document.delete(position..position + 1);
+        self.modified = true;
+
+        self.clamp_cursor();
+        self.adjust_view();
         Ok(())
     }
 
-    pub fn insert_byte(&mut self, value: u8) -> Result<()> {
+    /// Удаление байта перед курсором (Backspace) или выделенного диапазона
+    pub fn delete_before_cursor(&mut self) -> Result<()> {
         if self.readonly {
-            bail!("Cannot insert in read-only mode");
+            bail!("Cannot delete in read-only mode");
         }
 
-        let position = self.cursor_pos;
+        // Backspace сначала отменяет набранный полубайт
+        if self.half_byte.take().is_some() {
+            return Ok(());
+        }
 
-        // Вставляем байт в текущую позицию курсора
-        self.data.insert(position, value);
+        if self.selection_range().is_some() {
+            return self.delete_selection();
+        }
+
+        if self.cursor_pos == 0 || self.cursor_pos > self.document.len() {
+            return Ok(());
+        }
+
+        let position = self.cursor_pos - 1;
+        self.check_locks(position..position + 1, 0)?;
+        self.document.delete(position..position + 1);
         self.modified = true;
 
-        // Сохраняем операцию для undo/redo
-        self.undo_redo_stack.push(EditOperation::new_insert_byte(position, value));
+        self.cursor_pos = position;
+        self.clamp_cursor();
+        self.adjust_view();
+        Ok(())
+    }
 
-        // Перемещаем курсор на следующую позицию
-        if position < self.data.len() - 1 {
-            self.cursor_pos += 1;
+    fn delete_selection(&mut self) -> Result<()> {
+        let Some(range) = self.selection_range() else {
+            return Ok(());
+        };
+
+        if range.is_empty() {
+            self.clear_selection();
+            return Ok(());
         }
 
+        let position = range.start;
+        self.check_locks(range.clone(), 0)?;
+        self.document.delete(range);
+        self.modified = true;
+
+        self.clear_selection();
+        self.cursor_pos = position;
+        self.clamp_cursor();
         self.adjust_view();
         Ok(())
     }
 
+    fn clamp_cursor(&mut self) {
+        self.cursor_pos = self.cursor_pos.min(self.document.len().saturating_sub(1));
+    }
+
     pub fn insert_bytes(&mut self, bytes: &[u8]) -> Result<()> {
         if self.readonly {
             bail!("Cannot insert in read-only mode");
@@ -422,16 +3278,12 @@ impl HexEditor {
         }
 
         let position = self.cursor_pos;
+        self.check_locks(position..position, bytes.len())?;
 
         // Вставляем байты в текущую позицию курсора
-        for (i, &byte) in bytes.iter().enumerate() {
-            self.data.insert(position + i, byte);
-        }
+        self.document.insert(position, bytes);
         self.modified = true;
 
-        // Сохраняем операцию для undo/redo
-        self.undo_redo_stack.push(EditOperation::new_insert_bytes(position, bytes.to_vec()));
-
         // Перемещаем курсор в конец вставленного блока
         self.cursor_pos = position + bytes.len();
 
@@ -439,6 +3291,410 @@ impl HexEditor {
         Ok(())
     }
 
+    /// Замена байтов начиная с `position`; хвост, выходящий за конец, дописывается
+    pub fn replace_bytes(&mut self, position: usize, bytes: &[u8]) -> Result<()> {
+        if bytes.is_empty() || position > self.document.len() {
+            return Ok(());
+        }
+
+        let end_pos = (position + bytes.len()).min(self.document.len());
+        self.replace_range(position..end_pos, bytes)
+    }
+
+    /// Замена диапазона содержимым произвольной длины одним шагом отмены
+    pub fn replace_range(&mut self, range: std::ops::Range<usize>, new_values: &[u8]) -> Result<()> {
+        if self.readonly {
+            bail!("Cannot modify in read-only mode");
+        }
+
+        if range.is_empty() && new_values.is_empty() {
+            return Ok(());
+        }
+        self.check_locks(range.clone(), new_values.len())?;
+
+        self.document.replace(range, new_values);
+        self.modified = true;
+        Ok(())
+    }
+
+    /// Вставка содержимого другого файла в позицию курсора
+    pub fn insert_file_from_input(&mut self) -> Result<()> {
+        let path = utils::get_user_input("Insert file: ")?;
+        let path = path.trim();
+
+        if path.is_empty() {
+            return Ok(());
+        }
+
+        let bytes = std::fs::read(path).map_err(|e| anyhow!("Cannot read {}: {}", path, e))?;
+        self.insert_bytes(&bytes)?;
+        self.set_message(format!("Inserted {} bytes from {}", bytes.len(), path));
+        Ok(())
+    }
+
+    /// Запись содержимого файла поверх данных с указанного смещения (по умолчанию - курсор)
+    pub fn overwrite_from_file_input(&mut self) -> Result<()> {
+        let path = utils::get_user_input("Overwrite from file: ")?;
+        let path = path.trim();
+
+        if path.is_empty() {
+            return Ok(());
+        }
+
+        let bytes = std::fs::read(path).map_err(|e| anyhow!("Cannot read {}: {}", path, e))?;
+
+        let prompt = format!("At offset [0x{:X}]: ", self.cursor_pos);
+        let input = utils::get_user_input(&prompt)?;
+        let offset = match input.trim() {
+            "" => self.cursor_pos,
+            expr => utils::eval_offset_expression(expr, self.cursor_pos, self.document.len())?,
+        };
+
+        if offset > self.document.len() {
+            bail!("Offset 0x{:X} is past the end of the file", offset);
+        }
+
+        self.replace_bytes(offset, &bytes)?;
+        self.jump_to(offset);
+        self.set_message(format!("Wrote {} bytes from {} at 0x{:X}", bytes.len(), path, offset));
+        Ok(())
+    }
+
+    /// Обрезает данные по курсору: байт под курсором и все после него удаляются
+    pub fn truncate_at_cursor(&mut self) -> Result<()> {
+        let len = self.document.len();
+        if self.cursor_pos >= len {
+            bail!("Nothing to truncate after the cursor");
+        }
+
+        let size = self.cursor_pos;
+        self.resize(size, 0)?;
+        self.set_message(format!("Truncated to {} bytes (removed {})", size, len - size));
+        Ok(())
+    }
+
+    /// Изменение размера до введенного значения; новые байты заполняются указанным байтом
+    pub fn resize_from_input(&mut self) -> Result<()> {
+        let len = self.document.len();
+        let prompt = format!("New size (now {} / 0x{:X}; e.g. 0x10000, end+512): ", len, len);
+        let input = utils::get_user_input(&prompt)?;
+
+        if input.trim().is_empty() {
+            return Ok(());
+        }
+
+        let size = utils::eval_offset_expression(&input, self.cursor_pos, len)?;
+        let fill = if size > len {
+            let fill = utils::get_user_input("Fill byte (hex) [00]: ")?;
+            match fill.trim() {
+                "" => 0,
+                hex => u8::from_str_radix(hex.trim_start_matches("0x"), 16)
+                    .map_err(|_| anyhow!("Invalid fill byte: {}", hex))?,
+            }
+        } else {
+            0
+        };
+
+        self.resize(size, fill)?;
+        self.set_message(format!("Size changed from {} to {} bytes", len, size));
+        Ok(())
+    }
+
+    /// Новый размер одной операцией undo: лишнее отрезается, недостающее дописывается `fill`
+    pub fn resize(&mut self, size: usize, fill: u8) -> Result<()> {
+        let len = self.document.len();
+        if size < len {
+            self.replace_range(size..len, &[])?;
+        } else if size > len {
+            self.replace_range(len..len, &vec![fill; size - len])?;
+        }
+
+        self.clamp_cursor();
+        self.adjust_view();
+        Ok(())
+    }
+
+    /// Заполнение диапазона [start, end) повторяющимся шаблоном одной операцией undo
+    pub fn fill_range(&mut self, start: usize, end: usize, pattern: &[u8]) -> Result<()> {
+        if pattern.is_empty() {
+            bail!("Fill pattern is empty");
+        }
+
+        let end = end.min(self.document.len());
+        if start >= end {
+            return Ok(());
+        }
+
+        let filled: Vec<u8> = pattern.iter().cycle().take(end - start).cloned().collect();
+        self.replace_bytes(start, &filled)
+    }
+
+    pub fn fill_selection_from_input(&mut self) -> Result<()> {
+        let Some(range) = self.selection_range().filter(|range| !range.is_empty()) else {
+            self.set_warning("No selection to fill");
+            return Ok(());
+        };
+
+        let input = utils::get_user_input("Fill pattern (hex): ")?;
+
+        if input.trim().is_empty() {
+            return Ok(());
+        }
+
+        // Лишняя цифра в конце - скорее опечатка, чем половина байта: узор не урезается молча
+        let pattern = utils::parse_hex_text(&input)
+            .ok_or_else(|| anyhow!("Not a hex pattern: expected an even number of hex digits"))?;
+        let len = range.len();
+        self.fill_range(range.start, range.end, &pattern)?;
+        self.set_message(format!("Filled {} bytes", len));
+        Ok(())
+    }
+
+    /// Побайтовое преобразование выделения (XOR с ключом, сложение, сдвиг битов...)
+    /// одним шагом отмены
+    pub fn transform_selection_from_input(&mut self) -> Result<()> {
+        let Some(range) = self.selection_range().filter(|range| !range.is_empty()) else {
+            self.set_warning("No selection to transform");
+            return Ok(());
+        };
+
+        let prompt = format!("Transform {} bytes ({}): ", range.len(), transform::SYNTAX);
+        let input = utils::get_user_input(&prompt)?;
+        if input.trim().is_empty() {
+            return Ok(());
+        }
+
+        let transform = Transform::parse(&input)?;
+        let mut bytes = self.document.range(range.clone()).into_owned();
+        transform.apply(&mut bytes)?;
+        self.replace_range(range.clone(), &bytes)?;
+        self.set_message(format!("Transformed {} bytes: {}", range.len(), input.trim()));
+        Ok(())
+    }
+
+    /// Сжатый поток в выделении или, без него, начиная с курсора: формат узнается
+    /// по заголовку, иначе спрашивается. `None`, если ввод отменен
+    pub fn decompress_from_input(&mut self) -> Result<Option<(Vec<u8>, Packed)>> {
+        let range = match self.selection_range().filter(|range| !range.is_empty()) {
+            Some(range) => range,
+            None => self.cursor_pos..self.document.len(),
+        };
+        let bytes = self.document.range(range.clone());
+
+        let format = match Format::detect(&bytes) {
+            Some(format) => format,
+            None => {
+                let prompt = format!("No known header at 0x{:X}. Format (zlib/gzip/lz4/zstd): ", range.start);
+                match utils::get_user_input(&prompt)?.trim() {
+                    "" => return Ok(None),
+                    name => Format::parse(name)?,
+                }
+            }
+        };
+
+        let (data, used) = format.decompress(&bytes)?;
+        let packed = Packed {
+            source: self.file_path.clone(),
+            range: range.start..range.start + used,
+            format,
+        };
+        Ok(Some((data, packed)))
+    }
+
+    pub fn packed(&self) -> Option<&Packed> {
+        self.packed.as_ref()
+    }
+
+    /// После обратного сжатия поток в источнике занимает новую длину
+    pub fn set_packed_range(&mut self, range: std::ops::Range<usize>) {
+        if let Some(packed) = &mut self.packed {
+            packed.range = range;
+        }
+    }
+
+    /// Кодирование выделения в base64, hex-строку или URL-кодировку и обратно; результат
+    /// показывается до замены и остается выделенным
+    pub fn encode_selection_from_input(&mut self) -> Result<()> {
+        let Some(range) = self.selection_range().filter(|range| !range.is_empty()) else {
+            self.set_warning("No selection to encode or decode");
+            return Ok(());
+        };
+
+        let prompt = format!("[e]ncode or [d]ecode {} bytes: ", range.len());
+        let decode = match utils::prompt_choice(&prompt, "ed")? {
+            Some(choice) => choice == 'd',
+            None => return Ok(()),
+        };
+        let codec = match utils::prompt_choice("[b]ase64, [h]ex or [u]rl: ", "bhu")? {
+            Some('b') => Codec::Base64,
+            Some('h') => Codec::Hex,
+            Some(_) => Codec::Url,
+            None => return Ok(()),
+        };
+
+        let bytes = self.document.range(range.clone());
+        let (verb, result) = if decode {
+            ("Decoded", codec.decode(&bytes)?)
+        } else {
+            ("Encoded", codec.encode(&bytes))
+        };
+
+        // Раскодированные байты показываются в hex, закодированный текст - как есть
+        let shown = &result[..result.len().min(CODEC_PREVIEW_BYTES)];
+        let mut preview = if decode {
+            utils::bytes_to_hex_string(shown)
+        } else {
+            String::from_utf8_lossy(shown).into_owned()
+        };
+        if shown.len() < result.len() {
+            preview.push_str("...");
+        }
+        let prompt = format!("{} bytes -> {} bytes: {} Replace? [y/N]: ", range.len(), result.len(), preview);
+        if utils::prompt_choice(&prompt, "yn")? != Some('y') {
+            self.set_message("Cancelled");
+            return Ok(());
+        }
+
+        self.replace_range(range.clone(), &result)?;
+        self.select_range(range.start..range.start + result.len());
+        self.set_message(format!("{} {} bytes as {}: {} bytes", verb, range.len(), codec.name(), result.len()));
+        Ok(())
+    }
+
+    pub fn copy_selection(&mut self) {
+        match self.selection_range() {
+            Some(range) if !range.is_empty() => {
+                self.clipboard = self.document.range(range).into_owned();
+                self.set_message(format!("Copied {} bytes", self.clipboard.len()));
+            }
+            _ => self.set_warning("No selection to copy"),
+        }
+    }
+
+    pub fn cut_selection(&mut self) -> Result<()> {
+        if self.readonly {
+            bail!("Cannot cut in read-only mode");
+        }
+
+        match self.selection_range() {
+            Some(range) if !range.is_empty() => {
+                self.clipboard = self.document.range(range).into_owned();
+                self.delete_selection()?;
+                self.set_message(format!("Cut {} bytes", self.clipboard.len()));
+            }
+            _ => self.set_warning("No selection to cut"),
+        }
+        Ok(())
+    }
+
+    /// Вставка буфера обмена в позицию курсора с учетом режима OVR/INS
+    pub fn paste(&mut self) -> Result<()> {
+        if self.clipboard.is_empty() {
+            self.set_warning("Clipboard is empty");
+            return Ok(());
+        }
+
+        let bytes = self.clipboard.clone();
+        self.paste_bytes(&bytes)?;
+        self.set_message(format!("Pasted {} bytes", bytes.len()));
+        Ok(())
+    }
+
+    fn paste_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        if self.insert_mode {
+            return self.insert_bytes(bytes);
+        }
+
+        let position = self.cursor_pos;
+        self.replace_bytes(position, bytes)?;
+        self.cursor_pos = position + bytes.len();
+        self.clamp_cursor();
+        self.adjust_view();
+        Ok(())
+    }
+
+    /// Копирование выделения в системный буфер: hex-строкой или как текст
+    pub fn copy_selection_to_system(&mut self, as_text: bool) {
+        let Some(range) = self.selection_range().filter(|range| !range.is_empty()) else {
+            self.set_warning("No selection to copy");
+            return;
+        };
+
+        let bytes = self.document.range(range);
+        let text = if as_text {
+            String::from_utf8_lossy(&bytes).into_owned()
+        } else {
+            utils::bytes_to_hex_string(&bytes)
+        };
+        let len = bytes.len();
+
+        match self.system_clipboard.set_text(text) {
+            Ok(()) => self.set_message(format!("Copied {} bytes to system clipboard", len)),
+            Err(e) => self.report_error(&e),
+        }
+    }
+
+    /// Выделение как массив для исходного кода, hex или base64: в системный буфер или в файл
+    pub fn copy_selection_as(&mut self) -> Result<()> {
+        let Some(range) = self.selection_range().filter(|range| !range.is_empty()) else {
+            self.set_warning("No selection to copy");
+            return Ok(());
+        };
+
+        let choices: String = ExportFormat::ALL.map(ExportFormat::key).iter().collect();
+        let options: Vec<String> = ExportFormat::ALL
+            .map(|format| format!("[{}] {}", format.key(), format.name()))
+            .to_vec();
+        let prompt = format!("Copy as {}: ", options.join(", "));
+        let Some(format) = utils::prompt_choice(&prompt, &choices)?.and_then(ExportFormat::from_key) else {
+            return Ok(());
+        };
+
+        let path = utils::get_user_input("Save to file (empty: system clipboard): ")?;
+        let path = path.trim();
+        let text = format.render(&self.document.range(range.clone()));
+
+        if path.is_empty() {
+            match self.system_clipboard.set_text(text) {
+                Ok(()) => self.set_message(format!("Copied {} bytes as {}", range.len(), format.name())),
+                Err(e) => self.report_error(&e),
+            }
+            return Ok(());
+        }
+
+        if std::path::Path::new(path).exists() {
+            let prompt = format!("{} exists. Overwrite? [y/N]: ", path);
+            if utils::prompt_choice(&prompt, "yn")? != Some('y') {
+                self.set_message("Export cancelled");
+                return Ok(());
+            }
+        }
+        utils::write_file_atomic(path, [text.as_bytes()], false)?;
+        self.set_message(format!("Wrote {} bytes as {} to {}", range.len(), format.name(), path));
+        Ok(())
+    }
+
+    /// Вставка из системного буфера: hex-текст разбирается в байты, иначе берется как есть
+    pub fn paste_from_system(&mut self) -> Result<()> {
+        let text = match self.system_clipboard.get_text() {
+            Ok(text) => text,
+            Err(e) => {
+                self.report_error(&e);
+                return Ok(());
+            }
+        };
+
+        let bytes = utils::parse_hex_text(&text).unwrap_or_else(|| text.into_bytes());
+        if bytes.is_empty() {
+            self.set_warning("System clipboard is empty");
+            return Ok(());
+        }
+
+        self.paste_bytes(&bytes)?;
+        self.set_message(format!("Pasted {} bytes from system clipboard", bytes.len()));
+        Ok(())
+    }
+
     pub fn insert_from_hex_string(&mut self, hex_string: &str) -> Result<()> {
         let bytes = utils::hex_string_to_bytes(hex_string)?;
         self.insert_bytes(&bytes)
@@ -469,3 +3725,11 @@ impl HexEditor {
         self.insert_from_ascii_string(&input)
     }
 }
+
+/// Загруженное значение или значение по умолчанию; ошибка откладывается до предупреждения
+fn or_warn<T: Default>(loaded: Result<T>, warnings: &mut Vec<anyhow::Error>) -> T {
+    loaded.unwrap_or_else(|e| {
+        warnings.push(e);
+        T::default()
+    })
+}
@@ -0,0 +1,116 @@
+//! Выделение в виде исходного кода для вставки в программы: массивы C, Rust и Go,
+//! литерал bytes в Python, hex-строка и base64
+
+use hexr_core::codec::Codec;
+use hexr_core::hex;
+
+/// Сколько байт в строке массива, как у `xxd -i`
+const ARRAY_BYTES_PER_LINE: usize = 12;
+
+/// Сколько байт в строке литерала Python
+const PYTHON_BYTES_PER_LINE: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    C,
+    RustArray,
+    RustSlice,
+    Python,
+    Go,
+    Hex,
+    Base64,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 7] = [
+        ExportFormat::C,
+        ExportFormat::RustArray,
+        ExportFormat::RustSlice,
+        ExportFormat::Python,
+        ExportFormat::Go,
+        ExportFormat::Hex,
+        ExportFormat::Base64,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ExportFormat::C => "C array",
+            ExportFormat::RustArray => "Rust array",
+            ExportFormat::RustSlice => "Rust slice",
+            ExportFormat::Python => "Python bytes",
+            ExportFormat::Go => "Go slice",
+            ExportFormat::Hex => "hex",
+            ExportFormat::Base64 => "base64",
+        }
+    }
+
+    /// Клавиша формата в запросе
+    pub fn key(self) -> char {
+        match self {
+            ExportFormat::C => 'c',
+            ExportFormat::RustArray => 'r',
+            ExportFormat::RustSlice => 's',
+            ExportFormat::Python => 'p',
+            ExportFormat::Go => 'g',
+            ExportFormat::Hex => 'h',
+            ExportFormat::Base64 => 'b',
+        }
+    }
+
+    pub fn from_key(key: char) -> Option<Self> {
+        Self::ALL.into_iter().find(|format| format.key() == key)
+    }
+
+    /// Текст для вставки в код; у массивов в конце перевод строки
+    pub fn render(self, bytes: &[u8]) -> String {
+        match self {
+            ExportFormat::C => format!(
+                "const unsigned char data[{}] = {{\n{}}};\n",
+                bytes.len(),
+                array_body(bytes, "    ")
+            ),
+            ExportFormat::RustArray => format!(
+                "const DATA: [u8; {}] = [\n{}];\n",
+                bytes.len(),
+                array_body(bytes, "    ")
+            ),
+            ExportFormat::RustSlice => {
+                format!("const DATA: &[u8] = &[\n{}];\n", array_body(bytes, "    "))
+            }
+            // gofmt отступает табуляцией
+            ExportFormat::Go => format!("var data = []byte{{\n{}}}\n", array_body(bytes, "\t")),
+            ExportFormat::Python => python_bytes(bytes),
+            ExportFormat::Hex => hex::bytes_to_hex_string(bytes),
+            ExportFormat::Base64 => {
+                String::from_utf8_lossy(&Codec::Base64.encode(bytes)).into_owned()
+            }
+        }
+    }
+}
+
+/// Строки `0x7f, 0x45, ...,` с отступом и запятой после каждого байта
+fn array_body(bytes: &[u8], indent: &str) -> String {
+    bytes
+        .chunks(ARRAY_BYTES_PER_LINE)
+        .map(|line| {
+            let items: Vec<String> = line.iter().map(|byte| format!("0x{:02x},", byte)).collect();
+            format!("{}{}\n", indent, items.join(" "))
+        })
+        .collect()
+}
+
+/// `data = b"\x7f..."`; длинные данные - соседними литералами в скобках
+fn python_bytes(bytes: &[u8]) -> String {
+    let lines: Vec<String> = bytes
+        .chunks(PYTHON_BYTES_PER_LINE)
+        .map(|line| {
+            let escaped: String = line.iter().map(|byte| format!("\\x{:02x}", byte)).collect();
+            format!("b\"{}\"", escaped)
+        })
+        .collect();
+    match lines.as_slice() {
+        [] => "data = b\"\"\n".to_string(),
+        [line] => format!("data = {}\n", line),
+        lines => format!("data = (\n    {}\n)\n", lines.join("\n    ")),
+    }
+}
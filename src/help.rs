@@ -0,0 +1,131 @@
+use crate::keymap::{CONTEXT_KEYS, Category, Keymap};
+use crate::plugin::Plugins;
+use crate::vim::MODAL_KEYS;
+
+/// Ширина колонки клавиш, дальше описание
+const CHORD_COLUMN_MAX: usize = 30;
+
+pub enum HelpLine {
+    Heading(&'static str),
+    // Клавиши, описание и имя команды для [keys]
+    Entry(String, String, String),
+    Blank,
+}
+
+/// Справка по клавишам, собранная из действующей раскладки
+pub struct Help {
+    lines: Vec<HelpLine>,
+    chord_width: usize,
+    scroll: usize,
+}
+
+impl Help {
+    pub fn new(keymap: &Keymap, plugins: &Plugins) -> Self {
+        let mut lines = Vec::new();
+        let actions = keymap.actions();
+
+        for category in Category::ALL {
+            let mut entries = actions
+                .iter()
+                .filter(|action| action.category() == category)
+                .peekable();
+            if entries.peek().is_none() {
+                continue;
+            }
+
+            lines.push(HelpLine::Heading(category.label()));
+            for &action in entries {
+                let chords: Vec<String> = keymap
+                    .chords(action)
+                    .map(|chord| chord.to_string())
+                    .collect();
+                lines.push(HelpLine::Entry(
+                    chords.join(", "),
+                    action.description().to_string(),
+                    action.name().to_string(),
+                ));
+            }
+            lines.push(HelpLine::Blank);
+        }
+
+        // Команды плагинов видны и без клавиши: по имени их назначают в [keys]
+        if !plugins.commands().is_empty() {
+            lines.push(HelpLine::Heading("Plugins"));
+            for (index, command) in plugins.commands().iter().enumerate() {
+                let chords: Vec<String> = keymap
+                    .plugin_chords(index)
+                    .map(|chord| chord.to_string())
+                    .collect();
+                lines.push(HelpLine::Entry(
+                    chords.join(", "),
+                    command.description.clone(),
+                    command.name.clone(),
+                ));
+            }
+            lines.push(HelpLine::Blank);
+        }
+
+        // Нормальный режим vim: свои команды поверх обычных
+        if keymap.is_modal() {
+            lines.push(HelpLine::Heading("Vim normal mode"));
+            for &(chord, action) in keymap.normal_bindings() {
+                lines.push(HelpLine::Entry(
+                    chord.to_string(),
+                    action.description().to_string(),
+                    action.name().to_string(),
+                ));
+            }
+            for &(keys, description) in MODAL_KEYS {
+                lines.push(HelpLine::Entry(
+                    keys.to_string(),
+                    description.to_string(),
+                    String::new(),
+                ));
+            }
+            lines.push(HelpLine::Blank);
+        }
+
+        lines.push(HelpLine::Heading("In context"));
+        for &(keys, description) in CONTEXT_KEYS {
+            lines.push(HelpLine::Entry(
+                keys.to_string(),
+                description.to_string(),
+                String::new(),
+            ));
+        }
+
+        let chord_width = lines
+            .iter()
+            .map(|line| match line {
+                HelpLine::Entry(chords, _, _) => chords.len(),
+                _ => 0,
+            })
+            .max()
+            .unwrap_or(0)
+            .min(CHORD_COLUMN_MAX);
+
+        Self {
+            lines,
+            chord_width,
+            scroll: 0,
+        }
+    }
+
+    pub fn lines(&self) -> &[HelpLine] {
+        &self.lines
+    }
+
+    pub fn chord_width(&self) -> usize {
+        self.chord_width
+    }
+
+    pub fn scroll_offset(&self) -> usize {
+        self.scroll
+    }
+
+    /// Прокрутка так, чтобы последняя строка не уходила выше низа окна высотой `rows`
+    pub fn scroll(&mut self, lines: isize, rows: usize) {
+        let max = self.lines.len().saturating_sub(rows);
+        self.scroll = self.scroll.saturating_add_signed(lines).min(max);
+    }
+}
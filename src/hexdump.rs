@@ -0,0 +1,302 @@
+use crate::charset::Charset;
+use crate::theme::Theme;
+use crate::utils;
+use anyhow::{Context, Result, anyhow, bail};
+use crossterm::queue;
+use crossterm::style::{ResetColor, SetForegroundColor};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+
+/// Допустимые размеры групп байт в hex-колонке
+pub const BYTE_GROUPS: [usize; 4] = [1, 2, 4, 8];
+
+/// Размер группы из конфигурации; значение вне допустимых считается отсутствием групп
+pub fn byte_group(configured: usize) -> usize {
+    if BYTE_GROUPS.contains(&configured) {
+        configured
+    } else {
+        1
+    }
+}
+
+/// Система счисления колонки адресов
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OffsetRadix {
+    #[default]
+    Hex,
+    Decimal,
+    Octal,
+}
+
+impl OffsetRadix {
+    pub fn label(self) -> &'static str {
+        match self {
+            OffsetRadix::Hex => "hex",
+            OffsetRadix::Decimal => "decimal",
+            OffsetRadix::Octal => "octal",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            OffsetRadix::Hex => OffsetRadix::Decimal,
+            OffsetRadix::Decimal => OffsetRadix::Octal,
+            OffsetRadix::Octal => OffsetRadix::Hex,
+        }
+    }
+
+    /// Ширина адреса: столько цифр занимает 32-битное смещение
+    pub fn digits(self) -> usize {
+        match self {
+            OffsetRadix::Hex => 8,
+            OffsetRadix::Decimal => 10,
+            OffsetRadix::Octal => 11,
+        }
+    }
+
+    pub fn format(self, offset: usize) -> String {
+        let width = self.digits();
+        match self {
+            OffsetRadix::Hex => format!("{:0width$X}", offset),
+            OffsetRadix::Decimal => format!("{:0width$}", offset),
+            OffsetRadix::Octal => format!("{:0width$o}", offset),
+        }
+    }
+}
+
+/// Байт в строке в режиме `auto`: сколько помещается в `width` колонок
+/// с учетом ширины адреса и разделителей групп
+pub fn auto_bytes_per_line(width: usize, radix: OffsetRadix, group: usize) -> usize {
+    let offset_width = radix.digits() + 2;
+    let ascii_label_width = 8;
+    let separator_width = 2;
+    let available = width.saturating_sub(offset_width + separator_width + ascii_label_width);
+
+    // Байт занимает 4 колонки (hex и символ), между группами - еще одна на разделитель
+    let bytes = if group == 1 {
+        available / 4
+    } else {
+        (available + 1) * group / (4 * group + 1)
+    };
+    let bytes = bytes.clamp(8, 32);
+    bytes - bytes % group
+}
+
+/// Нужен ли разделитель после байта `byte_idx` строки: между группами, но не в конце строки
+pub fn is_group_end(byte_idx: usize, group: usize, bytes_per_line: usize) -> bool {
+    group > 1 && (byte_idx + 1).is_multiple_of(group) && byte_idx + 1 < bytes_per_line
+}
+
+/// Колонки строки дампа, те же, что в окне редактора
+#[derive(Debug, Clone, Copy)]
+pub struct Layout {
+    pub bytes_per_line: usize,
+    pub group: usize,
+    pub radix: OffsetRadix,
+    pub charset: Charset,
+}
+
+/// Сколько строк декодируется за раз: большой файл не раскладывается в символы целиком
+const DUMP_CHUNK_LINES: usize = 4096;
+
+/// Печатает `data`, начиная с адреса `offset`, строками редактора: адрес, hex-колонка, текст.
+/// С темой байты раскрашиваются по классам, как в окне редактора
+pub fn write_dump(
+    out: &mut impl Write,
+    data: &[u8],
+    offset: usize,
+    layout: &Layout,
+    theme: Option<&Theme>,
+) -> Result<()> {
+    let chunk_len = layout.bytes_per_line * DUMP_CHUNK_LINES;
+
+    for (chunk_idx, chunk) in data.chunks(chunk_len).enumerate() {
+        let chunk_offset = offset + chunk_idx * chunk_len;
+        let text = layout.charset.decode(chunk, chunk_offset);
+
+        for (line_idx, bytes) in chunk.chunks(layout.bytes_per_line).enumerate() {
+            let start = line_idx * layout.bytes_per_line;
+            let line_text = &text[start..start + bytes.len()];
+            write_line(out, chunk_offset + start, bytes, line_text, layout, theme)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_line(
+    out: &mut impl Write,
+    offset: usize,
+    bytes: &[u8],
+    text: &[char],
+    layout: &Layout,
+    theme: Option<&Theme>,
+) -> Result<()> {
+    if let Some(theme) = theme {
+        queue!(out, SetForegroundColor(theme.offset))?;
+    }
+    write!(out, "{}", layout.radix.format(offset))?;
+    reset_color(out, theme)?;
+    write!(out, "  ")?;
+
+    // Неполная последняя строка дополняется пробелами, чтобы текст стоял в своей колонке
+    for byte_idx in 0..layout.bytes_per_line {
+        match bytes.get(byte_idx) {
+            Some(&byte) => {
+                set_byte_color(out, theme, byte)?;
+                write!(out, "{:02X}", byte)?;
+                reset_color(out, theme)?;
+                write!(out, " ")?;
+            }
+            None => write!(out, "   ")?,
+        }
+        if is_group_end(byte_idx, layout.group, layout.bytes_per_line) {
+            write!(out, " ")?;
+        }
+    }
+
+    write!(out, " ")?;
+    for (&byte, ch) in bytes.iter().zip(text) {
+        set_byte_color(out, theme, byte)?;
+        write!(out, "{}", ch)?;
+        reset_color(out, theme)?;
+    }
+    writeln!(out)?;
+    Ok(())
+}
+
+fn set_byte_color(out: &mut impl Write, theme: Option<&Theme>, byte: u8) -> Result<()> {
+    if let Some(color) = theme.and_then(|theme| theme.byte_color(byte)) {
+        queue!(out, SetForegroundColor(color))?;
+    }
+    Ok(())
+}
+
+fn reset_color(out: &mut impl Write, theme: Option<&Theme>) -> Result<()> {
+    if theme.is_some() {
+        queue!(out, ResetColor)?;
+    }
+    Ok(())
+}
+
+/// Дальше стольких байт за концом данных адрес строки не уводит: это скорее опечатка,
+/// чем дамп с середины огромного файла
+const MAX_OFFSET_GAP: usize = 1 << 30;
+
+/// Байты из текста дампа: вывод `hexr dump`, xxd или просто hex. Как `xxd -r`, байты
+/// строки с адресом ложатся по этому адресу, а пропуск перед ним заполняется нулями;
+/// после `*` (свернутые одинаковые строки) пропуск заполняется повторами строки над ней
+pub fn parse_dump(text: &str) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    let mut previous: Vec<u8> = Vec::new();
+    let mut squeezed = false;
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line.trim() == "*" {
+            squeezed = true;
+            continue;
+        }
+        let (offset, bytes) = parse_line(line).with_context(|| format!("Line {}", index + 1))?;
+        let at = offset.unwrap_or(data.len());
+        if at > data.len() {
+            if at - data.len() > MAX_OFFSET_GAP {
+                bail!(
+                    "Line {}: offset 0x{:X} is too far past the data",
+                    index + 1,
+                    at
+                );
+            }
+            let fill = at - data.len();
+            if squeezed && !previous.is_empty() {
+                data.extend(previous.iter().cycle().take(fill));
+            } else {
+                data.resize(at, 0);
+            }
+        }
+        squeezed = false;
+        if bytes.is_empty() {
+            continue;
+        }
+
+        // Адрес внутри уже собранных данных переписывает их, как `xxd -r`
+        let overlap = (data.len() - at).min(bytes.len());
+        data[at..at + overlap].copy_from_slice(&bytes[..overlap]);
+        data.extend_from_slice(&bytes[overlap..]);
+        previous = bytes;
+    }
+    Ok(data)
+}
+
+/// Адрес строки, если он есть, и ее байты
+fn parse_line(line: &str) -> Result<(Option<usize>, Vec<u8>)> {
+    // Слова строки и где каждое кончается
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while let Some(skip) = line[pos..].find(|c: char| !c.is_whitespace()) {
+        let start = pos + skip;
+        let end = line[start..]
+            .find(char::is_whitespace)
+            .map_or(line.len(), |len| start + len);
+        tokens.push((end, &line[start..end]));
+        pos = end;
+    }
+    let Some(&(_, first)) = tokens.first() else {
+        return Ok((None, Vec::new()));
+    };
+
+    // Первое слово - адрес, если за ним двоеточие (xxd) или после hex-колонки идет
+    // текстовая. Иначе это уже байты: простой hex с длинными группами не теряется
+    let digits = first.strip_suffix(':').unwrap_or(first);
+    let is_number = !digits.is_empty() && digits.chars().all(|c| c.is_ascii_hexdigit());
+    if is_number {
+        let offset = || {
+            usize::from_str_radix(digits, 16)
+                .map_err(|_| anyhow!("offset '{}' is too large", digits))
+        };
+        if first.ends_with(':') {
+            let bytes = hex_column(line, &tokens[1..]).map(|(bytes, _)| bytes);
+            return Ok((Some(offset()?), bytes.unwrap_or_default()));
+        }
+        if let Some((bytes, true)) = hex_column(line, &tokens[1..]) {
+            return Ok((Some(offset()?), bytes));
+        }
+    }
+
+    match hex_column(line, &tokens) {
+        Some((bytes, _)) => Ok((None, bytes)),
+        None => bail!("expected hex bytes, found '{}'", first),
+    }
+}
+
+/// Байты hex-колонки из слов строки и есть ли за ней текстовая колонка; None, если
+/// первое слово не hex
+fn hex_column(line: &str, tokens: &[(usize, &str)]) -> Option<(Vec<u8>, bool)> {
+    // Байты после каждого hex-токена; текстовая колонка тоже может выглядеть как hex
+    let mut bytes = Vec::new();
+    let mut counts = Vec::new();
+    for &(_, token) in tokens {
+        let Some(decoded) = utils::parse_hex_text(token) else {
+            break;
+        };
+        bytes.extend(decoded);
+        counts.push(bytes.len());
+    }
+    if counts.is_empty() {
+        return None;
+    }
+
+    // Конец hex-колонки там, где после нее остается ровно по символу текста на байт.
+    // Если так не выходит (текст правили или его нет), берутся все hex-токены подряд
+    let text = (1..=counts.len())
+        .rev()
+        .find(|&count| is_text_column(&line[tokens[count - 1].0..], counts[count - 1]));
+    let hex_tokens = text.unwrap_or(counts.len());
+    bytes.truncate(counts[hex_tokens - 1]);
+    Some((bytes, text.is_some()))
+}
+
+/// Остаток строки после hex-колонки: не меньше двух пробелов и `bytes` символов текста
+fn is_text_column(rest: &str, bytes: usize) -> bool {
+    let chars = rest.chars().count();
+    chars >= bytes + 2 && rest.chars().take(chars - bytes).all(char::is_whitespace)
+}
@@ -0,0 +1,156 @@
+//! Правила подсветки `[[highlight]]`: байты по шаблону (сигнатуры, маркеры) или серии
+//! одного значения (заполнение нулями, 0xFF стертой флеш-памяти) красятся своим цветом
+
+use crate::theme;
+use anyhow::{Result, anyhow, bail};
+use crossterm::style::Color;
+use hexr_core::buffer::PieceTable;
+use hexr_core::hex;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// Серия короче этого не подсвечивается, если `min_run` не задан
+const DEFAULT_MIN_RUN: usize = 8;
+
+/// Правило из конфигурации: задается либо `pattern`, либо `run`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HighlightRule {
+    // Hex-шаблон: "FF D8 FF"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    // Значение байта серии: "00"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run: Option<String>,
+    // Наименьшая длина подсвечиваемой серии
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_run: Option<usize>,
+    pub color: String,
+}
+
+impl HighlightRule {
+    /// Правило из строки запроса: hex-шаблон или `run:BYTE[:MIN_LEN]`
+    pub fn parse(input: &str, color: &str) -> Result<Self> {
+        let input = input.trim();
+        let rule = match input.strip_prefix("run:") {
+            Some(run) => {
+                let (byte, min_run) = match run.split_once(':') {
+                    Some((byte, min_run)) => {
+                        let min_run = min_run
+                            .trim()
+                            .parse()
+                            .map_err(|_| anyhow!("Invalid run length '{}'", min_run.trim()))?;
+                        (byte, Some(min_run))
+                    }
+                    None => (run, None),
+                };
+                HighlightRule {
+                    pattern: None,
+                    run: Some(byte.trim().to_string()),
+                    min_run,
+                    color: color.to_string(),
+                }
+            }
+            None => HighlightRule {
+                pattern: Some(input.to_string()),
+                run: None,
+                min_run: None,
+                color: color.to_string(),
+            },
+        };
+        Matcher::of(&rule)?;
+        Ok(rule)
+    }
+}
+
+enum Matcher {
+    Pattern(Vec<u8>),
+    Run { byte: u8, min_len: usize },
+}
+
+impl Matcher {
+    fn of(rule: &HighlightRule) -> Result<Self> {
+        match (&rule.pattern, &rule.run) {
+            (Some(pattern), None) => {
+                let bytes = hex::hex_string_to_bytes(pattern)?;
+                if bytes.is_empty() {
+                    bail!("Highlight pattern '{}' is empty", pattern);
+                }
+                Ok(Matcher::Pattern(bytes))
+            }
+            (None, Some(run)) => {
+                let byte =
+                    u8::from_str_radix(run.trim().trim_start_matches("0x"), 16).map_err(|_| {
+                        anyhow!("Invalid run byte '{}': expected a hex value like 00", run)
+                    })?;
+                let min_len = rule.min_run.unwrap_or(DEFAULT_MIN_RUN).max(1);
+                Ok(Matcher::Run { byte, min_len })
+            }
+            _ => bail!("A highlight rule needs either 'pattern' or 'run'"),
+        }
+    }
+
+    /// Сколько байт вокруг окна нужно, чтобы узнать совпадения на его границах
+    fn reach(&self) -> usize {
+        match self {
+            Matcher::Pattern(bytes) => bytes.len(),
+            Matcher::Run { min_len, .. } => *min_len,
+        }
+    }
+}
+
+/// Разобранные правила; первое совпавшее правило задает цвет
+#[derive(Default)]
+pub struct Highlights {
+    rules: Vec<(Matcher, Color)>,
+}
+
+impl Highlights {
+    pub fn load(rules: &[HighlightRule]) -> Result<Self> {
+        let mut loaded = Self::default();
+        for (idx, rule) in rules.iter().enumerate() {
+            let matcher =
+                Matcher::of(rule).map_err(|e| anyhow!("[[highlight]] #{}: {}", idx + 1, e))?;
+            let color = theme::parse_color(&rule.color)
+                .map_err(|e| anyhow!("[[highlight]] #{}: {}", idx + 1, e))?;
+            loaded.rules.push((matcher, color));
+        }
+        Ok(loaded)
+    }
+
+    /// Подсвеченные участки окна `window`; совпадение может начинаться до окна
+    pub fn spans(&self, data: &PieceTable, window: Range<usize>) -> Vec<(Range<usize>, Color)> {
+        let Some(reach) = self.rules.iter().map(|(matcher, _)| matcher.reach()).max() else {
+            return Vec::new();
+        };
+
+        let start = window.start.saturating_sub(reach - 1);
+        let bytes = data.range(start..window.end.saturating_add(reach - 1));
+        let visible = |range: &Range<usize>| range.start < window.end && range.end > window.start;
+        let mut spans = Vec::new();
+        for (matcher, color) in &self.rules {
+            match matcher {
+                Matcher::Pattern(pattern) => {
+                    for (idx, candidate) in bytes.windows(pattern.len()).enumerate() {
+                        let range = start + idx..start + idx + pattern.len();
+                        if candidate == pattern.as_slice() && visible(&range) {
+                            spans.push((range, *color));
+                        }
+                    }
+                }
+                // Серия, обрезанная краем прочитанного участка, все равно длиннее min_len
+                Matcher::Run { byte, min_len } => {
+                    let mut idx = 0;
+                    while idx < bytes.len() {
+                        let len = bytes[idx..].iter().take_while(|&&b| b == *byte).count();
+                        let range = start + idx..start + idx + len;
+                        if len >= *min_len && visible(&range) {
+                            spans.push((range, *color));
+                        }
+                        idx += len.max(1);
+                    }
+                }
+            }
+        }
+        spans
+    }
+}
@@ -0,0 +1,46 @@
+/// Максимальное число запоминаемых переходов
+const JUMP_HISTORY_LIMIT: usize = 100;
+
+/// Список переходов курсора как в vim: назад (Ctrl+O) и вперед
+#[derive(Debug, Default)]
+pub struct JumpHistory {
+    back: Vec<usize>,
+    forward: Vec<usize>,
+}
+
+impl JumpHistory {
+    /// Запоминает позицию, с которой совершается переход
+    pub fn record(&mut self, from: usize) {
+        // Новый переход обрывает ветку "вперед"
+        self.forward.clear();
+
+        if self.back.last() == Some(&from) {
+            return;
+        }
+
+        if self.back.len() == JUMP_HISTORY_LIMIT {
+            self.back.remove(0);
+        }
+        self.back.push(from);
+    }
+
+    pub fn back(&mut self, current: usize) -> Option<usize> {
+        let target = self.back.pop()?;
+        self.forward.push(current);
+        Some(target)
+    }
+
+    pub fn forward(&mut self, current: usize) -> Option<usize> {
+        let target = self.forward.pop()?;
+        self.back.push(current);
+        Some(target)
+    }
+
+    pub fn back_depth(&self) -> usize {
+        self.back.len()
+    }
+
+    pub fn forward_depth(&self) -> usize {
+        self.forward.len()
+    }
+}
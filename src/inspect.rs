@@ -0,0 +1,199 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// Разница между эпохой FILETIME (1601-01-01) и эпохой Unix в секундах
+const FILETIME_UNIX_OFFSET: i64 = 11_644_473_600;
+
+/// Расшифровка байт начиная с курсора; `None`, если байт не хватает или значение невалидно
+pub type Decoder = fn(&[u8]) -> Option<String>;
+
+/// Расшифровки байт под курсором для инспектора: подпись и функция.
+/// Многобайтовые значения читаются в little endian, как их хранят Windows и большинство форматов
+pub const DECODERS: [(&str, Decoder); 12] = [
+    ("unix32", unix32),
+    ("unix64", unix64),
+    ("filetime", filetime),
+    ("dos", dos_datetime),
+    ("guid", guid),
+    ("uuid", uuid),
+    ("ipv4", ipv4),
+    ("ipv6", ipv6),
+    ("uleb128", uleb128),
+    ("sleb128", sleb128),
+    ("pb int64", protobuf_int64),
+    ("pb sint64", protobuf_sint64),
+];
+
+/// Самая длинная 64-битная varint: 10 байт по 7 бит
+const VARINT_MAX_BYTES: usize = 10;
+
+fn array<const N: usize>(bytes: &[u8]) -> Option<[u8; N]> {
+    bytes.get(..N)?.try_into().ok()
+}
+
+/// Секунды от 1970-01-01 (знаковое 32-битное, как time_t)
+fn unix32(bytes: &[u8]) -> Option<String> {
+    format_utc(i32::from_le_bytes(array(bytes)?) as i64)
+}
+
+fn unix64(bytes: &[u8]) -> Option<String> {
+    format_utc(i64::from_le_bytes(array(bytes)?))
+}
+
+/// Интервалы по 100 нс от 1601-01-01
+fn filetime(bytes: &[u8]) -> Option<String> {
+    let ticks = u64::from_le_bytes(array(bytes)?);
+    format_utc((ticks / 10_000_000) as i64 - FILETIME_UNIX_OFFSET)
+}
+
+/// Время и дата MS-DOS/FAT: два слова, сначала время, затем дата
+fn dos_datetime(bytes: &[u8]) -> Option<String> {
+    let [t0, t1, d0, d1] = array(bytes)?;
+    let time = u16::from_le_bytes([t0, t1]);
+    let date = u16::from_le_bytes([d0, d1]);
+
+    let (year, month, day) = (1980 + (date >> 9), (date >> 5) & 0x0F, date & 0x1F);
+    let (hour, minute, second) = (time >> 11, (time >> 5) & 0x3F, (time & 0x1F) * 2);
+
+    if !(1..=12).contains(&month) || day == 0 || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    Some(format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}", year, month, day, hour, minute, second))
+}
+
+/// GUID в представлении Windows: первые три поля хранятся в little endian
+fn guid(bytes: &[u8]) -> Option<String> {
+    let b: [u8; 16] = array(bytes)?;
+    Some(format!(
+        "{{{:08X}-{:04X}-{:04X}-{:02X}{:02X}-{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}}}",
+        u32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+        u16::from_le_bytes([b[4], b[5]]),
+        u16::from_le_bytes([b[6], b[7]]),
+        b[8],
+        b[9],
+        b[10],
+        b[11],
+        b[12],
+        b[13],
+        b[14],
+        b[15]
+    ))
+}
+
+/// UUID по RFC 4122: байты по порядку
+fn uuid(bytes: &[u8]) -> Option<String> {
+    let b: [u8; 16] = array(bytes)?;
+    let hex: Vec<String> = b.iter().map(|byte| format!("{:02x}", byte)).collect();
+    Some(format!(
+        "{}-{}-{}-{}-{}",
+        hex[..4].concat(),
+        hex[4..6].concat(),
+        hex[6..8].concat(),
+        hex[8..10].concat(),
+        hex[10..].concat()
+    ))
+}
+
+fn ipv4(bytes: &[u8]) -> Option<String> {
+    Some(Ipv4Addr::from(array::<4>(bytes)?).to_string())
+}
+
+fn ipv6(bytes: &[u8]) -> Option<String> {
+    Some(Ipv6Addr::from(array::<16>(bytes)?).to_string())
+}
+
+/// Беззнаковое LEB128 (оно же varint protobuf) и число прочитанных байт
+fn read_uleb128(bytes: &[u8]) -> Option<(u64, usize)> {
+    let mut value: u64 = 0;
+
+    for (idx, &byte) in bytes.iter().take(VARINT_MAX_BYTES).enumerate() {
+        let shift = idx as u32 * 7;
+        let bits = (byte & 0x7F) as u64;
+        // В десятом байте помещается только старший бит u64
+        if shift == 63 && bits > 1 {
+            return None;
+        }
+        value |= bits << shift;
+
+        if byte & 0x80 == 0 {
+            return Some((value, idx + 1));
+        }
+    }
+
+    None
+}
+
+fn uleb128(bytes: &[u8]) -> Option<String> {
+    let (value, len) = read_uleb128(bytes)?;
+    Some(with_length(value, len))
+}
+
+/// Знаковое LEB128: знак берется из бита 0x40 последнего байта
+fn sleb128(bytes: &[u8]) -> Option<String> {
+    let mut value: i64 = 0;
+
+    for (idx, &byte) in bytes.iter().take(VARINT_MAX_BYTES).enumerate() {
+        let shift = idx as u32 * 7;
+        value |= ((byte & 0x7F) as i64) << shift;
+
+        if byte & 0x80 == 0 {
+            if shift + 7 < 64 && byte & 0x40 != 0 {
+                value |= -1i64 << (shift + 7);
+            }
+            return Some(with_length(value, idx + 1));
+        }
+    }
+
+    None
+}
+
+/// int64 в protobuf: дополнительный код, отрицательные числа занимают 10 байт
+fn protobuf_int64(bytes: &[u8]) -> Option<String> {
+    let (value, len) = read_uleb128(bytes)?;
+    Some(with_length(value as i64, len))
+}
+
+/// sint64 в protobuf: zigzag-кодирование, 0 -1 1 -2 ... -> 0 1 2 3 ...
+fn protobuf_sint64(bytes: &[u8]) -> Option<String> {
+    let (value, len) = read_uleb128(bytes)?;
+    let decoded = (value >> 1) as i64 ^ -((value & 1) as i64);
+    Some(with_length(decoded, len))
+}
+
+/// Значение varint вместе с числом занятых байт
+fn with_length(value: impl std::fmt::Display, len: usize) -> String {
+    format!("{} ({} byte{})", value, len, if len == 1 { "" } else { "s" })
+}
+
+/// Дата и время UTC для секунд от эпохи Unix; `None` вне 0001..9999 годов
+fn format_utc(seconds: i64) -> Option<String> {
+    let days = seconds.div_euclid(86_400);
+    let time = seconds.rem_euclid(86_400);
+    let (year, month, day) = civil_from_days(days);
+
+    if !(1..=9999).contains(&year) {
+        return None;
+    }
+    Some(format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    ))
+}
+
+/// Григорианская дата по числу дней от 1970-01-01 (алгоритм Говарда Хиннанта)
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
@@ -0,0 +1,659 @@
+use crate::vim;
+use anyhow::{Result, anyhow, bail};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Клавиша вместе с модификаторами, приведенная к одному виду независимо от терминала
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        // Одни терминалы присылают 'S' с Shift, другие без него, третьи 's' с Shift:
+        // буквы хранятся строчными с явным Shift, у остальных символов Shift уже учтен
+        let (code, modifiers) = match code {
+            KeyCode::Char(c) if c.is_ascii_uppercase() => (
+                KeyCode::Char(c.to_ascii_lowercase()),
+                modifiers | KeyModifiers::SHIFT,
+            ),
+            KeyCode::Char(c) if !c.is_ascii_lowercase() => (code, modifiers - KeyModifiers::SHIFT),
+            KeyCode::BackTab => (code, modifiers - KeyModifiers::SHIFT),
+            _ => (code, modifiers),
+        };
+        Self { code, modifiers }
+    }
+
+    pub fn from_event(key: &KeyEvent) -> Self {
+        Self::new(key.code, key.modifiers)
+    }
+
+    fn plain(self) -> Self {
+        Self::new(self.code, KeyModifiers::NONE)
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) || self.code == KeyCode::BackTab {
+            write!(f, "Shift+")?;
+        }
+
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{}", c.to_ascii_uppercase()),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            KeyCode::BackTab => write!(f, "Tab"),
+            KeyCode::PageUp => write!(f, "PgUp"),
+            KeyCode::PageDown => write!(f, "PgDn"),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+/// Запись вроде `ctrl+shift+s`, `alt+1`, `f3`, `pgdn` или `G`; регистр важен только у
+/// символа без Ctrl и Alt
+impl FromStr for KeyChord {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut rest = s.trim();
+        let mut modifiers = KeyModifiers::NONE;
+
+        // Модификаторы снимаются с начала, поэтому `ctrl++` - это Ctrl и клавиша '+'
+        loop {
+            let lower = rest.to_ascii_lowercase();
+            let (modifier, len) = if lower.starts_with("ctrl+") {
+                (KeyModifiers::CONTROL, 5)
+            } else if lower.starts_with("alt+") {
+                (KeyModifiers::ALT, 4)
+            } else if lower.starts_with("shift+") {
+                (KeyModifiers::SHIFT, 6)
+            } else {
+                break;
+            };
+            modifiers |= modifier;
+            rest = &rest[len..];
+        }
+
+        // С Ctrl и Alt регистр буквы не важен ("Ctrl+S" - это ctrl+s), Shift пишется явно
+        let with_command = modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) if with_command => KeyCode::Char(c.to_ascii_lowercase()),
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match rest.to_ascii_lowercase().as_str() {
+                "space" => KeyCode::Char(' '),
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "enter" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" | "ins" => KeyCode::Insert,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pgup" | "pageup" => KeyCode::PageUp,
+                "pgdn" | "pagedown" => KeyCode::PageDown,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                key => match key.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                    Some(n @ 1..=24) => KeyCode::F(n),
+                    _ => bail!("Unknown key '{}'", s),
+                },
+            },
+        };
+
+        // Shift+Tab приходит от терминала как BackTab
+        let code = match code {
+            KeyCode::Tab if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+            code => code,
+        };
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+/// Раздел справки
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Category {
+    General,
+    File,
+    Navigation,
+    Selection,
+    Search,
+    Editing,
+    Tools,
+    View,
+    Bookmarks,
+}
+
+impl Category {
+    pub const ALL: [Category; 9] = [
+        Category::General,
+        Category::File,
+        Category::Navigation,
+        Category::Selection,
+        Category::Search,
+        Category::Editing,
+        Category::Tools,
+        Category::View,
+        Category::Bookmarks,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Category::General => "General",
+            Category::File => "Files and tabs",
+            Category::Navigation => "Navigation",
+            Category::Selection => "Selection and clipboard",
+            Category::Search => "Search",
+            Category::Editing => "Editing",
+            Category::Tools => "Tools",
+            Category::View => "View",
+            Category::Bookmarks => "Bookmarks and annotations",
+        }
+    }
+}
+
+/// Команды, которые можно назначить на клавишу: вариант, имя в [keys] и описание для справки,
+/// по разделам справки
+macro_rules! actions {
+    ($($category:ident { $($action:ident => $name:literal, $description:literal;)* })*) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum Action {
+            $($($action,)*)*
+        }
+
+        impl FromStr for Action {
+            type Err = anyhow::Error;
+
+            fn from_str(s: &str) -> Result<Self> {
+                Action::ALL
+                    .iter()
+                    .copied()
+                    .find(|action| action.name() == s)
+                    .ok_or_else(|| anyhow!("Unknown command '{}'", s))
+            }
+        }
+
+        impl Action {
+            pub const ALL: &[Action] = &[$($(Action::$action,)*)*];
+
+            /// Имя команды в разделе [keys] конфигурации
+            pub fn name(self) -> &'static str {
+                match self {
+                    $($(Action::$action => $name,)*)*
+                }
+            }
+
+            pub fn category(self) -> Category {
+                match self {
+                    $($(Action::$action => Category::$category,)*)*
+                }
+            }
+
+            pub fn description(self) -> &'static str {
+                match self {
+                    $($(Action::$action => $description,)*)*
+                }
+            }
+
+            /// Команды, которые счетчик vim (`16l`, `4x`) повторяет
+            pub fn repeats(self) -> bool {
+                matches!(
+                    self,
+                    Action::MoveUp
+                        | Action::MoveDown
+                        | Action::MoveLeft
+                        | Action::MoveRight
+                        | Action::PageUp
+                        | Action::PageDown
+                        | Action::NextChange
+                        | Action::PreviousChange
+                        | Action::NextHighEntropy
+                        | Action::PreviousHighEntropy
+                        | Action::SkipRun
+                        | Action::SkipRunBack
+                        | Action::NextData
+                        | Action::SearchNext
+                        | Action::SearchPrevious
+                        | Action::NextSameByte
+                        | Action::PreviousSameByte
+                        | Action::DeleteAtCursor
+                        | Action::DeleteBeforeCursor
+                        | Action::InsertFf
+                        | Action::InsertZero
+                        | Action::Paste
+                        | Action::Undo
+                        | Action::Redo
+                )
+            }
+        }
+    };
+}
+
+actions! {
+    General {
+        Help => "help", "Show this help";
+        Quit => "quit", "Quit (asks about unsaved changes)";
+        QuitWithoutSaving => "quit_without_saving", "Quit without saving";
+        Undo => "undo", "Undo";
+        Redo => "redo", "Redo";
+        NextUndoBranch => "next_undo_branch", "Next redo branch after an undo";
+        PreviousUndoBranch => "previous_undo_branch", "Previous redo branch";
+    }
+    File {
+        Save => "save", "Save";
+        SaveAs => "save_as", "Save as";
+        SaveCopy => "save_copy", "Save a copy";
+        Revert => "revert", "Revert to the saved version";
+        ToggleReadOnly => "toggle_read_only", "Toggle read-only mode (asks before allowing writes)";
+        OpenFile => "open_file", "Open a file in a new tab";
+        ProcessRegions => "process_regions", "Memory regions of the attached process (hexr --pid)";
+        CloseTab => "close_tab", "Close the tab";
+        NextTab => "next_tab", "Next tab";
+        PreviousTab => "previous_tab", "Previous tab";
+        ToggleCompare => "toggle_compare", "Compare with the next tab";
+        NextDifference => "next_difference", "Next difference";
+        PreviousDifference => "previous_difference", "Previous difference";
+        ExportPatch => "export_patch", "Export changes as an IPS/BPS patch";
+    }
+    Navigation {
+        MoveUp => "move_up", "Line up (Shift: extend selection)";
+        MoveDown => "move_down", "Line down (Shift: extend selection)";
+        MoveLeft => "move_left", "Byte left (Shift: extend selection)";
+        MoveRight => "move_right", "Byte right (Shift: extend selection)";
+        PageUp => "page_up", "Page up";
+        PageDown => "page_down", "Page down";
+        LineStart => "line_start", "Start of line";
+        LineEnd => "line_end", "End of line";
+        FileStart => "file_start", "Start of file (vim: Ngg - line N)";
+        FileEnd => "file_end", "End of file (vim: NG - line N)";
+        Goto => "goto", "Go to address";
+        JumpBack => "jump_back", "Jump back";
+        JumpForward => "jump_forward", "Jump forward";
+        NextChange => "next_change", "Next changed bytes";
+        PreviousChange => "previous_change", "Previous changed bytes";
+        NextHighEntropy => "next_high_entropy", "Next high-entropy (compressed/encrypted) region";
+        PreviousHighEntropy => "previous_high_entropy", "Previous high-entropy region";
+        SkipRun => "skip_run", "Skip past the run of identical bytes under cursor";
+        SkipRunBack => "skip_run_back", "Skip back before the run of identical bytes under cursor";
+        NextData => "next_data", "Start of the data after the next 0x00/0xFF padding";
+    }
+    Selection {
+        ToggleVisual => "toggle_visual", "Visual selection";
+        Copy => "copy", "Copy";
+        Cut => "cut", "Cut";
+        Paste => "paste", "Paste";
+        CopyHexToSystem => "copy_hex_to_system", "Copy to system clipboard as hex";
+        CopyTextToSystem => "copy_text_to_system", "Copy to system clipboard as text";
+        CopyAs => "copy_as", "Copy as a C, Rust, Python or Go array, hex or base64";
+        PasteFromSystem => "paste_from_system", "Paste from system clipboard";
+        FillSelection => "fill_selection", "Fill selection with a pattern";
+        WriteSelection => "write_selection", "Write selection to a file";
+    }
+    Search {
+        Search => "search", "Search";
+        FindAll => "find_all", "Find all";
+        SearchNext => "search_next", "Find next";
+        SearchPrevious => "search_previous", "Find previous";
+        NextSameByte => "next_same_byte", "Next byte equal to the one under cursor, past its run";
+        PreviousSameByte => "previous_same_byte", "Previous byte equal to the one under cursor, before its run";
+        FindByteValue => "find_byte_value", "Next occurrence of a byte value";
+        CycleSearchMode => "cycle_search_mode", "Search mode: hex / text / UTF-16";
+        ToggleIgnoreCase => "toggle_ignore_case", "Toggle case-insensitive search";
+        Replace => "replace", "Search and replace";
+    }
+    Editing {
+        ToggleInsertMode => "toggle_insert_mode", "Overwrite / insert typing";
+        InsertHex => "insert_hex", "Insert hex string";
+        InsertText => "insert_text", "Insert text";
+        InsertFf => "insert_ff", "Insert byte 0xFF";
+        InsertZero => "insert_zero", "Insert byte 0x00";
+        DeleteAtCursor => "delete_at_cursor", "Delete byte under cursor";
+        DeleteBeforeCursor => "delete_before_cursor", "Delete byte before cursor";
+        Truncate => "truncate", "Truncate at cursor";
+        Resize => "resize", "Resize file";
+        InsertFile => "insert_file", "Insert a file";
+        OverwriteFromFile => "overwrite_from_file", "Overwrite with a file";
+    }
+    Tools {
+        FixChecksum => "fix_checksum", "Recompute a checksum field";
+        RefixChecksums => "refix_checksums", "Recompute remembered checksums";
+        TransformSelection => "transform_selection", "Transform selection: XOR, add, rotate, swap words";
+        EncodeSelection => "encode_selection", "Encode or decode selection: base64, hex, URL";
+        Decompress => "decompress", "Unpack zlib/gzip/LZ4/zstd at cursor into a new tab";
+        Recompress => "recompress", "Pack an unpacked tab back into its source";
+    }
+    View {
+        ToggleMode => "toggle_mode", "Switch hex / text column";
+        ToggleBitMode => "toggle_bit_mode", "Bit view";
+        ToggleInspector => "toggle_inspector", "Data inspector";
+        ToggleSplit => "toggle_split", "Split window";
+        SwitchPane => "switch_pane", "Switch split half";
+        CycleByteGroup => "cycle_byte_group", "Byte grouping";
+        CycleOffsetRadix => "cycle_offset_radix", "Offset radix";
+        CycleCharset => "cycle_charset", "Text column charset";
+        ToggleByteColors => "toggle_byte_colors", "Color bytes by class";
+        CycleMinimap => "cycle_minimap", "Scrollbar minimap";
+        ToggleSqueeze => "toggle_squeeze", "Collapse runs of identical lines, like xxd";
+        ByteStats => "byte_stats", "Byte statistics of the file or selection";
+        IdentifyData => "identify_data", "Identify the file type at the cursor or selection";
+        FindSignatures => "find_signatures", "List embedded file signatures";
+        Template => "template", "Apply a binary template and show its field tree";
+        Disassembly => "disassembly", "Toggle the disassembly pane at the cursor";
+        DisasmArch => "disasm_arch", "Choose the disassembly architecture";
+        Assemble => "assemble", "Assemble instructions over the bytes at the cursor";
+    }
+    Bookmarks {
+        AddBookmark => "add_bookmark", "Add bookmark";
+        BookmarkPanel => "bookmark_panel", "Bookmark list";
+        Annotate => "annotate", "Annotate selection";
+        RemoveAnnotation => "remove_annotation", "Remove annotation under cursor";
+        ToggleLock => "toggle_lock", "Lock the selection against edits, or unlock the range under cursor";
+        Highlight => "highlight", "Add a highlight rule for a byte pattern or run (clear: remove all)";
+    }
+}
+
+pub(crate) const NONE: KeyModifiers = KeyModifiers::NONE;
+pub(crate) const CTRL: KeyModifiers = KeyModifiers::CONTROL;
+const ALT: KeyModifiers = KeyModifiers::ALT;
+pub(crate) const SHIFT: KeyModifiers = KeyModifiers::SHIFT;
+const CTRL_SHIFT: KeyModifiers = CTRL.union(SHIFT);
+const ALT_SHIFT: KeyModifiers = ALT.union(SHIFT);
+
+/// Назначения по умолчанию
+const DEFAULT_BINDINGS: &[(KeyCode, KeyModifiers, Action)] = &[
+    (KeyCode::F(1), NONE, Action::Help),
+    (KeyCode::Char('q'), CTRL, Action::Quit),
+    (KeyCode::Char('q'), ALT, Action::QuitWithoutSaving),
+    (KeyCode::Char('z'), CTRL, Action::Undo),
+    (KeyCode::Char('y'), CTRL, Action::Redo),
+    (KeyCode::F(9), NONE, Action::NextUndoBranch),
+    (KeyCode::F(9), SHIFT, Action::PreviousUndoBranch),
+    (KeyCode::Char('s'), CTRL, Action::Save),
+    // Alt+S там, где терминал не отличает Ctrl+Shift+S от Ctrl+S
+    (KeyCode::Char('s'), CTRL_SHIFT, Action::SaveAs),
+    (KeyCode::Char('s'), ALT, Action::SaveAs),
+    (KeyCode::Char('s'), ALT_SHIFT, Action::SaveCopy),
+    (KeyCode::Char('r'), ALT, Action::Revert),
+    (KeyCode::Char('r'), ALT_SHIFT, Action::ToggleReadOnly),
+    (KeyCode::Char('e'), CTRL, Action::OpenFile),
+    (KeyCode::Char('g'), ALT_SHIFT, Action::ProcessRegions),
+    (KeyCode::Char('w'), ALT, Action::CloseTab),
+    (KeyCode::Tab, CTRL, Action::NextTab),
+    (KeyCode::Right, ALT, Action::NextTab),
+    (KeyCode::BackTab, CTRL, Action::PreviousTab),
+    (KeyCode::Left, ALT, Action::PreviousTab),
+    (KeyCode::Char('d'), ALT, Action::ToggleCompare),
+    (KeyCode::F(8), NONE, Action::NextDifference),
+    (KeyCode::F(8), SHIFT, Action::PreviousDifference),
+    (KeyCode::Char('x'), ALT, Action::ExportPatch),
+    (KeyCode::Up, NONE, Action::MoveUp),
+    (KeyCode::Down, NONE, Action::MoveDown),
+    (KeyCode::Left, NONE, Action::MoveLeft),
+    (KeyCode::Right, NONE, Action::MoveRight),
+    (KeyCode::PageUp, NONE, Action::PageUp),
+    (KeyCode::PageDown, NONE, Action::PageDown),
+    (KeyCode::Home, NONE, Action::LineStart),
+    (KeyCode::End, NONE, Action::LineEnd),
+    (KeyCode::Home, CTRL, Action::FileStart),
+    (KeyCode::End, CTRL, Action::FileEnd),
+    (KeyCode::Char('g'), CTRL, Action::Goto),
+    (KeyCode::Char('o'), CTRL, Action::JumpBack),
+    (KeyCode::Char('o'), ALT, Action::JumpForward),
+    (KeyCode::F(7), NONE, Action::NextChange),
+    (KeyCode::F(7), SHIFT, Action::PreviousChange),
+    (KeyCode::F(4), NONE, Action::NextHighEntropy),
+    (KeyCode::F(4), SHIFT, Action::PreviousHighEntropy),
+    (KeyCode::Char('>'), ALT, Action::SkipRun),
+    (KeyCode::Char('<'), ALT, Action::SkipRunBack),
+    (KeyCode::Char('n'), ALT_SHIFT, Action::NextData),
+    (KeyCode::Char(' '), CTRL, Action::ToggleVisual),
+    (KeyCode::Char('c'), CTRL, Action::Copy),
+    (KeyCode::Char('x'), CTRL, Action::Cut),
+    (KeyCode::Char('v'), CTRL, Action::Paste),
+    (KeyCode::Char('c'), ALT, Action::CopyHexToSystem),
+    (KeyCode::Char('c'), ALT_SHIFT, Action::CopyTextToSystem),
+    (KeyCode::Char('e'), ALT_SHIFT, Action::CopyAs),
+    (KeyCode::Char('v'), ALT, Action::PasteFromSystem),
+    (KeyCode::Char('f'), ALT, Action::FillSelection),
+    (KeyCode::Char('w'), CTRL, Action::WriteSelection),
+    (KeyCode::Char('f'), CTRL, Action::Search),
+    (KeyCode::Char('a'), ALT, Action::FindAll),
+    (KeyCode::F(3), NONE, Action::SearchNext),
+    (KeyCode::F(3), SHIFT, Action::SearchPrevious),
+    (KeyCode::Char('m'), ALT, Action::CycleSearchMode),
+    (KeyCode::Char('.'), ALT, Action::NextSameByte),
+    (KeyCode::Char(','), ALT, Action::PreviousSameByte),
+    (KeyCode::Char('/'), ALT, Action::FindByteValue),
+    (KeyCode::Char('i'), ALT, Action::ToggleIgnoreCase),
+    (KeyCode::Char('r'), CTRL, Action::Replace),
+    (KeyCode::Insert, NONE, Action::ToggleInsertMode),
+    (KeyCode::Char('i'), CTRL, Action::InsertHex),
+    (KeyCode::Char('t'), CTRL, Action::InsertText),
+    (KeyCode::Insert, CTRL, Action::InsertFf),
+    (KeyCode::Insert, ALT, Action::InsertZero),
+    (KeyCode::Delete, NONE, Action::DeleteAtCursor),
+    (KeyCode::Backspace, NONE, Action::DeleteBeforeCursor),
+    (KeyCode::Char('t'), ALT, Action::Truncate),
+    (KeyCode::Char('e'), ALT, Action::Resize),
+    (KeyCode::Char('l'), ALT, Action::InsertFile),
+    (KeyCode::Char('l'), ALT_SHIFT, Action::OverwriteFromFile),
+    (KeyCode::Char('k'), CTRL, Action::FixChecksum),
+    (KeyCode::F(5), NONE, Action::RefixChecksums),
+    (KeyCode::Char('u'), CTRL, Action::TransformSelection),
+    (KeyCode::Char('d'), CTRL, Action::EncodeSelection),
+    (KeyCode::Char('p'), CTRL, Action::Decompress),
+    (KeyCode::Char('p'), ALT_SHIFT, Action::Recompress),
+    (KeyCode::Tab, NONE, Action::ToggleMode),
+    (KeyCode::Char('j'), ALT, Action::ToggleBitMode),
+    (KeyCode::Char('k'), ALT, Action::ToggleInspector),
+    (KeyCode::Char('p'), ALT, Action::ToggleSplit),
+    (KeyCode::F(6), NONE, Action::SwitchPane),
+    (KeyCode::Char('g'), ALT, Action::CycleByteGroup),
+    (KeyCode::Char('h'), ALT, Action::CycleOffsetRadix),
+    (KeyCode::Char('u'), ALT, Action::CycleCharset),
+    (KeyCode::Char('y'), ALT, Action::ToggleByteColors),
+    (KeyCode::Char('z'), ALT, Action::CycleMinimap),
+    (KeyCode::Char('z'), ALT_SHIFT, Action::ToggleSqueeze),
+    (KeyCode::Char('h'), ALT_SHIFT, Action::ByteStats),
+    (KeyCode::Char('i'), ALT_SHIFT, Action::IdentifyData),
+    (KeyCode::Char('f'), ALT_SHIFT, Action::FindSignatures),
+    (KeyCode::Char('t'), ALT_SHIFT, Action::Template),
+    (KeyCode::Char('d'), ALT_SHIFT, Action::Disassembly),
+    (KeyCode::Char('a'), ALT_SHIFT, Action::DisasmArch),
+    (KeyCode::Char('m'), ALT_SHIFT, Action::Assemble),
+    (KeyCode::Char('b'), CTRL, Action::AddBookmark),
+    (KeyCode::Char('b'), ALT, Action::BookmarkPanel),
+    (KeyCode::Char('n'), CTRL, Action::Annotate),
+    (KeyCode::Char('n'), ALT, Action::RemoveAnnotation),
+    (KeyCode::Char('k'), ALT_SHIFT, Action::ToggleLock),
+    (KeyCode::Char('u'), ALT_SHIFT, Action::Highlight),
+];
+
+/// Клавиши, которые работают только в своем контексте и не переназначаются
+pub const CONTEXT_KEYS: &[(&str, &str)] = &[
+    ("0-9 A-F", "Type a nibble (hex column)"),
+    ("Any character", "Type a byte (text column)"),
+    ("0 / 1 / Space", "Set, clear or flip the bit (bit view)"),
+    ("Alt+1..9", "Jump to bookmark N"),
+    ("n / p, Up / Down", "Move in the match or bookmark list"),
+    ("Enter", "Go to the selected match or bookmark"),
+    ("D / Delete", "Remove the selected bookmark"),
+    ("Ctrl+arrows, Enter", "Pick and edit an inspector value"),
+    (
+        "Up / Down, Tab",
+        "Open prompt: recent files, path completion",
+    ),
+    ("Esc", "Cancel search, half byte, open panel, selection"),
+    (
+        "Mouse",
+        "Click: cursor, Shift+click / drag: select, wheel: scroll",
+    ),
+];
+
+/// Набор назначений: обычный или модальный в духе vim
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyPreset {
+    #[default]
+    Default,
+    Vim,
+}
+
+/// Назначение клавиш командам
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<(KeyChord, Action)>,
+    // Команды нормального режима vim; пусто в обычном наборе
+    normal: Vec<(KeyChord, Action)>,
+    // Команды плагинов по номеру в списке plugin::Plugins::commands
+    plugins: Vec<(KeyChord, usize)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            bindings: chords(DEFAULT_BINDINGS),
+            normal: Vec::new(),
+            plugins: Vec::new(),
+        }
+    }
+}
+
+fn chords(table: &[(KeyCode, KeyModifiers, Action)]) -> Vec<(KeyChord, Action)> {
+    table
+        .iter()
+        .map(|&(code, modifiers, action)| (KeyChord::new(code, modifiers), action))
+        .collect()
+}
+
+impl Keymap {
+    /// Раскладка по умолчанию с назначениями из [keys] поверх нее: `"ctrl+s" = "save"`
+    /// переназначает клавишу, `"ctrl+s" = "none"` снимает назначение. `plugin_commands` -
+    /// имена команд плагинов, их тоже можно назначить: `"alt+shift+z" = "zlib.inflate"`
+    pub fn load(
+        keys: &BTreeMap<String, String>,
+        preset: KeyPreset,
+        plugin_commands: &[&str],
+    ) -> Result<Self> {
+        let mut keymap = Self::default();
+        if preset == KeyPreset::Vim {
+            keymap.normal = chords(vim::NORMAL_BINDINGS);
+        }
+        let mut seen: Vec<(KeyChord, &str)> = Vec::new();
+
+        for (text, command) in keys {
+            let chord = text
+                .parse::<KeyChord>()
+                .map_err(|e| anyhow!("[keys] {}", e))?;
+
+            // Разные записи одной клавиши: "Ctrl+S" и "ctrl+s", "shift+tab" и "backtab"
+            if let Some((_, other)) = seen.iter().find(|(bound, _)| *bound == chord) {
+                bail!(
+                    "[keys] '{}' and '{}' are the same key {}",
+                    other,
+                    text,
+                    chord
+                );
+            }
+            seen.push((chord, text));
+
+            keymap.bindings.retain(|(bound, _)| *bound != chord);
+            if command == "none" {
+                continue;
+            }
+            match plugin_commands.iter().position(|name| name == command) {
+                Some(index) => keymap.plugins.push((chord, index)),
+                None => {
+                    let action = command
+                        .parse::<Action>()
+                        .map_err(|e| anyhow!("[keys] {} for '{}'", e, text))?;
+                    keymap.bindings.push((chord, action));
+                }
+            }
+        }
+
+        Ok(keymap)
+    }
+
+    /// Команда для нажатия. Служебные клавиши (стрелки, Delete, Tab, ...) без своего
+    /// назначения с модификатором работают как без него: Shift со стрелками расширяет выделение
+    pub fn action(&self, key: &KeyEvent) -> Option<Action> {
+        let chord = KeyChord::from_event(key);
+        self.lookup(chord).or_else(|| match chord.code {
+            KeyCode::Char(_) => None,
+            _ => self.lookup(chord.plain()),
+        })
+    }
+
+    fn lookup(&self, chord: KeyChord) -> Option<Action> {
+        find(&self.bindings, chord)
+    }
+
+    /// Команда плагина, назначенная нажатию; их клавиши совпадают точно, с модификаторами
+    pub fn plugin_command(&self, key: &KeyEvent) -> Option<usize> {
+        let chord = KeyChord::from_event(key);
+        find(&self.plugins, chord)
+    }
+
+    pub fn plugin_chords(&self, index: usize) -> impl Iterator<Item = KeyChord> + '_ {
+        self.plugins
+            .iter()
+            .filter(move |&&(_, bound)| bound == index)
+            .map(|&(chord, _)| chord)
+    }
+
+    pub fn is_modal(&self) -> bool {
+        !self.normal.is_empty()
+    }
+
+    /// Команда нормального режима vim
+    pub fn normal_action(&self, chord: KeyChord) -> Option<Action> {
+        find(&self.normal, chord)
+    }
+
+    pub fn normal_bindings(&self) -> &[(KeyChord, Action)] {
+        &self.normal
+    }
+
+    /// Все клавиши, назначенные команде, в порядке назначения
+    pub fn chords(&self, action: Action) -> impl Iterator<Item = KeyChord> + '_ {
+        self.bindings
+            .iter()
+            .filter(move |(_, bound)| *bound == action)
+            .map(|&(chord, _)| chord)
+    }
+
+    /// Команды в порядке первого назначения
+    pub fn actions(&self) -> Vec<Action> {
+        let mut actions: Vec<Action> = Vec::new();
+        for &(_, action) in &self.bindings {
+            if !actions.contains(&action) {
+                actions.push(action);
+            }
+        }
+        actions
+    }
+}
+
+fn find<T: Copy>(bindings: &[(KeyChord, T)], chord: KeyChord) -> Option<T> {
+    bindings
+        .iter()
+        .find(|(bound, _)| *bound == chord)
+        .map(|&(_, action)| action)
+}
@@ -0,0 +1,81 @@
+use crate::sidecar;
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+const LOCKS_FILE: &str = "locks.toml";
+
+/// Защищенный от правок диапазон байт [start, end)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Lock {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Lock {
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+}
+
+/// Замки по возрастанию начала; пересекающиеся диапазоны сливаются в один
+#[derive(Debug, Default)]
+pub struct Locks {
+    items: Vec<Lock>,
+}
+
+impl Locks {
+    pub fn load(file_path: &str) -> Result<Self> {
+        let mut items: Vec<Lock> = sidecar::load(LOCKS_FILE, file_path)?.unwrap_or_default();
+        items.sort_by_key(|lock| lock.start);
+        Ok(Self { items })
+    }
+
+    pub fn save(&self, file_path: &str) -> Result<()> {
+        let items = (!self.items.is_empty()).then(|| self.items.clone());
+        sidecar::store(LOCKS_FILE, file_path, items)
+    }
+
+    pub fn add(&mut self, range: Range<usize>) -> Result<()> {
+        if range.is_empty() {
+            bail!("Cannot lock an empty range");
+        }
+
+        let (mut start, mut end) = (range.start, range.end);
+        self.items.retain(|lock| {
+            let touches = lock.start <= end && start <= lock.end;
+            if touches {
+                start = start.min(lock.start);
+                end = end.max(lock.end);
+            }
+            !touches
+        });
+        let idx = self.items.partition_point(|lock| lock.start < start);
+        self.items.insert(idx, Lock { start, end });
+        Ok(())
+    }
+
+    pub fn at(&self, pos: usize) -> Option<&Lock> {
+        self.items.iter().find(|lock| lock.range().contains(&pos))
+    }
+
+    pub fn remove_at(&mut self, pos: usize) -> Option<Lock> {
+        let idx = self
+            .items
+            .iter()
+            .position(|lock| lock.range().contains(&pos))?;
+        Some(self.items.remove(idx))
+    }
+
+    /// Замок, который нарушит замена `range` на `new_len` байт: пересечение с диапазоном,
+    /// а при изменении длины - любой замок дальше по файлу, ведь его байты сдвинутся
+    pub fn conflict(&self, range: Range<usize>, new_len: usize) -> Option<&Lock> {
+        if range.len() == new_len {
+            self.items
+                .iter()
+                .find(|lock| lock.start < range.end && range.start < lock.end)
+        } else {
+            self.items.iter().find(|lock| lock.end > range.start)
+        }
+    }
+}
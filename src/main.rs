@@ -1,23 +1,61 @@
+mod annotations;
+mod batch;
+mod bookmarks;
+mod charset;
+mod checksums;
+mod clipboard;
 mod config;
+mod diff;
 mod display;
 mod editor;
-mod undo_redo;
+mod export;
+mod help;
+mod highlight;
+mod hexdump;
+mod history;
+mod inspect;
+mod keymap;
+mod locks;
+mod notify;
+mod patch;
+mod pipe;
+mod plugin;
+mod recent;
+mod recovery;
+mod screen;
+mod session;
+mod sidecar;
+mod tabs;
+mod templates;
+mod theme;
 mod utils;
+mod vim;
+mod watch;
 
-use anyhow::Result;
-use clap::Parser;
+use anyhow::{Context, Result, bail};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
 use crossterm::{
     ExecutableCommand,
-    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind,
+        KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+    },
     terminal::{self, ClearType, DisableLineWrap},
 };
-use std::io::stdout;
+use hexr_core::buffer;
+use hexr_core::hexfile::{HexFormat, Image};
+use hexr_core::progress::Silent;
+use keymap::Action;
+use std::io::{IsTerminal, stdout};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// Path to the file to edit
-    file_path: Option<String>,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Paths to the files to edit, each opens in its own tab; '-' reads stdin
+    file_paths: Vec<String>,
 
     /// Create a new file with specified size in bytes
     #[arg(short = 'n', long)]
@@ -31,44 +69,500 @@ struct Args {
     #[arg(short, long)]
     readonly: bool,
 
-    /// Number of bytes per line (default: 16)
-    #[arg(short = 'w', long, default_value = "16")]
-    bytes_per_line: usize,
+    /// Browse the memory of a running process (Linux, needs ptrace permission); opens
+    /// read-only, Alt+Shift+G picks another region from /proc/PID/maps
+    #[arg(long, value_name = "PID", conflicts_with_all = ["file_paths", "new", "script"])]
+    pid: Option<u32>,
+
+    /// Bytes per line: 8, 16, 24, 32 or auto to fit the terminal (default: from config)
+    #[arg(short = 'w', long)]
+    bytes_per_line: Option<config::BytesPerLine>,
 
     /// Configuration file path
     #[arg(short, long)]
     config: Option<String>,
+
+    /// Start with the cursor and view at this offset of the first file (decimal or 0x hex)
+    #[arg(long, value_parser = utils::parse_size)]
+    offset: Option<usize>,
+
+    /// Select LENGTH bytes from --offset, so find, replace and fill work inside that window
+    #[arg(long, requires = "offset", value_parser = utils::parse_size)]
+    length: Option<usize>,
+
+    /// Apply the edits of a script file (as for `hexr edit --script`) to the first file
+    /// before the editor starts; they stay unsaved and undo as one step
+    #[arg(long, value_name = "FILE", value_parser = batch::parse_script_file)]
+    script: Option<batch::Script>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Compare two files side by side, byte for byte
+    Diff {
+        /// File shown on the left
+        first: String,
+
+        /// File shown on the right
+        second: String,
+
+        /// Print the differing ranges instead of opening the editor (exit status 1 if the files differ)
+        #[arg(long)]
+        summary: bool,
+
+        /// Output format of the summary
+        #[arg(long, value_enum, default_value = "text")]
+        format: diff::ReportFormat,
+
+        /// Bytes of each side to print per range (0: all)
+        #[arg(long, default_value = "16")]
+        bytes: usize,
+    },
+
+    /// Print a hexdump of FILE to stdout, in the editor's layout
+    Dump {
+        /// File to dump, '-' for stdin
+        file: String,
+
+        /// First byte to print (decimal or 0x hex)
+        #[arg(short, long, default_value = "0", value_parser = utils::parse_size)]
+        offset: usize,
+
+        /// Bytes to print (default: up to the end of the file)
+        #[arg(short, long, value_parser = utils::parse_size)]
+        length: Option<usize>,
+
+        /// Bytes per line
+        #[arg(short, long, default_value = "16")]
+        width: usize,
+
+        /// Never color the output
+        #[arg(long, conflicts_with = "color")]
+        plain: bool,
+
+        /// Color bytes by class even when stdout is not a terminal
+        #[arg(long)]
+        color: bool,
+    },
+
+    /// Rebuild binary data from a hexdump: hexr dump, xxd or plain hex
+    Undump {
+        /// Hexdump to read (default: stdin)
+        input: Option<String>,
+
+        /// Write the bytes here instead of stdout
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+
+    /// Apply edits to FILE without the editor and save it; edits run in the order given
+    Edit {
+        /// File to edit, '-' for stdin (the result goes to stdout)
+        file: String,
+
+        /// Write bytes at an offset, e.g. 0x1F0:DEADBEEF (at the end: append)
+        #[arg(long, value_name = "OFFSET:HEX", value_parser = batch::parse_write_at)]
+        write_at: Vec<batch::Edit>,
+
+        /// Fill START..END (END excluded) with a repeated pattern, e.g. 0x200..0x300:00
+        #[arg(long, value_name = "START..END:HEX", value_parser = batch::parse_fill)]
+        fill: Vec<batch::Edit>,
+
+        /// Cut the data at SIZE bytes, or pad it with zeros up to SIZE
+        #[arg(long, value_name = "SIZE", value_parser = batch::parse_truncate)]
+        truncate: Vec<batch::Edit>,
+
+        /// Apply the edits listed in a script file, one per line (write-at, fill, truncate)
+        #[arg(long, value_name = "FILE", value_parser = batch::parse_script_file)]
+        script: Vec<batch::Script>,
+
+        /// Write the result here instead of changing FILE
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+
+    /// Convert between raw binary, Intel HEX and Motorola S-record
+    Convert {
+        /// File to read
+        input: String,
+
+        /// File to write
+        output: String,
+
+        /// Format of INPUT: bin, ihex or srec (default: from the extension, otherwise bin)
+        #[arg(long)]
+        from: Option<String>,
+
+        /// Format of OUTPUT: bin, ihex or srec (default: from the extension, otherwise bin)
+        #[arg(long)]
+        to: Option<String>,
+
+        /// Load address of binary INPUT in the written records (decimal or 0x hex)
+        #[arg(long, default_value = "0", value_parser = utils::parse_size)]
+        base: usize,
+    },
+
+    /// Create or apply IPS/BPS patches
+    Patch {
+        #[command(subcommand)]
+        action: PatchCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum PatchCommand {
+    /// Write a patch that turns OLD into NEW; the format follows the extension of OUT (.ips or .bps)
+    Create {
+        old: String,
+        new: String,
+        out: String,
+    },
+
+    /// Apply an IPS or BPS patch to FILE
+    Apply {
+        file: String,
+        patch: String,
+
+        /// Write the result here instead of replacing FILE
+        #[arg(short, long)]
+        output: Option<String>,
+    },
 }
 
 fn main() -> Result<()> {
-    let args = Args::parse();
+    // Порядок правок `hexr edit` есть только в разобранных аргументах
+    let matches = Args::command().get_matches();
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+    // Отчет о различиях печатается без запуска редактора
+    if let Some(Command::Diff {
+        first,
+        second,
+        summary: true,
+        format,
+        bytes,
+    }) = &args.command
+    {
+        // Как у diff(1): 0 - файлы совпадают, 1 - различаются, 2 - ошибка
+        let code = match print_diff_summary(first, second, *format, *bytes) {
+            Ok(differs) => i32::from(differs),
+            Err(e) => {
+                eprintln!("hexr: {:#}", e);
+                2
+            }
+        };
+        std::process::exit(code);
+    }
+
+    if let Some(Command::Undump { input, output }) = &args.command {
+        return run_undump(input.as_deref(), output.as_deref());
+    }
+
+    if let Some(Command::Patch { action }) = &args.command {
+        return run_patch_command(action);
+    }
+
+    if let Some(Command::Convert {
+        input,
+        output,
+        from,
+        to,
+        base,
+    }) = &args.command
+    {
+        return run_convert(input, output, from.as_deref(), to.as_deref(), *base);
+    }
 
     // Загрузка конфигурации
-    let config = config::Config::load();
+    let mut config = config::Config::load();
+    config.editor.bytes_per_line_override = args.bytes_per_line;
+
+    // Ошибка в цветах или теме не мешает запуску
+    let theme = theme::Theme::load(&config.colors).unwrap_or_else(|e| {
+        eprintln!("Warning: {:#}", e);
+        eprintln!("Using default colors.");
+        theme::Theme::default()
+    });
+
+    if let Some(Command::Edit { file, output, .. }) = &args.command {
+        let edits = matches
+            .subcommand_matches("edit")
+            .map(batch::edits_in_order)
+            .unwrap_or_default();
+        return batch::run(file, &edits, output.as_deref(), config);
+    }
+
+    // Дамп печатается без запуска редактора: группы, адреса и кодировка из конфигурации
+    if let Some(Command::Dump {
+        file,
+        offset,
+        length,
+        width,
+        plain,
+        color,
+    }) = &args.command
+    {
+        // По умолчанию цвета только в терминале, в файл или канал идет чистый текст
+        let colored = *color || (!*plain && stdout().is_terminal());
+        let theme = colored.then_some(&theme);
+        return run_dump(file, *offset, *length, *width, &config, theme);
+    }
+
+    // Ошибка в [plugins] тоже: редактор запускается без плагинов
+    let plugins = plugin::Plugins::load(&config.plugins).unwrap_or_else(|e| {
+        eprintln!("Warning: {:#}", e);
+        eprintln!("Plugins are disabled.");
+        config.plugins.clear();
+        plugin::Plugins::default()
+    });
+
+    // Ошибка в [[highlight]] тоже: редактор запускается без подсветки
+    if let Err(e) = highlight::Highlights::load(&config.highlight) {
+        eprintln!("Warning: {:#}", e);
+        eprintln!("Highlight rules are disabled.");
+    }
+
+    // Ошибка в [keys] тоже: остается раскладка по умолчанию
+    let plugin_commands: Vec<&str> = plugins
+        .commands()
+        .iter()
+        .map(|command| command.name.as_str())
+        .collect();
+    let keymap = keymap::Keymap::load(&config.keys, config.editor.keymap, &plugin_commands)
+        .unwrap_or_else(|e| {
+            eprintln!("Warning: {:#}", e);
+            eprintln!("Using default key bindings.");
+            keymap::Keymap::default()
+        });
 
     // Запуск редактора
-    run_editor(args, config)?;
+    run_editor(args, config, theme, keymap, plugins)?;
 
     Ok(())
 }
 
-fn run_editor(args: Args, config: config::Config) -> Result<()> {
+/// Печатает различия двух файлов; true, если они есть
+fn print_diff_summary(
+    first: &str,
+    second: &str,
+    format: diff::ReportFormat,
+    bytes: usize,
+) -> Result<bool> {
+    let a = buffer::Buffer::open(first).with_context(|| format!("Cannot open {}", first))?;
+    let b = buffer::Buffer::open(second).with_context(|| format!("Cannot open {}", second))?;
+
+    let mut out = std::io::BufWriter::new(stdout().lock());
+    let count = diff::write_report(&mut out, (first, second), &*a, &*b, format, bytes)?;
+    std::io::Write::flush(&mut out)?;
+
+    Ok(count > 0)
+}
+
+fn run_dump(
+    file: &str,
+    offset: usize,
+    length: Option<usize>,
+    width: usize,
+    config: &config::Config,
+    theme: Option<&theme::Theme>,
+) -> Result<()> {
+    if width == 0 {
+        bail!("--width must be at least 1");
+    }
+
+    let data = if file == pipe::STDIO_PATH {
+        buffer::Buffer::from(pipe::read_stdin()?)
+    } else {
+        buffer::Buffer::open(file).with_context(|| format!("Cannot open {}", file))?
+    };
+    if offset > data.len() {
+        bail!(
+            "Offset {} is past the end of {} ({} bytes)",
+            offset,
+            file,
+            data.len()
+        );
+    }
+    let end = length
+        .map_or(data.len(), |length| offset.saturating_add(length))
+        .min(data.len());
+
+    let layout = hexdump::Layout {
+        bytes_per_line: width,
+        group: hexdump::byte_group(config.display.byte_group),
+        radix: config.display.offset_radix,
+        charset: config.display.charset,
+    };
+
+    let mut out = std::io::BufWriter::new(stdout().lock());
+    let written = hexdump::write_dump(&mut out, &data[offset..end], offset, &layout, theme)
+        .and_then(|()| Ok(std::io::Write::flush(&mut out)?));
+
+    // `hexr dump file | head` закрывает канал раньше конца: это не ошибка
+    match written {
+        Err(e) if is_broken_pipe(&e) => Ok(()),
+        other => other,
+    }
+}
+
+fn run_undump(input: Option<&str>, output: Option<&str>) -> Result<()> {
+    let text = match input {
+        Some(path) if path != "-" => {
+            std::fs::read_to_string(path).with_context(|| format!("Cannot read {}", path))?
+        }
+        _ => std::io::read_to_string(std::io::stdin()).context("Cannot read stdin")?,
+    };
+    let data = hexdump::parse_dump(&text)?;
+
+    if let Some(path) = output {
+        return utils::write_file_atomic(path, [&data[..]], false);
+    }
+
+    let mut out = stdout().lock();
+    let written = std::io::Write::write_all(&mut out, &data)
+        .and_then(|()| std::io::Write::flush(&mut out))
+        .map_err(anyhow::Error::from);
+    match written {
+        Err(e) if is_broken_pipe(&e) => Ok(()),
+        other => other,
+    }
+}
+
+fn is_broken_pipe(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<std::io::Error>()
+        .is_some_and(|e| e.kind() == std::io::ErrorKind::BrokenPipe)
+}
+
+fn run_patch_command(action: &PatchCommand) -> Result<()> {
+    match action {
+        PatchCommand::Create { old, new, out } => {
+            let format = patch::PatchFormat::from_path(out)?;
+            let old_data =
+                buffer::Buffer::open(old).with_context(|| format!("Cannot open {}", old))?;
+            let new_data =
+                buffer::Buffer::open(new).with_context(|| format!("Cannot open {}", new))?;
+
+            let patch = patch::create(format, &*old_data, &*new_data, &mut Silent)?;
+            utils::write_file_atomic(out, [&patch[..]], false)?;
+            println!("Wrote {} byte patch to {}", patch.len(), out);
+        }
+        PatchCommand::Apply {
+            file,
+            patch: patch_path,
+            output,
+        } => {
+            let data =
+                buffer::Buffer::open(file).with_context(|| format!("Cannot open {}", file))?;
+            let patch =
+                std::fs::read(patch_path).with_context(|| format!("Cannot open {}", patch_path))?;
+
+            let patched = patch::apply(&data, &patch)?;
+            // Отображение исходного файла нужно закрыть до того, как он будет заменен
+            drop(data);
+
+            let target = output.as_deref().unwrap_or(file);
+            utils::write_file_atomic(target, [&patched[..]], false)?;
+            println!("Patched {} ({} bytes)", target, patched.len());
+        }
+    }
+
+    Ok(())
+}
+
+/// Формат для `hexr convert`: указанный явно или по расширению; None - сырые байты
+fn image_format(name: Option<&str>, path: &str) -> Result<Option<HexFormat>> {
+    match name {
+        Some("bin" | "raw") => Ok(None),
+        Some(name) => HexFormat::parse(name).map(Some),
+        None => Ok(HexFormat::from_path(path)),
+    }
+}
+
+fn run_convert(
+    input: &str,
+    output: &str,
+    from: Option<&str>,
+    to: Option<&str>,
+    base: usize,
+) -> Result<()> {
+    let bytes = std::fs::read(input).with_context(|| format!("Cannot read {}", input))?;
+    let (data, image) = match image_format(from, input)? {
+        Some(format) => format
+            .decode(&bytes)
+            .with_context(|| format!("{} is not valid {}", input, format.label()))?,
+        None => (
+            bytes,
+            Image {
+                base: base as u64,
+                ..Image::default()
+            },
+        ),
+    };
+
+    let written = match image_format(to, output)? {
+        Some(format) => format.encode(&data, &image)?,
+        None => data.clone(),
+    };
+    utils::write_file_atomic(output, [&written[..]], false)?;
+    println!(
+        "Wrote {} bytes at 0x{:X}..0x{:X} to {}",
+        data.len(),
+        image.base,
+        image.base + data.len() as u64,
+        output
+    );
+    Ok(())
+}
+
+fn run_editor(
+    args: Args,
+    config: config::Config,
+    theme: theme::Theme,
+    keymap: keymap::Keymap,
+    plugins: plugin::Plugins,
+) -> Result<()> {
+    // `hexr diff a b` открывает оба файла и сразу включает сравнение
+    let (paths, compare) = match args.command {
+        Some(Command::Diff { first, second, .. }) => (vec![first, second], true),
+        _ => (args.file_paths, false),
+    };
+
+    // Данные из канала читаются до того, как интерфейс займет терминал
+    let stdin_data = match paths.first() {
+        Some(path) if path == pipe::STDIO_PATH => Some(pipe::read_stdin()?),
+        _ => None,
+    };
+    if paths.iter().skip(1).any(|path| path == pipe::STDIO_PATH) {
+        bail!("Only the first file can be '-' (stdin)");
+    }
+    let mut piped_stdout = pipe::redirect_stdout_to_tty()?;
+
     // Инициализация терминала
     terminal::enable_raw_mode()?;
     stdout()
         .execute(terminal::EnterAlternateScreen)?
         .execute(terminal::Clear(ClearType::All))?
-        .execute(DisableLineWrap)?;
+        .execute(DisableLineWrap)?
+        .execute(EnableMouseCapture)?;
+
+    let result = (|| -> Result<Result<()>> {
+        let mut paths = paths.into_iter();
 
-    let result = (|| -> Result<()> {
-        // Создание редактора
-        let mut editor = if let Some(size) = args.new {
+        // Создание редактора для первой вкладки
+        let mut editor = if let Some(data) = stdin_data {
+            // `cat blob | hexr -`: сохранение отдает данные в stdout, если он перенаправлен
+            paths.next();
+            editor::HexEditor::from_pipe(data, piped_stdout.is_some(), config.clone())?
+        } else if let Some(pid) = args.pid {
+            editor::HexEditor::attach(pid, config.clone())?
+        } else if let Some(size) = args.new {
             // Создаем новый файл с указанным размером
-            let file_path = args.file_path.unwrap_or_else(|| "untitled".to_string());
+            let file_path = paths.next().unwrap_or_else(|| "untitled".to_string());
             let mut editor = editor::HexEditor::new_with_size(&args.fill, size, config.clone())?;
             editor.file_path = file_path; // Устанавливаем имя файла
             editor
-        } else if let Some(file_path) = args.file_path {
+        } else if let Some(file_path) = paths.next() {
             // Открываем существующий файл
             editor::HexEditor::open(&file_path, args.readonly, config.clone())?
         } else {
@@ -76,140 +570,736 @@ fn run_editor(args: Args, config: config::Config) -> Result<()> {
             editor::HexEditor::new(config.clone())?
         };
 
+        // Сценарий из --script: правки видны в редакторе и еще не сохранены
+        if let Some(script) = &args.script {
+            script.apply(&mut editor).context("Script failed")?;
+            editor.set_message(format!("Script applied: {} edits", script.0.len()));
+        }
+        if let Some(offset) = args.offset {
+            editor.open_at(offset, args.length)?;
+        }
+
+        // Остальные файлы из командной строки открываются в своих вкладках
+        let mut tabs = tabs::Tabs::new(editor, config.clone());
+        for path in paths {
+            tabs.open(&path, args.readonly)?;
+        }
+        tabs.select(0);
+        if compare {
+            tabs.toggle_compare()?;
+        }
+
         // Создание display
         let mut display = display::Display::new()?;
+        display.set_theme(theme);
+        display.set_plugins(plugins);
 
-        // Основной цикл
+        let mut modal = vim::Modal::default();
+
+        // Основной цикл: кадр перерисовывается, только когда что-то изменилось
+        let mut dirty = true;
         loop {
-            display.draw(&editor)?;
-
-            if event::poll(std::time::Duration::from_millis(100))? {
-                if let Event::Key(key) = event::read()? {
-                    // КРИТИЧНО: обрабатываем только события нажатия клавиш
-                    if key.kind == KeyEventKind::Press {
-                        if !handle_input(&mut editor, key)? {
-                            break;
-                        }
+            if dirty || screen::is_invalidated() {
+                // Размер терминала мог измениться; открытые вкладки получают его до отрисовки
+                let viewport = display.resize()?;
+                for editor in tabs.editors_mut() {
+                    editor.set_viewport(viewport);
+                }
+                display.set_key_status(modal.status(&keymap));
+                display.draw(&tabs)?;
+                dirty = false;
+            }
+
+            // Без фоновой работы цикл спит до следующего события
+            let wakeup = tabs
+                .editors()
+                .iter()
+                .filter_map(|editor| editor.next_wakeup())
+                .chain(tabs.active().next_file_check())
+                .min();
+            let ready = match wakeup {
+                Some(timeout) => event::poll(timeout)?,
+                None => true,
+            };
+            if ready {
+                // КРИТИЧНО: обрабатываем только события нажатия клавиш
+                match event::read()? {
+                    Event::Key(key)
+                        if key.kind == KeyEventKind::Press
+                            && !handle_key(&mut tabs, &mut display, &keymap, &mut modal, key)? =>
+                    {
+                        break;
                     }
+                    Event::Mouse(mouse) => handle_mouse(&mut tabs, &mut display, mouse),
+                    // Терминал после смены размера мог переложить строки: кадр выводится целиком
+                    Event::Resize(..) => screen::invalidate(),
+                    _ => {}
+                }
+                dirty = true;
+            }
+
+            // Результаты фоновых поисков, в том числе на неактивных вкладках
+            for editor in tabs.editors_mut() {
+                dirty |= editor.poll_search();
+                dirty |= editor.expire_messages();
+                // Журнал не повод прерывать работу
+                if let Err(e) = editor.write_journal() {
+                    editor.report_error(&e.context("Edit journal not written"));
+                    dirty = true;
                 }
             }
 
+            // Файл мог изменить сборщик или другая программа
+            let editor = tabs.active_mut();
+            if let Err(e) = editor.check_file_changed() {
+                editor.report_error(&e);
+                dirty = true;
+            }
+
             // Обработка auto-save
             if config.editor.auto_save {
-                editor.check_auto_save()?;
+                for editor in tabs.editors_mut() {
+                    // Без файла восстановления правки остаются в редакторе, выходить незачем
+                    match editor.check_auto_save() {
+                        Ok(written) => dirty |= written,
+                        Err(e) => {
+                            editor.report_error(&e.context("Recovery file not written"));
+                            dirty = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        tabs.discard_recovery();
+
+        // Сохраненные данные из канала уходят дальше по конвейеру
+        if let Some(out) = &mut piped_stdout {
+            for editor in tabs.editors() {
+                editor.write_stdout_output(out)?;
             }
         }
 
-        Ok(())
+        // Позиции во вкладках запоминаются до следующего открытия файлов
+        Ok(tabs.save_sessions())
     })();
 
     // Восстановление терминала
     terminal::disable_raw_mode()?;
-    stdout().execute(terminal::LeaveAlternateScreen)?;
+    stdout()
+        .execute(DisableMouseCapture)?
+        .execute(terminal::LeaveAlternateScreen)?;
 
-    result
+    // Несохраненная сессия не повод считать выход неудачным
+    if let Err(e) = result? {
+        eprintln!("Warning: failed to save session: {:#}", e);
+    }
+    Ok(())
 }
 
-fn handle_input(editor: &mut editor::HexEditor, key: KeyEvent) -> Result<bool> {
-    match key {
-        // Выход
-        KeyEvent {
-            code: KeyCode::Char('q'),
-            modifiers: KeyModifiers::CONTROL,
-            ..
-        } => return Ok(false),
+/// Строк за один шаг колеса мыши
+const MOUSE_SCROLL_LINES: isize = 3;
 
-        // Сохранение
-        KeyEvent {
-            code: KeyCode::Char('s'),
-            modifiers: KeyModifiers::CONTROL,
-            ..
-        } => {
-            editor.save()?;
+/// Щелчок ставит курсор (с Shift - продлевает выделение), перетаскивание выделяет,
+/// колесо прокручивает
+fn handle_mouse(tabs: &mut tabs::Tabs, display: &mut display::Display, mouse: MouseEvent) {
+    if display.is_help_open() {
+        match mouse.kind {
+            MouseEventKind::ScrollUp => display.scroll_help(-MOUSE_SCROLL_LINES),
+            MouseEventKind::ScrollDown => display.scroll_help(MOUSE_SCROLL_LINES),
+            _ => {}
         }
+        return;
+    }
 
-        // Undo
-        KeyEvent {
-            code: KeyCode::Char('z'),
-            modifiers: KeyModifiers::CONTROL,
-            ..
-        } => {
-            let _ = editor.undo();
+    let hit = display.hit_test(tabs, mouse.column, mouse.row);
+    let editor = tabs.active_mut();
+
+    match mouse.kind {
+        MouseEventKind::Down(MouseButton::Left) => {
+            if let Some(hit) = hit {
+                editor.click(hit, mouse.modifiers.contains(KeyModifiers::SHIFT));
+            }
         }
+        // Перетаскивание не переходит в другую половину окна
+        MouseEventKind::Drag(MouseButton::Left) => {
+            if let Some(hit) = hit.filter(|hit| hit.focused) {
+                editor.click(hit, true);
+            }
+        }
+        MouseEventKind::ScrollUp => editor.scroll_view(-MOUSE_SCROLL_LINES),
+        MouseEventKind::ScrollDown => editor.scroll_view(MOUSE_SCROLL_LINES),
+        _ => {}
+    }
+}
 
-        // Redo
-        KeyEvent {
-            code: KeyCode::Char('y'),
-            modifiers: KeyModifiers::CONTROL,
-            ..
-        } => {
-            let _ = editor.redo();
+/// Команды уровня вкладок; остальные клавиши обрабатывает активный редактор.
+/// Возвращает false, когда нужно выйти
+fn handle_key(
+    tabs: &mut tabs::Tabs,
+    display: &mut display::Display,
+    keymap: &keymap::Keymap,
+    modal: &mut vim::Modal,
+    key: KeyEvent,
+) -> Result<bool> {
+    // Справка перехватывает все клавиши, пока открыта
+    if display.is_help_open() {
+        let action = keymap.action(&key);
+        let page = display.help_rows() as isize;
+        match key.code {
+            KeyCode::Up => display.scroll_help(-1),
+            KeyCode::Down => display.scroll_help(1),
+            KeyCode::PageUp => display.scroll_help(-page),
+            KeyCode::PageDown => display.scroll_help(page),
+            KeyCode::Home => display.scroll_help(isize::MIN),
+            KeyCode::End => display.scroll_help(isize::MAX),
+            KeyCode::Esc | KeyCode::Char('q') => display.close_help(),
+            _ if action == Some(Action::Help) => display.close_help(),
+            _ => {}
         }
+        return Ok(true);
+    }
 
-        // Навигация
-        KeyEvent {
-            code: KeyCode::Up, ..
-        } => editor.move_cursor_up(),
+    // Команды плагинов назначаются только в [keys] и не зависят от режима vim
+    if let Some(command) = keymap
+        .plugin_command(&key)
+        .and_then(|index| display.plugins().command(index))
+    {
+        let editor = tabs.active_mut();
+        editor.clear_message();
+        if let Err(e) = command.run(editor) {
+            editor.report_error(&e);
+        }
+        return Ok(true);
+    }
 
-        KeyEvent {
-            code: KeyCode::Down,
-            ..
-        } => editor.move_cursor_down(),
+    // Esc отменяет одно состояние за нажатие, раньше смены режима vim; если отменять
+    // нечего, клавиша идет дальше как обычно
+    if key.code == KeyCode::Esc {
+        let editor = tabs.active_mut();
+        editor.clear_message();
+        if editor.cancel() {
+            return Ok(true);
+        }
+    }
 
-        KeyEvent {
-            code: KeyCode::Left,
-            ..
-        } => editor.move_cursor_left(),
+    // Списки совпадений и закладок разбирают n/p, цифры и Enter сами, мимо режимов vim
+    let editor = tabs.active();
+    let resolved = if editor.get_match_list().is_some()
+        || editor.is_bookmark_panel_open()
+        || editor.is_template_panel_open()
+        || editor.is_region_panel_open()
+    {
+        modal.resolve_unmodal(keymap, &key)
+    } else {
+        modal.resolve(keymap, &key)
+    };
+    let (action, count) = match resolved {
+        vim::Resolved::Action(action, count) => (Some(action), count),
+        vim::Resolved::Unbound => (None, None),
+        vim::Resolved::Pending => return Ok(true),
+    };
 
-        KeyEvent {
-            code: KeyCode::Right,
-            ..
-        } => editor.move_cursor_right(),
+    match action {
+        Some(Action::Help) => display.open_help(keymap),
 
-        KeyEvent {
-            code: KeyCode::PageUp,
-            ..
-        } => editor.page_up(),
+        // Выход с подтверждением для вкладок с несохраненными изменениями
+        Some(Action::Quit) => {
+            tabs.active_mut().clear_message();
+            return match confirm_quit_all(tabs, display) {
+                Ok(quit) => Ok(!quit),
+                Err(e) => {
+                    tabs.active_mut().report_error(&e);
+                    Ok(true)
+                }
+            };
+        }
 
-        KeyEvent {
-            code: KeyCode::PageDown,
-            ..
-        } => editor.page_down(),
+        Some(Action::QuitWithoutSaving) => return Ok(false),
 
-        KeyEvent {
-            code: KeyCode::Home,
-            ..
-        } => editor.move_to_line_start(),
+        Some(Action::NextTab) => tabs.next(),
+        Some(Action::PreviousTab) => tabs.previous(),
 
-        KeyEvent {
-            code: KeyCode::End, ..
-        } => editor.move_to_line_end(),
+        // Сравнение с соседней вкладкой и переход по различиям
+        Some(Action::ToggleCompare) => {
+            tabs.active_mut().clear_message();
+            if let Err(e) = tabs.toggle_compare() {
+                tabs.active_mut().report_error(&e);
+            }
+        }
+
+        Some(action @ (Action::NextDifference | Action::PreviousDifference)) => {
+            tabs.active_mut().clear_message();
+            if let Err(e) = tabs.jump_to_difference(action == Action::NextDifference) {
+                tabs.active_mut().report_error(&e);
+            }
+        }
+
+        // Открыть файл в новой вкладке
+        Some(Action::OpenFile) => {
+            tabs.active_mut().clear_message();
+            if let Err(e) = open_from_input(tabs) {
+                tabs.active_mut().report_error(&e);
+            }
+        }
+
+        // Сжатый поток распаковывается в новую вкладку и сжимается обратно в источник
+        Some(Action::Decompress) => {
+            tabs.active_mut().clear_message();
+            if let Err(e) = tabs.decompress() {
+                tabs.active_mut().report_error(&e);
+            }
+        }
+
+        Some(Action::Recompress) => {
+            tabs.active_mut().clear_message();
+            if let Err(e) = tabs.recompress() {
+                tabs.active_mut().report_error(&e);
+            }
+        }
+
+        // Закрыть вкладку; закрытие последней - это выход
+        Some(Action::CloseTab) => {
+            tabs.active_mut().clear_message();
+            match tabs.active_mut().confirm_quit() {
+                Ok(true) => {
+                    let saved = tabs.active().save_session();
+                    // Последняя вкладка запомнится при выходе вместе с остальными
+                    if !tabs.close_active() {
+                        return Ok(false);
+                    }
+                    if let Err(e) = saved.context("Failed to save session") {
+                        tabs.active_mut().report_error(&e);
+                    }
+                }
+                Ok(false) => {}
+                Err(e) => tabs.active_mut().report_error(&e),
+            }
+        }
+
+        // Ошибки команд показываются в строке состояния, а не завершают редактор
+        _ => {
+            let editor = tabs.active_mut();
+            if let Err(e) = handle_input(editor, key, action, count) {
+                editor.report_error(&e);
+            }
+        }
+    }
+
+    Ok(true)
+}
+
+/// Для каждой вкладки с изменениями спрашивает, что с ними делать; false - выход отменен
+fn confirm_quit_all(tabs: &mut tabs::Tabs, display: &mut display::Display) -> Result<bool> {
+    for index in 0..tabs.len() {
+        if !tabs.editors()[index].is_modified() {
+            continue;
+        }
+
+        tabs.select(index);
+        display.draw(tabs)?;
+        if !tabs.active_mut().confirm_quit()? {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+fn open_from_input(tabs: &mut tabs::Tabs) -> Result<()> {
+    let path = utils::get_path_input("Open file: ", &recent::load())?;
+    let path = path.trim();
+
+    if path.is_empty() {
+        return Ok(());
+    }
+
+    tabs.open(path, false)
+}
+
+fn handle_input(
+    editor: &mut editor::HexEditor,
+    key: KeyEvent,
+    action: Option<Action>,
+    count: Option<usize>,
+) -> Result<()> {
+    // Сообщение в статус-баре живет до следующего нажатия
+    editor.clear_message();
+
+    // Панель совпадений перехватывает клавиши навигации по списку
+    if editor.get_match_list().is_some() {
+        match key.code {
+            KeyCode::Char('n') | KeyCode::Down | KeyCode::PageDown => {
+                editor.match_list_next();
+                return Ok(());
+            }
+            KeyCode::Char('p') | KeyCode::Up | KeyCode::PageUp => {
+                editor.match_list_previous();
+                return Ok(());
+            }
+            KeyCode::Enter => {
+                editor.close_match_list();
+                return Ok(());
+            }
+            _ => {}
+        }
+    }
+
+    // Панель закладок: навигация, переход по номеру, удаление
+    if editor.is_bookmark_panel_open() {
+        match key.code {
+            KeyCode::Char('n') | KeyCode::Down => {
+                editor.bookmark_next();
+                return Ok(());
+            }
+            KeyCode::Char('p') | KeyCode::Up => {
+                editor.bookmark_previous();
+                return Ok(());
+            }
+            KeyCode::Char(c @ '1'..='9') => {
+                editor.jump_to_bookmark(c as usize - '0' as usize);
+                editor.close_bookmark_panel();
+                return Ok(());
+            }
+            KeyCode::Enter => {
+                editor.jump_to_selected_bookmark();
+                editor.close_bookmark_panel();
+                return Ok(());
+            }
+            KeyCode::Delete | KeyCode::Char('d') => {
+                editor.remove_selected_bookmark()?;
+                return Ok(());
+            }
+            _ => {}
+        }
+    }
+
+    // Дерево полей шаблона: перемещение, сворачивание, выделение поля
+    if editor.is_template_panel_open() {
+        match key.code {
+            KeyCode::Char('n') | KeyCode::Down => {
+                editor.template_next();
+                return Ok(());
+            }
+            KeyCode::Char('p') | KeyCode::Up => {
+                editor.template_previous();
+                return Ok(());
+            }
+            KeyCode::Right | KeyCode::Char('+') => {
+                editor.template_expand();
+                return Ok(());
+            }
+            KeyCode::Left | KeyCode::Char('-') => {
+                editor.template_collapse();
+                return Ok(());
+            }
+            KeyCode::Enter => {
+                editor.jump_to_template_field(true);
+                return Ok(());
+            }
+            KeyCode::Char('r') => {
+                editor.reapply_template();
+                return Ok(());
+            }
+            KeyCode::Char('a') => {
+                editor.apply_template_from_input()?;
+                return Ok(());
+            }
+            KeyCode::Char('/') => {
+                editor.find_template_field_from_input()?;
+                return Ok(());
+            }
+            KeyCode::Delete | KeyCode::Char('d') => {
+                editor.remove_template();
+                return Ok(());
+            }
+            _ => {}
+        }
+    }
+
+    // Панель регионов процесса: выбор и открытие региона
+    if editor.is_region_panel_open() {
+        match key.code {
+            KeyCode::Char('n') | KeyCode::Down => {
+                editor.region_next();
+                return Ok(());
+            }
+            KeyCode::Char('p') | KeyCode::Up => {
+                editor.region_previous();
+                return Ok(());
+            }
+            KeyCode::Enter => {
+                editor.open_selected_region()?;
+                return Ok(());
+            }
+            _ => {}
+        }
+    }
+
+    // Инспектор данных: выбор ячейки Ctrl+стрелками, Enter - ввод значения
+    if editor.is_inspector_open() {
+        let movement = match key.code {
+            _ if key.modifiers != KeyModifiers::CONTROL => None,
+            KeyCode::Up => Some((-1, 0)),
+            KeyCode::Down => Some((1, 0)),
+            KeyCode::Left => Some((0, -1)),
+            KeyCode::Right => Some((0, 1)),
+            _ => None,
+        };
+        if let Some((rows, columns)) = movement {
+            editor.move_inspector_cell(rows, columns);
+            return Ok(());
+        }
+
+        if key.code == KeyCode::Enter {
+            editor.edit_inspector_value()?;
+            return Ok(());
+        }
+    }
+
+    let Some(action) = action else {
+        return handle_typing(editor, key);
+    };
+
+    // Shift при навигации расширяет выделение; у символов (G в vim) Shift - часть клавиши
+    let extend =
+        key.modifiers.contains(KeyModifiers::SHIFT) && !matches!(key.code, KeyCode::Char(_));
+
+    // Счетчик vim у gg и G - номер строки, у остальных команд - число повторов
+    match (action, count) {
+        (Action::FileStart | Action::FileEnd, Some(line)) => {
+            editor.move_cursor(|editor| editor.goto_line(line.saturating_sub(1)), extend);
+        }
+        // Повторы (4x) отменяются одним шагом, как одна команда
+        (action, count) if action.repeats() => editor.undo_group(|editor| {
+            for _ in 0..count.unwrap_or(1) {
+                run_action(editor, action, extend)?;
+            }
+            Ok(())
+        })?,
+        (action, _) => run_action(editor, action, extend)?,
+    }
+
+    Ok(())
+}
+
+fn run_action(editor: &mut editor::HexEditor, action: Action, extend: bool) -> Result<()> {
+    match action {
+        Action::Save => editor.save_or_prompt()?,
+        Action::SaveAs => editor.save_as_from_input()?,
+        Action::SaveCopy => editor.save_copy_from_input()?,
+        Action::ToggleReadOnly => editor.toggle_readonly()?,
+        Action::ProcessRegions => editor.toggle_region_panel()?,
+        Action::Revert => editor.revert_to_saved()?,
+
+        Action::Undo => editor.undo()?,
+        Action::Redo => editor.redo()?,
+        Action::NextUndoBranch => editor.switch_undo_branch(true),
+        Action::PreviousUndoBranch => editor.switch_undo_branch(false),
+
+        // Навигация (с Shift - расширение выделения)
+        Action::MoveUp => editor.move_cursor(editor::HexEditor::move_cursor_up, extend),
+        Action::MoveDown => editor.move_cursor(editor::HexEditor::move_cursor_down, extend),
+        Action::MoveLeft => editor.move_cursor(editor::HexEditor::move_cursor_left, extend),
+        Action::MoveRight => editor.move_cursor(editor::HexEditor::move_cursor_right, extend),
+        Action::PageUp => editor.move_cursor(editor::HexEditor::page_up, extend),
+        Action::PageDown => editor.move_cursor(editor::HexEditor::page_down, extend),
+        Action::LineStart => editor.move_cursor(editor::HexEditor::move_to_line_start, extend),
+        Action::LineEnd => editor.move_cursor(editor::HexEditor::move_to_line_end, extend),
+        Action::FileStart => editor.move_cursor(|editor| editor.jump_to(0), extend),
+        Action::FileEnd => {
+            let last = editor.data_len().saturating_sub(1);
+            editor.move_cursor(|editor| editor.jump_to(last), extend);
+        }
 
         // Переключение между hex и ASCII
-        KeyEvent {
-            code: KeyCode::Tab, ..
-        } => editor.toggle_mode(),
+        Action::ToggleMode => editor.toggle_mode(),
+
+        // Поиск, в том числе с панелью результатов, и повтор вперед / назад
+        Action::Search => editor.start_search()?,
+        Action::FindAll => editor.find_all_from_input()?,
+        Action::SearchNext => editor.search_next(),
+        Action::SearchPrevious => editor.search_previous(),
+        Action::NextSameByte => editor.find_same_byte(true),
+        Action::PreviousSameByte => editor.find_same_byte(false),
+        Action::FindByteValue => editor.find_byte_value_from_input()?,
+
+        // Режим поиска: hex / текст / UTF-16, и учет регистра
+        Action::CycleSearchMode => editor.cycle_search_mode(),
+        Action::ToggleIgnoreCase => editor.toggle_search_ignore_case(),
+
+        // Visual режим выделения
+        Action::ToggleVisual => editor.toggle_visual_mode(),
+
+        // Буфер обмена
+        Action::Copy => editor.copy_selection(),
+        Action::Cut => editor.cut_selection()?,
+        Action::Paste => editor.paste()?,
+
+        // Системный буфер обмена: как hex, как текст, вставка
+        Action::CopyHexToSystem => editor.copy_selection_to_system(false),
+        Action::CopyTextToSystem => editor.copy_selection_to_system(true),
+        Action::CopyAs => editor.copy_selection_as()?,
+        Action::PasteFromSystem => editor.paste_from_system()?,
+
+        // Заполнение выделения шаблоном и запись выделения в файл
+        Action::FillSelection => editor.fill_selection_from_input()?,
+        Action::WriteSelection => editor.write_selection_from_input()?,
+
+        // Поиск и замена
+        Action::Replace => editor.replace_from_input()?,
+
+        // Переход к адресу
+        Action::Goto => editor.goto_address()?,
+
+        // История переходов
+        Action::JumpBack => editor.jump_back(),
+        Action::JumpForward => editor.jump_forward(),
+
+        // Экспорт изменений в IPS/BPS-патч
+        Action::ExportPatch => editor.export_patch_from_input()?,
+
+        // Инспектор данных, статистика байт, сигнатуры файлов, шаблоны и битовый режим
+        Action::ToggleInspector => editor.toggle_inspector(),
+        Action::ByteStats => editor.toggle_stats_panel()?,
+        Action::IdentifyData => editor.identify_at_cursor(),
+        Action::FindSignatures => editor.find_file_signatures(),
+        Action::Template => editor.toggle_template_panel()?,
+        Action::Disassembly => editor.toggle_disasm()?,
+        Action::DisasmArch => editor.choose_disasm_arch()?,
+        Action::Assemble => editor.assemble_at_cursor()?,
+        Action::ToggleBitMode => editor.toggle_bit_mode(),
+
+        // Настройки вида действуют до выхода; постоянные задаются в конфигурации
+        Action::CycleByteGroup => {
+            editor.cycle_byte_group();
+            editor.set_message(format!("Byte group: {}", editor.byte_group()));
+        }
+
+        Action::CycleOffsetRadix => {
+            editor.cycle_offset_radix();
+            editor.set_message(format!("Offsets: {}", editor.offset_radix().label()));
+        }
+
+        Action::ToggleByteColors => editor.toggle_byte_colors(),
+
+        Action::ToggleSqueeze => {
+            editor.toggle_squeeze();
+            let state = if editor.squeeze() { "on" } else { "off" };
+            editor.set_message(format!("Squeeze identical lines: {}", state));
+        }
+
+        Action::CycleMinimap => {
+            editor.cycle_minimap();
+            editor.set_message(format!("Minimap: {}", editor.minimap().label()));
+        }
+
+        Action::CycleCharset => {
+            editor.cycle_charset();
+            editor.set_message(format!("Text column: {}", editor.charset().label()));
+        }
 
-        // Поиск
+        // Разделение окна и переключение между половинами
+        Action::ToggleSplit => editor.toggle_split(),
+        Action::SwitchPane => editor.switch_pane(),
+
+        // Измененные байты: следующий и предыдущий участок
+        Action::NextChange => editor.jump_to_modified(true),
+        Action::PreviousChange => editor.jump_to_modified(false),
+
+        // Сжатые и зашифрованные области
+        Action::NextHighEntropy => editor.jump_to_high_entropy(true)?,
+        Action::SkipRun => editor.skip_run(true),
+        Action::SkipRunBack => editor.skip_run(false),
+        Action::NextData => editor.jump_to_next_data(),
+        Action::PreviousHighEntropy => editor.jump_to_high_entropy(false)?,
+
+        // Закладки и аннотации
+        Action::AddBookmark => editor.add_bookmark_from_input()?,
+        Action::BookmarkPanel => editor.toggle_bookmark_panel(),
+        Action::Annotate => editor.annotate_from_input()?,
+        Action::RemoveAnnotation => editor.remove_annotation_at_cursor()?,
+        Action::ToggleLock => editor.toggle_lock()?,
+        Action::Highlight => editor.add_highlight_from_input()?,
+
+        // Обрезать по курсору и изменить размер
+        Action::Truncate => editor.truncate_at_cursor()?,
+        Action::Resize => editor.resize_from_input()?,
+
+        // Поля контрольных сумм: пересчет с вводом и по запомненным рецептам
+        Action::FixChecksum => editor.fix_checksum_from_input()?,
+        Action::RefixChecksums => editor.refix_checksums()?,
+
+        // Побайтовые преобразования и кодирование выделения
+        Action::TransformSelection => editor.transform_selection_from_input()?,
+        Action::EncodeSelection => editor.encode_selection_from_input()?,
+
+        // Вставить файл или записать файл поверх данных
+        Action::InsertFile => editor.insert_file_from_input()?,
+        Action::OverwriteFromFile => editor.overwrite_from_file_input()?,
+
+        // Вставка hex и ASCII строки
+        Action::InsertHex => editor.insert_from_hex_input()?,
+        Action::InsertText => editor.insert_from_ascii_input()?,
+
+        // Удаление байта под курсором и перед ним
+        Action::DeleteAtCursor => editor.delete_at_cursor()?,
+        Action::DeleteBeforeCursor => editor.delete_before_cursor()?,
+
+        // Вставка байт 0xFF и 0x00
+        Action::InsertFf => editor.insert_byte(0xFF)?,
+        Action::InsertZero => editor.insert_byte(0x00)?,
+
+        // Переключение режима ввода OVR/INS
+        Action::ToggleInsertMode => {
+            editor
+                .toggle_insert_mode()
+                .context("Failed to save config")?;
+        }
+
+        // Команды уровня вкладок обрабатывает handle_key
+        Action::Help
+        | Action::Quit
+        | Action::QuitWithoutSaving
+        | Action::OpenFile
+        | Action::Decompress
+        | Action::Recompress
+        | Action::CloseTab
+        | Action::NextTab
+        | Action::PreviousTab
+        | Action::ToggleCompare
+        | Action::NextDifference
+        | Action::PreviousDifference => {}
+    }
+
+    Ok(())
+}
+
+/// Клавиши без назначения: ввод данных в текущей колонке и переход по закладке
+fn handle_typing(editor: &mut editor::HexEditor, key: KeyEvent) -> Result<()> {
+    match key {
+        // Переход к закладке по номеру (Alt+1..9)
         KeyEvent {
-            code: KeyCode::Char('f'),
-            modifiers: KeyModifiers::CONTROL,
+            code: KeyCode::Char(c @ '1'..='9'),
+            modifiers: KeyModifiers::ALT,
             ..
-        } => editor.start_search()?,
+        } => editor.jump_to_bookmark(c as usize - '0' as usize),
 
-        // Переход к адресу
+        // Установка и переключение битов (в битовом режиме)
         KeyEvent {
-            code: KeyCode::Char('g'),
-            modifiers: KeyModifiers::CONTROL,
+            code: KeyCode::Char(c @ ('0' | '1' | ' ')),
+            modifiers: KeyModifiers::NONE,
             ..
-        } => editor.goto_address()?,
+        } if editor.is_bit_mode() => {
+            editor.input_bit(c)?;
+        }
 
         // Ввод hex значения
         KeyEvent {
             code: KeyCode::Char(c),
             modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
             ..
-        } if c.is_ascii_hexdigit() => {
+        } if c.is_ascii_hexdigit() && !editor.is_ascii_mode() => {
             editor.input_hex_char(c)?;
         }
 
@@ -222,39 +1312,8 @@ fn handle_input(editor: &mut editor::HexEditor, key: KeyEvent) -> Result<bool> {
             editor.input_ascii_char(c)?;
         }
 
-        // Вставка hex строки (Ctrl+I)
-        KeyEvent {
-            code: KeyCode::Char('i'),
-            modifiers: KeyModifiers::CONTROL,
-            ..
-        } => editor.insert_from_hex_input()?,
-
-        // Вставка ASCII строки (Ctrl+V)
-        KeyEvent {
-            code: KeyCode::Char('v'),
-            modifiers: KeyModifiers::CONTROL,
-            ..
-        } => editor.insert_from_ascii_input()?,
-
-        // Вставка байта 0xFF (Ctrl+Insert)
-        KeyEvent {
-            code: KeyCode::Insert,
-            modifiers: KeyModifiers::CONTROL,
-            ..
-        } => {
-            editor.insert_byte(0xFF)?;
-        }
-
-        // Вставка байта 0x00 (Insert key)
-        KeyEvent {
-            code: KeyCode::Insert,
-            ..
-        } => {
-            editor.insert_byte(0x00)?;
-        }
-
         _ => {}
     }
 
-    Ok(true)
+    Ok(())
 }
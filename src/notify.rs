@@ -0,0 +1,76 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    /// Сколько сообщение остается в строке состояния
+    fn lifetime(self) -> Duration {
+        match self {
+            Severity::Info => Duration::from_secs(4),
+            Severity::Warning => Duration::from_secs(6),
+            Severity::Error => Duration::from_secs(10),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub text: String,
+    pub severity: Severity,
+}
+
+/// Очередь сообщений строки состояния: показывается первое, следующее - когда оно истечет
+#[derive(Debug, Default)]
+pub struct Notifications {
+    queue: VecDeque<Notification>,
+    // Когда первое сообщение очереди появилось на экране
+    shown_at: Option<Instant>,
+}
+
+impl Notifications {
+    pub fn push(&mut self, text: impl Into<String>, severity: Severity) {
+        if self.queue.is_empty() {
+            self.shown_at = Some(Instant::now());
+        }
+        self.queue.push_back(Notification {
+            text: text.into(),
+            severity,
+        });
+    }
+
+    pub fn current(&self) -> Option<&Notification> {
+        self.queue.front()
+    }
+
+    /// Убирает истекшие сообщения; следующее начинает отсчет с момента показа.
+    /// true, если сообщение сменилось
+    pub fn expire(&mut self, now: Instant) -> bool {
+        let mut expired = false;
+        while let (Some(front), Some(shown_at)) = (self.queue.front(), self.shown_at) {
+            if now.duration_since(shown_at) < front.severity.lifetime() {
+                break;
+            }
+            self.queue.pop_front();
+            self.shown_at = (!self.queue.is_empty()).then_some(now);
+            expired = true;
+        }
+        expired
+    }
+
+    /// Через сколько истечет показанное сообщение
+    pub fn expires_in(&self, now: Instant) -> Option<Duration> {
+        let lifetime = self.queue.front()?.severity.lifetime();
+        Some(lifetime.saturating_sub(now.duration_since(self.shown_at?)))
+    }
+
+    pub fn clear(&mut self) {
+        self.queue.clear();
+        self.shown_at = None;
+    }
+}
@@ -0,0 +1,342 @@
+use crate::diff;
+use anyhow::{Result, anyhow, bail};
+use hexr_core::checksum::{Algorithm, crc32};
+use hexr_core::progress::Progress;
+use hexr_core::search::SearchSource;
+use std::ops::Range;
+use std::path::Path;
+
+const IPS_MAGIC: &[u8] = b"PATCH";
+const IPS_EOF: &[u8] = b"EOF";
+/// Смещение в IPS - 3 байта, поэтому патч не может затрагивать данные дальше 16 MiB
+const IPS_MAX_OFFSET: usize = 0xFF_FFFF;
+const IPS_MAX_RECORD: usize = 0xFFFF;
+
+const BPS_MAGIC: &[u8] = b"BPS1";
+const BPS_SOURCE_READ: u64 = 0;
+const BPS_TARGET_READ: u64 = 1;
+const BPS_SOURCE_COPY: u64 = 2;
+const BPS_TARGET_COPY: u64 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PatchFormat {
+    Ips,
+    Bps,
+}
+
+impl PatchFormat {
+    /// Формат по расширению файла патча
+    pub fn from_path(path: &str) -> Result<Self> {
+        let extension = Path::new(path)
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+
+        match extension.as_deref() {
+            Some("ips") => Ok(PatchFormat::Ips),
+            Some("bps") => Ok(PatchFormat::Bps),
+            _ => bail!("Unknown patch format for {} (use .ips or .bps)", path),
+        }
+    }
+}
+
+/// Патч, превращающий `source` в `target`
+pub fn create<A, B>(format: PatchFormat, source: &A, target: &B, progress: &mut dyn Progress) -> Result<Vec<u8>>
+where
+    A: SearchSource + ?Sized,
+    B: SearchSource + ?Sized,
+{
+    match format {
+        PatchFormat::Ips => create_ips(source, target, progress),
+        PatchFormat::Bps => create_bps(source, target, progress),
+    }
+}
+
+/// Применяет патч; формат определяется по сигнатуре
+pub fn apply(source: &[u8], patch: &[u8]) -> Result<Vec<u8>> {
+    if patch.starts_with(IPS_MAGIC) {
+        apply_ips(source, patch)
+    } else if patch.starts_with(BPS_MAGIC) {
+        apply_bps(source, patch)
+    } else {
+        bail!("Not an IPS or BPS patch")
+    }
+}
+
+/// Участки различий в пределах `target`: байты за его концом патчем не описываются
+fn target_runs<'a, A, B>(source: &'a A, target: &'a B) -> impl Iterator<Item = Range<usize>> + 'a
+where
+    A: SearchSource + ?Sized,
+    B: SearchSource + ?Sized,
+{
+    let target_len = target.len();
+    diff::differing_runs(source, target)
+        .map(move |run| run.start..run.end.min(target_len))
+        .filter(|run| !run.is_empty())
+}
+
+fn create_ips<A, B>(source: &A, target: &B, progress: &mut dyn Progress) -> Result<Vec<u8>>
+where
+    A: SearchSource + ?Sized,
+    B: SearchSource + ?Sized,
+{
+    let mut patch = IPS_MAGIC.to_vec();
+
+    for run in target_runs(source, target) {
+        progress.update(run.start, target.len())?;
+        let mut start = run.start;
+        while start < run.end {
+            // Запись со смещением 0x454F46 читалась бы как "EOF": начинаем ее на байт раньше
+            if start == 0x45_4F46 {
+                start -= 1;
+            }
+            if start > IPS_MAX_OFFSET {
+                bail!("IPS patches cannot change data past 16 MiB, use .bps instead");
+            }
+
+            let end = run.end.min(start + IPS_MAX_RECORD);
+
+            let offset = start as u32;
+            patch.extend_from_slice(&offset.to_be_bytes()[1..]);
+            patch.extend_from_slice(&((end - start) as u16).to_be_bytes());
+            patch.extend_from_slice(&target.range(start..end));
+            start = end;
+        }
+    }
+
+    patch.extend_from_slice(IPS_EOF);
+    // Расширение формата: длина результата после EOF, если файл укоротился
+    if target.len() < source.len() {
+        if target.len() > IPS_MAX_OFFSET {
+            bail!("IPS patches cannot truncate files larger than 16 MiB, use .bps instead");
+        }
+        patch.extend_from_slice(&(target.len() as u32).to_be_bytes()[1..]);
+    }
+
+    Ok(patch)
+}
+
+fn apply_ips(source: &[u8], patch: &[u8]) -> Result<Vec<u8>> {
+    let mut reader = PatchReader::new(&patch[IPS_MAGIC.len()..]);
+    let mut output = source.to_vec();
+
+    loop {
+        let header = reader.take(3)?;
+        if header == IPS_EOF {
+            break;
+        }
+        let offset = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+        let size = u16::from_be_bytes(reader.take(2)?.try_into()?) as usize;
+
+        // Нулевой размер - RLE-запись: количество и повторяемый байт
+        let (len, bytes) = if size == 0 {
+            let count = u16::from_be_bytes(reader.take(2)?.try_into()?) as usize;
+            let value = reader.take(1)?[0];
+            (count, vec![value; count])
+        } else {
+            (size, reader.take(size)?.to_vec())
+        };
+
+        if output.len() < offset + len {
+            output.resize(offset + len, 0);
+        }
+        output[offset..offset + len].copy_from_slice(&bytes);
+    }
+
+    if let Ok(truncate) = reader.take(3) {
+        let len = u32::from_be_bytes([0, truncate[0], truncate[1], truncate[2]]) as usize;
+        output.truncate(len);
+    }
+
+    Ok(output)
+}
+
+/// BPS из последовательных SourceRead (байты совпадают) и TargetRead (новые байты)
+fn create_bps<A, B>(source: &A, target: &B, progress: &mut dyn Progress) -> Result<Vec<u8>>
+where
+    A: SearchSource + ?Sized,
+    B: SearchSource + ?Sized,
+{
+    let mut patch = BPS_MAGIC.to_vec();
+    write_varint(&mut patch, source.len() as u64);
+    write_varint(&mut patch, target.len() as u64);
+    // Без метаданных
+    write_varint(&mut patch, 0);
+
+    let mut pos = 0;
+    for run in target_runs(source, target) {
+        progress.update(run.start, target.len())?;
+        if run.start > pos {
+            write_action(&mut patch, BPS_SOURCE_READ, run.start - pos);
+        }
+        write_action(&mut patch, BPS_TARGET_READ, run.len());
+        patch.extend_from_slice(&target.range(run.clone()));
+        pos = run.end;
+    }
+    if target.len() > pos {
+        write_action(&mut patch, BPS_SOURCE_READ, target.len() - pos);
+    }
+
+    patch.extend_from_slice(&Algorithm::Crc32.compute(source, 0..source.len(), progress)?.to_le_bytes());
+    patch.extend_from_slice(&Algorithm::Crc32.compute(target, 0..target.len(), progress)?.to_le_bytes());
+    let patch_crc = crc32(0, &patch);
+    patch.extend_from_slice(&patch_crc.to_le_bytes());
+    Ok(patch)
+}
+
+fn apply_bps(source: &[u8], patch: &[u8]) -> Result<Vec<u8>> {
+    if patch.len() < BPS_MAGIC.len() + 12 {
+        bail!("BPS patch is truncated");
+    }
+
+    let (body, footer) = patch.split_at(patch.len() - 12);
+    let crc = |idx: usize| u32::from_le_bytes(footer[idx * 4..idx * 4 + 4].try_into().unwrap());
+    if crc32(0, &patch[..patch.len() - 4]) != crc(2) {
+        bail!("BPS patch is corrupted (checksum mismatch)");
+    }
+    if crc32(0, source) != crc(0) {
+        bail!("This patch is for a different file (source checksum mismatch)");
+    }
+
+    let mut reader = PatchReader::new(&body[BPS_MAGIC.len()..]);
+    let source_size = reader.varint()? as usize;
+    let target_size = reader.varint()? as usize;
+    let metadata_size = reader.varint()? as usize;
+    reader.take(metadata_size)?;
+
+    if source_size != source.len() {
+        bail!("This patch expects a {} byte file, got {} bytes", source_size, source.len());
+    }
+
+    // Размер из патча - только подсказка, выделять по нему память целиком нельзя
+    let mut output = Vec::with_capacity(target_size.min(source.len() + patch.len()));
+    let mut source_offset: usize = 0;
+    let mut target_offset: usize = 0;
+
+    while !reader.is_empty() {
+        let action = reader.varint()?;
+        let len = (action >> 2) as usize + 1;
+        // Патч с верными суммами все равно может требовать сколько угодно памяти
+        let end = output
+            .len()
+            .checked_add(len)
+            .filter(|&end| end <= target_size)
+            .ok_or_else(|| anyhow!("BPS patch writes past the {} byte target", target_size))?;
+
+        match action & 3 {
+            BPS_SOURCE_READ => {
+                let bytes = source.get(output.len()..end).ok_or_else(|| anyhow!("BPS read past the source"))?;
+                output.extend_from_slice(bytes);
+            }
+            BPS_TARGET_READ => output.extend_from_slice(reader.take(len)?),
+            BPS_SOURCE_COPY => {
+                source_offset = reader.relative_offset(source_offset)?;
+                let copy_end = source_offset
+                    .checked_add(len)
+                    .ok_or_else(|| anyhow!("BPS copy past the source"))?;
+                let bytes = source
+                    .get(source_offset..copy_end)
+                    .ok_or_else(|| anyhow!("BPS copy past the source"))?;
+                output.extend_from_slice(bytes);
+                source_offset = copy_end;
+            }
+            BPS_TARGET_COPY => {
+                target_offset = reader.relative_offset(target_offset)?;
+                // Копия может перекрываться с тем, что пишется сейчас, поэтому побайтно
+                for _ in 0..len {
+                    let byte = *output
+                        .get(target_offset)
+                        .ok_or_else(|| anyhow!("BPS copy past the output"))?;
+                    output.push(byte);
+                    target_offset += 1;
+                }
+            }
+            _ => unreachable!("action is two bits"),
+        }
+    }
+
+    if output.len() != target_size {
+        bail!("BPS patch produced {} bytes, expected {}", output.len(), target_size);
+    }
+    if crc32(0, &output) != crc(1) {
+        bail!("Patched data does not match the expected checksum");
+    }
+
+    Ok(output)
+}
+
+fn write_action(patch: &mut Vec<u8>, action: u64, len: usize) {
+    write_varint(patch, ((len as u64 - 1) << 2) | action);
+}
+
+/// Число переменной длины из BPS: 7 бит на байт, старший бит отмечает последний байт
+fn write_varint(patch: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let bits = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            patch.push(0x80 | bits);
+            return;
+        }
+        patch.push(bits);
+        value -= 1;
+    }
+}
+
+struct PatchReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> PatchReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos.saturating_add(len))
+            .ok_or_else(|| anyhow!("Patch ends unexpectedly"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    /// Больше 10 байт по 7 бит в u64 не помещается
+    fn varint(&mut self) -> Result<u64> {
+        const MAX_LEN: usize = 10;
+        let too_large = || anyhow!("Number in patch is too large");
+        let mut value: u64 = 0;
+        let mut shift: u64 = 1;
+
+        for _ in 0..MAX_LEN {
+            let byte = self.take(1)?[0];
+            value = (byte as u64 & 0x7F)
+                .checked_mul(shift)
+                .and_then(|add| value.checked_add(add))
+                .ok_or_else(too_large)?;
+            if byte & 0x80 != 0 {
+                return Ok(value);
+            }
+            shift = shift.checked_mul(0x80).ok_or_else(too_large)?;
+            value = value.checked_add(shift).ok_or_else(too_large)?;
+        }
+        Err(too_large())
+    }
+
+    /// Смещение для SourceCopy/TargetCopy: младший бит - знак, остальное - сдвиг от `base`
+    fn relative_offset(&mut self, base: usize) -> Result<usize> {
+        let value = self.varint()?;
+        let delta = (value >> 1) as usize;
+
+        if value & 1 != 0 {
+            base.checked_sub(delta)
+        } else {
+            base.checked_add(delta)
+        }
+        .ok_or_else(|| anyhow!("BPS copy offset is out of range"))
+    }
+}
@@ -0,0 +1,52 @@
+use anyhow::{Context, Result, bail};
+use std::fs::File;
+use std::io::{IsTerminal, Read};
+
+/// Имя файла для стандартного ввода: `cat blob | hexr -`
+pub const STDIO_PATH: &str = "-";
+
+/// Читает весь стандартный ввод; из терминала читать нечего
+pub fn read_stdin() -> Result<Vec<u8>> {
+    let mut stdin = std::io::stdin().lock();
+    if stdin.is_terminal() {
+        bail!("'-' reads data from a pipe: cat file | hexr -");
+    }
+
+    let mut data = Vec::new();
+    stdin.read_to_end(&mut data).context("Cannot read stdin")?;
+    Ok(data)
+}
+
+/// Если stdout - канал или файл, интерфейс выводится в /dev/tty, а исходный stdout
+/// возвращается для записи результата. Клавиши crossterm и так читает из /dev/tty
+#[cfg(unix)]
+pub fn redirect_stdout_to_tty() -> Result<Option<File>> {
+    use std::os::fd::{AsFd, AsRawFd};
+
+    let stdout = std::io::stdout();
+    if stdout.is_terminal() {
+        return Ok(None);
+    }
+
+    let tty = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .context("The editor needs a terminal: cannot open /dev/tty")?;
+    let output = stdout.as_fd().try_clone_to_owned()?;
+
+    // SAFETY: оба дескриптора открыты; dup2 атомарно заменяет stdout копией tty
+    if unsafe { libc::dup2(tty.as_raw_fd(), libc::STDOUT_FILENO) } == -1 {
+        return Err(std::io::Error::last_os_error())
+            .context("Cannot redirect stdout to the terminal");
+    }
+    Ok(Some(File::from(output)))
+}
+
+#[cfg(not(unix))]
+pub fn redirect_stdout_to_tty() -> Result<Option<File>> {
+    if !std::io::stdout().is_terminal() {
+        bail!("The editor needs a terminal on stdout");
+    }
+    Ok(None)
+}
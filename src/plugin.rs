@@ -0,0 +1,344 @@
+use crate::editor::HexEditor;
+use crate::theme;
+use crate::utils;
+use anyhow::{Context, Result, anyhow, bail};
+use crossterm::style::Color;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::ops::Range;
+use std::process::{Command as Process, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Команда успевает обработать и большой выделенный блок
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Строка инспектора пересчитывается при каждом движении курсора и не должна его тормозить
+const INSPECT_TIMEOUT: Duration = Duration::from_millis(500);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Сколько байт под курсором получает строка инспектора, как и встроенные расшифровки
+const INSPECT_BYTES: usize = 16;
+
+/// Плагин из раздела `[plugins.<имя>]`: внешние программы, которые добавляют команды,
+/// строки инспектора и раскраску байт без изменения самого hexr
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PluginConfig {
+    // Команды: имя в [keys] - "<плагин>.<команда>"
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub commands: BTreeMap<String, CommandConfig>,
+    // Строки инспектора: подпись и программа, которая печатает значение
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub inspect: BTreeMap<String, Vec<String>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub colors: Vec<ColorRule>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandConfig {
+    // Программа и аргументы; выделение (или весь файл) приходит на stdin
+    pub run: Vec<String>,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub output: Output,
+}
+
+/// Что делать со stdout команды
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Output {
+    // Первая строка показывается в строке состояния
+    #[default]
+    Message,
+    // Заменяет выделение (или весь файл) одним шагом отмены
+    Replace,
+    // Вставляется в позицию курсора
+    Insert,
+}
+
+/// Байты, совпавшие с шаблоном, красятся цветом: сигнатуры и маркеры формата
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColorRule {
+    pub pattern: String,
+    pub color: String,
+}
+
+/// Строк, которые плагины добавляют в инспектор
+pub fn inspector_rows(plugins: &BTreeMap<String, PluginConfig>) -> usize {
+    plugins.values().map(|plugin| plugin.inspect.len()).sum()
+}
+
+#[derive(Debug, Clone)]
+pub struct Command {
+    // Имя для [keys] и справки: "<плагин>.<команда>"
+    pub name: String,
+    pub description: String,
+    run: Vec<String>,
+    output: Output,
+}
+
+#[derive(Debug, Clone)]
+struct Inspector {
+    label: String,
+    run: Vec<String>,
+}
+
+/// Плагины из конфигурации, разобранные и проверенные при запуске
+#[derive(Debug, Default)]
+pub struct Plugins {
+    commands: Vec<Command>,
+    inspectors: Vec<Inspector>,
+    colors: Vec<(Vec<u8>, Color)>,
+    // Последний запрос инспектора: позиция, байты и ответы, чтобы не запускать программы на каждой перерисовке
+    inspected: Option<(usize, Vec<u8>, Vec<String>)>,
+}
+
+impl Plugins {
+    pub fn load(plugins: &BTreeMap<String, PluginConfig>) -> Result<Self> {
+        let mut loaded = Self::default();
+
+        for (plugin, config) in plugins {
+            check_name(plugin).map_err(|e| anyhow!("[plugins] {}", e))?;
+
+            for (command, command_config) in &config.commands {
+                let name = format!("{}.{}", plugin, command);
+                check_name(command).map_err(|e| anyhow!("[plugins.{}] {}", plugin, e))?;
+                if command_config.run.is_empty() {
+                    bail!(
+                        "[plugins.{}] Command '{}' has nothing to run",
+                        plugin,
+                        command
+                    );
+                }
+                loaded.commands.push(Command {
+                    description: match command_config.description.as_str() {
+                        "" => name.clone(),
+                        description => description.to_string(),
+                    },
+                    name,
+                    run: command_config.run.clone(),
+                    output: command_config.output,
+                });
+            }
+
+            for (label, run) in &config.inspect {
+                if run.is_empty() {
+                    bail!(
+                        "[plugins.{}] Inspector '{}' has nothing to run",
+                        plugin,
+                        label
+                    );
+                }
+                loaded.inspectors.push(Inspector {
+                    label: label.clone(),
+                    run: run.clone(),
+                });
+            }
+
+            for rule in &config.colors {
+                let pattern = utils::hex_string_to_bytes(&rule.pattern)
+                    .map_err(|e| anyhow!("[plugins.{}] {}", plugin, e))?;
+                if pattern.is_empty() {
+                    bail!("[plugins.{}] Color pattern is empty", plugin);
+                }
+                let color = theme::parse_color(&rule.color)
+                    .map_err(|e| anyhow!("[plugins.{}] {}", plugin, e))?;
+                loaded.colors.push((pattern, color));
+            }
+        }
+
+        Ok(loaded)
+    }
+
+    pub fn commands(&self) -> &[Command] {
+        &self.commands
+    }
+
+    pub fn command(&self, index: usize) -> Option<&Command> {
+        self.commands.get(index)
+    }
+
+    /// Подписи и значения строк инспектора для байт под курсором; ошибка программы
+    /// показывается вместо значения
+    pub fn inspect(&mut self, editor: &HexEditor) -> Vec<(String, String)> {
+        let cursor_pos = editor.get_cursor_pos();
+        let bytes = editor.get_range(cursor_pos, INSPECT_BYTES).into_owned();
+
+        let cached =
+            matches!(&self.inspected, Some((pos, seen, _)) if *pos == cursor_pos && *seen == bytes);
+        if !cached {
+            let env = [("HEXR_OFFSET", cursor_pos.to_string())];
+            let values = self
+                .inspectors
+                .iter()
+                .map(|inspector| {
+                    match execute(&inspector.run, bytes.clone(), &env, INSPECT_TIMEOUT) {
+                        Ok(output) => first_line(&output),
+                        Err(e) => format!("error: {:#}", e),
+                    }
+                })
+                .collect();
+            self.inspected = Some((cursor_pos, bytes, values));
+        }
+
+        let values = self
+            .inspected
+            .as_ref()
+            .map(|(_, _, values)| values.as_slice())
+            .unwrap_or_default();
+        self.inspectors
+            .iter()
+            .map(|inspector| inspector.label.clone())
+            .zip(values.iter().cloned())
+            .collect()
+    }
+
+    /// Участки окна `window`, совпавшие с шаблонами раскраски; совпадения на границе окна
+    /// тоже находятся
+    pub fn colors(&self, editor: &HexEditor, window: Range<usize>) -> Vec<(Range<usize>, Color)> {
+        let Some(longest) = self.colors.iter().map(|(pattern, _)| pattern.len()).max() else {
+            return Vec::new();
+        };
+
+        let start = window.start.saturating_sub(longest - 1);
+        let data = editor.get_range(start, window.end + longest - 1 - start);
+        let mut spans = Vec::new();
+        for (pattern, color) in &self.colors {
+            for (idx, candidate) in data.windows(pattern.len()).enumerate() {
+                let range = start + idx..start + idx + pattern.len();
+                if candidate == pattern.as_slice()
+                    && range.start < window.end
+                    && range.end > window.start
+                {
+                    spans.push((range, *color));
+                }
+            }
+        }
+        spans
+    }
+}
+
+impl Command {
+    /// Отдает программе выделение или, без него, весь файл и применяет ее вывод
+    pub fn run(&self, editor: &mut HexEditor) -> Result<()> {
+        let range = editor
+            .selection_range()
+            .filter(|range| !range.is_empty())
+            .unwrap_or(0..editor.data_len());
+        let input = editor.get_range(range.start, range.len()).into_owned();
+        let env = [
+            ("HEXR_FILE", editor.file_path.clone()),
+            ("HEXR_OFFSET", range.start.to_string()),
+            ("HEXR_LENGTH", range.len().to_string()),
+            ("HEXR_CURSOR", editor.get_cursor_pos().to_string()),
+            ("HEXR_SIZE", editor.data_len().to_string()),
+        ];
+        let output =
+            execute(&self.run, input, &env, COMMAND_TIMEOUT).with_context(|| self.name.clone())?;
+
+        match self.output {
+            Output::Message => match first_line(&output) {
+                line if line.is_empty() => editor.set_message(format!("{}: done", self.name)),
+                line => editor.set_message(format!("{}: {}", self.name, line)),
+            },
+            Output::Replace => {
+                editor.replace_range(range.clone(), &output)?;
+                editor.clear_selection();
+                editor.jump_to(range.start.min(editor.data_len().saturating_sub(1)));
+                editor.set_message(format!(
+                    "{}: replaced {} bytes with {}",
+                    self.name,
+                    range.len(),
+                    output.len()
+                ));
+            }
+            Output::Insert => {
+                editor.insert_bytes(&output)?;
+                editor.set_message(format!("{}: inserted {} bytes", self.name, output.len()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Имена плагинов и команд входят в имя команды для [keys] и не могут содержать точку
+fn check_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.contains(['.', ' ']) {
+        bail!("Invalid plugin or command name '{}'", name);
+    }
+    Ok(())
+}
+
+fn first_line(output: &[u8]) -> String {
+    String::from_utf8_lossy(output)
+        .lines()
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_string()
+}
+
+/// Запускает программу с `input` на stdin и возвращает ее stdout; зависшая программа
+/// снимается по таймауту, чтобы не блокировать редактор
+fn execute(
+    run: &[String],
+    input: Vec<u8>,
+    env: &[(&str, String)],
+    timeout: Duration,
+) -> Result<Vec<u8>> {
+    let (program, args) = run.split_first().ok_or_else(|| anyhow!("Nothing to run"))?;
+    let mut child = Process::new(program)
+        .args(args)
+        .envs(env.iter().map(|(key, value)| (key, value)))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Cannot run {}", program))?;
+
+    // Ввод и вывод в своих потоках: большой ввод не упрется в заполненный канал вывода.
+    // Программа может не читать ввод до конца, это не ошибка
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    thread::spawn(move || stdin.write_all(&input));
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let reader = thread::spawn(move || {
+        let mut output = Vec::new();
+        stdout.read_to_end(&mut output).map(|_| output)
+    });
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    let errors = thread::spawn(move || {
+        let mut output = Vec::new();
+        let _ = stderr.read_to_end(&mut output);
+        output
+    });
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            bail!("{} did not finish in {} ms", program, timeout.as_millis());
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    let output = reader
+        .join()
+        .map_err(|_| anyhow!("Reading output of {} failed", program))?
+        .with_context(|| format!("Cannot read output of {}", program))?;
+    if !status.success() {
+        let errors = errors.join().unwrap_or_default();
+        match first_line(&errors) {
+            line if line.is_empty() => bail!("{} failed: {}", program, status),
+            line => bail!("{} failed: {}", program, line),
+        }
+    }
+    Ok(output)
+}
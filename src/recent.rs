@@ -0,0 +1,40 @@
+use crate::sidecar;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+const RECENT_FILE: &str = "recent.toml";
+
+/// Сколько файлов помнит список
+const RECENT_LIMIT: usize = 10;
+
+/// Недавно открытые файлы, последний - первым
+#[derive(Default, Serialize, Deserialize)]
+struct Recent {
+    #[serde(default)]
+    files: Vec<String>,
+}
+
+/// Недавние файлы; поврежденный или отсутствующий список считается пустым
+pub fn load() -> Vec<String> {
+    std::fs::read_to_string(sidecar::sidecar_path(RECENT_FILE))
+        .ok()
+        .and_then(|content| toml::from_str::<Recent>(&content).ok())
+        .map(|recent| recent.files)
+        .unwrap_or_default()
+}
+
+/// Поднимает файл в начало списка
+pub fn record(file_path: &str) -> Result<()> {
+    let path = sidecar::absolute_path(file_path);
+    let mut files = load();
+    files.retain(|file| *file != path);
+    files.insert(0, path);
+    files.truncate(RECENT_LIMIT);
+
+    let list = sidecar::sidecar_path(RECENT_FILE);
+    if let Some(parent) = list.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(list, toml::to_string_pretty(&Recent { files })?)?;
+    Ok(())
+}
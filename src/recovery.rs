@@ -0,0 +1,113 @@
+//! Восстановление после сбоя. Рядом с файлом ведется журнал правок `<файл>.hexr-journal`,
+//! а auto-save пишет несохраненное содержимое в `<файл>.hexr~`; сам файл меняет только
+//! явное сохранение. Оставшиеся после аварийного выхода журнал или файл восстановления
+//! предлагается применить при следующем открытии
+
+use crate::utils;
+use anyhow::Result;
+use hexr_core::buffer::PieceTable;
+use hexr_core::checksum::crc32;
+use hexr_core::document::JournalEntry;
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+
+const SUFFIX: &str = ".hexr~";
+const JOURNAL_SUFFIX: &str = ".hexr-journal";
+
+pub fn path(file_path: &str) -> String {
+    format!("{}{}", file_path, SUFFIX)
+}
+
+pub fn journal_path(file_path: &str) -> String {
+    format!("{}{}", file_path, JOURNAL_SUFFIX)
+}
+
+/// Файл восстановления, если он есть
+pub fn find(file_path: &str) -> Option<String> {
+    let path = path(file_path);
+    std::path::Path::new(&path).is_file().then_some(path)
+}
+
+pub fn write(file_path: &str, data: &PieceTable) -> Result<()> {
+    utils::write_file_atomic(&path(file_path), data.chunks(), false)
+}
+
+/// Сколько байт с начала и с конца содержимого входит в его контрольную сумму
+const FINGERPRINT_BLOCK: usize = 64 * 1024;
+
+/// Первая строка журнала: сохраненное содержимое, к которому относятся правки. Файл,
+/// пересобранный с той же длиной, отличается временем изменения или суммой
+#[derive(Serialize, Deserialize, PartialEq)]
+struct JournalHeader {
+    len: usize,
+    // Время изменения файла в наносекундах от эпохи; None, если система его не дает
+    modified: Option<u64>,
+    // CRC-32 первого и последнего блоков содержимого
+    crc: u32,
+}
+
+impl JournalHeader {
+    fn of(file_path: &str, data: &[u8]) -> Self {
+        let modified = std::fs::metadata(file_path)
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|since| since.as_nanos() as u64);
+        let head = &data[..data.len().min(FINGERPRINT_BLOCK)];
+        let tail = &data[data.len().saturating_sub(FINGERPRINT_BLOCK)..];
+        Self { len: data.len(), modified, crc: crc32(crc32(0, head), tail) }
+    }
+}
+
+/// Журнал, оставшийся от прошлого запуска
+pub struct Journal {
+    pub path: String,
+    header: JournalHeader,
+    pub entries: Vec<JournalEntry>,
+}
+
+impl Journal {
+    /// Файл тот же, что при записи журнала: правки можно повторять поверх него
+    pub fn matches(&self, file_path: &str, data: &[u8]) -> bool {
+        self.header == JournalHeader::of(file_path, data)
+    }
+}
+
+/// Начинает журнал заново для сохраненного содержимого `data`: прежний журнал, даже
+/// отложенный при открытии, перезаписывается
+pub fn start_journal(file_path: &str, data: &[u8]) -> Result<()> {
+    let header = JournalHeader::of(file_path, data);
+    let mut file = std::fs::File::create(journal_path(file_path))?;
+    writeln!(file, "{}", serde_json::to_string(&header)?)?;
+    Ok(())
+}
+
+/// Дописывает правки по одной на строку: сбой посреди записи портит только последнюю
+pub fn append_journal(file_path: &str, entries: &[JournalEntry]) -> Result<()> {
+    let file = OpenOptions::new().append(true).open(journal_path(file_path))?;
+    let mut out = BufWriter::new(file);
+    for entry in entries {
+        writeln!(out, "{}", serde_json::to_string(entry)?)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Журнал с правками; чтение останавливается на первой испорченной строке
+pub fn read_journal(file_path: &str) -> Option<Journal> {
+    let path = journal_path(file_path);
+    let mut lines = BufReader::new(std::fs::File::open(&path).ok()?).lines();
+    let header: JournalHeader = serde_json::from_str(&lines.next()?.ok()?).ok()?;
+    let entries: Vec<JournalEntry> = lines
+        .map_while(|line| serde_json::from_str(&line.ok()?).ok())
+        .collect();
+    (!entries.is_empty()).then_some(Journal { path, header, entries })
+}
+
+/// Правки сохранены или отброшены: журнал и файл восстановления удаляются; отсутствующие
+/// файлы не ошибка
+pub fn remove(file_path: &str) {
+    let _ = std::fs::remove_file(path(file_path));
+    let _ = std::fs::remove_file(journal_path(file_path));
+}
@@ -0,0 +1,257 @@
+//! Кадр экрана в памяти. Отрисовка пишет в него те же escape-последовательности, что
+//! писала бы в терминал, а в терминал уходят только ячейки, изменившиеся с прошлого
+//! кадра: на медленном соединении экран не перерисовывается целиком каждый раз
+
+use crossterm::{
+    cursor, queue,
+    style::{Color, ResetColor, SetBackgroundColor, SetForegroundColor},
+};
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Экран изменили в обход кадра (строка ввода, полоса прогресса)
+static INVALIDATED: AtomicBool = AtomicBool::new(false);
+
+/// Отмечает, что терминал больше не совпадает с прошлым кадром: следующий кадр
+/// выводится целиком
+pub fn invalidate() {
+    INVALIDATED.store(true, Ordering::Relaxed);
+}
+
+pub fn is_invalidated() -> bool {
+    INVALIDATED.load(Ordering::Relaxed)
+}
+
+/// Цвета ячейки; None - цвет терминала по умолчанию
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+struct Style {
+    foreground: Option<Color>,
+    background: Option<Color>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Cell {
+    ch: char,
+    style: Style,
+}
+
+const BLANK: Cell = Cell {
+    ch: ' ',
+    style: Style {
+        foreground: None,
+        background: None,
+    },
+};
+
+#[derive(Debug, Default)]
+pub struct Screen {
+    width: usize,
+    height: usize,
+    cells: Vec<Cell>,
+    // Что сейчас на терминале; None - неизвестно, кадр выводится целиком
+    shown: Option<Vec<Cell>>,
+    x: usize,
+    y: usize,
+    style: Style,
+    // Начало escape-последовательности или символа UTF-8, разорванного между записями
+    pending: Vec<u8>,
+}
+
+impl Screen {
+    /// Новый кадр того же размера начинается с позиции (0, 0); при смене размера терминала
+    /// прошлый кадр забывается
+    pub fn begin(&mut self, width: u16, height: u16) {
+        let (width, height) = (width as usize, height as usize);
+        if (width, height) != (self.width, self.height) {
+            self.width = width;
+            self.height = height;
+            self.cells = vec![BLANK; width * height];
+            self.shown = None;
+        }
+        if INVALIDATED.swap(false, Ordering::Relaxed) {
+            self.shown = None;
+        }
+        self.x = 0;
+        self.y = 0;
+        self.style = Style::default();
+        self.pending.clear();
+    }
+
+    /// Выводит изменившиеся ячейки
+    pub fn present(&mut self, out: &mut impl Write) -> io::Result<()> {
+        let mut cursor_at = None;
+        let mut style = None;
+        for (idx, cell) in self.cells.iter().enumerate() {
+            if self.shown.as_ref().is_some_and(|shown| shown[idx] == *cell) {
+                continue;
+            }
+
+            let (x, y) = (idx % self.width, idx / self.width);
+            if cursor_at != Some((x, y)) {
+                queue!(out, cursor::MoveTo(x as u16, y as u16))?;
+            }
+            if style != Some(cell.style) {
+                queue!(
+                    out,
+                    ResetColor,
+                    SetForegroundColor(cell.style.foreground.unwrap_or(Color::Reset)),
+                    SetBackgroundColor(cell.style.background.unwrap_or(Color::Reset))
+                )?;
+                style = Some(cell.style);
+            }
+            write!(out, "{}", cell.ch)?;
+            // После последнего столбца курсор не переходит на следующую строку сам
+            cursor_at = (x + 1 < self.width).then_some((x + 1, y));
+        }
+
+        if style.is_some() {
+            queue!(out, ResetColor)?;
+        }
+        out.flush()?;
+        self.shown = Some(self.cells.clone());
+        Ok(())
+    }
+
+    fn put(&mut self, ch: char) {
+        if self.x < self.width && self.y < self.height {
+            self.cells[self.y * self.width + self.x] = Cell {
+                ch,
+                style: self.style,
+            };
+        }
+        self.x += 1;
+    }
+
+    /// Очистка до конца строки: как и терминал, заполняет текущим фоном
+    fn clear_line_from(&mut self, x: usize) {
+        if self.y >= self.height {
+            return;
+        }
+        let blank = Cell {
+            ch: ' ',
+            style: Style {
+                foreground: None,
+                background: self.style.background,
+            },
+        };
+        let row = self.y * self.width;
+        for cell in &mut self.cells[row + x.min(self.width)..row + self.width] {
+            *cell = blank;
+        }
+    }
+
+    /// CSI-последовательность, которую выводит отрисовка: перемещение курсора,
+    /// цвета и очистка строки
+    fn apply_csi(&mut self, params: &str, command: u8) {
+        let numbers: Vec<usize> = params
+            .split(';')
+            .map(|param| param.parse().unwrap_or(0))
+            .collect();
+        match command {
+            b'H' => {
+                self.y = numbers.first().copied().unwrap_or(1).saturating_sub(1);
+                self.x = numbers.get(1).copied().unwrap_or(1).saturating_sub(1);
+            }
+            b'K' => match numbers[0] {
+                2 => self.clear_line_from(0),
+                _ => self.clear_line_from(self.x),
+            },
+            b'm' => self.apply_sgr(&numbers),
+            _ => {}
+        }
+    }
+
+    fn apply_sgr(&mut self, numbers: &[usize]) {
+        let mut idx = 0;
+        while idx < numbers.len() {
+            match numbers[idx] {
+                0 => self.style = Style::default(),
+                39 => self.style.foreground = None,
+                49 => self.style.background = None,
+                code @ (38 | 48) => {
+                    let color = match numbers.get(idx + 1) {
+                        Some(5) => {
+                            idx += 2;
+                            numbers.get(idx).map(|&value| Color::AnsiValue(value as u8))
+                        }
+                        Some(2) => {
+                            idx += 4;
+                            numbers.get(idx - 2..=idx).map(|rgb| Color::Rgb {
+                                r: rgb[0] as u8,
+                                g: rgb[1] as u8,
+                                b: rgb[2] as u8,
+                            })
+                        }
+                        _ => None,
+                    };
+                    if code == 38 {
+                        self.style.foreground = color;
+                    } else {
+                        self.style.background = color;
+                    }
+                }
+                // Атрибуты (жирный, инверсия) отрисовка не использует
+                _ => {}
+            }
+            idx += 1;
+        }
+    }
+}
+
+impl Write for Screen {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        let bytes = std::mem::take(&mut self.pending);
+
+        let mut idx = 0;
+        while idx < bytes.len() {
+            if bytes[idx] == 0x1b {
+                // ESC [ параметры команда
+                let Some(end) = bytes[idx..]
+                    .iter()
+                    .skip(2)
+                    .position(|byte| (0x40..=0x7e).contains(byte))
+                else {
+                    break;
+                };
+                let end = idx + 2 + end;
+                if bytes.get(idx + 1) == Some(&b'[') {
+                    let params = String::from_utf8_lossy(&bytes[idx + 2..end]).into_owned();
+                    self.apply_csi(&params, bytes[end]);
+                }
+                idx = end + 1;
+                continue;
+            }
+
+            let text_end = bytes[idx..]
+                .iter()
+                .position(|&byte| byte == 0x1b)
+                .map_or(bytes.len(), |pos| idx + pos);
+            let (text, error) = match std::str::from_utf8(&bytes[idx..text_end]) {
+                Ok(text) => (text, None),
+                Err(e) => (
+                    std::str::from_utf8(&bytes[idx..idx + e.valid_up_to()]).unwrap_or_default(),
+                    Some(e.error_len()),
+                ),
+            };
+            text.chars().for_each(|ch| self.put(ch));
+            idx += text.len();
+            match error {
+                None => {}
+                Some(Some(invalid)) => {
+                    self.put(char::REPLACEMENT_CHARACTER);
+                    idx += invalid;
+                }
+                // Символ разорван между записями: его конец придет следующей записью
+                Some(None) => break,
+            }
+        }
+
+        self.pending = bytes[idx..].to_vec();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,36 @@
+use crate::editor::EditMode;
+use crate::sidecar;
+use anyhow::Result;
+use hexr_core::search::SearchMode;
+use serde::{Deserialize, Serialize};
+
+const SESSIONS_FILE: &str = "sessions.toml";
+
+/// Последний поиск: строка и режим, в котором она разбирается заново
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LastSearch {
+    pub input: String,
+    pub mode: SearchMode,
+    #[serde(default)]
+    pub ignore_case: bool,
+}
+
+/// Состояние файла к моменту выхода; закладки хранятся отдельно в bookmarks.toml
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub cursor_pos: usize,
+    pub view_offset: usize,
+    pub mode: EditMode,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub search: Option<LastSearch>,
+}
+
+impl Session {
+    pub fn load(file_path: &str) -> Result<Option<Self>> {
+        sidecar::load(SESSIONS_FILE, file_path)
+    }
+
+    pub fn save(&self, file_path: &str) -> Result<()> {
+        sidecar::store(SESSIONS_FILE, file_path, Some(self.clone()))
+    }
+}
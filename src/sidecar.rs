@@ -0,0 +1,69 @@
+use crate::utils;
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Файл с данными для всех редактируемых файлов: ключ - абсолютный путь файла
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "V: Serialize + DeserializeOwned")]
+struct Sidecar<V> {
+    #[serde(default = "BTreeMap::new")]
+    files: BTreeMap<String, V>,
+}
+
+/// Читает запись о файле; None, если записи нет. Испорченный файл - ошибка, которую
+/// показывают предупреждением, а не повод открывать файл без записи молча
+pub fn load<V: Serialize + DeserializeOwned>(name: &str, file_path: &str) -> Result<Option<V>> {
+    Ok(read::<V>(name)?.files.remove(&absolute_path(file_path)))
+}
+
+/// Сохраняет запись о файле, `None` удаляет ее
+pub fn store<V: Serialize + DeserializeOwned>(name: &str, file_path: &str, value: Option<V>) -> Result<()> {
+    let path = sidecar_path(name);
+    // Файл, который не удалось разобрать, не перезаписывается: в нем записи других файлов
+    let mut sidecar = read::<V>(name)?;
+
+    match value {
+        Some(value) => sidecar.files.insert(absolute_path(file_path), value),
+        None => sidecar.files.remove(&absolute_path(file_path)),
+    };
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let content = toml::to_string_pretty(&sidecar)?;
+    utils::write_file_atomic(&path.to_string_lossy(), [content.as_bytes()], false)
+}
+
+/// Отсутствующий файл - пустой
+fn read<V: Serialize + DeserializeOwned>(name: &str) -> Result<Sidecar<V>> {
+    let path = sidecar_path(name);
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Sidecar { files: BTreeMap::new() }),
+        Err(e) => return Err(e).with_context(|| format!("Cannot read {}", path.display())),
+    };
+    toml::from_str(&content).with_context(|| format!("{} is damaged", path.display()))
+}
+
+/// Абсолютный путь как ключ: один файл, открытый по-разному, - одна запись
+pub fn absolute_path(file_path: &str) -> String {
+    let path = Path::new(file_path);
+    let absolute = std::fs::canonicalize(path).unwrap_or_else(|_| {
+        std::env::current_dir()
+            .map(|dir| dir.join(path))
+            .unwrap_or_else(|_| path.to_path_buf())
+    });
+    absolute.to_string_lossy().into_owned()
+}
+
+/// Файл `name` в каталоге настроек hexr
+pub fn sidecar_path(name: &str) -> PathBuf {
+    let mut path = dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."));
+    path.push("hexr");
+    path.push(name);
+    path
+}
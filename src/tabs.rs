@@ -0,0 +1,213 @@
+use crate::config::Config;
+use crate::diff;
+use crate::editor::HexEditor;
+use anyhow::{Result, anyhow, bail};
+
+/// Открытые файлы: по редактору на вкладку, одна вкладка активна
+pub struct Tabs {
+    editors: Vec<HexEditor>,
+    active: usize,
+    config: Config,
+    // Пара вкладок в режиме сравнения: левая и правая сторона
+    compare: Option<[usize; 2]>,
+}
+
+impl Tabs {
+    pub fn new(first: HexEditor, config: Config) -> Self {
+        Self {
+            editors: vec![first],
+            active: 0,
+            config,
+            compare: None,
+        }
+    }
+
+    pub fn active(&self) -> &HexEditor {
+        &self.editors[self.active]
+    }
+
+    pub fn active_mut(&mut self) -> &mut HexEditor {
+        &mut self.editors[self.active]
+    }
+
+    pub fn active_index(&self) -> usize {
+        self.active
+    }
+
+    pub fn len(&self) -> usize {
+        self.editors.len()
+    }
+
+    pub fn editors(&self) -> &[HexEditor] {
+        &self.editors
+    }
+
+    pub fn editors_mut(&mut self) -> &mut [HexEditor] {
+        &mut self.editors
+    }
+
+    pub fn select(&mut self, index: usize) {
+        if index < self.editors.len() {
+            self.active = index;
+        }
+    }
+
+    pub fn next(&mut self) {
+        self.active = (self.active + 1) % self.editors.len();
+    }
+
+    pub fn previous(&mut self) {
+        self.active = (self.active + self.editors.len() - 1) % self.editors.len();
+    }
+
+    /// Открывает файл в новой вкладке; уже открытый файл просто становится активным
+    pub fn open(&mut self, path: &str, readonly: bool) -> Result<()> {
+        if let Some(index) = self.editors.iter().position(|editor| editor.file_path == path) {
+            self.active = index;
+            return Ok(());
+        }
+
+        let editor = HexEditor::open(path, readonly, self.config.clone())?;
+        self.editors.push(editor);
+        self.active = self.editors.len() - 1;
+        Ok(())
+    }
+
+    /// Распаковывает сжатый поток из активной вкладки в новую
+    pub fn decompress(&mut self) -> Result<()> {
+        let Some((data, packed)) = self.active_mut().decompress_from_input()? else {
+            return Ok(());
+        };
+
+        let message = format!(
+            "Unpacked {} {} bytes at 0x{:X} into {} bytes; Alt+Shift+P packs them back",
+            packed.format.name(),
+            packed.range.len(),
+            packed.range.start,
+            data.len()
+        );
+        let unpacked = HexEditor::unpacked(data, packed, self.config.clone())?;
+        self.editors.push(unpacked);
+        self.active = self.editors.len() - 1;
+        self.active_mut().set_message(message);
+        Ok(())
+    }
+
+    /// Сжимает распакованную вкладку и ставит результат на место потока в источнике
+    /// одним шагом отмены; источник становится активной вкладкой. Правки источника,
+    /// сдвинувшие поток, не отслеживаются
+    pub fn recompress(&mut self) -> Result<()> {
+        let editor = self.active();
+        let packed = editor
+            .packed()
+            .cloned()
+            .ok_or_else(|| anyhow!("This tab was not unpacked from another (Ctrl+P)"))?;
+        let data = editor.get_range(0, editor.data_len());
+        let compressed = packed.format.compress(&data)?;
+
+        let index = self
+            .editors
+            .iter()
+            .position(|editor| editor.file_path == packed.source)
+            .ok_or_else(|| anyhow!("{} is no longer open", packed.source))?;
+        if packed.range.end > self.editors[index].data_len() {
+            bail!(
+                "The stream at 0x{:X} is past the end of {}",
+                packed.range.start,
+                packed.source
+            );
+        }
+
+        let range = packed.range.start..packed.range.start + compressed.len();
+        self.editors[index].replace_range(packed.range.clone(), &compressed)?;
+        self.active_mut().set_packed_range(range);
+
+        self.active = index;
+        let source = self.active_mut();
+        source.jump_to(packed.range.start);
+        source.set_message(format!(
+            "Packed as {}: {} -> {} bytes at 0x{:X}",
+            packed.format.name(),
+            packed.range.len(),
+            compressed.len(),
+            packed.range.start
+        ));
+        Ok(())
+    }
+
+    /// Закрывает активную вкладку; false, если она была последней
+    pub fn close_active(&mut self) -> bool {
+        if self.editors.len() == 1 {
+            return false;
+        }
+
+        // Вкладку закрывают, только решив судьбу правок
+        self.editors.remove(self.active).discard_recovery();
+        self.compare = None;
+        self.active = self.active.min(self.editors.len() - 1);
+        true
+    }
+
+    /// Выход без сбоя: файлы восстановления больше не нужны
+    pub fn discard_recovery(&mut self) {
+        for editor in &mut self.editors {
+            editor.discard_recovery();
+        }
+    }
+
+    /// Сессии всех вкладок; ошибка одной не мешает сохранить остальные
+    pub fn save_sessions(&self) -> Result<()> {
+        let mut result = Ok(());
+        for editor in &self.editors {
+            if let Err(e) = editor.save_session() {
+                result = result.and(Err(e));
+            }
+        }
+        result
+    }
+
+    /// Сравнение активной вкладки со следующей; повторный вызов выключает сравнение
+    pub fn toggle_compare(&mut self) -> Result<()> {
+        if self.compare.take().is_some() {
+            return Ok(());
+        }
+        if self.editors.len() < 2 {
+            bail!("Open a second file to compare with (Ctrl+E)");
+        }
+
+        self.compare = Some([self.active, (self.active + 1) % self.editors.len()]);
+        Ok(())
+    }
+
+    /// Левая и правая стороны сравнения, если активная вкладка в нем участвует
+    pub fn compare_pair(&self) -> Option<(&HexEditor, &HexEditor)> {
+        let [left, right] = self.compare.filter(|pair| pair.contains(&self.active))?;
+        Some((&self.editors[left], &self.editors[right]))
+    }
+
+    /// Переход к следующему или предыдущему участку, которым файлы отличаются
+    pub fn jump_to_difference(&mut self, forward: bool) -> Result<()> {
+        let (left, right) = self
+            .compare_pair()
+            .ok_or_else(|| anyhow!("Compare mode is off (Alt+D)"))?;
+        let from = self.active().get_cursor_pos();
+
+        let found = if forward {
+            diff::next_difference(left.data(), right.data(), from)
+        } else {
+            diff::previous_difference(left.data(), right.data(), from)
+        };
+
+        let editor = self.active_mut();
+        match found {
+            // Различие в хвосте другого, более длинного файла
+            Some(pos) if pos >= editor.data_len() => {
+                editor.jump_to(editor.data_len().saturating_sub(1));
+                editor.set_message("The other file continues past the end of this one");
+            }
+            Some(pos) => editor.jump_to(pos),
+            None => editor.set_warning("No more differences"),
+        }
+        Ok(())
+    }
+}
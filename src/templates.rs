@@ -0,0 +1,238 @@
+use crate::config::Config;
+use anyhow::{Context, Result, bail};
+use crossterm::style::Color;
+use hexr_core::formats::Format;
+use hexr_core::search::SearchSource;
+use hexr_core::template::{Field, Layout, Template};
+use std::ops::Range;
+use std::path::PathBuf;
+
+/// Расширение файлов шаблонов в каталоге templates
+const TEMPLATE_EXTENSION: &str = "tpl";
+
+/// Цвета листьев по очереди, если в шаблоне цвет не задан
+const PALETTE: [Color; 4] = [Color::Cyan, Color::Yellow, Color::Green, Color::Magenta];
+
+/// Откуда берется разметка: файл шаблона или встроенный разборщик формата
+pub enum Source {
+    Template(Template),
+    Builtin(Format),
+}
+
+impl Source {
+    fn layout<D: SearchSource + ?Sized>(&self, data: &D, range: Range<usize>) -> Layout {
+        match self {
+            Source::Template(template) => template.apply(data, range),
+            Source::Builtin(format) => format.layout(data, range),
+        }
+    }
+}
+
+/// Шаблон по пути к файлу или по имени в каталоге шаблонов (`png` - templates/png.tpl);
+/// если такого файла нет, имя встроенного разборщика
+pub fn load(name: &str) -> Result<(String, Source)> {
+    let mut path = PathBuf::from(name);
+    if !path.exists() {
+        path = Config::templates_dir().join(format!("{}.{}", name, TEMPLATE_EXTENSION));
+    }
+    if !path.exists() {
+        match Format::parse(name) {
+            Some(format) => return Ok((format.name().to_string(), Source::Builtin(format))),
+            None => bail!(
+                "No template '{}' in {} (built-in: {})",
+                name,
+                Config::templates_dir().display(),
+                Format::ALL.map(Format::name).join(", ")
+            ),
+        }
+    }
+
+    let text = std::fs::read_to_string(&path)
+        .with_context(|| format!("Cannot read template {}", path.display()))?;
+    let template = Template::parse(&text).with_context(|| format!("{}", path.display()))?;
+
+    let name = path.file_stem().map_or_else(
+        || name.to_string(),
+        |stem| stem.to_string_lossy().into_owned(),
+    );
+    Ok((name, Source::Template(template)))
+}
+
+/// Шаблон, наложенный на данные: поля, их цвета и состояние дерева в панели
+pub struct TemplateView {
+    pub name: String,
+    source: Source,
+    range: Range<usize>,
+    pub layout: Layout,
+    // Свернутые узлы дерева, по индексам полей
+    collapsed: Vec<bool>,
+    pub selected: usize,
+    // Листья по возрастанию начала: поиск поля под байтом делением пополам
+    leaves: Vec<usize>,
+    colors: Vec<Color>,
+}
+
+impl TemplateView {
+    pub fn new<D: SearchSource + ?Sized>(
+        name: String,
+        source: Source,
+        data: &D,
+        range: Range<usize>,
+    ) -> Self {
+        let mut view = Self {
+            name,
+            source,
+            range,
+            layout: Layout::default(),
+            collapsed: Vec::new(),
+            selected: 0,
+            leaves: Vec::new(),
+            colors: Vec::new(),
+        };
+        view.reapply(data);
+        view
+    }
+
+    /// Размечает данные заново после правок; дерево снова свернуто, выбран корень
+    pub fn reapply<D: SearchSource + ?Sized>(&mut self, data: &D) {
+        self.layout = self
+            .source
+            .layout(data, self.range.start..self.range.end.min(data.len()));
+        let fields = &self.layout.fields;
+
+        // Массивы структур свернуты, чтобы длинные таблицы не заслоняли остальное
+        self.collapsed = fields
+            .iter()
+            .map(|field| field.value.is_none() && field.type_name.ends_with(']'))
+            .collect();
+        self.selected = 0;
+
+        self.leaves = (0..fields.len())
+            .filter(|&idx| fields[idx].value.is_some() && !fields[idx].range.is_empty())
+            .collect();
+        self.leaves.sort_by_key(|&idx| fields[idx].range.start);
+
+        self.colors = vec![PALETTE[0]; fields.len()];
+        for (number, &idx) in self.leaves.iter().enumerate() {
+            // Цвет листа - свой, иначе ближайшего предка, иначе по очереди из палитры
+            let mut field = Some(idx);
+            let mut color = None;
+            while let (Some(current), None) = (field, color) {
+                color = fields[current]
+                    .color
+                    .as_deref()
+                    .and_then(|name| Color::try_from(name).ok());
+                field = fields[current].parent;
+            }
+            self.colors[idx] = color.unwrap_or(PALETTE[number % PALETTE.len()]);
+        }
+    }
+
+    /// Лист, которому принадлежит байт
+    pub fn leaf_at(&self, pos: usize) -> Option<usize> {
+        let fields = &self.layout.fields;
+        let idx = self
+            .leaves
+            .partition_point(|&leaf| fields[leaf].range.start <= pos);
+        let leaf = *self.leaves.get(idx.checked_sub(1)?)?;
+        fields[leaf].range.contains(&pos).then_some(leaf)
+    }
+
+    pub fn color_at(&self, pos: usize) -> Option<Color> {
+        self.leaf_at(pos).map(|leaf| self.colors[leaf])
+    }
+
+    /// Полное имя поля от корня: `Png.chunks[2].length`
+    pub fn path(&self, idx: usize) -> String {
+        let fields = &self.layout.fields;
+        let mut names = Vec::new();
+        let mut field = Some(idx);
+        while let Some(current) = field {
+            names.push(fields[current].name.as_str());
+            field = fields[current].parent;
+        }
+        names.reverse();
+        names.join(".").replace(".[", "[")
+    }
+
+    pub fn field(&self, idx: usize) -> Option<&Field> {
+        self.layout.fields.get(idx)
+    }
+
+    pub fn has_children(&self, idx: usize) -> bool {
+        self.layout.fields[idx].end > idx + 1
+    }
+
+    pub fn is_collapsed(&self, idx: usize) -> bool {
+        self.collapsed[idx]
+    }
+
+    /// Строки дерева с учетом свернутых узлов
+    pub fn visible(&self) -> Vec<usize> {
+        let mut rows = Vec::new();
+        let mut idx = 0;
+        while idx < self.layout.fields.len() {
+            rows.push(idx);
+            idx = if self.collapsed[idx] {
+                self.layout.fields[idx].end
+            } else {
+                idx + 1
+            };
+        }
+        rows
+    }
+
+    pub fn select_next(&mut self) {
+        let rows = self.visible();
+        if let Some(&next) = rows.iter().find(|&&row| row > self.selected) {
+            self.selected = next;
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        let rows = self.visible();
+        if let Some(&previous) = rows.iter().rev().find(|&&row| row < self.selected) {
+            self.selected = previous;
+        }
+    }
+
+    /// Следующее за выбранным поле, в имени которого есть `query` (без учета регистра);
+    /// свернутые предки разворачиваются
+    pub fn find(&mut self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        let count = self.layout.fields.len();
+        let found = (1..=count)
+            .map(|step| (self.selected + step) % count)
+            .find(|&idx| self.layout.fields[idx].name.to_lowercase().contains(&query));
+        let Some(found) = found else {
+            return false;
+        };
+
+        let mut parent = self.layout.fields[found].parent;
+        while let Some(idx) = parent {
+            self.collapsed[idx] = false;
+            parent = self.layout.fields[idx].parent;
+        }
+        self.selected = found;
+        true
+    }
+
+    /// Разворачивает выбранный узел
+    pub fn expand(&mut self) {
+        if let Some(collapsed) = self.collapsed.get_mut(self.selected) {
+            *collapsed = false;
+        }
+    }
+
+    /// Сворачивает выбранный узел; у листа или свернутого узла выбирает родителя
+    pub fn collapse(&mut self) {
+        if self.layout.fields.is_empty() {
+            return;
+        }
+        if self.has_children(self.selected) && !self.collapsed[self.selected] {
+            self.collapsed[self.selected] = true;
+        } else if let Some(parent) = self.layout.fields[self.selected].parent {
+            self.selected = parent;
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use crate::config::{ColorConfig, Config};
+use anyhow::{Context, Result, anyhow};
+use crossterm::style::Color;
+
+/// Цвета интерфейса, разобранные из [colors] и файла темы
+#[derive(Debug, Clone)]
+pub struct Theme {
+    pub foreground: Color,
+    pub header: Color,
+    pub offset: Color,
+    pub cursor: Color,
+    pub selection: Color,
+    pub current_line: Color,
+    pub status_bar: Color,
+    pub modified: Color,
+    pub null_byte: Color,
+    pub printable_byte: Color,
+    pub high_byte: Color,
+    pub ff_byte: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            foreground: Color::White,
+            header: Color::DarkBlue,
+            offset: Color::Yellow,
+            cursor: Color::DarkGreen,
+            selection: Color::Blue,
+            current_line: Color::AnsiValue(236),
+            status_bar: Color::DarkGrey,
+            modified: Color::Red,
+            null_byte: Color::DarkGrey,
+            printable_byte: Color::Cyan,
+            high_byte: Color::Yellow,
+            ff_byte: Color::Magenta,
+        }
+    }
+}
+
+impl Theme {
+    /// Цвета из конфигурации; если задана тема, ее файл `themes/<имя>.toml` в каталоге
+    /// конфигурации переопределяет перечисленные в нем ключи
+    pub fn load(colors: &ColorConfig) -> Result<Self> {
+        let colors = match colors.theme.as_deref() {
+            Some(name) => with_theme_file(colors, name)?,
+            None => colors.clone(),
+        };
+
+        Ok(Self {
+            foreground: parse_color(&colors.foreground)?,
+            header: parse_color(&colors.header)?,
+            offset: parse_color(&colors.offset)?,
+            cursor: parse_color(&colors.cursor)?,
+            selection: parse_color(&colors.selection)?,
+            current_line: parse_color(&colors.current_line)?,
+            status_bar: parse_color(&colors.status_bar)?,
+            modified: parse_color(&colors.modified_indicator)?,
+            null_byte: parse_color(&colors.null_byte)?,
+            printable_byte: parse_color(&colors.printable_byte)?,
+            high_byte: parse_color(&colors.high_byte)?,
+            ff_byte: parse_color(&colors.ff_byte)?,
+        })
+    }
+
+    /// Цвет байта по классу; управляющие символы остаются цветом по умолчанию
+    pub fn byte_color(&self, byte: u8) -> Option<Color> {
+        match byte {
+            0x00 => Some(self.null_byte),
+            0xFF => Some(self.ff_byte),
+            0x20..=0x7E => Some(self.printable_byte),
+            0x80.. => Some(self.high_byte),
+            _ => None,
+        }
+    }
+}
+
+fn with_theme_file(colors: &ColorConfig, name: &str) -> Result<ColorConfig> {
+    let path = Config::themes_dir().join(format!("{}.toml", name));
+    let content =
+        std::fs::read_to_string(&path).with_context(|| format!("Cannot read theme {}", path.display()))?;
+    let theme: toml::Table =
+        toml::from_str(&content).with_context(|| format!("Cannot parse theme {}", path.display()))?;
+
+    // Ключи темы накладываются на [colors], остальные значения сохраняются
+    let mut merged = toml::Table::try_from(colors)?;
+    merged.extend(theme);
+    Ok(merged.try_into()?)
+}
+
+/// Имя цвета (`red`, `dark_blue`, ...), `#rrggbb` или номер из 256-цветной палитры
+pub fn parse_color(value: &str) -> Result<Color> {
+    let value = value.trim().to_ascii_lowercase();
+
+    if let Some(hex) = value.strip_prefix('#') {
+        let rgb = u32::from_str_radix(hex, 16).ok().filter(|_| hex.len() == 6);
+        let rgb = rgb.ok_or_else(|| anyhow!("Invalid color '{}': expected #rrggbb", value))?;
+        return Ok(Color::Rgb { r: (rgb >> 16) as u8, g: (rgb >> 8) as u8, b: rgb as u8 });
+    }
+
+    if let Ok(index) = value.parse::<u8>() {
+        return Ok(Color::AnsiValue(index));
+    }
+
+    let color = match value.replace(['_', '-', ' '], "").as_str() {
+        "reset" | "default" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "darkred" => Color::DarkRed,
+        "green" => Color::Green,
+        "darkgreen" => Color::DarkGreen,
+        "yellow" => Color::Yellow,
+        "darkyellow" => Color::DarkYellow,
+        "blue" => Color::Blue,
+        "darkblue" => Color::DarkBlue,
+        "magenta" => Color::Magenta,
+        "darkmagenta" => Color::DarkMagenta,
+        "cyan" => Color::Cyan,
+        "darkcyan" => Color::DarkCyan,
+        "white" => Color::White,
+        "grey" | "gray" => Color::Grey,
+        "darkgrey" | "darkgray" => Color::DarkGrey,
+        _ => return Err(anyhow!("Unknown color '{}'", value)),
+    };
+    Ok(color)
+}
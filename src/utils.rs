@@ -1,13 +1,19 @@
-use anyhow::Result;
+use crate::screen;
+use anyhow::{Result, anyhow, bail};
 use crossterm::{
     ExecutableCommand, cursor,
-    event::{self, Event, KeyCode},
-    style::{Color, Print, ResetColor, SetForegroundColor},
+    event::{self, Event, KeyCode, KeyEventKind},
+    style::{Attribute, Color, Print, ResetColor, SetAttribute, SetForegroundColor},
     terminal::{self, ClearType},
 };
+use std::fs::{self, OpenOptions};
 use std::io::{Write, stdout};
+use std::path::{Path, PathBuf};
+
+pub use hexr_core::hex::{bytes_to_hex_string, hex_string_to_bytes, parse_byte_pattern, parse_hex_text};
 
 pub fn get_user_input(prompt: &str) -> Result<String> {
+    screen::invalidate();
     let (_, height) = terminal::size()?;
     stdout().execute(cursor::MoveTo(0, height - 3))?;
     stdout().execute(terminal::Clear(ClearType::CurrentLine))?;
@@ -23,14 +29,12 @@ pub fn get_user_input(prompt: &str) -> Result<String> {
             match key.code {
                 KeyCode::Enter => break,
                 KeyCode::Esc => return Ok(String::new()),
-                KeyCode::Backspace => {
-                    if !input.is_empty() {
-                        input.pop();
-                        stdout().execute(cursor::MoveLeft(1))?;
-                        stdout().execute(Print(" "))?;
-                        stdout().execute(cursor::MoveLeft(1))?;
-                        stdout().flush()?;
-                    }
+                KeyCode::Backspace if !input.is_empty() => {
+                    input.pop();
+                    stdout().execute(cursor::MoveLeft(1))?;
+                    stdout().execute(Print(" "))?;
+                    stdout().execute(cursor::MoveLeft(1))?;
+                    stdout().flush()?;
                 }
                 KeyCode::Char(c) => {
                     input.push(c);
@@ -45,18 +49,421 @@ pub fn get_user_input(prompt: &str) -> Result<String> {
     Ok(input)
 }
 
-pub fn hex_string_to_bytes(hex: &str) -> Result<Vec<u8>> {
-    let hex = hex.replace(" ", "");
-    let mut bytes = Vec::new();
+/// Участок `START..END`, конец не включается
+pub fn parse_range(input: &str) -> Result<std::ops::Range<usize>> {
+    let (start, end) = input
+        .split_once("..")
+        .ok_or_else(|| anyhow!("Expected START..END, got '{}'", input))?;
+    let (start, end) = (parse_size(start.trim())?, parse_size(end.trim())?);
+    if start > end {
+        bail!("Range {} ends before it starts", input);
+    }
+    Ok(start..end)
+}
+
+/// Смещение или размер из командной строки: десятичное или 0x-шестнадцатеричное число
+pub fn parse_size(input: &str) -> Result<usize> {
+    let parsed = match input.strip_prefix("0x").or_else(|| input.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => input.parse(),
+    };
+    parsed.map_err(|_| anyhow!("Invalid number '{}'", input))
+}
+
+/// Строк над вводом пути: недавние файлы или варианты дополнения
+const PATH_LIST_ROWS: usize = 10;
+
+/// Ввод пути над строкой состояния: Up/Down выбирают недавний файл, Tab дополняет путь.
+/// Esc отменяет ввод и возвращает пустую строку
+pub fn get_path_input(prompt: &str, recent: &[String]) -> Result<String> {
+    screen::invalidate();
+    let mut input = String::new();
+    let mut selected: Option<usize> = None;
+    // Варианты дополнения показываются вместо недавних, пока путь не изменится
+    let mut candidates: Vec<String> = Vec::new();
+    let mut drawn_rows = 0;
+
+    loop {
+        let (list, highlight) = if candidates.is_empty() {
+            (recent, selected)
+        } else {
+            (&candidates[..], None)
+        };
+        drawn_rows = draw_path_prompt(prompt, &input, list, highlight, drawn_rows)?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        match key.code {
+            KeyCode::Enter => return Ok(input),
+            KeyCode::Esc => return Ok(String::new()),
+            KeyCode::Up | KeyCode::Down if !recent.is_empty() => {
+                let index = match (key.code, selected) {
+                    (KeyCode::Down, Some(index)) => (index + 1).min(recent.len() - 1),
+                    (KeyCode::Up, Some(index)) => index.saturating_sub(1),
+                    _ => 0,
+                };
+                selected = Some(index);
+                input = recent[index].clone();
+                candidates.clear();
+            }
+            KeyCode::Tab => {
+                let (completed, matches) = complete_path(&input);
+                input = completed;
+                candidates = if matches.len() > 1 { matches } else { Vec::new() };
+                selected = None;
+            }
+            KeyCode::Backspace => {
+                input.pop();
+                selected = None;
+                candidates.clear();
+            }
+            KeyCode::Char(c) => {
+                input.push(c);
+                selected = None;
+                candidates.clear();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Рисует список над строкой ввода и саму строку; возвращает, сколько строк списка занято,
+/// чтобы следующий, более короткий список стер остатки прежнего
+fn draw_path_prompt(prompt: &str, input: &str, list: &[String], selected: Option<usize>, drawn_rows: usize) -> Result<usize> {
+    let (width, height) = terminal::size()?;
+    let prompt_row = height.saturating_sub(3);
+    // Заголовок и вкладки не закрываются списком
+    let available = (prompt_row as usize).saturating_sub(2).min(PATH_LIST_ROWS);
+    let rows = list.len().min(available).max(drawn_rows.min(available));
+
+    let mut out = stdout();
+    for row in 0..rows {
+        out.execute(cursor::MoveTo(0, prompt_row - (rows - row) as u16))?;
+        out.execute(terminal::Clear(ClearType::CurrentLine))?;
+        let Some(entry) = list.get(row) else {
+            continue;
+        };
 
-    for chunk in hex.as_bytes().chunks(2) {
-        if chunk.len() == 2 {
-            let s = std::str::from_utf8(chunk)?;
-            let byte = u8::from_str_radix(s, 16)?;
-            bytes.push(byte);
+        // Длинный путь обрезается слева: важнее имя файла
+        let room = (width as usize).saturating_sub(2);
+        let skip = entry.chars().count().saturating_sub(room);
+        let shown: String = entry.chars().skip(skip).collect();
+
+        if selected == Some(row) {
+            out.execute(SetAttribute(Attribute::Reverse))?;
         }
+        print!(" {}", shown);
+        out.execute(SetAttribute(Attribute::Reset))?;
     }
 
-    Ok(bytes)
+    out.execute(cursor::MoveTo(0, prompt_row))?;
+    out.execute(terminal::Clear(ClearType::CurrentLine))?;
+    out.execute(SetForegroundColor(Color::Cyan))?;
+    print!("{}", prompt);
+    out.execute(ResetColor)?;
+    print!("{}", input);
+    out.flush()?;
+
+    Ok(rows)
 }
 
+/// Дополняет последний компонент пути до общего начала подходящих имен.
+/// Возвращает новый путь и подходящие имена (каталоги - с разделителем на конце)
+pub fn complete_path(input: &str) -> (String, Vec<String>) {
+    let split = input.rfind(std::path::is_separator).map_or(0, |index| index + 1);
+    let (dir, prefix) = input.split_at(split);
+    let dir_path = if dir.is_empty() { Path::new(".") } else { Path::new(dir) };
+
+    let Ok(entries) = fs::read_dir(dir_path) else {
+        return (input.to_string(), Vec::new());
+    };
+
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let mut name = entry.file_name().into_string().ok()?;
+            // Скрытые файлы - только если точку уже набрали
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            if entry.path().is_dir() {
+                name.push(std::path::MAIN_SEPARATOR);
+            }
+            Some(name)
+        })
+        .collect();
+    names.sort();
+
+    let Some(first) = names.first() else {
+        return (input.to_string(), names);
+    };
+    let common = names[1..].iter().fold(first.as_str(), |common, name| common_prefix(common, name));
+    (format!("{}{}", dir, common), names)
+}
+
+fn common_prefix<'a>(a: &'a str, b: &str) -> &'a str {
+    let len = a
+        .char_indices()
+        .zip(b.chars())
+        .take_while(|((_, x), y)| x == y)
+        .last()
+        .map_or(0, |((index, c), _)| index + c.len_utf8());
+    &a[..len]
+}
+
+/// Модальный вопрос с выбором одной клавиши из `choices`; Esc отменяет выбор.
+/// Регистр не важен, возвращается символ в нижнем регистре
+pub fn prompt_choice(prompt: &str, choices: &str) -> Result<Option<char>> {
+    screen::invalidate();
+    let (_, height) = terminal::size()?;
+    stdout().execute(cursor::MoveTo(0, height - 3))?;
+    stdout().execute(terminal::Clear(ClearType::CurrentLine))?;
+    stdout().execute(SetForegroundColor(Color::Yellow))?;
+    print!("{}", prompt);
+    stdout().execute(ResetColor)?;
+    stdout().flush()?;
+
+    loop {
+        if let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+        {
+            match key.code {
+                KeyCode::Esc => return Ok(None),
+                KeyCode::Char(c) if choices.contains(c.to_ascii_lowercase()) => return Ok(Some(c.to_ascii_lowercase())),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Атомарная запись: данные пишутся во временный файл рядом с целевым и
+/// переименовываются поверх него, поэтому сбой записи не портит исходный файл.
+/// Данные передаются кусками. Права исходного файла сохраняются; при `keep_backup` старое содержимое копируется в `<имя>.bak`
+pub fn write_file_atomic<'a>(path: &str, chunks: impl IntoIterator<Item = &'a [u8]>, keep_backup: bool) -> Result<()> {
+    // Для символической ссылки заменяем файл, на который она указывает, а не саму ссылку
+    let target = fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path));
+    let existing = fs::metadata(&target).ok();
+
+    let file_name = target
+        .file_name()
+        .ok_or_else(|| anyhow!("Invalid file name: {}", path))?
+        .to_string_lossy()
+        .into_owned();
+    let dir = match target.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let temp = dir.join(format!(".{}.hexr-{}.tmp", file_name, std::process::id()));
+
+    let result = write_temp_file(&temp, chunks, existing.as_ref()).and_then(|()| {
+        if keep_backup && existing.is_some() {
+            fs::copy(&target, dir.join(format!("{}.bak", file_name)))?;
+        }
+        fs::rename(&temp, &target)?;
+        Ok(())
+    });
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+fn write_temp_file<'a>(
+    temp: &Path,
+    chunks: impl IntoIterator<Item = &'a [u8]>,
+    existing: Option<&fs::Metadata>,
+) -> Result<()> {
+    // Буферизация: кусков после правок может быть много и они мелкие
+    let mut file = std::io::BufWriter::new(OpenOptions::new().write(true).create_new(true).open(temp)?);
+    for chunk in chunks {
+        file.write_all(chunk)?;
+    }
+    let file = file.into_inner().map_err(|e| e.into_error())?;
+    file.sync_all()?;
+
+    if let Some(metadata) = existing {
+        fs::set_permissions(temp, metadata.permissions())?;
+    }
+    Ok(())
+}
+
+/// Вычисление смещения для перехода: `0x400*3+8`, `end-16`, `+0x200`, `-512`.
+/// Числа десятичные, либо hex с префиксом 0x; `end` - длина данных, `cur` - позиция курсора.
+/// Ведущий знак `+`/`-` означает переход относительно курсора
+pub fn eval_offset_expression(input: &str, cursor: usize, end: usize) -> Result<usize> {
+    let tokens = tokenize_offset_expression(input)?;
+    if tokens.is_empty() {
+        bail!("Empty offset expression");
+    }
+
+    let relative = matches!(tokens.first(), Some(OffsetToken::Op('+' | '-')));
+    let mut parser = OffsetParser {
+        tokens: &tokens,
+        pos: 0,
+        cursor: cursor as i128,
+        end: end as i128,
+    };
+
+    let value = parser.expression()?;
+    if let Some(token) = tokens.get(parser.pos) {
+        bail!("Unexpected {} in offset expression", token.describe());
+    }
+
+    let value = if relative { cursor as i128 + value } else { value };
+    if value < 0 {
+        bail!("Offset {} is before the start of the file", value);
+    }
+
+    usize::try_from(value).map_err(|_| anyhow!("Offset is too large"))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum OffsetToken {
+    Number(i128),
+    End,
+    Cursor,
+    Op(char),
+    Open,
+    Close,
+}
+
+impl OffsetToken {
+    fn describe(&self) -> String {
+        match self {
+            OffsetToken::Number(n) => format!("number {}", n),
+            OffsetToken::End => "'end'".to_string(),
+            OffsetToken::Cursor => "'cur'".to_string(),
+            OffsetToken::Op(c) => format!("'{}'", c),
+            OffsetToken::Open => "'('".to_string(),
+            OffsetToken::Close => "')'".to_string(),
+        }
+    }
+}
+
+fn tokenize_offset_expression(input: &str) -> Result<Vec<OffsetToken>> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = input.chars().collect();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            ' ' | '\t' => i += 1,
+            '+' | '-' | '*' | '/' | '%' => {
+                tokens.push(OffsetToken::Op(c));
+                i += 1;
+            }
+            '(' => {
+                tokens.push(OffsetToken::Open);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(OffsetToken::Close);
+                i += 1;
+            }
+            _ if c.is_ascii_alphanumeric() => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().filter(|&&c| c != '_').collect();
+                let lower = word.to_ascii_lowercase();
+
+                let token = if lower == "end" {
+                    OffsetToken::End
+                } else if lower == "cur" {
+                    OffsetToken::Cursor
+                } else if let Some(hex) = lower.strip_prefix("0x") {
+                    let value = i128::from_str_radix(hex, 16).map_err(|_| anyhow!("Invalid hex number '{}'", word))?;
+                    OffsetToken::Number(value)
+                } else {
+                    let value = lower
+                        .parse::<i128>()
+                        .map_err(|_| anyhow!("Invalid number '{}' (use 0x prefix for hex)", word))?;
+                    OffsetToken::Number(value)
+                };
+                tokens.push(token);
+            }
+            _ => bail!("Unexpected character '{}' in offset expression", c),
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Рекурсивный спуск: expression := term (('+'|'-') term)*, term := factor (('*'|'/'|'%') factor)*
+struct OffsetParser<'a> {
+    tokens: &'a [OffsetToken],
+    pos: usize,
+    cursor: i128,
+    end: i128,
+}
+
+impl OffsetParser<'_> {
+    fn peek(&self) -> Option<&OffsetToken> {
+        self.tokens.get(self.pos)
+    }
+
+    fn expression(&mut self) -> Result<i128> {
+        // Унарный знак в начале выражения или после '('
+        let mut value = match self.peek() {
+            Some(OffsetToken::Op('+' | '-')) => 0,
+            _ => self.term()?,
+        };
+
+        while let Some(OffsetToken::Op(op @ ('+' | '-'))) = self.peek().cloned() {
+            self.pos += 1;
+            let rhs = self.term()?;
+            let result = if op == '+' { value.checked_add(rhs) } else { value.checked_sub(rhs) };
+            value = result.ok_or_else(|| anyhow!("Offset is too large"))?;
+        }
+
+        Ok(value)
+    }
+
+    fn term(&mut self) -> Result<i128> {
+        let mut value = self.factor()?;
+
+        while let Some(OffsetToken::Op(op @ ('*' | '/' | '%'))) = self.peek().cloned() {
+            self.pos += 1;
+            let rhs = self.factor()?;
+            value = match op {
+                '*' => value.checked_mul(rhs).ok_or_else(|| anyhow!("Offset is too large"))?,
+                _ if rhs == 0 => bail!("Division by zero in offset expression"),
+                // i128::MIN / -1 тоже переполняет
+                '/' => value.checked_div(rhs).ok_or_else(|| anyhow!("Offset is too large"))?,
+                _ => value.checked_rem(rhs).ok_or_else(|| anyhow!("Offset is too large"))?,
+            };
+        }
+
+        Ok(value)
+    }
+
+    fn factor(&mut self) -> Result<i128> {
+        let Some(token) = self.peek().cloned() else {
+            bail!("Offset expression ends unexpectedly");
+        };
+        self.pos += 1;
+
+        match token {
+            OffsetToken::Number(n) => Ok(n),
+            OffsetToken::End => Ok(self.end),
+            OffsetToken::Cursor => Ok(self.cursor),
+            OffsetToken::Open => {
+                let value = self.expression()?;
+                if self.peek() != Some(&OffsetToken::Close) {
+                    bail!("Missing ')' in offset expression");
+                }
+                self.pos += 1;
+                Ok(value)
+            }
+            other => bail!("Unexpected {} in offset expression", other.describe()),
+        }
+    }
+}
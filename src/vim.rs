@@ -0,0 +1,160 @@
+use crate::keymap::{Action, CTRL, KeyChord, Keymap, NONE, SHIFT};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Команды нормального режима; клавиши обычного набора (Ctrl+..., F-клавиши, стрелки)
+/// продолжают работать, если не перекрыты здесь
+pub const NORMAL_BINDINGS: &[(KeyCode, KeyModifiers, Action)] = &[
+    (KeyCode::Char('h'), NONE, Action::MoveLeft),
+    (KeyCode::Char('j'), NONE, Action::MoveDown),
+    (KeyCode::Char('k'), NONE, Action::MoveUp),
+    (KeyCode::Char('l'), NONE, Action::MoveRight),
+    (KeyCode::Char('0'), NONE, Action::LineStart),
+    (KeyCode::Char('$'), NONE, Action::LineEnd),
+    (KeyCode::Char('g'), SHIFT, Action::FileEnd),
+    (KeyCode::Char('f'), CTRL, Action::PageDown),
+    (KeyCode::Char('b'), CTRL, Action::PageUp),
+    (KeyCode::Char('x'), NONE, Action::DeleteAtCursor),
+    (KeyCode::Char('x'), SHIFT, Action::DeleteBeforeCursor),
+    (KeyCode::Char('v'), NONE, Action::ToggleVisual),
+    (KeyCode::Char('y'), NONE, Action::Copy),
+    (KeyCode::Char('d'), NONE, Action::Cut),
+    (KeyCode::Char('p'), NONE, Action::Paste),
+    (KeyCode::Char('u'), NONE, Action::Undo),
+    (KeyCode::Char('r'), CTRL, Action::Redo),
+    (KeyCode::Char('/'), NONE, Action::Search),
+    (KeyCode::Char('n'), NONE, Action::SearchNext),
+    (KeyCode::Char('n'), SHIFT, Action::SearchPrevious),
+    (KeyCode::Char('?'), NONE, Action::Help),
+];
+
+/// Клавиши, которые разбирает сам автомат режимов, для справки
+pub const MODAL_KEYS: &[(&str, &str)] = &[
+    ("i", "Insert mode: type bytes as usual"),
+    ("Esc", "Back to normal mode"),
+    ("g g", "Start of file (Ngg - line N)"),
+    ("1-9...", "Count for the next command: 16l, 4x, 3j"),
+];
+
+/// Счетчик не дает случайно набранному числу зациклить редактор
+const COUNT_MAX: usize = 1 << 20;
+
+/// Что делать с нажатием
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Resolved {
+    /// Команда и набранный перед ней счетчик
+    Action(Action, Option<usize>),
+    /// Нажатие поглощено: часть счетчика, префикс, смена режима
+    Pending,
+    /// Нет назначения: ввод данных или клавиша панели
+    Unbound,
+}
+
+/// Автомат модального ввода: нормальный режим и режим вставки, счетчик и префикс g.
+/// С обычным набором клавиш просто ищет команду в раскладке
+#[derive(Debug, Default)]
+pub struct Modal {
+    insert: bool,
+    count: Option<usize>,
+    g_pending: bool,
+}
+
+impl Modal {
+    pub fn resolve(&mut self, keymap: &Keymap, key: &KeyEvent) -> Resolved {
+        if !keymap.is_modal() {
+            return unmodal(keymap, key);
+        }
+
+        if self.insert {
+            if key.code == KeyCode::Esc {
+                self.insert = false;
+                return Resolved::Pending;
+            }
+            return unmodal(keymap, key);
+        }
+
+        let chord = KeyChord::from_event(key);
+        let plain = chord.modifiers - KeyModifiers::SHIFT == KeyModifiers::NONE;
+
+        // Префикс g ждет второго g; любая другая клавиша его отменяет
+        if std::mem::take(&mut self.g_pending) {
+            let count = self.count.take();
+            return match chord.code {
+                KeyCode::Char('g') if chord.modifiers == KeyModifiers::NONE => {
+                    Resolved::Action(Action::FileStart, count)
+                }
+                _ => Resolved::Pending,
+            };
+        }
+
+        match chord.code {
+            // 0 без набранного счетчика - это начало строки
+            KeyCode::Char(c @ '0'..='9') if plain && (c != '0' || self.count.is_some()) => {
+                let digit = c as usize - '0' as usize;
+                let count = self
+                    .count
+                    .unwrap_or(0)
+                    .saturating_mul(10)
+                    .saturating_add(digit);
+                self.count = Some(count.min(COUNT_MAX));
+                return Resolved::Pending;
+            }
+            KeyCode::Char('g') if chord.modifiers == KeyModifiers::NONE => {
+                self.g_pending = true;
+                return Resolved::Pending;
+            }
+            KeyCode::Char('i') if chord.modifiers == KeyModifiers::NONE => {
+                self.count = None;
+                self.insert = true;
+                return Resolved::Pending;
+            }
+            _ => {}
+        }
+
+        let count = self.count.take();
+        if let Some(action) = keymap.normal_action(chord).or_else(|| keymap.action(key)) {
+            return Resolved::Action(action, count);
+        }
+
+        // В нормальном режиме символы не вводятся в файл
+        match chord.code {
+            KeyCode::Char(_) if plain => Resolved::Pending,
+            _ => Resolved::Unbound,
+        }
+    }
+
+    /// Нажатие для панели со своими клавишами (список совпадений, закладки): мимо режимов,
+    /// набранный счетчик сбрасывается
+    pub fn resolve_unmodal(&mut self, keymap: &Keymap, key: &KeyEvent) -> Resolved {
+        self.count = None;
+        self.g_pending = false;
+        unmodal(keymap, key)
+    }
+
+    /// Режим и набранный счетчик для строки состояния; `None` у обычного набора клавиш
+    pub fn status(&self, keymap: &Keymap) -> Option<String> {
+        if !keymap.is_modal() {
+            return None;
+        }
+        if self.insert {
+            return Some("-- INSERT --".to_string());
+        }
+
+        let mut status = "NORMAL".to_string();
+        if self.count.is_some() || self.g_pending {
+            status.push(' ');
+        }
+        if let Some(count) = self.count {
+            status.push_str(&count.to_string());
+        }
+        if self.g_pending {
+            status.push('g');
+        }
+        Some(status)
+    }
+}
+
+fn unmodal(keymap: &Keymap, key: &KeyEvent) -> Resolved {
+    keymap
+        .action(key)
+        .map_or(Resolved::Unbound, |action| Resolved::Action(action, None))
+}
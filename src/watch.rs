@@ -0,0 +1,62 @@
+use std::time::{Duration, Instant, SystemTime};
+
+/// Как часто проверяется файл на диске
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Время изменения и размер файла на момент открытия или сохранения
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct DiskStamp {
+    modified: SystemTime,
+    len: u64,
+}
+
+impl DiskStamp {
+    fn read(path: &str) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        Some(Self {
+            modified: metadata.modified().ok()?,
+            len: metadata.len(),
+        })
+    }
+}
+
+/// Отслеживание изменений файла другими программами опросом mtime
+#[derive(Debug)]
+pub struct FileWatcher {
+    stamp: Option<DiskStamp>,
+    last_check: Instant,
+}
+
+impl FileWatcher {
+    pub fn new(path: &str) -> Self {
+        Self {
+            stamp: DiskStamp::read(path),
+            last_check: Instant::now(),
+        }
+    }
+
+    /// Запоминает текущее состояние файла (после открытия, сохранения, перезагрузки)
+    pub fn refresh(&mut self, path: &str) {
+        self.stamp = DiskStamp::read(path);
+        self.last_check = Instant::now();
+    }
+
+    /// Через сколько файл можно проверить снова
+    pub fn next_check(&self) -> Duration {
+        CHECK_INTERVAL.saturating_sub(self.last_check.elapsed())
+    }
+
+    /// true, если с последней отметки файл изменился; проверка не чаще раза в секунду.
+    /// Отсутствующий или недоступный файл изменением не считается
+    pub fn changed(&mut self, path: &str) -> bool {
+        if self.last_check.elapsed() < CHECK_INTERVAL {
+            return false;
+        }
+        self.last_check = Instant::now();
+
+        match (self.stamp, DiskStamp::read(path)) {
+            (Some(known), Some(current)) => known != current,
+            _ => false,
+        }
+    }
+}